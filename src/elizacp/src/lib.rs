@@ -3,6 +3,7 @@ pub mod eliza;
 use anyhow::Result;
 use eliza::Eliza;
 use sacp::AgentToClient;
+use sacp::mcp_client::McpClient;
 use sacp::schema::{
     AgentCapabilities, ContentBlock, ContentChunk, InitializeRequest, InitializeResponse,
     LoadSessionRequest, LoadSessionResponse, McpServer, NewSessionRequest, NewSessionResponse,
@@ -120,7 +121,7 @@ impl ElizaAgent {
             // List tools from a specific server
             tracing::debug!("Listing tools from MCP server: {}", server_name);
 
-            match self.list_tools(&session_id, &server_name).await {
+            match self.list_tools(&session_id, &server_name, &cx).await {
                 Ok(tools) => format!("Available tools:\n{}", tools),
                 Err(e) => format!("ERROR: {}", e),
             }
@@ -134,7 +135,7 @@ impl ElizaAgent {
             );
 
             match self
-                .execute_tool_call(&session_id, &server_name, &tool_name, &params_json)
+                .execute_tool_call(&session_id, &server_name, &tool_name, &params_json, &cx)
                 .await
             {
                 Ok(result) => format!("OK: {}", result),
@@ -174,18 +175,13 @@ impl ElizaAgent {
         &self,
         session_id: &SessionId,
         server_name: &str,
+        cx: &JrConnectionCx<AgentToClient>,
         operation: F,
     ) -> Result<T>
     where
-        F: FnOnce(rmcp::service::RunningService<rmcp::RoleClient, ()>) -> Fut,
+        F: FnOnce(McpClient) -> Fut,
         Fut: std::future::Future<Output = Result<T>>,
     {
-        use rmcp::{
-            ServiceExt,
-            transport::{ConfigureCommandExt, TokioChildProcess},
-        };
-        use tokio::process::Command;
-
         // Get MCP servers for this session
         let mcp_servers = self
             .get_mcp_servers(session_id)
@@ -202,74 +198,34 @@ impl ElizaAgent {
             })
             .ok_or_else(|| anyhow::anyhow!("MCP server '{}' not found", server_name))?;
 
-        // Spawn MCP client based on server type
-        match mcp_server {
-            McpServer::Stdio(stdio) => {
-                tracing::debug!(
-                    command = ?stdio.command,
-                    args = ?stdio.args,
-                    server_name = %server_name,
-                    "Starting MCP client"
-                );
-
-                // Create MCP client by spawning the process
-                let mcp_client = ()
-                    .serve(TokioChildProcess::new(
-                        Command::new(&stdio.command).configure(|cmd| {
-                            cmd.args(&stdio.args);
-                            for env_var in &stdio.env {
-                                cmd.env(&env_var.name, &env_var.value);
-                            }
-                        }),
-                    )?)
-                    .await?;
-
-                tracing::debug!("MCP client connected");
-
-                // Execute the operation
-                let result = operation(mcp_client).await?;
-
-                Ok(result)
-            }
-            McpServer::Http(http) => {
-                use rmcp::transport::StreamableHttpClientTransport;
-
-                tracing::debug!(
-                    url = %http.url,
-                    server_name = %server_name,
-                    "Starting HTTP MCP client"
-                );
+        tracing::debug!(?mcp_server, server_name = %server_name, "Starting MCP client");
 
-                // Create HTTP MCP client
-                let mcp_client =
-                    ().serve(StreamableHttpClientTransport::from_uri(http.url.as_str()))
-                        .await?;
+        // Connect using whatever transport the server entry calls for
+        let mcp_client = McpClient::connect(mcp_server, cx).await?;
 
-                tracing::debug!("HTTP MCP client connected");
+        tracing::debug!("MCP client connected");
 
-                // Execute the operation
-                let result = operation(mcp_client).await?;
-
-                Ok(result)
-            }
-            McpServer::Sse(_) => Err(anyhow::anyhow!("SSE MCP servers not yet supported")),
-            _ => Err(anyhow::anyhow!("Unknown MCP server type")),
-        }
+        // Execute the operation
+        operation(mcp_client).await
     }
 
-    async fn list_tools(&self, session_id: &SessionId, server_name: &str) -> Result<String> {
-        self.with_mcp_client(session_id, server_name, async move |mcp_client| {
+    async fn list_tools(
+        &self,
+        session_id: &SessionId,
+        server_name: &str,
+        cx: &JrConnectionCx<AgentToClient>,
+    ) -> Result<String> {
+        self.with_mcp_client(session_id, server_name, cx, async move |mcp_client| {
             // List the tools
-            let tools_result = mcp_client.list_tools(None).await?;
+            let tools = mcp_client.list_tools().await?;
 
-            tracing::debug!("Tools result: {:?}", tools_result);
+            tracing::debug!("Tools result: {:?}", tools);
 
             // Clean up the client
-            mcp_client.cancel().await?;
+            mcp_client.close().await?;
 
             // Format the tools list
-            let tools_list = tools_result
-                .tools
+            let tools_list = tools
                 .iter()
                 .map(|tool| {
                     format!(
@@ -292,31 +248,30 @@ impl ElizaAgent {
         server_name: &str,
         tool_name: &str,
         params_json: &str,
+        cx: &JrConnectionCx<AgentToClient>,
     ) -> Result<String> {
-        use rmcp::model::CallToolRequestParam;
-
         // Parse params JSON
         let params = serde_json::from_str::<serde_json::Value>(params_json)
             .map_err(|e| anyhow::anyhow!("Invalid JSON params: {}", e))?;
 
-        let params_obj = params.as_object().cloned();
+        // Non-object params are passed as no arguments
+        let params = if params.is_object() {
+            params
+        } else {
+            serde_json::Value::Null
+        };
         let tool_name = tool_name.to_string();
 
-        self.with_mcp_client(session_id, server_name, async move |mcp_client| {
+        self.with_mcp_client(session_id, server_name, cx, async move |mcp_client| {
             tracing::debug!("Calling tool: {}", tool_name);
 
             // Call the tool
-            let tool_result = mcp_client
-                .call_tool(CallToolRequestParam {
-                    name: tool_name.into(),
-                    arguments: params_obj,
-                })
-                .await?;
+            let tool_result = mcp_client.call_tool(tool_name, params).await?;
 
             tracing::debug!("Tool call result: {:?}", tool_result);
 
             // Clean up the client
-            mcp_client.cancel().await?;
+            mcp_client.close().await?;
 
             // Format the result
             Ok(format!("{:?}", tool_result))
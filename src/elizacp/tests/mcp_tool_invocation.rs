@@ -113,3 +113,47 @@ async fn test_elizacp_mcp_tool_call() -> Result<(), sacp::Error> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn test_elizacp_mcp_over_acp_tool_call() -> Result<(), sacp::Error> {
+    use sacp::ClientToAgent;
+    use sacp::mcp_server::McpServer;
+
+    #[derive(serde::Deserialize, schemars::JsonSchema)]
+    struct EchoInput {
+        message: String,
+    }
+
+    // The MCP server lives in the client and is offered to the agent with an `acp:` URL
+    let mcp_server = McpServer::<ClientToAgent, _>::builder("tools")
+        .tool_fn(
+            "echo",
+            "Echo a message back",
+            async |input: EchoInput, _cx| Ok(format!("Echo: {}", input.message)),
+            sacp::tool_fn!(),
+        )
+        .build();
+
+    ClientToAgent::builder()
+        .name("test-client")
+        .run_until(ElizaAgent::new(), async |cx| {
+            cx.send_request(InitializeRequest::new(ProtocolVersion::LATEST))
+                .block_task()
+                .await?;
+
+            cx.build_session(PathBuf::from("/tmp"))
+                .with_mcp_server(mcp_server)?
+                .block_task()
+                .run_until(async |mut session| {
+                    session.send_prompt(r#"Use tool tools::echo with {"message": "over acp"}"#)?;
+                    let response = session.read_to_string().await?;
+
+                    expect![[r#"
+                        "OK: CallToolResult { content: [Annotated { raw: Text(RawTextContent { text: \"\\\"Echo: over acp\\\"\", meta: None }), annotations: None }], structured_content: None, is_error: Some(false), meta: None }"
+                    "#]].assert_debug_eq(&response);
+                    Ok(())
+                })
+                .await
+        })
+        .await
+}
@@ -117,7 +117,8 @@ use futures::{
     channel::mpsc::{self},
 };
 use sacp::{
-    AgentPeer, BoxFuture, ClientPeer, Component, Error, HasPeer, JrMessage,
+    AgentPeer, BoxFuture, ClientPeer, Component, Error, HasPeer, JrMessage, McpAcpTransport,
+    MetaCapabilityExt,
     link::{
         AgentToClient, ConductorToAgent, ConductorToClient, ConductorToConductor, ConductorToProxy,
         ProxyToConductor,
@@ -127,8 +128,8 @@ use sacp::{
 use sacp::{
     Handled,
     schema::{
-        McpConnectRequest, McpConnectResponse, McpDisconnectNotification, McpOverAcpMessage,
-        SuccessorMessage,
+        LogNotification, METHOD_LOG_NOTIFICATION, McpConnectRequest, McpConnectResponse,
+        McpDisconnectNotification, McpOverAcpMessage, SuccessorMessage,
     },
 };
 use sacp::{
@@ -137,7 +138,10 @@ use sacp::{
 };
 use sacp::{
     JrMessageHandler, JrResponder, JrResponsePayload,
-    schema::{InitializeProxyRequest, InitializeRequest, NewSessionRequest},
+    schema::{
+        InitializeProxyRequest, InitializeRequest, InitializeResponse, LoadSessionRequest,
+        NewSessionRequest, SessionId,
+    },
     util::MatchMessageFrom,
 };
 use tracing::{debug, info};
@@ -246,6 +250,7 @@ impl<Link: ConductorLink> Conductor<Link> {
             bridge_listeners: Default::default(),
             bridge_connections: Default::default(),
             mcp_bridge_mode: self.mcp_bridge_mode,
+            agent_mcp_acp_transport: false,
             proxies: Default::default(),
             successor: Arc::new(sacp::util::internal_error("successor not initialized")),
             trace_writer: self.trace_writer,
@@ -375,6 +380,11 @@ where
     /// Mode for the MCP bridge (determines how to spawn bridge processes).
     mcp_bridge_mode: crate::McpBridgeMode,
 
+    /// True if the agent advertised the `mcp_acp_transport` capability in its
+    /// `initialize` response. Such agents speak MCP-over-ACP themselves, so we
+    /// pass `acp:` MCP servers through unchanged rather than bridging them.
+    agent_mcp_acp_transport: bool,
+
     /// Optional trace writer for sequence diagram visualization.
     trace_writer: Option<crate::trace::TraceWriter>,
 
@@ -430,44 +440,11 @@ where
     fn extract_trace_info<R: sacp::JrRequest, N: sacp::JrNotification>(
         message: &sacp::MessageCx<R, N>,
     ) -> Result<(crate::trace::Protocol, String, serde_json::Value), sacp::Error> {
-        match message {
-            sacp::MessageCx::Request(request, _) => {
-                let untyped = request.to_untyped_message()?;
-
-                // Try to parse as MCP-over-ACP request
-                if let Some(Ok(mcp_req)) = <McpOverAcpMessage<UntypedMessage>>::parse_message(
-                    &untyped.method,
-                    &untyped.params,
-                ) {
-                    return Ok((
-                        crate::trace::Protocol::Mcp,
-                        mcp_req.message.method,
-                        mcp_req.message.params,
-                    ));
-                }
-
-                // Regular ACP request
-                Ok((crate::trace::Protocol::Acp, untyped.method, untyped.params))
-            }
-            sacp::MessageCx::Notification(notification) => {
-                let untyped = notification.to_untyped_message()?;
-
-                // Try to parse as MCP-over-ACP notification
-                if let Some(Ok(mcp_notif)) = <McpOverAcpMessage<UntypedMessage>>::parse_message(
-                    &untyped.method,
-                    &untyped.params,
-                ) {
-                    return Ok((
-                        crate::trace::Protocol::Mcp,
-                        mcp_notif.message.method,
-                        mcp_notif.message.params,
-                    ));
-                }
-
-                // Regular ACP notification
-                Ok((crate::trace::Protocol::Acp, untyped.method, untyped.params))
-            }
-        }
+        let untyped = match message {
+            sacp::MessageCx::Request(request, _) => request.to_untyped_message()?,
+            sacp::MessageCx::Notification(notification) => notification.to_untyped_message()?,
+        };
+        Ok(crate::trace::traced_message(untyped))
     }
 
     /// Trace a client-to-agent message (request or notification).
@@ -488,6 +465,7 @@ where
         let to = self.component_name(target_index);
 
         let (protocol, method, params) = Self::extract_trace_info(message)?;
+        let session = crate::trace::traced_session(&params);
 
         let writer = self.trace_writer.as_mut().unwrap();
         match message.id() {
@@ -496,10 +474,10 @@ where
                 let id_key = id.to_string();
                 self.pending_requests
                     .insert(id_key, (from.clone(), to.clone()));
-                writer.request(protocol, from, to, id, &method, None, params);
+                writer.request(protocol, from, to, id, &method, session, params);
             }
             None => {
-                writer.notification(protocol, from, to, &method, None, params);
+                writer.notification(protocol, from, to, &method, session, params);
             }
         }
         Ok(())
@@ -529,6 +507,7 @@ where
         };
 
         let (protocol, method, params) = Self::extract_trace_info(message)?;
+        let session = crate::trace::traced_session(&params);
 
         let writer = self.trace_writer.as_mut().unwrap();
         match message.id() {
@@ -537,10 +516,10 @@ where
                 let id_key = id.to_string();
                 self.pending_requests
                     .insert(id_key, (from.clone(), to.clone()));
-                writer.request(protocol, from, to, id, &method, None, params);
+                writer.request(protocol, from, to, id, &method, session, params);
             }
             None => {
-                writer.notification(protocol, from, to, &method, None, params);
+                writer.notification(protocol, from, to, &method, session, params);
             }
         }
         Ok(())
@@ -570,6 +549,22 @@ where
         }
     }
 
+    /// Record a log notification sent by a component.
+    fn trace_component_log(
+        &self,
+        source_index: SourceComponentIndex,
+        notification: &UntypedMessage,
+    ) {
+        let Some(writer) = &self.trace_writer else {
+            return;
+        };
+        match LogNotification::parse_message(&notification.method, &notification.params) {
+            Some(Ok(log)) => writer.log(self.source_component_name(source_index), log),
+            Some(Err(e)) => tracing::warn!("Malformed log notification: {e}"),
+            None => {}
+        }
+    }
+
     /// Recursively spawns components and builds the proxy chain.
     ///
     /// This function implements the recursive chain building pattern:
@@ -596,7 +591,8 @@ where
     ///
     /// * We send `InitializeProxyRequest` to proxy components and `InitializeRequest` to the agent component.
     /// * We modify "session/new" requests that use `acp:...` as the URL for an MCP server to redirect
-    ///   through a stdio server that runs on localhost and bridges messages.
+    ///   through a stdio server that runs on localhost and bridges messages, unless the agent
+    ///   advertised the `mcp_acp_transport` capability and can speak MCP-over-ACP itself.
     async fn handle_conductor_message(
         &mut self,
         client: JrConnectionCx<Link>,
@@ -624,6 +620,13 @@ where
                     message_method = ?message.message().method(),
                     "Conductor: AgentToClient received"
                 );
+                // Log records are for the conductor's trace, not the client.
+                if let sacp::MessageCx::Notification(notification) = &message
+                    && notification.method == METHOD_LOG_NOTIFICATION
+                {
+                    self.trace_component_log(source_component_index, notification);
+                    return Ok(());
+                }
                 if let Err(e) = self.trace_agent_to_client(source_component_index, &message) {
                     tracing::warn!("Failed to trace agent-to-client message: {e}");
                 }
@@ -645,7 +648,7 @@ where
                     client,
                     self.proxies.len(),
                     McpConnectRequest {
-                        acp_url,
+                        acp_url: acp_url.clone(),
                         meta: None,
                     },
                 )
@@ -655,6 +658,7 @@ where
                         match result {
                             Ok(response) => conductor_tx
                                 .send(ConductorMessage::McpConnectionEstablished {
+                                    acp_url,
                                     response,
                                     actor,
                                     connection,
@@ -673,10 +677,27 @@ where
             // MCP connection successfully established. Spawn the actor
             // and insert the connection into our map fot future reference.
             ConductorMessage::McpConnectionEstablished {
+                acp_url,
                 response: McpConnectResponse { connection_id, .. },
                 actor,
                 connection,
             } => {
+                // If the bridge was closed while we waited for the connection id,
+                // drop the connection and let the MCP server know it is gone.
+                if !self
+                    .bridge_listeners
+                    .add_connection(&acp_url, &connection_id)
+                {
+                    return self.send_notification_to_predecessor_of(
+                        client,
+                        self.proxies.len(),
+                        McpDisconnectNotification {
+                            connection_id,
+                            meta: None,
+                        },
+                    );
+                }
+
                 self.bridge_connections
                     .insert(connection_id.clone(), connection);
                 client.spawn(actor.run(connection_id))
@@ -721,9 +742,38 @@ where
                 // We only get MCP-over-ACP requests when we are in bridging MCP for the final agent.
 
                 self.bridge_connections.remove(&notification.connection_id);
+                self.bridge_listeners
+                    .remove_connection(&notification.connection_id);
                 self.send_notification_to_predecessor_of(client, self.proxies.len(), notification)
             }
 
+            // The agent responded to a `session/new` or `session/load` request.
+            // Update which sessions use which bridges, closing the connections of
+            // bridges no longer in use. Each closed connection's actor will shut
+            // down and report back with `McpConnectionDisconnected`.
+            ConductorMessage::McpBridgeSessionResponse {
+                acp_urls,
+                session_id,
+            } => {
+                let closed_connection_ids = match session_id {
+                    Some(session_id) => self
+                        .bridge_listeners
+                        .session_started(&session_id, &acp_urls),
+                    None => self.bridge_listeners.request_failed(&acp_urls),
+                };
+                for connection_id in closed_connection_ids {
+                    self.bridge_connections.remove(&connection_id);
+                }
+                Ok(())
+            }
+
+            // The agent answered `initialize`; remember whether it speaks MCP-over-ACP natively.
+            ConductorMessage::AgentInitialized { mcp_acp_transport } => {
+                info!(mcp_acp_transport, "agent initialized");
+                self.agent_mcp_acp_transport = mcp_acp_transport;
+                Ok(())
+            }
+
             // Forward a response back to the original request context.
             // This ensures responses are processed in order with notifications by
             // going through the central conductor queue.
@@ -1025,27 +1075,103 @@ where
                 )
             })
             .await
+            .if_request(async |request: InitializeRequest, request_cx| {
+                // Note whether the agent supports MCP-over-ACP before the
+                // response makes its way back to the client.
+                let mut conductor_tx = self.conductor_tx.clone();
+                agent_cx.send_request(request).on_receiving_result(
+                    async move |result: Result<InitializeResponse, _>| {
+                        if let Ok(response) = &result {
+                            conductor_tx
+                                .send(ConductorMessage::AgentInitialized {
+                                    mcp_acp_transport: response
+                                        .has_meta_capability(McpAcpTransport),
+                                })
+                                .await
+                                .map_err(sacp::util::internal_error)?;
+                        }
+                        request_cx
+                            .respond_with_result_via(&conductor_tx, result)
+                            .await
+                    },
+                )
+            })
+            .await
             .if_request(async |mut request: NewSessionRequest, request_cx| {
-                // When forwarding "session/new" to the agent,
-                // we adjust MCP servers to manage "acp:" URLs.
-                for mcp_server in &mut request.mcp_servers {
-                    self.bridge_listeners
-                        .transform_mcp_server(
-                            conductor_cx.clone(),
-                            mcp_server,
-                            &self.conductor_tx,
-                            &self.mcp_bridge_mode,
-                        )
-                        .await?;
+                // When forwarding "session/new" to an agent that does not support
+                // MCP-over-ACP, we adjust MCP servers to manage "acp:" URLs.
+                // Agents that do support it get the "acp:" URLs unchanged and
+                // exchange `_mcp/*` messages with the owning proxy directly.
+                if self.agent_mcp_acp_transport {
+                    return agent_cx
+                        .send_request(request)
+                        .forward_response_via(&self.conductor_tx, request_cx);
+                }
+
+                // A server that cannot be bridged fails the request, not the conductor.
+                let acp_urls = match self
+                    .bridge_listeners
+                    .transform_mcp_servers(
+                        conductor_cx.clone(),
+                        &mut request.mcp_servers,
+                        &self.conductor_tx,
+                        &self.mcp_bridge_mode,
+                    )
+                    .await
+                {
+                    Ok(acp_urls) => acp_urls,
+                    Err(err) => return request_cx.respond_with_error(err),
+                };
+                self.forward_bridged_session_request(
+                    &agent_cx,
+                    request,
+                    request_cx,
+                    acp_urls,
+                    |response| response.session_id.clone(),
+                )
+            })
+            .await
+            .if_request(async |mut request: LoadSessionRequest, request_cx| {
+                // "session/load" carries MCP servers too, so they are bridged the same way.
+                if self.agent_mcp_acp_transport {
+                    return agent_cx
+                        .send_request(request)
+                        .forward_response_via(&self.conductor_tx, request_cx);
                 }
 
-                agent_cx
-                    .send_request(request)
-                    .forward_response_via(&self.conductor_tx, request_cx)
+                let acp_urls = match self
+                    .bridge_listeners
+                    .transform_mcp_servers(
+                        conductor_cx.clone(),
+                        &mut request.mcp_servers,
+                        &self.conductor_tx,
+                        &self.mcp_bridge_mode,
+                    )
+                    .await
+                {
+                    Ok(acp_urls) => acp_urls,
+                    Err(err) => return request_cx.respond_with_error(err),
+                };
+                let session_id = request.session_id.clone();
+                self.forward_bridged_session_request(
+                    &agent_cx,
+                    request,
+                    request_cx,
+                    acp_urls,
+                    move |_response| session_id,
+                )
             })
             .await
             .if_request(
                 async |request: McpOverAcpMessage<UntypedMessage>, request_cx| {
+                    // Agents with native MCP-over-ACP support handle these themselves.
+                    if self.agent_mcp_acp_transport {
+                        return Ok(Handled::No {
+                            message: (request, request_cx),
+                            retry: false,
+                        });
+                    }
+
                     let McpOverAcpMessage {
                         connection_id,
                         message: mcp_request,
@@ -1060,11 +1186,20 @@ where
                             ))
                         })?
                         .send(MessageCx::Request(mcp_request, request_cx))
-                        .await
+                        .await?;
+                    Ok(Handled::Yes)
                 },
             )
             .await
             .if_notification(async |notification: McpOverAcpMessage<UntypedMessage>| {
+                // Agents with native MCP-over-ACP support handle these themselves.
+                if self.agent_mcp_acp_transport {
+                    return Ok(Handled::No {
+                        message: notification,
+                        retry: false,
+                    });
+                }
+
                 let McpOverAcpMessage {
                     connection_id,
                     message: mcp_notification,
@@ -1079,7 +1214,8 @@ where
                         ))
                     })?
                     .send(MessageCx::Notification(mcp_notification))
-                    .await
+                    .await?;
+                Ok(Handled::Yes)
             })
             .await
             .otherwise(async |message| {
@@ -1088,6 +1224,34 @@ where
             })
             .await
     }
+
+    /// Forward a `session/new` or `session/load` request whose `acp:` MCP servers were
+    /// bridged, reporting the agent's response via [`ConductorMessage::McpBridgeSessionResponse`]
+    /// so that the bridges can be closed once no session uses them.
+    fn forward_bridged_session_request<Req: JrRequest>(
+        &self,
+        agent_cx: &JrConnectionCx<ConductorToAgent>,
+        request: Req,
+        request_cx: JrRequestCx<Req::Response>,
+        acp_urls: Vec<String>,
+        session_id: impl FnOnce(&Req::Response) -> SessionId + Send + 'static,
+    ) -> Result<(), sacp::Error> {
+        let mut conductor_tx = self.conductor_tx.clone();
+        agent_cx
+            .send_request(request)
+            .on_receiving_result(async move |result| {
+                conductor_tx
+                    .send(ConductorMessage::McpBridgeSessionResponse {
+                        acp_urls,
+                        session_id: result.as_ref().ok().map(session_id),
+                    })
+                    .await
+                    .map_err(sacp::util::internal_error)?;
+                request_cx
+                    .respond_with_result_via(&conductor_tx, result)
+                    .await
+            })
+    }
 }
 
 /// Identifies the source of an agent-to-client message.
@@ -1353,6 +1517,9 @@ pub enum ConductorMessage {
     /// The request must be sent back over ACP to receive the connection-id.
     /// Once the connection-id is received, the actor must be spawned.
     McpConnectionEstablished {
+        /// The acp:$UUID URL identifying this bridge
+        acp_url: String,
+
         response: McpConnectResponse,
 
         /// The actor that should be spawned once the connection-id is available.
@@ -1376,6 +1543,28 @@ pub enum ConductorMessage {
         notification: McpDisconnectNotification,
     },
 
+    /// The agent responded to a `session/new` or `session/load` request
+    /// whose `acp:` MCP servers were bridged.
+    ///
+    /// Sent before the response is forwarded, so that it is processed before
+    /// any later request for the same session.
+    McpBridgeSessionResponse {
+        /// The `acp:` URLs of the bridged MCP servers.
+        acp_urls: Vec<String>,
+
+        /// The session now using those bridges, or `None` if the request failed.
+        session_id: Option<SessionId>,
+    },
+
+    /// The agent responded to `initialize`.
+    ///
+    /// Sent before the response is forwarded, so that it is processed before
+    /// any `session/new` request that follows.
+    AgentInitialized {
+        /// Whether the agent advertised the `mcp_acp_transport` capability.
+        mcp_acp_transport: bool,
+    },
+
     /// Forward a response back to a request context.
     ///
     /// This variant avoids a subtle race condition by preserving the
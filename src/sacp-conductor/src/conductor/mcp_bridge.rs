@@ -2,12 +2,13 @@ pub mod actor;
 pub mod http;
 pub mod stdio;
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
 
-use futures::{SinkExt, channel::mpsc};
-use sacp::schema::{McpServer, McpServerHttp, McpServerStdio};
-use sacp::{self, JrLink};
+use futures::channel::{mpsc, oneshot};
+use futures::{SinkExt, future::Either};
+use sacp::schema::{EnvVariable, HttpHeader, McpServer, McpServerHttp, McpServerStdio, SessionId};
+use sacp::{self, BoxFuture, JrLink};
 use sacp::{JrConnectionCx, MessageCx};
 use tokio::net::TcpListener;
 use tracing::info;
@@ -16,6 +17,12 @@ pub use self::actor::McpBridgeConnectionActor;
 use crate::conductor::ConductorMessage;
 
 /// Maintains bridges for MCP message routing.
+///
+/// Each bridge is shared by the sessions whose MCP servers include its `acp:` URL.
+/// When the last of those sessions goes away, the bridge's listener is stopped and
+/// its open connections are closed. ACP has no message that ends a session, so a
+/// session stops using its bridges when it is loaded again with a different set of
+/// MCP servers, and all bridges are stopped when the conductor shuts down.
 #[derive(Default)]
 pub struct McpBridgeListeners {
     /// Mapping of acp:$UUID URLs to TCP bridge information for MCP message routing
@@ -23,10 +30,23 @@ pub struct McpBridgeListeners {
 }
 
 /// Information about an MCP bridge that is listening for connections from MCP clients.
-#[derive(Clone, Debug)]
+#[derive(Debug)]
 pub(super) struct McpBridgeListener {
     /// The replacement MCP server
     pub server: McpServer,
+
+    /// Sessions whose MCP servers include this bridge.
+    sessions: HashSet<SessionId>,
+
+    /// Number of `session/new` or `session/load` requests using this bridge
+    /// that are still awaiting a response from the agent.
+    pending_requests: usize,
+
+    /// Connections that MCP clients have made through this bridge.
+    connection_ids: HashSet<String>,
+
+    /// Stops the listener task when dropped.
+    _stop_tx: oneshot::Sender<()>,
 }
 
 /// Connection handle for sending messages to an MCP client.
@@ -50,37 +70,76 @@ impl McpBridgeConnection {
 }
 
 impl McpBridgeListeners {
-    /// Transforms MCP servers with `acp:$UUID` URLs for agents that need bridging.
+    /// Transforms the MCP servers of a `session/new` or `session/load` request
+    /// for agents that need bridging.
+    ///
+    /// Returns the `acp:` URLs that were bridged. Each counts as a pending request
+    /// for its bridge until the agent's response is reported to
+    /// [`session_started`](Self::session_started) or [`request_failed`](Self::request_failed).
+    /// If any server cannot be bridged, the bridges counted so far are released again.
+    pub async fn transform_mcp_servers(
+        &mut self,
+        cx: JrConnectionCx<impl JrLink>,
+        mcp_servers: &mut [McpServer],
+        conductor_tx: &mpsc::Sender<ConductorMessage>,
+        mcp_bridge_mode: &crate::McpBridgeMode,
+    ) -> Result<Vec<String>, sacp::Error> {
+        let mut acp_urls = vec![];
+        for mcp_server in mcp_servers {
+            match self
+                .transform_mcp_server(cx.clone(), mcp_server, conductor_tx, mcp_bridge_mode)
+                .await
+            {
+                Ok(Some(acp_url)) => {
+                    if let Some(listener) = self.listeners.get_mut(&acp_url) {
+                        listener.pending_requests += 1;
+                    }
+                    acp_urls.push(acp_url);
+                }
+                Ok(None) => {}
+                Err(err) => {
+                    // The agent never learns of bridges spawned for this request,
+                    // so the ones closed here have no connections to report.
+                    self.request_failed(&acp_urls);
+                    return Err(err);
+                }
+            }
+        }
+        Ok(acp_urls)
+    }
+
+    /// Transforms an MCP server with an `acp:$UUID` URL for agents that need bridging.
     ///
-    /// For each MCP server with an `acp:` URL:
+    /// For an MCP server with an `acp:` URL:
     /// 1. Spawns a TCP listener on an ephemeral port
     /// 2. Stores the mapping for message routing
     /// 3. Transforms the server to use either stdio or HTTP transport depending on bridge mode
     ///
-    /// Other MCP servers are left unchanged.
-    pub async fn transform_mcp_server(
+    /// Returns the `acp:` URL if the server was transformed; other MCP servers are left unchanged.
+    async fn transform_mcp_server(
         &mut self,
         cx: JrConnectionCx<impl JrLink>,
         mcp_server: &mut McpServer,
         conductor_tx: &mpsc::Sender<ConductorMessage>,
         mcp_bridge_mode: &crate::McpBridgeMode,
-    ) -> Result<(), sacp::Error> {
-        use sacp::schema::McpServer;
-
+    ) -> Result<Option<String>, sacp::Error> {
         let McpServer::Http(http) = mcp_server else {
-            return Ok(());
+            return Ok(None);
         };
 
         if !http.url.starts_with("acp:") {
-            return Ok(());
+            return Ok(None);
         }
 
         if !http.headers.is_empty() {
-            return Err(sacp::Error::internal_error());
+            return Err(sacp::Error::invalid_params().data(format!(
+                "MCP server `{}` has an `acp:` URL; headers on `acp:` servers are not supported",
+                http.name
+            )));
         }
 
         let name = &http.name;
-        let url = &http.url;
+        let url = http.url.clone();
 
         info!(
             server_name = name,
@@ -90,10 +149,78 @@ impl McpBridgeListeners {
 
         // Create oneshot channel for session_id delivery
         let transformed = self
-            .spawn_bridge(cx, name, url, conductor_tx, mcp_bridge_mode)
+            .spawn_bridge(cx, name, &url, conductor_tx, mcp_bridge_mode)
             .await?;
         *mcp_server = transformed;
-        Ok(())
+        Ok(Some(url))
+    }
+
+    /// Record that the agent accepted a `session/new` or `session/load` request
+    /// for `session_id` whose bridged MCP servers were `acp_urls`.
+    ///
+    /// The session stops using any other bridges, as its MCP servers have been replaced.
+    /// Returns the connections of bridges that are no longer used, which should be closed.
+    pub fn session_started(&mut self, session_id: &SessionId, acp_urls: &[String]) -> Vec<String> {
+        for acp_url in acp_urls {
+            if let Some(listener) = self.listeners.get_mut(acp_url) {
+                listener.pending_requests -= 1;
+                listener.sessions.insert(session_id.clone());
+            }
+        }
+        for (acp_url, listener) in &mut self.listeners {
+            if !acp_urls.contains(acp_url) {
+                listener.sessions.remove(session_id);
+            }
+        }
+        self.close_unused()
+    }
+
+    /// Record that the agent rejected a `session/new` or `session/load` request
+    /// whose bridged MCP servers were `acp_urls`.
+    ///
+    /// Returns the connections of bridges that are no longer used, which should be closed.
+    pub fn request_failed(&mut self, acp_urls: &[String]) -> Vec<String> {
+        for acp_url in acp_urls {
+            if let Some(listener) = self.listeners.get_mut(acp_url) {
+                listener.pending_requests -= 1;
+            }
+        }
+        self.close_unused()
+    }
+
+    /// Record a connection made through the bridge for `acp_url`.
+    ///
+    /// Returns false if that bridge has been closed in the meantime,
+    /// in which case the connection should be closed as well.
+    pub fn add_connection(&mut self, acp_url: &str, connection_id: &str) -> bool {
+        match self.listeners.get_mut(acp_url) {
+            Some(listener) => {
+                listener.connection_ids.insert(connection_id.to_string());
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Forget a connection that was closed by its MCP client.
+    pub fn remove_connection(&mut self, connection_id: &str) {
+        for listener in self.listeners.values_mut() {
+            listener.connection_ids.remove(connection_id);
+        }
+    }
+
+    /// Stop the bridges that no session uses, returning their open connections.
+    fn close_unused(&mut self) -> Vec<String> {
+        let mut connection_ids = vec![];
+        self.listeners.retain(|acp_url, listener| {
+            let in_use = !listener.sessions.is_empty() || listener.pending_requests > 0;
+            if !in_use {
+                info!(acp_url, "Closing unused MCP bridge");
+                connection_ids.extend(listener.connection_ids.drain());
+            }
+            in_use
+        });
+        connection_ids
     }
 
     /// Spawn a bridge listener (HTTP or stdio) for an MCP server with ACP transport
@@ -110,57 +237,118 @@ impl McpBridgeListeners {
             return Ok(listener.server.clone());
         }
 
-        // Bind to ephemeral port
-        let tcp_listener = TcpListener::bind("127.0.0.1:0").await?;
-        let tcp_port = tcp_listener.local_addr()?.port();
-
-        info!(acp_url = acp_url, tcp_port, "Bound listener for MCP bridge");
-
-        let new_server = match mcp_bridge_mode {
-            crate::McpBridgeMode::Stdio { conductor_command } => McpServer::Stdio(
-                McpServerStdio::new(
-                    server_name.to_string(),
-                    PathBuf::from(&conductor_command[0]),
-                )
-                .args(
-                    conductor_command[1..]
-                        .iter()
-                        .cloned()
-                        .chain(vec!["mcp".to_string(), format!("{tcp_port}")])
-                        .collect::<Vec<_>>(),
-                ),
-            ),
-
-            crate::McpBridgeMode::Http => McpServer::Http(McpServerHttp::new(
-                server_name.to_string(),
-                format!("http://localhost:{tcp_port}"),
-            )),
-        };
+        // Any local process can connect to the listener, so connections must present
+        // this secret, which only the agent learns (from the rewritten MCP server).
+        // The stdio bridge gets it through its environment rather than its arguments,
+        // as arguments are visible to other users.
+        let auth_token = uuid::Uuid::new_v4().to_string();
+
+        // Bind the listener and work out how the agent reaches it
+        let (new_server, run_listener): (McpServer, BoxFuture<'static, Result<(), sacp::Error>>) =
+            match mcp_bridge_mode {
+                crate::McpBridgeMode::Stdio { conductor_command } => {
+                    let tcp_listener = TcpListener::bind("127.0.0.1:0").await?;
+                    let tcp_port = tcp_listener.local_addr()?.port();
+                    info!(acp_url, tcp_port, "Bound TCP listener for MCP bridge");
+
+                    (
+                        stdio_bridge_server(
+                            server_name,
+                            conductor_command,
+                            vec![format!("{tcp_port}")],
+                            &auth_token,
+                        ),
+                        Box::pin(stdio::run_listener(
+                            tcp_listener,
+                            acp_url.to_string(),
+                            auth_token,
+                            conductor_tx.clone(),
+                        )),
+                    )
+                }
+
+                #[cfg(unix)]
+                crate::McpBridgeMode::UnixSocket { conductor_command } => {
+                    let socket_dir = SocketDir::create()?;
+                    let unix_listener = socket_dir.bind()?;
+                    info!(acp_url, socket = %socket_dir.socket_path().display(), "Bound Unix socket for MCP bridge");
+
+                    (
+                        stdio_bridge_server(
+                            server_name,
+                            conductor_command,
+                            vec![
+                                "--socket".to_string(),
+                                socket_dir.socket_path().display().to_string(),
+                            ],
+                            &auth_token,
+                        ),
+                        Box::pin({
+                            let run_listener = stdio::run_listener(
+                                unix_listener,
+                                acp_url.to_string(),
+                                auth_token,
+                                conductor_tx.clone(),
+                            );
+                            async move {
+                                // Remove the socket once we stop listening
+                                let _socket_dir = socket_dir;
+                                run_listener.await
+                            }
+                        }),
+                    )
+                }
+
+                crate::McpBridgeMode::Http => {
+                    let tcp_listener = TcpListener::bind("127.0.0.1:0").await?;
+                    let tcp_port = tcp_listener.local_addr()?.port();
+                    info!(acp_url, tcp_port, "Bound TCP listener for MCP bridge");
+
+                    (
+                        McpServer::Http(
+                            McpServerHttp::new(
+                                server_name.to_string(),
+                                format!("http://localhost:{tcp_port}"),
+                            )
+                            .headers(vec![HttpHeader::new(
+                                "Authorization",
+                                format!("Bearer {auth_token}"),
+                            )]),
+                        ),
+                        Box::pin(http::run_http_listener(
+                            tcp_listener,
+                            acp_url.to_string(),
+                            auth_token,
+                            conductor_tx.clone(),
+                        )),
+                    )
+                }
+            };
 
         // remember for later
+        let (stop_tx, stop_rx) = oneshot::channel();
         self.listeners.insert(
             acp_url.to_string(),
             McpBridgeListener {
                 server: new_server.clone(),
+                sessions: Default::default(),
+                pending_requests: 0,
+                connection_ids: Default::default(),
+                _stop_tx: stop_tx,
             },
         );
 
         cx.spawn({
             let acp_url = acp_url.to_string();
-            let conductor_tx = conductor_tx.clone();
-            let mcp_bridge_mode = mcp_bridge_mode.clone();
             async move {
-                info!(
-                    acp_url = acp_url,
-                    tcp_port, "now accepting bridge connections"
-                );
-
-                match mcp_bridge_mode {
-                    crate::McpBridgeMode::Stdio {
-                        conductor_command: _,
-                    } => stdio::run_tcp_listener(tcp_listener, acp_url, conductor_tx).await,
-                    crate::McpBridgeMode::Http => {
-                        http::run_http_listener(tcp_listener, acp_url, conductor_tx).await
+                info!(acp_url = acp_url, "now accepting bridge connections");
+
+                // Run until the listener is removed from `McpBridgeListeners`
+                match futures::future::select(run_listener, stop_rx).await {
+                    Either::Left((result, _)) => result,
+                    Either::Right(_) => {
+                        info!(acp_url, "MCP bridge listener stopped");
+                        Ok(())
                     }
                 }
             }
@@ -169,3 +357,84 @@ impl McpBridgeListeners {
         Ok(new_server)
     }
 }
+
+/// The stdio MCP server that runs `conductor mcp` with the given connection arguments.
+fn stdio_bridge_server(
+    server_name: &str,
+    conductor_command: &[String],
+    connection_args: Vec<String>,
+    auth_token: &str,
+) -> McpServer {
+    McpServer::Stdio(
+        McpServerStdio::new(
+            server_name.to_string(),
+            PathBuf::from(&conductor_command[0]),
+        )
+        .args(
+            conductor_command[1..]
+                .iter()
+                .cloned()
+                .chain(Some("mcp".to_string()))
+                .chain(connection_args)
+                .collect::<Vec<_>>(),
+        )
+        .env(vec![EnvVariable::new(
+            crate::mcp_bridge::MCP_BRIDGE_TOKEN_ENV,
+            auth_token,
+        )]),
+    )
+}
+
+/// A private directory holding the Unix socket of a bridge, removed when dropped.
+///
+/// The directory is readable only by the current user, so other users cannot
+/// reach the socket regardless of the umask it was created under.
+#[cfg(unix)]
+struct SocketDir {
+    path: PathBuf,
+}
+
+#[cfg(unix)]
+impl SocketDir {
+    /// Create a fresh directory under the system temporary directory.
+    fn create() -> std::io::Result<Self> {
+        use std::os::unix::fs::DirBuilderExt;
+
+        let path = std::env::temp_dir().join(format!("sacp-mcp-{}", uuid::Uuid::new_v4()));
+        std::fs::DirBuilder::new().mode(0o700).create(&path)?;
+        Ok(SocketDir { path })
+    }
+
+    fn socket_path(&self) -> PathBuf {
+        self.path.join("bridge.sock")
+    }
+
+    /// Bind a listener on the socket, accessible only to the current user.
+    fn bind(&self) -> std::io::Result<tokio::net::UnixListener> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let listener = tokio::net::UnixListener::bind(self.socket_path())?;
+        std::fs::set_permissions(self.socket_path(), std::fs::Permissions::from_mode(0o600))?;
+        Ok(listener)
+    }
+}
+
+#[cfg(unix)]
+impl Drop for SocketDir {
+    fn drop(&mut self) {
+        if let Err(error) = std::fs::remove_dir_all(&self.path) {
+            tracing::warn!(path = %self.path.display(), %error, "failed to remove MCP bridge socket");
+        }
+    }
+}
+
+/// Compare a token presented by a bridge client against the expected one,
+/// taking the same time regardless of where they differ.
+fn is_valid_token(expected: &str, presented: &str) -> bool {
+    expected.len() == presented.len()
+        && expected
+            .bytes()
+            .zip(presented.bytes())
+            .fold(0, |difference, (a, b)| difference | (a ^ b))
+            == 0
+}
@@ -1,27 +1,40 @@
 use axum::{
     Router,
-    extract::State,
-    http::StatusCode,
+    extract::{Request, State},
+    http::{HeaderMap, HeaderName, HeaderValue, StatusCode, header::AUTHORIZATION},
+    middleware::{self, Next},
     response::{IntoResponse, Response, Sse},
     routing::post,
 };
-use futures::{SinkExt, StreamExt as _, channel::mpsc, future::Either, stream::Stream};
-use futures_concurrency::future::FutureExt as _;
+use futures::{
+    SinkExt, StreamExt as _,
+    channel::{mpsc, oneshot},
+    future::Either,
+};
 use futures_concurrency::stream::StreamExt as _;
 use fxhash::FxHashMap;
 use sacp::{BoxFuture, Channel, Component, jsonrpcmsg::Message};
-use std::{collections::VecDeque, pin::pin, sync::Arc};
+use std::{
+    collections::VecDeque,
+    fmt,
+    pin::pin,
+    sync::{Arc, Mutex},
+};
 use tokio::net::TcpListener;
 
 use crate::conductor::{
     ConductorMessage,
-    mcp_bridge::{McpBridgeConnection, McpBridgeConnectionActor},
+    mcp_bridge::{McpBridgeConnection, McpBridgeConnectionActor, is_valid_token},
 };
 
 /// Runs an HTTP listener for MCP bridge connections
+///
+/// Requests must carry `Authorization: Bearer <auth_token>`; others are rejected
+/// with `401 Unauthorized`.
 pub async fn run_http_listener(
     tcp_listener: TcpListener,
     acp_url: String,
+    auth_token: String,
     mut conductor_tx: mpsc::Sender<ConductorMessage>,
 ) -> Result<(), sacp::Error> {
     let (to_mcp_client_tx, to_mcp_client_rx) = mpsc::channel(128);
@@ -34,7 +47,7 @@ pub async fn run_http_listener(
         .send(ConductorMessage::McpConnectionReceived {
             acp_url: acp_url,
             actor: McpBridgeConnectionActor::new(
-                HttpMcpBridge::new(tcp_listener),
+                HttpMcpBridge::new(tcp_listener, auth_token),
                 conductor_tx.clone(),
                 to_mcp_client_rx,
             ),
@@ -49,12 +62,18 @@ pub async fn run_http_listener(
 /// A component that receives HTTP requests/responses using the HTTP transport defined by the MCP protocol.
 struct HttpMcpBridge {
     listener: tokio::net::TcpListener,
+
+    /// The bearer token that clients must present.
+    auth_token: String,
 }
 
 impl HttpMcpBridge {
     /// Creates a new HTTP-MCP bridge from an existing TCP listener.
-    fn new(listener: tokio::net::TcpListener) -> Self {
-        Self { listener }
+    fn new(listener: tokio::net::TcpListener, auth_token: String) -> Self {
+        Self {
+            listener,
+            auth_token,
+        }
     }
 }
 
@@ -72,7 +91,10 @@ impl<L: sacp::link::JrLink> sacp::Component<L> for HttpMcpBridge {
         Self: Sized,
     {
         let (channel_a, channel_b) = Channel::duplex();
-        (channel_a, Box::pin(run(self.listener, channel_b)))
+        (
+            channel_a,
+            Box::pin(run(self.listener, self.auth_token, channel_b)),
+        )
     }
 }
 
@@ -94,12 +116,30 @@ impl IntoResponse for HttpError {
     }
 }
 
+/// Header through which the server assigns, and the client echoes, the MCP session id.
+const MCP_SESSION_ID: HeaderName = HeaderName::from_static("mcp-session-id");
+
+/// Header with which a client resumes an SSE stream after the last event it received.
+const LAST_EVENT_ID: HeaderName = HeaderName::from_static("last-event-id");
+
+/// How many sent events we remember so that they can be replayed to resumed streams.
+const REPLAY_BUFFER_LEN: usize = 256;
+
 /// Run a webserver listening on `listener` for HTTP requests at `/`
 /// and communicating those requests over `channel` to the JSON-RPC server.
-async fn run(listener: TcpListener, channel: Channel) -> Result<(), sacp::Error> {
+async fn run(
+    listener: TcpListener,
+    auth_token: String,
+    channel: Channel,
+) -> Result<(), sacp::Error> {
     let (registration_tx, registration_rx) = mpsc::unbounded();
+    let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
 
-    let state = BridgeState { registration_tx };
+    let state = Arc::new(BridgeState {
+        registration_tx,
+        auth_token,
+        mcp_session: Mutex::new(McpSession::Uninitialized),
+    });
 
     // The way that the MCP protocol works is a bit "special".
     //
@@ -112,25 +152,138 @@ async fn run(listener: TcpListener, channel: Channel) -> Result<(), sacp::Error>
     // Non-reply messages can be send to any open stream (POST, GET, etc) but must be sent to
     // exactly one.
     //
-    // There are provisions for "resuming" from a blocked point by tagging each message in the SSE stream
-    // with an id, but we are not implementing that because I am lazy.
-    async {
+    // Each message in an SSE stream is tagged with an event id. A client whose stream
+    // broke can GET a new one with `Last-Event-ID`, and we replay what it missed.
+    //
+    // The response to `initialize` assigns an `Mcp-Session-Id`, which the client must
+    // send with every later request. A `DELETE` with that header ends the session;
+    // the bridge keeps serving, and a new `initialize` starts a fresh session.
+    let serve = async {
         let app = Router::new()
-            .route("/", post(handle_post).get(handle_get))
-            .with_state(Arc::new(state));
+            .route("/", post(handle_post).get(handle_get).delete(handle_delete))
+            .route_layer(middleware::from_fn_with_state(
+                state.clone(),
+                require_authorization,
+            ))
+            .with_state(state);
 
         axum::serve(listener, app)
+            .with_graceful_shutdown(async move {
+                let _ = shutdown_rx.await;
+            })
             .await
             .map_err(sacp::util::internal_error)
-    }
-    .race(RunningServer::new().run(channel, registration_rx))
-    .await
+    };
+
+    let running = async move {
+        let result = RunningServer::new().run(channel, registration_rx).await;
+
+        // Finish in-flight responses before we stop serving.
+        drop(shutdown_tx);
+        result
+    };
+
+    futures::try_join!(serve, running).map(|((), ())| ())
 }
 
 /// The state we pass to our POST/GET handlers.
 struct BridgeState {
     /// Where to send registration messages.
     registration_tx: mpsc::UnboundedSender<HttpMessage>,
+
+    /// The bearer token that clients must present.
+    auth_token: String,
+
+    /// The MCP session negotiated through the `Mcp-Session-Id` header.
+    mcp_session: Mutex<McpSession>,
+}
+
+/// State of the MCP session that a bridge is currently serving.
+///
+/// Sessions are served one after another: once the client ends one with `DELETE`,
+/// the next `initialize` starts a new one.
+enum McpSession {
+    /// No `initialize` request has been received yet.
+    Uninitialized,
+
+    /// Initialized with the given session id.
+    Active(String),
+
+    /// The client ended the session with `DELETE` and has not started a new one.
+    Terminated,
+}
+
+/// Why a request was refused, as returned to the client.
+type Rejection = (StatusCode, &'static str);
+
+impl BridgeState {
+    /// Start the MCP session in response to an `initialize` request, returning its id.
+    fn start_session(&self) -> Result<String, Rejection> {
+        let mut mcp_session = self.mcp_session.lock().unwrap();
+        match *mcp_session {
+            McpSession::Uninitialized | McpSession::Terminated => {
+                let session_id = uuid::Uuid::new_v4().to_string();
+                *mcp_session = McpSession::Active(session_id.clone());
+                Ok(session_id)
+            }
+            McpSession::Active(_) => {
+                Err((StatusCode::BAD_REQUEST, "MCP session already initialized"))
+            }
+        }
+    }
+
+    /// Check that a request carries the id of the MCP session.
+    ///
+    /// Requests made before `initialize` are accepted without one.
+    fn check_session(&self, headers: &HeaderMap) -> Result<(), Rejection> {
+        let presented = headers
+            .get(MCP_SESSION_ID)
+            .and_then(|value| value.to_str().ok());
+
+        match (&*self.mcp_session.lock().unwrap(), presented) {
+            (McpSession::Uninitialized, _) => Ok(()),
+            (McpSession::Active(session_id), Some(presented)) if session_id == presented => Ok(()),
+            (McpSession::Active(_), None) => {
+                Err((StatusCode::BAD_REQUEST, "missing Mcp-Session-Id header"))
+            }
+            (McpSession::Active(_), Some(_)) | (McpSession::Terminated, _) => {
+                Err((StatusCode::NOT_FOUND, "unknown MCP session"))
+            }
+        }
+    }
+
+    /// End the MCP session identified by the request's `Mcp-Session-Id` header.
+    fn terminate_session(&self, headers: &HeaderMap) -> Result<(), Rejection> {
+        self.check_session(headers)?;
+
+        let mut mcp_session = self.mcp_session.lock().unwrap();
+        if !matches!(*mcp_session, McpSession::Active(_)) {
+            return Err((StatusCode::NOT_FOUND, "no MCP session to terminate"));
+        }
+        *mcp_session = McpSession::Terminated;
+        Ok(())
+    }
+}
+
+/// Reject requests that do not carry the bridge's bearer token.
+async fn require_authorization(
+    State(state): State<Arc<BridgeState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let authorized = request
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|token| is_valid_token(&state.auth_token, token));
+
+    if !authorized {
+        tracing::warn!("rejected unauthenticated MCP bridge request");
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+
+    next.run(request).await
 }
 
 /// Messages from HTTP handlers to the bridge server.
@@ -140,7 +293,7 @@ enum HttpMessage {
     Request {
         http_request_id: uuid::Uuid,
         request: sacp::jsonrpcmsg::Request,
-        response_tx: mpsc::UnboundedSender<sacp::jsonrpcmsg::Message>,
+        response_tx: mpsc::UnboundedSender<SseEvent>,
     },
     /// A JSON-RPC notification (no id, no response expected)
     Notification {
@@ -155,8 +308,46 @@ enum HttpMessage {
     /// A GET request to open an SSE stream for server-initiated messages
     Get {
         http_request_id: uuid::Uuid,
-        response_tx: mpsc::UnboundedSender<sacp::jsonrpcmsg::Message>,
+        /// The `Last-Event-ID` of the stream being resumed, if any
+        last_event_id: Option<EventId>,
+        response_tx: mpsc::UnboundedSender<SseEvent>,
     },
+    /// A DELETE request ending the current MCP session
+    Terminate { http_request_id: uuid::Uuid },
+}
+
+/// Identifies an event that we sent on an SSE stream.
+///
+/// Formatted as `<stream id>/<sequence number>`, where the sequence number
+/// counts all events sent by the bridge.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+struct EventId {
+    stream_id: uuid::Uuid,
+    seq: u64,
+}
+
+impl EventId {
+    /// Parse an event id as sent back to us in `Last-Event-ID`.
+    fn parse(s: &str) -> Option<Self> {
+        let (stream_id, seq) = s.split_once('/')?;
+        Some(EventId {
+            stream_id: stream_id.parse().ok()?,
+            seq: seq.parse().ok()?,
+        })
+    }
+}
+
+impl fmt::Display for EventId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.stream_id, self.seq)
+    }
+}
+
+/// A JSON-RPC message to be sent as an SSE event.
+#[derive(Clone, Debug)]
+struct SseEvent {
+    id: EventId,
+    message: sacp::jsonrpcmsg::Message,
 }
 
 /// Clone of `sacp::jsonrpcmsg::Id` since for unfathomable reasons that does not impl Hash
@@ -184,6 +375,7 @@ struct RunningServer {
     waiting_sessions: FxHashMap<JsonRpcId, RegisteredSession>,
     general_sessions: Vec<RegisteredSession>,
     message_deque: VecDeque<sacp::jsonrpcmsg::Message>,
+    replay_buffer: ReplayBuffer,
 }
 
 impl RunningServer {
@@ -192,6 +384,7 @@ impl RunningServer {
             waiting_sessions: Default::default(),
             general_sessions: Default::default(),
             message_deque: VecDeque::with_capacity(32),
+            replay_buffer: Default::default(),
         }
     }
 
@@ -222,7 +415,7 @@ impl RunningServer {
             match message {
                 MultiplexMessage::FromHttpToChannel(http_message) => {
                     self.handle_http_message(http_message, &mut channel.tx)
-                        .await?
+                        .await?;
                 }
 
                 MultiplexMessage::FromChannelToHttp(message) => {
@@ -249,7 +442,7 @@ impl RunningServer {
         Ok(())
     }
 
-    /// Handle an incoming HTTP message (request, notification, response, or GET).
+    /// Handle an incoming HTTP message (request, notification, response, GET, or DELETE).
     async fn handle_http_message(
         &mut self,
         message: HttpMessage,
@@ -305,6 +498,31 @@ impl RunningServer {
 
             HttpMessage::Get {
                 http_request_id,
+                last_event_id: Some(last_event_id),
+                response_tx,
+            } => {
+                // Resuming a stream: it keeps its id, and first receives the events it missed
+                let session = RegisteredSession::resume(last_event_id.stream_id, response_tx);
+                let mut replayed = 0;
+                for event in self.replay_buffer.events_after(last_event_id) {
+                    if session.outgoing_tx.unbounded_send(event.clone()).is_err() {
+                        break;
+                    }
+                    replayed += 1;
+                }
+                tracing::debug!(
+                    %http_request_id,
+                    session_id = %session.id,
+                    %last_event_id,
+                    replayed,
+                    "handling GET (resuming SSE stream)"
+                );
+                self.general_sessions.push(session);
+            }
+
+            HttpMessage::Get {
+                http_request_id,
+                last_event_id: None,
                 response_tx,
             } => {
                 let session = RegisteredSession::new(response_tx);
@@ -317,6 +535,17 @@ impl RunningServer {
                 // Register as a general session to receive server-initiated messages
                 self.general_sessions.push(session);
             }
+
+            HttpMessage::Terminate { http_request_id } => {
+                tracing::debug!(%http_request_id, "MCP session terminated by client");
+
+                // Close the session's streams and forget what was sent on them;
+                // the next session starts with a clean slate.
+                self.waiting_sessions.clear();
+                self.general_sessions.clear();
+                self.message_deque.clear();
+                self.replay_buffer.clear();
+            }
         }
 
         // Purge closed sessions for good hygiene
@@ -385,20 +614,19 @@ impl RunningServer {
             if let Some(session) = self.waiting_sessions.remove(message_id) {
                 tracing::debug!(session_id = %session.id, "found waiting session, attempting send");
 
-                match session.outgoing_tx.unbounded_send(message) {
+                match self.replay_buffer.send(&session, message) {
                     // Successfully sent the message, return
-                    Ok(()) => {
+                    None => {
                         tracing::debug!(session_id = %session.id, "sent to waiting session");
                         return Ok(None);
                     }
 
                     // If the sender died, just recover the message and send it to anyone.
-                    Err(m) => {
+                    Some(m) => {
                         tracing::debug!(session_id = %session.id, "waiting session disconnected");
                         // If that sender is dead, remove them from the list
                         // and recover the message.
-                        assert!(m.is_disconnected());
-                        message = m.into_inner();
+                        message = m;
                     }
                 }
             }
@@ -417,16 +645,15 @@ impl RunningServer {
             .chain(self.waiting_sessions.values_mut());
         for session in all_sessions {
             tracing::trace!(session_id = %session.id, "trying session");
-            match session.outgoing_tx.unbounded_send(message) {
-                Ok(()) => {
+            match self.replay_buffer.send(session, message) {
+                None => {
                     tracing::debug!(session_id = %session.id, "sent to session");
                     return Ok(None);
                 }
 
-                Err(m) => {
+                Some(m) => {
                     tracing::debug!(session_id = %session.id, "session disconnected, trying next");
-                    assert!(m.is_disconnected());
-                    message = m.into_inner();
+                    message = m;
                 }
             }
         }
@@ -446,17 +673,97 @@ impl RunningServer {
 }
 
 struct RegisteredSession {
+    /// Identifies the SSE stream; part of the id of every event sent on it.
     id: uuid::Uuid,
-    outgoing_tx: mpsc::UnboundedSender<sacp::jsonrpcmsg::Message>,
+    outgoing_tx: mpsc::UnboundedSender<SseEvent>,
 }
 
 impl RegisteredSession {
-    fn new(outgoing_tx: mpsc::UnboundedSender<sacp::jsonrpcmsg::Message>) -> Self {
-        Self {
-            id: uuid::Uuid::new_v4(),
-            outgoing_tx,
+    fn new(outgoing_tx: mpsc::UnboundedSender<SseEvent>) -> Self {
+        Self::resume(uuid::Uuid::new_v4(), outgoing_tx)
+    }
+
+    /// Continue the stream `id` on a new connection.
+    fn resume(id: uuid::Uuid, outgoing_tx: mpsc::UnboundedSender<SseEvent>) -> Self {
+        Self { id, outgoing_tx }
+    }
+}
+
+/// The most recent events we sent, kept so that they can be replayed
+/// to a client that resumes a stream with `Last-Event-ID`.
+#[derive(Default)]
+struct ReplayBuffer {
+    next_seq: u64,
+    events: VecDeque<SseEvent>,
+}
+
+impl ReplayBuffer {
+    /// Send `message` as the next event on `session`'s stream.
+    /// If the stream is closed, gives the message back.
+    fn send(
+        &mut self,
+        session: &RegisteredSession,
+        message: sacp::jsonrpcmsg::Message,
+    ) -> Option<sacp::jsonrpcmsg::Message> {
+        let event = SseEvent {
+            id: EventId {
+                stream_id: session.id,
+                seq: self.next_seq,
+            },
+            message,
+        };
+
+        match session.outgoing_tx.unbounded_send(event.clone()) {
+            Ok(()) => {
+                self.next_seq += 1;
+                if self.events.len() == REPLAY_BUFFER_LEN {
+                    self.events.pop_front();
+                }
+                self.events.push_back(event);
+                None
+            }
+
+            Err(e) => {
+                assert!(e.is_disconnected());
+                Some(e.into_inner().message)
+            }
         }
     }
+
+    /// Forget all remembered events.
+    ///
+    /// Sequence numbers keep counting, so event ids are never reused.
+    fn clear(&mut self) {
+        self.events.clear();
+    }
+
+    /// The remembered events sent on the same stream as `last_event_id`, after it.
+    fn events_after(&self, last_event_id: EventId) -> impl Iterator<Item = &SseEvent> {
+        self.events.iter().filter(move |event| {
+            event.id.stream_id == last_event_id.stream_id && event.id.seq > last_event_id.seq
+        })
+    }
+}
+
+/// Respond with an SSE stream of the events sent to `rx`.
+fn sse_response(
+    http_request_id: uuid::Uuid,
+    mut rx: mpsc::UnboundedReceiver<SseEvent>,
+) -> Response {
+    let stream = async_stream::stream! {
+        while let Some(event) = rx.next().await {
+            tracing::debug!(%http_request_id, event_id = %event.id, "sending SSE event");
+            match axum::response::sse::Event::default()
+                .id(event.id.to_string())
+                .json_data(event.message)
+            {
+                Ok(v) => yield Ok(v),
+                Err(e) => yield Err(HttpError::from(e)),
+            }
+        }
+        tracing::debug!(%http_request_id, "SSE stream completed");
+    };
+    Sse::new(stream).into_response()
 }
 
 /// Accept a POST request carrying a JSON-RPC message from an MCP client.
@@ -464,6 +771,7 @@ impl RegisteredSession {
 /// For notifications/responses (messages without id), we return 202 Accepted.
 async fn handle_post(
     State(state): State<Arc<BridgeState>>,
+    headers: HeaderMap,
     body: String,
 ) -> Result<Response, HttpError> {
     let http_request_id = uuid::Uuid::new_v4();
@@ -472,11 +780,27 @@ async fn handle_post(
     let message: sacp::jsonrpcmsg::Message =
         serde_json::from_str(&body).map_err(sacp::util::parse_error)?;
 
+    // `initialize` starts the MCP session; everything else must belong to it
+    let mcp_session_id = match &message {
+        Message::Request(request) if request.method == "initialize" => {
+            match state.start_session() {
+                Ok(mcp_session_id) => Some(mcp_session_id),
+                Err(rejection) => return Ok(rejection.into_response()),
+            }
+        }
+        _ => {
+            if let Err(rejection) = state.check_session(&headers) {
+                return Ok(rejection.into_response());
+            }
+            None
+        }
+    };
+
     match message {
         Message::Request(request) if request.id.is_some() => {
             tracing::debug!(%http_request_id, method = %request.method, "POST request received");
             // Request with id - return SSE stream for response
-            let (tx, mut rx) = mpsc::unbounded();
+            let (tx, rx) = mpsc::unbounded();
             state
                 .registration_tx
                 .unbounded_send(HttpMessage::Request {
@@ -486,17 +810,14 @@ async fn handle_post(
                 })
                 .map_err(sacp::util::internal_error)?;
 
-            let stream = async_stream::stream! {
-                while let Some(message) = rx.next().await {
-                    tracing::debug!(%http_request_id, "sending SSE event");
-                    match axum::response::sse::Event::default().json_data(message) {
-                        Ok(v) => yield Ok(v),
-                        Err(e) => yield Err(HttpError::from(e)),
-                    }
-                }
-                tracing::debug!(%http_request_id, "SSE stream completed");
-            };
-            Ok(Sse::new(stream).into_response())
+            let mut response = sse_response(http_request_id, rx);
+            if let Some(mcp_session_id) = mcp_session_id {
+                response.headers_mut().insert(
+                    MCP_SESSION_ID,
+                    HeaderValue::from_str(&mcp_session_id).map_err(sacp::util::internal_error)?,
+                );
+            }
+            Ok(response)
         }
 
         Message::Request(request) => {
@@ -528,32 +849,53 @@ async fn handle_post(
 }
 
 /// Accept a GET request from an MCP client.
-/// Opens an SSE stream for server-initiated messages.
+/// Opens an SSE stream for server-initiated messages,
+/// or resumes one if the request carries `Last-Event-ID`.
 async fn handle_get(
     State(state): State<Arc<BridgeState>>,
-) -> Result<Sse<impl Stream<Item = Result<axum::response::sse::Event, HttpError>>>, HttpError> {
+    headers: HeaderMap,
+) -> Result<Response, HttpError> {
     let http_request_id = uuid::Uuid::new_v4();
     tracing::debug!(%http_request_id, "GET request received");
 
-    let (tx, mut rx) = mpsc::unbounded();
+    if let Err(rejection) = state.check_session(&headers) {
+        return Ok(rejection.into_response());
+    }
+
+    let last_event_id = headers
+        .get(LAST_EVENT_ID)
+        .and_then(|value| value.to_str().ok())
+        .and_then(EventId::parse);
+
+    let (tx, rx) = mpsc::unbounded();
     state
         .registration_tx
         .unbounded_send(HttpMessage::Get {
             http_request_id,
+            last_event_id,
             response_tx: tx,
         })
         .map_err(sacp::util::internal_error)?;
 
-    let stream = async_stream::stream! {
-        while let Some(message) = rx.next().await {
-            tracing::debug!(%http_request_id, "sending SSE event");
-            match axum::response::sse::Event::default().json_data(message) {
-                Ok(v) => yield Ok(v),
-                Err(e) => yield Err(HttpError::from(e)),
-            }
-        }
-        tracing::debug!(%http_request_id, "SSE stream completed");
-    };
+    Ok(sse_response(http_request_id, rx))
+}
+
+/// Accept a DELETE request from an MCP client, ending the current MCP session.
+async fn handle_delete(
+    State(state): State<Arc<BridgeState>>,
+    headers: HeaderMap,
+) -> Result<Response, HttpError> {
+    let http_request_id = uuid::Uuid::new_v4();
+    tracing::debug!(%http_request_id, "DELETE request received");
+
+    if let Err(rejection) = state.terminate_session(&headers) {
+        return Ok(rejection.into_response());
+    }
+
+    state
+        .registration_tx
+        .unbounded_send(HttpMessage::Terminate { http_request_id })
+        .map_err(sacp::util::internal_error)?;
 
-    Ok(Sse::new(stream))
+    Ok(StatusCode::OK.into_response())
 }
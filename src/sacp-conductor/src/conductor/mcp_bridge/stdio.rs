@@ -1,48 +1,154 @@
-use futures::{SinkExt, channel::mpsc};
+use std::time::Duration;
+
+use futures::{SinkExt, StreamExt, channel::mpsc, stream::FuturesUnordered};
 use sacp::MessageCx;
-use tokio::net::{TcpListener, TcpStream};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite};
+use tokio::net::TcpListener;
+#[cfg(unix)]
+use tokio::net::UnixListener;
 use tokio_util::compat::{TokioAsyncReadCompatExt as _, TokioAsyncWriteCompatExt as _};
 
 use crate::conductor::ConductorMessage;
 
-use super::{McpBridgeConnection, McpBridgeConnectionActor};
+use super::{McpBridgeConnection, McpBridgeConnectionActor, is_valid_token};
+
+/// How long a new connection has to present its token.
+const AUTHENTICATION_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Upper bound on the length of the token line, so that a misbehaving
+/// client cannot make us buffer indefinitely.
+const MAX_TOKEN_LINE_LEN: usize = 256;
+
+/// A listener that the stdio bridge process can connect to.
+pub trait BridgeListener: Send + 'static {
+    /// The stream for an accepted connection.
+    type Stream: AsyncRead + AsyncWrite + Unpin + Send + 'static;
+
+    /// Accept the next connection, returning it along with a description
+    /// of the peer for logging.
+    fn accept_connection(
+        &self,
+    ) -> impl Future<Output = std::io::Result<(Self::Stream, String)>> + Send;
+}
+
+impl BridgeListener for TcpListener {
+    type Stream = tokio::net::TcpStream;
+
+    async fn accept_connection(&self) -> std::io::Result<(Self::Stream, String)> {
+        let (stream, addr) = self.accept().await?;
+        Ok((stream, addr.to_string()))
+    }
+}
+
+#[cfg(unix)]
+impl BridgeListener for UnixListener {
+    type Stream = tokio::net::UnixStream;
+
+    async fn accept_connection(&self) -> std::io::Result<(Self::Stream, String)> {
+        let (stream, _addr) = self.accept().await?;
+        // Peers of a listening socket are unnamed; their process is what identifies them.
+        let peer = match stream.peer_cred() {
+            Ok(cred) => format!("pid {:?}", cred.pid()),
+            Err(_) => "unknown peer".to_string(),
+        };
+        Ok((stream, peer))
+    }
+}
 
-/// Runs the stdio bridge TCP listener, accepting connections and creating bridge actors for each.
+/// Runs the stdio bridge listener, accepting connections and creating bridge actors for each.
+///
+/// Loops indefinitely, accepting incoming connections (over TCP or a Unix socket) and
+/// spawning an MCP bridge actor for each connection to handle bidirectional message
+/// forwarding between the MCP client and the conductor.
 ///
-/// Loops indefinitely, accepting incoming TCP connections and spawning an MCP bridge actor
-/// for each connection to handle bidirectional message forwarding between the MCP client
-/// and the conductor.
-pub async fn run_tcp_listener(
-    tcp_listener: TcpListener,
+/// Each connection must first send `auth_token` on a line of its own (see
+/// [`run_mcp_bridge`](crate::mcp_bridge::run_mcp_bridge)); connections that do not
+/// are dropped.
+pub async fn run_listener(
+    listener: impl BridgeListener,
     acp_url: String,
+    auth_token: String,
     mut conductor_tx: mpsc::Sender<ConductorMessage>,
 ) -> Result<(), sacp::Error> {
-    // Accept connections
+    let mut authentications = FuturesUnordered::new();
+
+    // Accept connections, authenticating them concurrently
     loop {
-        let (stream, _addr) = tcp_listener
-            .accept()
-            .await
-            .map_err(sacp::Error::into_internal_error)?;
-
-        let (to_mcp_client_tx, to_mcp_client_rx) = mpsc::channel(128);
-
-        conductor_tx
-            .send(ConductorMessage::McpConnectionReceived {
-                acp_url: acp_url.clone(),
-                actor: make_stdio_actor(stream, conductor_tx.clone(), to_mcp_client_rx),
-                connection: McpBridgeConnection::new(to_mcp_client_tx),
-            })
-            .await
-            .map_err(|_| sacp::Error::internal_error())?;
+        tokio::select! {
+            accepted = listener.accept_connection() => {
+                let (stream, peer) = accepted.map_err(sacp::Error::into_internal_error)?;
+                let auth_token = auth_token.clone();
+                authentications.push(async move {
+                    (authenticate(stream, &auth_token).await, peer)
+                });
+            }
+
+            Some((authenticated, peer)) = authentications.next() => {
+                let stream = match authenticated {
+                    Ok(stream) => stream,
+                    Err(error) => {
+                        tracing::warn!(%peer, %error, "rejected unauthenticated MCP bridge connection");
+                        continue;
+                    }
+                };
+
+                let (to_mcp_client_tx, to_mcp_client_rx) = mpsc::channel(128);
+
+                conductor_tx
+                    .send(ConductorMessage::McpConnectionReceived {
+                        acp_url: acp_url.clone(),
+                        actor: make_stdio_actor(stream, conductor_tx.clone(), to_mcp_client_rx),
+                        connection: McpBridgeConnection::new(to_mcp_client_tx),
+                    })
+                    .await
+                    .map_err(|_| sacp::Error::internal_error())?;
+            }
+        }
     }
 }
 
+/// Read the token line that a bridge client sends first, returning the stream if it is valid.
+///
+/// The line is read one byte at a time so that no MCP messages following it are buffered.
+async fn authenticate<S: AsyncRead + Unpin>(
+    mut stream: S,
+    auth_token: &str,
+) -> Result<S, sacp::Error> {
+    let read_token_line = async {
+        let mut line = Vec::new();
+        loop {
+            match stream
+                .read_u8()
+                .await
+                .map_err(sacp::Error::into_internal_error)?
+            {
+                b'\n' => return Ok(line),
+                _ if line.len() >= MAX_TOKEN_LINE_LEN => {
+                    return Err(sacp::util::internal_error("token line too long"));
+                }
+                byte => line.push(byte),
+            }
+        }
+    };
+
+    let line = tokio::time::timeout(AUTHENTICATION_TIMEOUT, read_token_line)
+        .await
+        .map_err(|_| sacp::util::internal_error("timed out waiting for token"))??;
+
+    let presented = String::from_utf8_lossy(&line);
+    if !is_valid_token(auth_token, presented.trim_end_matches('\r')) {
+        return Err(sacp::util::internal_error("invalid token"));
+    }
+
+    Ok(stream)
+}
+
 fn make_stdio_actor(
-    stream: TcpStream,
+    stream: impl AsyncRead + AsyncWrite + Send + 'static,
     conductor_tx: mpsc::Sender<ConductorMessage>,
     to_mcp_client_rx: mpsc::Receiver<MessageCx>,
 ) -> McpBridgeConnectionActor {
-    let (read_half, write_half) = stream.into_split();
+    let (read_half, write_half) = tokio::io::split(stream);
 
     // Establish bidirectional JSON-RPC connection
     // The bridge will send MCP requests (tools/call, etc.) to the conductor
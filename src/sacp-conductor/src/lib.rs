@@ -83,6 +83,11 @@ mod conductor;
 mod debug_logger;
 /// MCP bridge functionality for TCP-based MCP servers
 mod mcp_bridge;
+/// Replaying components recorded in traces
+pub mod replay;
+/// Assertions on traces for integration tests
+#[cfg(feature = "test-support")]
+pub mod test_support;
 /// Trace event types for sequence diagram viewer
 pub mod trace;
 
@@ -92,9 +97,9 @@ use clap::{Parser, Subcommand};
 
 use sacp::link::{AgentToClient, ProxyToConductor};
 use sacp::schema::InitializeRequest;
-use sacp_tokio::{AcpAgent, Stdio};
+use sacp_tokio::{AcpAgent, StderrPolicy, Stdio};
 use tracing::Instrument;
-use tracing_subscriber::{EnvFilter, layer::SubscriberExt, util::SubscriberInitExt};
+use tracing_subscriber::{EnvFilter, Layer, layer::SubscriberExt, util::SubscriberInitExt};
 
 /// Wrapper for command-line component lists that can serve as either
 /// proxies-only (for proxy mode) or proxies+agent (for agent mode).
@@ -180,10 +185,59 @@ pub enum McpBridgeMode {
         conductor_command: Vec<String>,
     },
 
+    /// Use stdio-based MCP bridge with a conductor subprocess that connects
+    /// back over a Unix domain socket rather than a TCP port.
+    ///
+    /// For environments that do not allow binding TCP. Each socket lives in its
+    /// own directory under the system temporary directory, accessible only to
+    /// the current user.
+    #[cfg(unix)]
+    UnixSocket {
+        /// Command and args to spawn conductor MCP bridge processes.
+        /// E.g., vec!["conductor"] or vec!["cargo", "run", "-p", "conductor", "--"]
+        conductor_command: Vec<String>,
+    },
+
     /// Use HTTP-based MCP bridge
     Http,
 }
 
+/// MCP bridge modes selectable from the command line; see [`McpBridgeMode`].
+#[derive(clap::ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum McpBridgeKind {
+    /// Agents connect to the conductor over HTTP on a localhost port
+    #[default]
+    Http,
+
+    /// Agents spawn `conductor mcp`, which connects to a localhost port
+    Stdio,
+
+    /// Agents spawn `conductor mcp`, which connects to a Unix domain socket
+    #[cfg(unix)]
+    Unix,
+}
+
+impl McpBridgeKind {
+    /// The bridge mode to use, spawning this executable for stdio bridges.
+    fn to_mode(self) -> Result<McpBridgeMode, sacp::Error> {
+        let conductor_command = || -> Result<Vec<String>, sacp::Error> {
+            let exe = std::env::current_exe().map_err(sacp::Error::into_internal_error)?;
+            Ok(vec![exe.display().to_string()])
+        };
+
+        Ok(match self {
+            McpBridgeKind::Http => McpBridgeMode::Http,
+            McpBridgeKind::Stdio => McpBridgeMode::Stdio {
+                conductor_command: conductor_command()?,
+            },
+            #[cfg(unix)]
+            McpBridgeKind::Unix => McpBridgeMode::UnixSocket {
+                conductor_command: conductor_command()?,
+            },
+        })
+    }
+}
+
 impl Default for McpBridgeMode {
     fn default() -> Self {
         McpBridgeMode::Http
@@ -202,7 +256,7 @@ pub struct ConductorArgs {
     pub debug_dir: Option<PathBuf>,
 
     /// Set log level (e.g., "trace", "debug", "info", "warn", "error", or module-specific like "conductor=debug")
-    /// Only applies when --debug is enabled
+    /// for the debug log (with --debug) and the trace (with --trace or --serve)
     #[arg(long)]
     pub log: Option<String>,
 
@@ -216,6 +270,49 @@ pub struct ConductorArgs {
     #[arg(long)]
     pub serve: bool,
 
+    /// Redact trace events with rules suited to sharing traces: MCP server and terminal
+    /// env and header values, file contents, bearer tokens and API keys; strings over 4 KiB
+    /// are truncated.
+    #[arg(long)]
+    pub redact_trace: bool,
+
+    /// Redact the value at a JSON pointer in message params and payloads, optionally only
+    /// for one method (e.g. "/mcpServers/*/env" or "session/prompt:/prompt/*/text").
+    /// A "*" segment matches every key or element. Can be repeated.
+    #[arg(long, value_name = "[METHOD:]POINTER")]
+    pub trace_redact: Vec<String>,
+
+    /// Replace matches of a regex in every string of every trace event. Can be repeated.
+    #[arg(long, value_name = "REGEX")]
+    pub trace_scrub: Vec<regex::Regex>,
+
+    /// Truncate strings in trace events longer than this many bytes.
+    #[arg(long, value_name = "BYTES")]
+    pub trace_max_string: Option<usize>,
+
+    /// Start a new trace file when the current one would exceed this many bytes,
+    /// keeping earlier ones as e.g. trace.1.jsons, trace.2.jsons.
+    #[arg(long, value_name = "BYTES", requires = "trace")]
+    pub trace_max_bytes: Option<u64>,
+
+    /// Number of rotated trace files to keep.
+    #[arg(
+        long,
+        value_name = "FILES",
+        default_value_t = 4,
+        requires = "trace_max_bytes"
+    )]
+    pub trace_keep: usize,
+
+    /// How agents that cannot speak MCP-over-ACP reach MCP servers provided by proxies.
+    #[arg(long, value_enum, default_value_t)]
+    pub mcp_bridge: McpBridgeKind,
+
+    /// Refuse messages larger than this many bytes, from components and from the client.
+    /// Oversized requests get an error response; other oversized messages are dropped.
+    #[arg(long, value_name = "BYTES")]
+    pub max_message_size: Option<usize>,
+
     #[command(subcommand)]
     pub command: ConductorCommand,
 }
@@ -242,10 +339,15 @@ pub enum ConductorCommand {
         proxies: Vec<String>,
     },
 
-    /// Run as MCP bridge connecting stdio to TCP
+    /// Run as MCP bridge connecting stdio to the conductor
     Mcp {
         /// TCP port to connect to on localhost
-        port: u16,
+        #[arg(required_unless_present = "socket", conflicts_with = "socket")]
+        port: Option<u16>,
+
+        /// Unix domain socket to connect to instead of a TCP port
+        #[arg(long)]
+        socket: Option<PathBuf>,
     },
 }
 
@@ -276,32 +378,10 @@ impl ConductorArgs {
             None
         };
 
-        if let Some(debug_logger) = &debug_logger {
-            // Set up log level from --log flag, defaulting to "info"
-            let log_level = self.log.as_deref().unwrap_or("info");
-
-            // Set up tracing to write to the debug file with "C !" prefix
-            let tracing_writer = debug_logger.create_tracing_writer();
-            tracing_subscriber::registry()
-                .with(EnvFilter::new(log_level))
-                .with(
-                    tracing_subscriber::fmt::layer()
-                        .with_target(true)
-                        .with_writer(move || tracing_writer.clone()),
-                )
-                .init();
-
-            tracing::info!(pid = %pid, cwd = %cwd, level = %log_level, "Conductor starting with debug logging");
-        };
-
         // Set up tracing based on --trace and --serve flags
         let (trace_writer, _viewer_server) = match (&self.trace, self.serve) {
             // --trace only: write to file
-            (Some(trace_path), false) => {
-                let writer = trace::TraceWriter::from_path(trace_path)
-                    .map_err(|e| anyhow::anyhow!("Failed to create trace writer: {}", e))?;
-                (Some(writer), None)
-            }
+            (Some(trace_path), false) => (Some(self.file_trace_writer(trace_path)?), None),
             // --serve only: in-memory with viewer
             (None, true) => {
                 let (handle, server) = sacp_trace_viewer::serve_memory(
@@ -313,8 +393,7 @@ impl ConductorArgs {
             }
             // --trace --serve: write to file and serve it
             (Some(trace_path), true) => {
-                let writer = trace::TraceWriter::from_path(trace_path)
-                    .map_err(|e| anyhow::anyhow!("Failed to create trace writer: {}", e))?;
+                let writer = self.file_trace_writer(trace_path)?;
                 let server = sacp_trace_viewer::serve_file(
                     trace_path.clone(),
                     sacp_trace_viewer::TraceViewerConfig::default(),
@@ -324,6 +403,29 @@ impl ConductorArgs {
             // Neither: no tracing
             (None, false) => (None, None),
         };
+        let trace_writer = trace_writer.map(|writer| writer.with_redaction(self.trace_redaction()));
+
+        // Send our own logs to the debug file with "C !" prefix, and to the trace
+        let log_level = self.log.as_deref().unwrap_or("info");
+        let debug_layer = debug_logger.as_ref().map(|debug_logger| {
+            let tracing_writer = debug_logger.create_tracing_writer();
+            tracing_subscriber::fmt::layer()
+                .with_target(true)
+                .with_writer(move || tracing_writer.clone())
+                .with_filter(EnvFilter::new(log_level))
+        });
+        let trace_layer = trace_writer
+            .clone()
+            .map(|writer| trace::TraceLayer::new(writer).with_filter(EnvFilter::new(log_level)));
+        if debug_layer.is_some() || trace_layer.is_some() {
+            tracing_subscriber::registry()
+                .with(debug_layer)
+                .with(trace_layer)
+                .init();
+        }
+        if debug_logger.is_some() {
+            tracing::info!(pid = %pid, cwd = %cwd, level = %log_level, "Conductor starting with debug logging");
+        }
 
         self.run(debug_logger.as_ref(), trace_writer)
             .instrument(tracing::info_span!("conductor", pid = %pid, cwd = %cwd))
@@ -331,6 +433,41 @@ impl ConductorArgs {
             .map_err(|err| anyhow::anyhow!("{err}"))
     }
 
+    fn file_trace_writer(
+        &self,
+        trace_path: &std::path::Path,
+    ) -> anyhow::Result<trace::TraceWriter> {
+        let writer = match self.trace_max_bytes {
+            Some(max_bytes) => trace::RotatingWriter::new(trace_path, max_bytes)
+                .map(|rotating| trace::TraceWriter::new(rotating.keep(self.trace_keep))),
+            None => trace::TraceWriter::from_path(trace_path),
+        };
+        writer.map_err(|e| anyhow::anyhow!("Failed to create trace writer: {}", e))
+    }
+
+    fn trace_redaction(&self) -> trace::Redaction {
+        let mut redaction = if self.redact_trace {
+            trace::Redaction::recommended()
+        } else {
+            trace::Redaction::new()
+        };
+        for rule in &self.trace_redact {
+            redaction = match rule.split_once(":/") {
+                Some((method, pointer)) if !rule.starts_with('/') => {
+                    redaction.redact_method(method, &format!("/{pointer}"))
+                }
+                _ => redaction.redact(rule),
+            };
+        }
+        for pattern in &self.trace_scrub {
+            redaction = redaction.scrub(pattern.clone(), trace::REDACTED);
+        }
+        if let Some(max) = self.trace_max_string {
+            redaction = redaction.max_string_len(max);
+        }
+        redaction
+    }
+
     async fn run(
         self,
         debug_logger: Option<&debug_logger::DebugLogger>,
@@ -338,26 +475,45 @@ impl ConductorArgs {
     ) -> Result<(), sacp::Error> {
         match self.command {
             ConductorCommand::Agent { name, components } => {
+                let mcp_mode = self.mcp_bridge.to_mode()?;
                 initialize_conductor(
                     debug_logger,
                     trace_writer,
+                    self.max_message_size,
                     name,
                     components,
-                    |name, providers, mcp_mode| Conductor::new_agent(name, providers, mcp_mode),
+                    true,
+                    |name, providers| Conductor::new_agent(name, providers, mcp_mode),
                 )
                 .await
             }
             ConductorCommand::Proxy { name, proxies } => {
+                let mcp_mode = self.mcp_bridge.to_mode()?;
                 initialize_conductor(
                     debug_logger,
                     trace_writer,
+                    self.max_message_size,
                     name,
                     proxies,
-                    |name, providers, mcp_mode| Conductor::new_proxy(name, providers, mcp_mode),
+                    false,
+                    |name, providers| Conductor::new_proxy(name, providers, mcp_mode),
                 )
                 .await
             }
-            ConductorCommand::Mcp { port } => mcp_bridge::run_mcp_bridge(port).await,
+            ConductorCommand::Mcp { port, socket } => {
+                let address = match (port, socket) {
+                    (Some(port), _) => mcp_bridge::BridgeAddress::Tcp(port),
+                    #[cfg(unix)]
+                    (None, Some(socket)) => mcp_bridge::BridgeAddress::Unix(socket),
+                    _ => {
+                        return Err(sacp::util::internal_error(
+                            "`mcp` needs a port or a Unix socket to connect to",
+                        ));
+                    }
+                };
+                let auth_token = std::env::var(mcp_bridge::MCP_BRIDGE_TOKEN_ENV).ok();
+                mcp_bridge::run_mcp_bridge(address, auth_token).await
+            }
         }
     }
 }
@@ -365,39 +521,79 @@ impl ConductorArgs {
 async fn initialize_conductor<Link: ConductorLink>(
     debug_logger: Option<&debug_logger::DebugLogger>,
     trace_writer: Option<trace::TraceWriter>,
+    max_message_size: Option<usize>,
     name: String,
     components: Vec<String>,
-    new_conductor: impl FnOnce(String, CommandLineComponents, crate::McpBridgeMode) -> Conductor<Link>,
+    ends_with_agent: bool,
+    new_conductor: impl FnOnce(String, CommandLineComponents) -> Conductor<Link>,
 ) -> Result<(), sacp::Error> {
     // Parse agents and optionally wrap with debug callbacks
+    let count = components.len();
     let providers: Vec<AcpAgent> = components
         .into_iter()
         .enumerate()
         .map(|(i, s)| {
-            let mut agent = AcpAgent::from_str(&s)?;
+            let component = if ends_with_agent && i + 1 == count {
+                "agent".to_string()
+            } else {
+                format!("proxy:{i}")
+            };
+            let mut agent = AcpAgent::from_str(&s)?.with_stderr_policy(stderr_policy(
+                component,
+                debug_logger,
+                &trace_writer,
+            ));
             if let Some(logger) = debug_logger {
                 agent = agent.with_debug(logger.create_callback(i.to_string()));
             }
+            if let Some(bytes) = max_message_size {
+                agent = agent.with_max_message_size(bytes);
+            }
             Ok(agent)
         })
         .collect::<Result<Vec<_>, sacp::Error>>()?;
 
     // Create Stdio component with optional debug logging
-    let stdio = if let Some(logger) = debug_logger {
+    let mut stdio = if let Some(logger) = debug_logger {
         Stdio::new().with_debug(logger.create_callback("C".to_string()))
     } else {
         Stdio::new()
     };
+    if let Some(bytes) = max_message_size {
+        stdio = stdio.with_max_message_size(bytes);
+    }
 
     // Create conductor with optional trace writer
-    let mut conductor = new_conductor(name, CommandLineComponents(providers), Default::default());
+    let mut conductor = new_conductor(name, CommandLineComponents(providers));
     if let Some(writer) = trace_writer {
         conductor = conductor.with_trace_writer(writer);
     }
 
-    conductor
-        .into_connection_builder()
-        .connect_to(stdio)?
-        .serve()
-        .await
+    let mut builder = conductor.into_connection_builder();
+    if let Some(bytes) = max_message_size {
+        builder = builder.max_message_size(bytes);
+    }
+    builder.connect_to(stdio)?.serve().await
+}
+
+/// How the stderr of the component named `component` (as it appears in traces) is forwarded.
+///
+/// Each line becomes a trace log event when tracing is enabled. Otherwise it is emitted as a
+/// `tracing` event, unless the debug logger is already recording stderr itself. (With a trace
+/// writer, the conductor's own `tracing` events are recorded too, so emitting both would
+/// record every line twice.)
+fn stderr_policy(
+    component: String,
+    debug_logger: Option<&debug_logger::DebugLogger>,
+    trace_writer: &Option<trace::TraceWriter>,
+) -> StderrPolicy {
+    let mut policy = StderrPolicy::new()
+        .component(component)
+        .tracing(debug_logger.is_none() && trace_writer.is_none());
+    if let Some(writer) = trace_writer.clone() {
+        policy = policy.on_line(move |line| {
+            writer.trace_log(line.component, line.level.into(), line.text, None);
+        });
+    }
+    policy
 }
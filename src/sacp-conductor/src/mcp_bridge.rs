@@ -2,25 +2,96 @@
 //!
 //! This module implements `conductor mcp $port` mode, which acts as an MCP server
 //! over stdio but forwards all messages to/from a TCP connection on localhost:$port.
+//! With `conductor mcp --socket $path`, it connects to a Unix domain socket instead.
 //!
 //! The main conductor (in agent mode) listens on the TCP port and translates between
 //! TCP (raw JSON-RPC) and ACP `_mcp/*` extension messages.
 
+use std::fmt;
+use std::future::Future;
+#[cfg(unix)]
+use std::path::PathBuf;
+
 use anyhow::Context;
 use serde_json::Value;
-use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
 use tokio::net::TcpStream;
 
-/// Run the MCP bridge: stdio ↔ TCP
+/// Environment variable through which the conductor passes the bridge's secret token.
+///
+/// The conductor sets this when it rewrites an `acp:` MCP server into a stdio
+/// server running `conductor mcp $port`.
+pub const MCP_BRIDGE_TOKEN_ENV: &str = "SACP_CONDUCTOR_MCP_TOKEN";
+
+/// Where the conductor is listening for the bridge.
+#[derive(Debug, Clone)]
+pub enum BridgeAddress {
+    /// A TCP port on localhost
+    Tcp(u16),
+
+    /// A Unix domain socket
+    #[cfg(unix)]
+    Unix(PathBuf),
+}
+
+impl fmt::Display for BridgeAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BridgeAddress::Tcp(port) => write!(f, "localhost:{port}"),
+            #[cfg(unix)]
+            BridgeAddress::Unix(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+/// Run the MCP bridge: stdio ↔ TCP (or Unix socket)
 ///
 /// Reads MCP JSON-RPC messages from stdin, forwards to TCP connection.
 /// Reads responses from TCP, writes to stdout.
-pub async fn run_mcp_bridge(port: u16) -> Result<(), sacp::Error> {
-    tracing::info!("MCP bridge starting, connecting to localhost:{}", port);
+///
+/// The conductor only accepts connections that begin with the bridge's token
+/// on a line of its own, so `auth_token` is sent before any messages.
+pub async fn run_mcp_bridge(
+    address: BridgeAddress,
+    auth_token: Option<String>,
+) -> Result<(), sacp::Error> {
+    tracing::info!("MCP bridge starting, connecting to {address}");
+
+    // Connect to the main conductor
+    match &address {
+        BridgeAddress::Tcp(port) => {
+            let stream =
+                connect_with_retry(&address, || TcpStream::connect(format!("127.0.0.1:{port}")))
+                    .await?;
+            bridge_stdio(stream, auth_token).await
+        }
 
-    // Connect to the main conductor via TCP
-    let stream = connect_with_retry(port).await?;
-    let (tcp_read, mut tcp_write) = stream.into_split();
+        #[cfg(unix)]
+        BridgeAddress::Unix(path) => {
+            let stream =
+                connect_with_retry(&address, || tokio::net::UnixStream::connect(path)).await?;
+            bridge_stdio(stream, auth_token).await
+        }
+    }
+}
+
+/// Forward messages between stdio and the connection to the conductor until either closes.
+async fn bridge_stdio(
+    stream: impl AsyncRead + AsyncWrite,
+    auth_token: Option<String>,
+) -> Result<(), sacp::Error> {
+    let (tcp_read, mut tcp_write) = tokio::io::split(stream);
+
+    // Authenticate ourselves to the conductor
+    match auth_token {
+        Some(auth_token) => tcp_write
+            .write_all(format!("{auth_token}\n").as_bytes())
+            .await
+            .context("Failed to send token to TCP")?,
+        None => tracing::warn!(
+            "{MCP_BRIDGE_TOKEN_ENV} is not set; the conductor will reject this connection"
+        ),
+    }
 
     // Set up stdio
     let stdin = tokio::io::stdin();
@@ -91,15 +162,21 @@ pub async fn run_mcp_bridge(port: u16) -> Result<(), sacp::Error> {
     Ok(())
 }
 
-/// Connect to TCP port with retry logic
-async fn connect_with_retry(port: u16) -> Result<TcpStream, sacp::Error> {
+/// Connect to the conductor with retry logic
+async fn connect_with_retry<S, F>(
+    address: &BridgeAddress,
+    connect: impl Fn() -> F,
+) -> Result<S, sacp::Error>
+where
+    F: Future<Output = std::io::Result<S>>,
+{
     let max_retries = 10;
     let mut retry_delay_ms = 50;
 
     for attempt in 1..=max_retries {
-        match TcpStream::connect(format!("127.0.0.1:{}", port)).await {
+        match connect().await {
             Ok(stream) => {
-                tracing::info!("Connected to localhost:{} on attempt {}", port, attempt);
+                tracing::info!("Connected to {} on attempt {}", address, attempt);
                 return Ok(stream);
             }
             Err(e) if attempt < max_retries => {
@@ -0,0 +1,605 @@
+//! Replaying a component recorded in a conductor trace.
+//!
+//! A [`ReplayAgent`] stands in for one member of a recorded chain, usually the
+//! agent. It expects the requests and notifications that member received from
+//! its predecessor, in the recorded order, and answers with the responses,
+//! notifications and requests the member sent back. This makes it possible to
+//! test proxies against a real agent session without running the agent.
+//!
+//! ```no_run
+//! # use sacp_conductor::{Conductor, ProxiesAndAgent, replay::ReplayAgent};
+//! # async fn example(proxy: impl sacp::Component<sacp::link::ProxyToConductor> + 'static) -> Result<(), sacp::Error> {
+//! let agent = ReplayAgent::from_path("session.jsons").map_err(sacp::Error::into_internal_error)?;
+//! let progress = agent.progress();
+//! let conductor = Conductor::new_agent(
+//!     "conductor".to_string(),
+//!     ProxiesAndAgent::new(agent).proxy(proxy),
+//!     Default::default(),
+//! );
+//! // ... drive the conductor with the recorded client's prompts ...
+//! assert!(progress.is_finished(), "{:?}", progress.divergence());
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Incoming messages are matched by method and params. Params are compared after
+//! removing the parts that differ from run to run, `/cwd`, `/mcpServers` and
+//! `/_meta` by default (see [`ReplayAgent::ignore_param`]). The first message that
+//! does not match is a divergence: it is answered with an error and recorded in
+//! the [`ReplayProgress`]. From then on every request is answered with the same
+//! error, and the replay ends with it when the connection closes.
+//!
+//! MCP traffic is not replayed, so tools a proxy provides are not called.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use futures::StreamExt;
+use futures::channel::mpsc;
+use sacp::link::AgentToClient;
+use sacp::{Component, JrConnectionCx, JrRequestCx, JrResponse, MessageCx, UntypedMessage};
+
+use crate::trace::{Protocol, TraceEvent};
+
+/// Params removed before comparing incoming messages, unless configured otherwise.
+const DEFAULT_IGNORED_PARAMS: &[&str] = &["/cwd", "/mcpServers", "/_meta"];
+
+/// Acts as a recorded component by replaying its side of a conductor trace.
+///
+/// See the [module documentation](self) for how messages are matched.
+pub struct ReplayAgent {
+    events: Vec<TraceEvent>,
+    component: String,
+    ignored_params: Vec<String>,
+    progress: ReplayProgress,
+}
+
+impl ReplayAgent {
+    /// Replay the agent recorded in `events`.
+    pub fn new(events: impl IntoIterator<Item = TraceEvent>) -> Self {
+        Self {
+            events: events.into_iter().collect(),
+            component: "agent".to_string(),
+            ignored_params: DEFAULT_IGNORED_PARAMS
+                .iter()
+                .map(|p| p.to_string())
+                .collect(),
+            progress: ReplayProgress::default(),
+        }
+    }
+
+    /// Replay the agent recorded in a `.jsons` trace file.
+    ///
+    /// Lines that are not trace events, such as one that was still being
+    /// written, are skipped with a warning.
+    pub fn from_path(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let text = std::fs::read_to_string(path)?;
+        let mut events = Vec::new();
+        for (index, line) in text.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str::<TraceEvent>(line) {
+                Ok(event) => events.push(event),
+                Err(error) => {
+                    tracing::warn!(line = index + 1, %error, "skipping unreadable trace line")
+                }
+            }
+        }
+        Ok(Self::new(events))
+    }
+
+    /// Replay another member of the chain, such as `"proxy:0"`.
+    ///
+    /// Only the messages it exchanged with its predecessor are replayed, so a
+    /// recorded proxy stands in as an agent for the rest of the chain.
+    pub fn component(mut self, name: impl Into<String>) -> Self {
+        self.component = name.into();
+        self
+    }
+
+    /// Ignore the part of incoming params at this JSON pointer (e.g. `/prompt/0/_meta`)
+    /// when matching them against the recording.
+    pub fn ignore_param(mut self, pointer: impl Into<String>) -> Self {
+        self.ignored_params.push(pointer.into());
+        self
+    }
+
+    /// A handle for checking how far the replay got once it has run.
+    pub fn progress(&self) -> ReplayProgress {
+        self.progress.clone()
+    }
+}
+
+/// How far a [`ReplayAgent`] has got through its recording.
+#[derive(Clone, Debug, Default)]
+pub struct ReplayProgress {
+    state: Arc<Mutex<ProgressState>>,
+}
+
+#[derive(Debug, Default)]
+struct ProgressState {
+    /// Steps of the recording not yet replayed, once the recording has been read.
+    remaining: Option<usize>,
+
+    divergence: Option<String>,
+}
+
+impl ReplayProgress {
+    /// Whether every recorded message has been replayed.
+    pub fn is_finished(&self) -> bool {
+        let state = self.state.lock().unwrap();
+        state.remaining == Some(0) && state.divergence.is_none()
+    }
+
+    /// How many recorded messages are still to be replayed, or `None` if the
+    /// replay has not started.
+    pub fn remaining(&self) -> Option<usize> {
+        self.state.lock().unwrap().remaining
+    }
+
+    /// A description of where the live conversation departed from the
+    /// recording, if it did.
+    pub fn divergence(&self) -> Option<String> {
+        self.state.lock().unwrap().divergence.clone()
+    }
+
+    fn set_remaining(&self, remaining: usize) {
+        self.state.lock().unwrap().remaining = Some(remaining);
+    }
+
+    fn diverged(&self, description: &str) {
+        self.state
+            .lock()
+            .unwrap()
+            .divergence
+            .get_or_insert_with(|| description.to_string());
+    }
+}
+
+/// One message of the recording, from the replayed component's point of view.
+#[derive(Debug)]
+enum Step {
+    /// The predecessor sends a request.
+    ReceiveRequest {
+        index: usize,
+        id: String,
+        method: String,
+        params: serde_json::Value,
+    },
+
+    /// The predecessor sends a notification.
+    ReceiveNotification {
+        index: usize,
+        method: String,
+        params: serde_json::Value,
+    },
+
+    /// The predecessor answers one of our requests.
+    ReceiveResponse {
+        index: usize,
+        id: String,
+        is_error: bool,
+        payload: serde_json::Value,
+    },
+
+    /// We send a request.
+    SendRequest {
+        id: String,
+        method: String,
+        params: serde_json::Value,
+    },
+
+    /// We send a notification.
+    SendNotification {
+        method: String,
+        params: serde_json::Value,
+    },
+
+    /// We answer a request from the predecessor.
+    Respond {
+        id: String,
+        is_error: bool,
+        payload: serde_json::Value,
+    },
+}
+
+/// Turn the recording into the steps `component` took with its predecessor.
+fn script(events: &[TraceEvent], component: &str) -> Result<Vec<Step>, sacp::Error> {
+    let peer = events
+        .iter()
+        .find_map(|event| match event {
+            TraceEvent::Request(request)
+                if request.to == component && request.protocol == Protocol::Acp =>
+            {
+                Some(request.from.as_str())
+            }
+            _ => None,
+        })
+        .ok_or_else(|| {
+            sacp::util::internal_error(format!("the trace has no requests to `{component}`"))
+        })?;
+
+    // The ids of the replayed requests each way, to pick out their responses.
+    let mut received = Vec::new();
+    let mut sent = Vec::new();
+
+    let mut steps = Vec::new();
+    for (index, event) in events.iter().enumerate() {
+        let step = match event {
+            TraceEvent::Request(request) if request.protocol == Protocol::Acp => {
+                let id = request.id.to_string();
+                if request.from == peer && request.to == component {
+                    received.push(id.clone());
+                    Step::ReceiveRequest {
+                        index,
+                        id,
+                        method: request.method.clone(),
+                        params: request.params.clone(),
+                    }
+                } else if request.from == component && request.to == peer {
+                    sent.push(id.clone());
+                    Step::SendRequest {
+                        id,
+                        method: request.method.clone(),
+                        params: request.params.clone(),
+                    }
+                } else {
+                    continue;
+                }
+            }
+            TraceEvent::Notification(notification) if notification.protocol == Protocol::Acp => {
+                if notification.from == peer && notification.to == component {
+                    Step::ReceiveNotification {
+                        index,
+                        method: notification.method.clone(),
+                        params: notification.params.clone(),
+                    }
+                } else if notification.from == component && notification.to == peer {
+                    Step::SendNotification {
+                        method: notification.method.clone(),
+                        params: notification.params.clone(),
+                    }
+                } else {
+                    continue;
+                }
+            }
+            TraceEvent::Response(response) => {
+                let id = response.id.to_string();
+                if response.from == component && response.to == peer && take(&mut received, &id) {
+                    Step::Respond {
+                        id,
+                        is_error: response.is_error,
+                        payload: response.payload.clone(),
+                    }
+                } else if response.from == peer && response.to == component && take(&mut sent, &id)
+                {
+                    Step::ReceiveResponse {
+                        index,
+                        id,
+                        is_error: response.is_error,
+                        payload: response.payload.clone(),
+                    }
+                } else {
+                    continue;
+                }
+            }
+            _ => continue,
+        };
+        steps.push(step);
+    }
+    Ok(steps)
+}
+
+/// Remove `id` from `ids`, returning whether it was there.
+fn take(ids: &mut Vec<String>, id: &str) -> bool {
+    match ids.iter().position(|i| i == id) {
+        Some(position) => {
+            ids.remove(position);
+            true
+        }
+        None => false,
+    }
+}
+
+impl Component<AgentToClient> for ReplayAgent {
+    async fn serve(
+        self,
+        client: impl Component<sacp::link::ClientToAgent>,
+    ) -> Result<(), sacp::Error> {
+        let steps = script(&self.events, &self.component)?;
+        let replay = Replay {
+            component: self.component,
+            ignored_params: self.ignored_params,
+            progress: self.progress,
+            requests: HashMap::new(),
+            responses: HashMap::new(),
+        };
+
+        // Incoming messages are handed to the replay task, which takes them in order.
+        let (incoming_tx, incoming_rx) = mpsc::unbounded();
+        AgentToClient::builder()
+            .name("replay")
+            .on_receive_message(
+                async move |message: MessageCx, _cx| {
+                    incoming_tx
+                        .unbounded_send(message)
+                        .map_err(sacp::Error::into_internal_error)
+                },
+                sacp::on_receive_message!(),
+            )
+            .with_spawned(async move |cx| replay.run(steps, cx, incoming_rx).await)
+            .connect_to(client)?
+            .serve()
+            .await
+    }
+}
+
+/// The state of a running replay.
+struct Replay {
+    component: String,
+    ignored_params: Vec<String>,
+    progress: ReplayProgress,
+
+    /// Requests received and not yet answered, by recorded id.
+    requests: HashMap<String, JrRequestCx<serde_json::Value>>,
+
+    /// Responses to the requests we sent, by recorded id.
+    responses: HashMap<String, JrResponse<serde_json::Value>>,
+}
+
+impl Replay {
+    async fn run(
+        mut self,
+        steps: Vec<Step>,
+        cx: JrConnectionCx<AgentToClient>,
+        mut incoming: mpsc::UnboundedReceiver<MessageCx>,
+    ) -> Result<(), sacp::Error> {
+        let error = match self.replay(steps, &cx, &mut incoming).await {
+            Ok(()) => return Ok(()),
+            Err(error) => error,
+        };
+
+        // Keep answering, so that the client sees the divergence rather than
+        // waiting on a connection that has gone quiet.
+        while let Some(message) = incoming.next().await {
+            if let MessageCx::Request(..) = message {
+                message.respond_with_error(error.clone(), cx.clone())?;
+            }
+        }
+        Err(error)
+    }
+
+    /// Play the steps, returning the divergence error if the live conversation
+    /// departs from them.
+    async fn replay(
+        &mut self,
+        steps: Vec<Step>,
+        cx: &JrConnectionCx<AgentToClient>,
+        incoming: &mut mpsc::UnboundedReceiver<MessageCx>,
+    ) -> Result<(), sacp::Error> {
+        let mut remaining = steps.len();
+        self.progress.set_remaining(remaining);
+
+        for step in steps {
+            match step {
+                Step::ReceiveRequest {
+                    index,
+                    id,
+                    method,
+                    params,
+                } => {
+                    let Some(message) = incoming.next().await else {
+                        return Ok(());
+                    };
+                    let expected = format!("request `{method}`");
+                    match message {
+                        MessageCx::Request(request, request_cx) if request.method == method => {
+                            if let Err(difference) = self.compare(&params, &request.params) {
+                                let error = self.divergence(index, &expected, &difference);
+                                request_cx.respond_with_error(error.clone())?;
+                                return Err(error);
+                            }
+                            self.requests.insert(id, request_cx);
+                        }
+                        message => return Err(self.unexpected(index, &expected, message, cx)?),
+                    }
+                }
+                Step::ReceiveNotification {
+                    index,
+                    method,
+                    params,
+                } => {
+                    let Some(message) = incoming.next().await else {
+                        return Ok(());
+                    };
+                    let expected = format!("notification `{method}`");
+                    match message {
+                        MessageCx::Notification(notification) if notification.method == method => {
+                            if let Err(difference) = self.compare(&params, &notification.params) {
+                                return Err(self.divergence(index, &expected, &difference));
+                            }
+                        }
+                        message => return Err(self.unexpected(index, &expected, message, cx)?),
+                    }
+                }
+                Step::ReceiveResponse {
+                    index,
+                    id,
+                    is_error,
+                    payload,
+                } => {
+                    // Every replayed response answers a replayed request.
+                    let response = self.responses.remove(&id).ok_or_else(|| {
+                        sacp::util::internal_error(format!("no request {id} was sent"))
+                    })?;
+                    let expected = format!("a response to request {id}");
+                    match (response.block_task().await, is_error) {
+                        (Ok(result), false) => {
+                            if let Err(difference) = self.compare(&payload, &result) {
+                                return Err(self.divergence(index, &expected, &difference));
+                            }
+                        }
+                        (Err(_), true) => {}
+                        (Ok(result), true) => {
+                            let got = format!("a successful response {result}");
+                            return Err(self.divergence(index, &expected, &got));
+                        }
+                        (Err(error), false) => {
+                            let got = format!("an error response ({error})");
+                            return Err(self.divergence(index, &expected, &got));
+                        }
+                    }
+                }
+                Step::SendRequest { id, method, params } => {
+                    let response = cx.send_request(UntypedMessage::new(&method, params)?);
+                    self.responses.insert(id, response);
+                }
+                Step::SendNotification { method, params } => {
+                    cx.send_notification(UntypedMessage::new(&method, params)?)?;
+                }
+                Step::Respond {
+                    id,
+                    is_error,
+                    payload,
+                } => {
+                    if let Some(request_cx) = self.requests.remove(&id) {
+                        if is_error {
+                            request_cx.respond_with_error(recorded_error(payload))?;
+                        } else {
+                            request_cx.respond(payload)?;
+                        }
+                    }
+                }
+            }
+            remaining -= 1;
+            self.progress.set_remaining(remaining);
+        }
+
+        tracing::debug!(component = %self.component, "replay finished");
+
+        // Anything more is beyond the end of the recording.
+        match incoming.next().await {
+            Some(message) => {
+                Err(self.unexpected(usize::MAX, "the end of the recording", message, cx)?)
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Compare recorded and live JSON, ignoring the configured params.
+    fn compare(
+        &self,
+        recorded: &serde_json::Value,
+        live: &serde_json::Value,
+    ) -> Result<(), String> {
+        let recorded = self.normalize(recorded);
+        let live = self.normalize(live);
+        match first_difference(&recorded, &live, String::new()) {
+            None => Ok(()),
+            Some(pointer) => {
+                let show = |value: &serde_json::Value| {
+                    value
+                        .pointer(&pointer)
+                        .map_or("nothing".to_string(), |v| v.to_string())
+                };
+                let location = if pointer.is_empty() { "/" } else { &pointer };
+                Err(format!(
+                    "params that differ at `{location}`: {} instead of {}",
+                    show(&live),
+                    show(&recorded)
+                ))
+            }
+        }
+    }
+
+    fn normalize(&self, value: &serde_json::Value) -> serde_json::Value {
+        let mut value = value.clone();
+        for pointer in &self.ignored_params {
+            let (parent, key) = pointer.rsplit_once('/').unwrap_or(("", pointer));
+            if let Some(serde_json::Value::Object(map)) = value.pointer_mut(parent) {
+                map.remove(&key.replace("~1", "/").replace("~0", "~"));
+            }
+        }
+        value
+    }
+
+    /// Report that a message other than the expected one arrived, answering it
+    /// with the divergence error if it is a request, and returning that error.
+    fn unexpected(
+        &self,
+        index: usize,
+        expected: &str,
+        message: MessageCx,
+        cx: &JrConnectionCx<AgentToClient>,
+    ) -> Result<sacp::Error, sacp::Error> {
+        let got = match &message {
+            MessageCx::Request(request, _) => format!("request `{}`", request.method),
+            MessageCx::Notification(notification) => {
+                format!("notification `{}`", notification.method)
+            }
+        };
+        let error = self.divergence(index, expected, &got);
+        if let MessageCx::Request(..) = message {
+            message.respond_with_error(error.clone(), cx.clone())?;
+        }
+        Ok(error)
+    }
+
+    /// Record and log a divergence from the recording, returning the error that
+    /// describes it.
+    fn divergence(&self, index: usize, expected: &str, got: &str) -> sacp::Error {
+        let at = if index == usize::MAX {
+            "after the recording ended".to_string()
+        } else {
+            format!("at trace event #{index}")
+        };
+        let description = format!(
+            "replay of `{}` diverged {at}: expected {expected}, got {got}",
+            self.component
+        );
+        tracing::error!("{description}");
+        self.progress.diverged(&description);
+        sacp::util::internal_error(description)
+    }
+}
+
+/// The JSON pointer of the first place `a` and `b` differ, if they do.
+fn first_difference(a: &serde_json::Value, b: &serde_json::Value, at: String) -> Option<String> {
+    match (a, b) {
+        (serde_json::Value::Object(a), serde_json::Value::Object(b)) => {
+            let mut keys: Vec<_> = a.keys().chain(b.keys()).collect();
+            keys.sort();
+            keys.dedup();
+            keys.into_iter().find_map(|key| {
+                let at = format!("{at}/{}", key.replace('~', "~0").replace('/', "~1"));
+                match (a.get(key), b.get(key)) {
+                    (Some(a), Some(b)) => first_difference(a, b, at),
+                    _ => Some(at),
+                }
+            })
+        }
+        (serde_json::Value::Array(a), serde_json::Value::Array(b)) if a.len() == b.len() => a
+            .iter()
+            .zip(b)
+            .enumerate()
+            .find_map(|(i, (a, b))| first_difference(a, b, format!("{at}/{i}"))),
+        _ if a == b => None,
+        _ => Some(at),
+    }
+}
+
+/// The error a recorded error response stands for.
+///
+/// The conductor records errors as `{"error": "<message>"}`; anything shaped
+/// like a JSON-RPC error is replayed as is.
+fn recorded_error(payload: serde_json::Value) -> sacp::Error {
+    match serde_json::from_value::<sacp::Error>(payload.clone()) {
+        Ok(error) => error,
+        Err(_) => match payload.get("error").and_then(|e| e.as_str()) {
+            Some(message) => sacp::util::internal_error(message),
+            None => sacp::util::internal_error(payload),
+        },
+    }
+}
@@ -0,0 +1,660 @@
+//! Assertions on conductor traces for integration tests.
+//!
+//! A [`TracedChain`] runs a chain under a conductor that records its trace in
+//! memory, and returns the recorded [`Trace`] with the client's result. The trace
+//! answers questions about what crossed the chain, such as "the client sent
+//! `session/prompt` to `proxy:0`" or "the agent called the `search` tool of
+//! `proxy:0` and got an answer within 500ms", with the whole trace in the panic
+//! message when the answer is no.
+//!
+//! ```no_run
+//! # use std::time::Duration;
+//! # use sacp_conductor::{ProxiesAndAgent, test_support::TracedChain};
+//! # async fn example(
+//! #     agent: impl sacp::Component<sacp::link::AgentToClient> + 'static,
+//! #     proxy: impl sacp::Component<sacp::link::ProxyToConductor> + 'static,
+//! #     prompt: impl AsyncFnOnce(sacp_conductor::Conductor<sacp::link::ConductorToClient>) -> Result<String, sacp::Error>,
+//! # ) -> Result<(), sacp::Error> {
+//! let (reply, trace) = TracedChain::new(ProxiesAndAgent::new(agent).proxy(proxy))
+//!     .run(async |conductor| prompt(conductor).await)
+//!     .await?;
+//!
+//! trace.request("session/prompt").from("client").to("proxy:0").exists();
+//! trace
+//!     .tool_call("search")
+//!     .to("proxy:0")
+//!     .exists()
+//!     .responded_within(Duration::from_millis(500))
+//!     .succeeded();
+//! trace.notification("session/update").from("agent").at_least(1);
+//!
+//! // Ids, timestamps, session ids and UUIDs are replaced for snapshots.
+//! expect_test::expect![[r#"..."#]].assert_debug_eq(&trace.normalized());
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! This module is only built with the `test-support` feature.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::Duration;
+
+use regex::Regex;
+use sacp::link::ConductorToClient;
+use serde_json::Value;
+
+use crate::trace::{Protocol, ResponseEvent, TraceEvent, WriteEvent};
+use crate::{Conductor, InstantiateProxiesAndAgent, McpBridgeMode};
+
+/// A chain to run under a conductor that records its trace in memory.
+pub struct TracedChain<I> {
+    name: String,
+    instantiator: I,
+    mcp_bridge_mode: McpBridgeMode,
+}
+
+impl<I: InstantiateProxiesAndAgent + 'static> TracedChain<I> {
+    /// Trace the chain created by `instantiator`, usually a
+    /// [`ProxiesAndAgent`](crate::ProxiesAndAgent).
+    pub fn new(instantiator: I) -> Self {
+        Self {
+            name: "conductor".to_string(),
+            instantiator,
+            mcp_bridge_mode: Default::default(),
+        }
+    }
+
+    /// Name the conductor (default `"conductor"`).
+    pub fn name(mut self, name: impl ToString) -> Self {
+        self.name = name.to_string();
+        self
+    }
+
+    /// Set how the conductor bridges MCP-over-ACP for agents without support
+    /// for it.
+    pub fn mcp_bridge_mode(mut self, mode: McpBridgeMode) -> Self {
+        self.mcp_bridge_mode = mode;
+        self
+    }
+
+    /// Run `client` against the conductor, returning its result and what was
+    /// traced until it returned.
+    ///
+    /// The conductor is a component the client connects to, for example with
+    /// `yopo::prompt(conductor, "Hello")`.
+    pub async fn run<T>(
+        self,
+        client: impl AsyncFnOnce(Conductor<ConductorToClient>) -> Result<T, sacp::Error>,
+    ) -> Result<(T, Trace), sacp::Error> {
+        let recorder = Recorder::default();
+        let conductor = Conductor::new_agent(self.name, self.instantiator, self.mcp_bridge_mode)
+            .trace_to(recorder.clone());
+        let result = client(conductor).await?;
+        let events = recorder.0.lock().expect("not poisoned").clone();
+        Ok((result, Trace::new(events)))
+    }
+}
+
+/// Collects trace events in memory.
+#[derive(Clone, Default)]
+struct Recorder(Arc<Mutex<Vec<TraceEvent>>>);
+
+impl WriteEvent for Recorder {
+    fn write_event(&mut self, event: &TraceEvent) -> std::io::Result<()> {
+        self.0.lock().expect("not poisoned").push(event.clone());
+        Ok(())
+    }
+}
+
+/// A recorded trace to make assertions about.
+///
+/// Displays as one line per event, which is how failed assertions show it.
+#[derive(Debug, Clone)]
+pub struct Trace {
+    events: Vec<TraceEvent>,
+}
+
+impl Trace {
+    /// A trace of `events`, such as those read from a trace file.
+    pub fn new(events: impl IntoIterator<Item = TraceEvent>) -> Self {
+        Self {
+            events: events.into_iter().collect(),
+        }
+    }
+
+    /// The recorded events, in order.
+    pub fn events(&self) -> &[TraceEvent] {
+        &self.events
+    }
+
+    /// Requests with the given method.
+    pub fn request(&self, method: impl Into<String>) -> Expect<'_> {
+        Expect::new(self, Kind::Request, Some(method.into()))
+    }
+
+    /// Notifications with the given method.
+    pub fn notification(&self, method: impl Into<String>) -> Expect<'_> {
+        Expect::new(self, Kind::Notification, Some(method.into()))
+    }
+
+    /// MCP `tools/call` requests for the tool named `tool`.
+    pub fn tool_call(&self, tool: impl Into<String>) -> Expect<'_> {
+        Expect::new(self, Kind::Request, Some("tools/call".to_string()))
+            .protocol(Protocol::Mcp)
+            .params(serde_json::json!({ "name": tool.into() }))
+    }
+
+    /// The events with volatile values replaced, for snapshot tests.
+    ///
+    /// Timestamps become `0.0`. Request ids become `"id:N"`, session ids (in
+    /// `session` and in `sessionId` params) become `"session:N"`, and other
+    /// UUIDs anywhere in params, payloads and logs become `"uuid:N"`, numbered
+    /// in order of first appearance.
+    pub fn normalized(&self) -> Vec<TraceEvent> {
+        let mut normalizer = Normalizer::default();
+        self.events
+            .iter()
+            .cloned()
+            .map(|event| normalizer.event(event))
+            .collect()
+    }
+
+    /// The response to the request at `index`: the first later response from
+    /// its recipient to its sender with the same id.
+    fn response_to(&self, index: usize) -> Option<&ResponseEvent> {
+        let TraceEvent::Request(request) = &self.events[index] else {
+            return None;
+        };
+        self.events[index + 1..]
+            .iter()
+            .find_map(|event| match event {
+                TraceEvent::Response(r)
+                    if r.from == request.to && r.to == request.from && r.id == request.id =>
+                {
+                    Some(r)
+                }
+                _ => None,
+            })
+    }
+}
+
+impl fmt::Display for Trace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, event) in self.events.iter().enumerate() {
+            write!(f, "{index:4} ")?;
+            match event {
+                TraceEvent::Request(r) => writeln!(
+                    f,
+                    "{:8.3}s {} -> {} {} {} (id {}) {}",
+                    r.ts,
+                    r.from,
+                    r.to,
+                    protocol_name(r.protocol),
+                    r.method,
+                    r.id,
+                    r.params
+                )?,
+                TraceEvent::Notification(n) => writeln!(
+                    f,
+                    "{:8.3}s {} -> {} {} {} {}",
+                    n.ts,
+                    n.from,
+                    n.to,
+                    protocol_name(n.protocol),
+                    n.method,
+                    n.params
+                )?,
+                TraceEvent::Response(r) => writeln!(
+                    f,
+                    "{:8.3}s {} -> {} {} (id {}) {}",
+                    r.ts,
+                    r.from,
+                    r.to,
+                    if r.is_error { "error" } else { "response" },
+                    r.id,
+                    r.payload
+                )?,
+                TraceEvent::Trace(t) => writeln!(
+                    f,
+                    "{:8.3}s {} log {:?} {}",
+                    t.ts, t.component, t.level, t.message
+                )?,
+            }
+        }
+        Ok(())
+    }
+}
+
+fn protocol_name(protocol: Protocol) -> &'static str {
+    match protocol {
+        Protocol::Acp => "acp",
+        Protocol::Mcp => "mcp",
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Kind {
+    Request,
+    Notification,
+}
+
+/// The requests or notifications of a [`Trace`] matching some criteria.
+///
+/// Criteria are added with [`from`](Self::from), [`to`](Self::to) and so on, and
+/// checked by one of the assertions ([`exists`](Self::exists),
+/// [`times`](Self::times), [`absent`](Self::absent), ...), which panic with the
+/// criteria and the whole trace if they do not hold.
+#[derive(Clone)]
+pub struct Expect<'t> {
+    trace: &'t Trace,
+    kind: Kind,
+    method: Option<String>,
+    from: Option<String>,
+    to: Option<String>,
+    protocol: Option<Protocol>,
+    session: Option<String>,
+    params: Option<Value>,
+}
+
+impl<'t> Expect<'t> {
+    fn new(trace: &'t Trace, kind: Kind, method: Option<String>) -> Self {
+        Self {
+            trace,
+            kind,
+            method,
+            from: None,
+            to: None,
+            protocol: None,
+            session: None,
+            params: None,
+        }
+    }
+
+    /// Only messages sent by `component`, such as `"client"` or `"proxy:0"`.
+    pub fn from(mut self, component: impl Into<String>) -> Self {
+        self.from = Some(component.into());
+        self
+    }
+
+    /// Only messages sent to `component`.
+    pub fn to(mut self, component: impl Into<String>) -> Self {
+        self.to = Some(component.into());
+        self
+    }
+
+    /// Only ACP or only MCP messages.
+    pub fn protocol(mut self, protocol: Protocol) -> Self {
+        self.protocol = Some(protocol);
+        self
+    }
+
+    /// Only messages in the given session.
+    pub fn session(mut self, session: impl Into<String>) -> Self {
+        self.session = Some(session.into());
+        self
+    }
+
+    /// Only messages whose params include `params`: every field of an object
+    /// must be present with a matching value, other values must be equal.
+    pub fn params(mut self, params: Value) -> Self {
+        self.params = Some(params);
+        self
+    }
+
+    /// The indices in the trace of the matching messages.
+    pub fn indices(&self) -> Vec<usize> {
+        self.trace
+            .events
+            .iter()
+            .enumerate()
+            .filter(|(_, event)| self.matches(event))
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    /// How many messages match.
+    pub fn count(&self) -> usize {
+        self.indices().len()
+    }
+
+    /// Assert that a message matches, returning the first.
+    #[track_caller]
+    pub fn exists(&self) -> Exchange<'t> {
+        match self.indices().first() {
+            Some(&index) => Exchange {
+                trace: self.trace,
+                index,
+            },
+            None => self.fail("no matching message"),
+        }
+    }
+
+    /// Assert that exactly `n` messages match, returning them.
+    #[track_caller]
+    pub fn times(&self, n: usize) -> Vec<Exchange<'t>> {
+        let exchanges = self.exchanges();
+        if exchanges.len() != n {
+            self.fail(&format!(
+                "expected {n} matching messages, found {}",
+                exchanges.len()
+            ));
+        }
+        exchanges
+    }
+
+    /// Assert that at least `n` messages match, returning them.
+    #[track_caller]
+    pub fn at_least(&self, n: usize) -> Vec<Exchange<'t>> {
+        let exchanges = self.exchanges();
+        if exchanges.len() < n {
+            self.fail(&format!(
+                "expected at least {n} matching messages, found {}",
+                exchanges.len()
+            ));
+        }
+        exchanges
+    }
+
+    /// Assert that no message matches.
+    #[track_caller]
+    pub fn absent(&self) {
+        if let Some(index) = self.indices().first() {
+            self.fail(&format!("expected no matching message, found #{index}"));
+        }
+    }
+
+    fn exchanges(&self) -> Vec<Exchange<'t>> {
+        self.indices()
+            .into_iter()
+            .map(|index| Exchange {
+                trace: self.trace,
+                index,
+            })
+            .collect()
+    }
+
+    fn matches(&self, event: &TraceEvent) -> bool {
+        let (from, to, protocol, method, session, params) = match (self.kind, event) {
+            (Kind::Request, TraceEvent::Request(r)) => {
+                (&r.from, &r.to, r.protocol, &r.method, &r.session, &r.params)
+            }
+            (Kind::Notification, TraceEvent::Notification(n)) => {
+                (&n.from, &n.to, n.protocol, &n.method, &n.session, &n.params)
+            }
+            _ => return false,
+        };
+        self.method.as_ref().is_none_or(|m| m == method)
+            && self.from.as_ref().is_none_or(|f| f == from)
+            && self.to.as_ref().is_none_or(|t| t == to)
+            && self.protocol.is_none_or(|p| p == protocol)
+            && self
+                .session
+                .as_ref()
+                .is_none_or(|s| session.as_ref() == Some(s))
+            && self
+                .params
+                .as_ref()
+                .is_none_or(|expected| includes(params, expected))
+    }
+
+    #[track_caller]
+    fn fail(&self, problem: &str) -> ! {
+        panic!("{problem}: {self}\ntrace:\n{}", self.trace)
+    }
+}
+
+impl fmt::Display for Expect<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self.kind {
+            Kind::Request => "request",
+            Kind::Notification => "notification",
+        };
+        write!(f, "{kind}")?;
+        if let Some(protocol) = self.protocol {
+            write!(f, " {}", protocol_name(protocol))?;
+        }
+        if let Some(method) = &self.method {
+            write!(f, " {method}")?;
+        }
+        if let Some(from) = &self.from {
+            write!(f, " from {from}")?;
+        }
+        if let Some(to) = &self.to {
+            write!(f, " to {to}")?;
+        }
+        if let Some(session) = &self.session {
+            write!(f, " in session {session}")?;
+        }
+        if let Some(params) = &self.params {
+            write!(f, " with params including {params}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Whether `actual` includes everything in `expected`.
+fn includes(actual: &Value, expected: &Value) -> bool {
+    match (actual, expected) {
+        (Value::Object(actual), Value::Object(expected)) => expected
+            .iter()
+            .all(|(key, value)| actual.get(key).is_some_and(|a| includes(a, value))),
+        _ => actual == expected,
+    }
+}
+
+/// A request or notification found in a [`Trace`], with assertions about its
+/// response and timing.
+#[derive(Clone, Copy)]
+pub struct Exchange<'t> {
+    trace: &'t Trace,
+    index: usize,
+}
+
+impl<'t> Exchange<'t> {
+    /// The position of the message in the trace.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// The traced message.
+    pub fn event(&self) -> &'t TraceEvent {
+        &self.trace.events[self.index]
+    }
+
+    /// The message's params.
+    pub fn params(&self) -> &'t Value {
+        match self.event() {
+            TraceEvent::Request(r) => &r.params,
+            TraceEvent::Notification(n) => &n.params,
+            _ => unreachable!("exchanges are requests or notifications"),
+        }
+    }
+
+    /// The response to this request, if one was traced.
+    pub fn response(&self) -> Option<&'t ResponseEvent> {
+        self.trace.response_to(self.index)
+    }
+
+    /// Assert that this request was answered, returning the response.
+    #[track_caller]
+    pub fn responded(&self) -> &'t ResponseEvent {
+        match self.response() {
+            Some(response) => response,
+            None => self.fail("no response"),
+        }
+    }
+
+    /// Assert that this request was answered with a result, returning it.
+    #[track_caller]
+    pub fn succeeded(&self) -> &'t Value {
+        let response = self.responded();
+        if response.is_error {
+            self.fail(&format!("failed with {}", response.payload));
+        }
+        &response.payload
+    }
+
+    /// Assert that this request was answered with an error, returning it.
+    #[track_caller]
+    pub fn failed(&self) -> &'t Value {
+        let response = self.responded();
+        if !response.is_error {
+            self.fail(&format!("succeeded with {}", response.payload));
+        }
+        &response.payload
+    }
+
+    /// Assert that this request was answered within `limit`.
+    #[track_caller]
+    pub fn responded_within(self, limit: Duration) -> Self {
+        let elapsed = self.responded().ts - self.ts();
+        if elapsed > limit.as_secs_f64() {
+            self.fail(&format!(
+                "responded after {:.1}ms, more than {}ms",
+                elapsed * 1000.0,
+                limit.as_millis()
+            ));
+        }
+        self
+    }
+
+    /// Assert that this message was traced before `other`.
+    #[track_caller]
+    pub fn before(self, other: &Exchange<'_>) -> Self {
+        if self.index >= other.index {
+            self.fail(&format!("not before #{}", other.index));
+        }
+        self
+    }
+
+    fn ts(&self) -> f64 {
+        match self.event() {
+            TraceEvent::Request(r) => r.ts,
+            TraceEvent::Notification(n) => n.ts,
+            _ => unreachable!("exchanges are requests or notifications"),
+        }
+    }
+
+    #[track_caller]
+    fn fail(&self, problem: &str) -> ! {
+        panic!("#{}: {problem}\ntrace:\n{}", self.index, self.trace)
+    }
+}
+
+impl fmt::Debug for Exchange<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Exchange")
+            .field("index", &self.index)
+            .field("event", self.event())
+            .finish()
+    }
+}
+
+static UUID: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new("[0-9a-fA-F]{8}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{12}")
+        .expect("valid regex")
+});
+
+/// Replaces volatile values with stable placeholders, see [`Trace::normalized`].
+#[derive(Default)]
+struct Normalizer {
+    ids: Placeholders,
+    sessions: Placeholders,
+    uuids: Placeholders,
+}
+
+/// Placeholders numbered in order of first appearance.
+#[derive(Default)]
+struct Placeholders(HashMap<String, usize>);
+
+impl Placeholders {
+    fn get(&mut self, prefix: &str, value: String) -> String {
+        let next = self.0.len();
+        format!("{prefix}:{}", self.0.entry(value).or_insert(next))
+    }
+}
+
+impl Normalizer {
+    fn event(&mut self, event: TraceEvent) -> TraceEvent {
+        match event {
+            TraceEvent::Request(mut r) => {
+                r.ts = 0.0;
+                r.id = self.id(r.id);
+                r.session = r.session.map(|s| self.sessions.get("session", s));
+                r.params = self.json(r.params);
+                TraceEvent::Request(r)
+            }
+            TraceEvent::Response(mut r) => {
+                r.ts = 0.0;
+                r.id = self.id(r.id);
+                r.payload = self.json(r.payload);
+                TraceEvent::Response(r)
+            }
+            TraceEvent::Notification(mut n) => {
+                n.ts = 0.0;
+                n.session = n.session.map(|s| self.sessions.get("session", s));
+                n.params = self.json(n.params);
+                TraceEvent::Notification(n)
+            }
+            TraceEvent::Trace(mut t) => {
+                t.ts = 0.0;
+                t.message = self.text(&t.message);
+                t.fields = t.fields.map(|fields| {
+                    fields
+                        .into_iter()
+                        .map(|(key, value)| (key, self.json(value)))
+                        .collect()
+                });
+                TraceEvent::Trace(t)
+            }
+        }
+    }
+
+    fn id(&mut self, id: Value) -> Value {
+        let id = match id {
+            Value::String(s) => s,
+            Value::Number(n) => n.to_string(),
+            other => return other,
+        };
+        Value::String(self.ids.get("id", id))
+    }
+
+    fn json(&mut self, value: Value) -> Value {
+        match value {
+            Value::Object(map) => Value::Object(
+                map.into_iter()
+                    .map(|(key, value)| {
+                        let value = match value {
+                            Value::String(s) if key == "sessionId" => {
+                                Value::String(self.sessions.get("session", s))
+                            }
+                            value => self.json(value),
+                        };
+                        (key, value)
+                    })
+                    .collect(),
+            ),
+            Value::Array(values) => {
+                Value::Array(values.into_iter().map(|v| self.json(v)).collect())
+            }
+            Value::String(s) => Value::String(self.text(&s)),
+            other => other,
+        }
+    }
+
+    /// Replace the UUIDs in `text`, using the session placeholder for UUIDs
+    /// already seen as session ids.
+    fn text(&mut self, text: &str) -> String {
+        UUID.replace_all(text, |captures: &regex::Captures<'_>| {
+            let uuid = captures[0].to_string();
+            if self.sessions.0.contains_key(&uuid) {
+                self.sessions.get("session", uuid)
+            } else {
+                self.uuids.get("uuid", uuid)
+            }
+        })
+        .into_owned()
+    }
+}
@@ -3,13 +3,23 @@
 //! Events are serialized as newline-delimited JSON (`.jsons` files).
 //! The viewer loads these files to render interactive sequence diagrams.
 
+use std::collections::HashMap;
 use std::fs::OpenOptions;
 use std::io::{BufWriter, Write};
 use std::path::Path;
+use std::sync::{Arc, Mutex};
 use std::time::Instant;
 
 use serde::{Deserialize, Serialize};
 
+mod layer;
+mod redact;
+mod rotate;
+
+pub use layer::TraceLayer;
+pub use redact::{REDACTED, Redaction};
+pub use rotate::RotatingWriter;
+
 /// A trace event representing message flow between components.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
@@ -145,6 +155,51 @@ pub enum TraceLevel {
     Error,
 }
 
+impl From<tracing::Level> for TraceLevel {
+    fn from(level: tracing::Level) -> Self {
+        match level {
+            tracing::Level::TRACE => TraceLevel::Trace,
+            tracing::Level::DEBUG => TraceLevel::Debug,
+            tracing::Level::INFO => TraceLevel::Info,
+            tracing::Level::WARN => TraceLevel::Warn,
+            tracing::Level::ERROR => TraceLevel::Error,
+        }
+    }
+}
+
+impl From<sacp::schema::LogLevel> for TraceLevel {
+    fn from(level: sacp::schema::LogLevel) -> Self {
+        match level {
+            sacp::schema::LogLevel::Trace => TraceLevel::Trace,
+            sacp::schema::LogLevel::Debug => TraceLevel::Debug,
+            sacp::schema::LogLevel::Info => TraceLevel::Info,
+            sacp::schema::LogLevel::Warn => TraceLevel::Warn,
+            sacp::schema::LogLevel::Error => TraceLevel::Error,
+        }
+    }
+}
+
+/// The protocol, method and params a message is traced with.
+///
+/// MCP-over-ACP messages are unwrapped into the MCP message they carry; other
+/// messages are ACP and traced as they are.
+pub fn traced_message(message: sacp::UntypedMessage) -> (Protocol, String, serde_json::Value) {
+    use sacp::JrMessage;
+
+    match sacp::schema::McpOverAcpMessage::<sacp::UntypedMessage>::parse_message(
+        &message.method,
+        &message.params,
+    ) {
+        Some(Ok(mcp)) => (Protocol::Mcp, mcp.message.method, mcp.message.params),
+        _ => (Protocol::Acp, message.method, message.params),
+    }
+}
+
+/// The session a traced message belongs to, taken from its `sessionId` param.
+pub fn traced_session(params: &serde_json::Value) -> Option<String> {
+    params.get("sessionId")?.as_str().map(str::to_string)
+}
+
 /// Trait for destinations that can receive trace events.
 pub trait WriteEvent: Send + 'static {
     /// Write a trace event to the destination.
@@ -180,17 +235,31 @@ impl WriteEvent for futures::channel::mpsc::UnboundedSender<TraceEvent> {
 }
 
 /// Writer for trace events.
+///
+/// Clones write to the same destination with the same start time, so that
+/// events from elsewhere (such as the stderr of spawned components) can be
+/// interleaved with the conductor's own.
+#[derive(Clone)]
 pub struct TraceWriter {
-    dest: Box<dyn WriteEvent>,
+    dest: Arc<Mutex<Box<dyn WriteEvent>>>,
     start_time: Instant,
+    redaction: Option<Arc<Redaction>>,
+    /// Methods of requests awaiting responses. Only tracked for method-scoped
+    /// redaction.
+    pending: Arc<Mutex<PendingMethods>>,
 }
 
+/// Request methods keyed by the responder, the requester and the request id.
+type PendingMethods = HashMap<(String, String, String), String>;
+
 impl TraceWriter {
     /// Create a new trace writer from any WriteEvent destination.
     pub fn new<D: WriteEvent>(dest: D) -> Self {
         Self {
-            dest: Box::new(dest),
+            dest: Arc::new(Mutex::new(Box::new(dest))),
             start_time: Instant::now(),
+            redaction: None,
+            pending: Default::default(),
         }
     }
 
@@ -204,20 +273,71 @@ impl TraceWriter {
         Ok(Self::new(EventWriter::new(BufWriter::new(file))))
     }
 
+    /// Create a new trace writer that writes to a file path, rotating it once
+    /// it would exceed `max_bytes`. See [`RotatingWriter`].
+    pub fn from_path_rotating(path: impl AsRef<Path>, max_bytes: u64) -> std::io::Result<Self> {
+        Ok(Self::new(RotatingWriter::new(path.as_ref(), max_bytes)?))
+    }
+
+    /// Redact events before they are written.
+    ///
+    /// Applies to events written through this writer and clones made from it
+    /// afterwards.
+    pub fn with_redaction(mut self, redaction: Redaction) -> Self {
+        self.redaction = (!redaction.is_empty()).then(|| Arc::new(redaction));
+        self
+    }
+
     /// Get the elapsed time since trace start, in seconds.
     pub fn elapsed(&self) -> f64 {
         self.start_time.elapsed().as_secs_f64()
     }
 
-    /// Write a trace event.
-    pub fn write_event(&mut self, event: &TraceEvent) {
+    /// Write a trace event, redacting it first if a redaction is configured.
+    pub fn write_event(&self, event: &TraceEvent) {
+        let redacted;
+        let event = match &self.redaction {
+            Some(redaction) => {
+                let method = self.method_of(event, redaction);
+                let mut copy = event.clone();
+                redaction.apply(&mut copy, method.as_deref());
+                redacted = copy;
+                &redacted
+            }
+            None => event,
+        };
+
         // Ignore errors - tracing should not break the conductor
-        let _ = self.dest.write_event(event);
+        if let Ok(mut dest) = self.dest.lock() {
+            let _ = dest.write_event(event);
+        }
+    }
+
+    /// The method `event` belongs to, remembering requests so that their
+    /// responses can be matched to them.
+    fn method_of(&self, event: &TraceEvent, redaction: &Redaction) -> Option<String> {
+        match event {
+            TraceEvent::Request(e) => {
+                if redaction.has_method_rules()
+                    && let Ok(mut pending) = self.pending.lock()
+                {
+                    let key = (e.to.clone(), e.from.clone(), e.id.to_string());
+                    pending.insert(key, e.method.clone());
+                }
+                Some(e.method.clone())
+            }
+            TraceEvent::Notification(e) => Some(e.method.clone()),
+            TraceEvent::Response(e) => {
+                let key = (e.from.clone(), e.to.clone(), e.id.to_string());
+                self.pending.lock().ok()?.remove(&key)
+            }
+            TraceEvent::Trace(_) => None,
+        }
     }
 
     /// Write a request event.
     pub fn request(
-        &mut self,
+        &self,
         protocol: Protocol,
         from: impl Into<String>,
         to: impl Into<String>,
@@ -240,7 +360,7 @@ impl TraceWriter {
 
     /// Write a response event.
     pub fn response(
-        &mut self,
+        &self,
         from: impl Into<String>,
         to: impl Into<String>,
         id: serde_json::Value,
@@ -259,7 +379,7 @@ impl TraceWriter {
 
     /// Write a notification event.
     pub fn notification(
-        &mut self,
+        &self,
         protocol: Protocol,
         from: impl Into<String>,
         to: impl Into<String>,
@@ -280,7 +400,7 @@ impl TraceWriter {
 
     /// Write a trace log event.
     pub fn trace_log(
-        &mut self,
+        &self,
         component: impl Into<String>,
         level: TraceLevel,
         message: impl Into<String>,
@@ -294,4 +414,19 @@ impl TraceWriter {
             fields,
         }));
     }
+
+    /// Write a log record sent by a component or captured from `tracing`.
+    /// Its target, if any, is recorded as a `target` field.
+    pub fn log(&self, component: impl Into<String>, log: sacp::schema::LogNotification) {
+        let mut fields = log.fields;
+        if let Some(target) = log.target {
+            fields.insert("target".to_string(), target.into());
+        }
+        self.trace_log(
+            component,
+            log.level.into(),
+            log.message,
+            (!fields.is_empty()).then_some(fields),
+        );
+    }
 }
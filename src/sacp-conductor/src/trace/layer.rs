@@ -0,0 +1,60 @@
+//! Recording the conductor's own `tracing` events in its trace.
+
+use std::cell::Cell;
+
+use sacp::schema::LogNotification;
+use tracing_subscriber::layer::Context;
+
+use super::TraceWriter;
+
+thread_local! {
+    /// Set while an event is being written, so that events logged by the trace
+    /// destination itself are dropped rather than deadlocking on the writer.
+    static WRITING: Cell<bool> = const { Cell::new(false) };
+}
+
+/// A [`tracing_subscriber::Layer`] writing events as [`TraceLogEvent`](super::TraceLogEvent)s.
+///
+/// Events are attributed to the `conductor` component unless another name is
+/// given. Combine the layer with a filter to choose which events are recorded;
+/// at `debug` and below the conductor logs every message it routes.
+pub struct TraceLayer {
+    writer: TraceWriter,
+    component: String,
+}
+
+impl TraceLayer {
+    /// Create a layer writing to `writer`.
+    pub fn new(writer: TraceWriter) -> Self {
+        Self {
+            writer,
+            component: "conductor".to_string(),
+        }
+    }
+
+    /// Attribute events to `component`.
+    pub fn component(mut self, component: impl Into<String>) -> Self {
+        self.component = component.into();
+        self
+    }
+}
+
+impl<S: tracing::Subscriber> tracing_subscriber::Layer<S> for TraceLayer {
+    fn on_event(&self, event: &tracing::Event<'_>, _cx: Context<'_, S>) {
+        if WRITING.replace(true) {
+            return;
+        }
+        let _writing = WritingGuard;
+        self.writer
+            .log(self.component.clone(), LogNotification::from_event(event));
+    }
+}
+
+/// Clears [`WRITING`] when dropped, even if writing the event panicked.
+struct WritingGuard;
+
+impl Drop for WritingGuard {
+    fn drop(&mut self) {
+        WRITING.set(false);
+    }
+}
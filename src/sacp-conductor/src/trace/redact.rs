@@ -0,0 +1,211 @@
+//! Redaction of trace event contents.
+
+use regex::Regex;
+use serde_json::Value;
+
+use super::TraceEvent;
+
+/// The value that replaces anything removed by a path rule.
+pub const REDACTED: &str = "[REDACTED]";
+
+/// Rules for removing sensitive or bulky content from trace events before
+/// they are written.
+///
+/// Rules apply in three passes: path rules replace whole values in request
+/// params and response payloads, scrubbers rewrite every match of a pattern in
+/// the strings that remain, and strings still longer than the limit are cut
+/// short with a marker saying how much was dropped.
+///
+/// ```
+/// use sacp_conductor::trace::{Redaction, TraceWriter};
+/// # fn example(writer: TraceWriter) {
+/// let redaction = Redaction::recommended()
+///     .redact_method("session/prompt", "/prompt/*/text")
+///     .scrub(regex::Regex::new(r"ghp_[A-Za-z0-9]+").unwrap(), "ghp_[REDACTED]");
+/// let writer = writer.with_redaction(redaction);
+/// # }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Redaction {
+    paths: Vec<PathRule>,
+    scrubbers: Vec<(Regex, String)>,
+    max_string_len: Option<usize>,
+}
+
+#[derive(Clone, Debug)]
+struct PathRule {
+    method: Option<String>,
+    pointer: Vec<String>,
+}
+
+impl Redaction {
+    /// Create a redaction that leaves events unchanged.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Rules for traces that will be shared: environment variable and header
+    /// values of MCP servers and terminals, file contents read or written by
+    /// agents, bearer tokens and common API key formats. Strings are truncated
+    /// to 4 KiB.
+    ///
+    /// Prompts and agent output are kept; add rules for them if they are
+    /// sensitive too.
+    pub fn recommended() -> Self {
+        Self::new()
+            .redact("/mcpServers/*/env/*/value")
+            .redact("/mcpServers/*/headers/*/value")
+            .redact_method("terminal/create", "/env/*/value")
+            .redact_method("fs/read_text_file", "/content")
+            .redact_method("fs/write_text_file", "/content")
+            .scrub(
+                Regex::new(r"(?i)\bbearer\s+[a-z0-9._~+/=-]+").unwrap(),
+                "Bearer [REDACTED]",
+            )
+            .scrub(
+                Regex::new(r"\bsk-[A-Za-z0-9_-]{16,}").unwrap(),
+                "sk-[REDACTED]",
+            )
+            .max_string_len(4096)
+    }
+
+    /// Replace the values at `pointer` in the params and payloads of every
+    /// message.
+    ///
+    /// `pointer` is a JSON pointer (`/mcpServers/0/env`) in which a `*`
+    /// segment matches every key of an object or element of an array.
+    pub fn redact(mut self, pointer: &str) -> Self {
+        self.paths.push(PathRule {
+            method: None,
+            pointer: parse_pointer(pointer),
+        });
+        self
+    }
+
+    /// Like [`redact`](Self::redact), but only for requests and notifications
+    /// of `method` and the responses to those requests.
+    pub fn redact_method(mut self, method: impl Into<String>, pointer: &str) -> Self {
+        self.paths.push(PathRule {
+            method: Some(method.into()),
+            pointer: parse_pointer(pointer),
+        });
+        self
+    }
+
+    /// Replace every match of `pattern` in every string of the event, including
+    /// trace log messages and fields. `replacement` may refer to capture groups
+    /// as in [`Regex::replace_all`].
+    pub fn scrub(mut self, pattern: Regex, replacement: impl Into<String>) -> Self {
+        self.scrubbers.push((pattern, replacement.into()));
+        self
+    }
+
+    /// Truncate strings longer than `bytes`, ending them with a
+    /// `…[truncated N bytes]` marker.
+    pub fn max_string_len(mut self, bytes: usize) -> Self {
+        self.max_string_len = Some(bytes);
+        self
+    }
+
+    /// True if the redaction leaves events unchanged.
+    pub fn is_empty(&self) -> bool {
+        self.paths.is_empty() && self.scrubbers.is_empty() && self.max_string_len.is_none()
+    }
+
+    /// True if some rule only applies to particular methods, so responses can
+    /// only be redacted by remembering the method of the request they answer.
+    pub(crate) fn has_method_rules(&self) -> bool {
+        self.paths.iter().any(|rule| rule.method.is_some())
+    }
+
+    /// Redact `event`, which is a message of `method` (or a response to one) if
+    /// that is known.
+    pub(crate) fn apply(&self, event: &mut TraceEvent, method: Option<&str>) {
+        match event {
+            TraceEvent::Request(e) => self.apply_value(&mut e.params, method),
+            TraceEvent::Notification(e) => self.apply_value(&mut e.params, method),
+            TraceEvent::Response(e) => self.apply_value(&mut e.payload, method),
+            TraceEvent::Trace(e) => {
+                self.apply_string(&mut e.message);
+                for value in e.fields.iter_mut().flat_map(|fields| fields.values_mut()) {
+                    self.apply_strings(value);
+                }
+            }
+        }
+    }
+
+    fn apply_value(&self, value: &mut Value, method: Option<&str>) {
+        for rule in &self.paths {
+            if rule.method.is_none() || rule.method.as_deref() == method {
+                redact_path(value, &rule.pointer);
+            }
+        }
+        self.apply_strings(value);
+    }
+
+    fn apply_strings(&self, value: &mut Value) {
+        match value {
+            Value::String(s) => self.apply_string(s),
+            Value::Array(values) => values.iter_mut().for_each(|v| self.apply_strings(v)),
+            Value::Object(map) => map.values_mut().for_each(|v| self.apply_strings(v)),
+            Value::Null | Value::Bool(_) | Value::Number(_) => {}
+        }
+    }
+
+    fn apply_string(&self, s: &mut String) {
+        if s == REDACTED {
+            return;
+        }
+        for (pattern, replacement) in &self.scrubbers {
+            if let std::borrow::Cow::Owned(scrubbed) = pattern.replace_all(s, replacement.as_str())
+            {
+                *s = scrubbed;
+            }
+        }
+        if let Some(max) = self.max_string_len
+            && s.len() > max
+        {
+            let mut end = max;
+            while !s.is_char_boundary(end) {
+                end -= 1;
+            }
+            let dropped = s.len() - end;
+            s.truncate(end);
+            s.push_str(&format!("…[truncated {dropped} bytes]"));
+        }
+    }
+}
+
+fn parse_pointer(pointer: &str) -> Vec<String> {
+    pointer
+        .split('/')
+        .skip(1)
+        .map(|segment| segment.replace("~1", "/").replace("~0", "~"))
+        .collect()
+}
+
+fn redact_path(value: &mut Value, pointer: &[String]) {
+    let Some((segment, rest)) = pointer.split_first() else {
+        *value = Value::String(REDACTED.to_string());
+        return;
+    };
+    match value {
+        Value::Object(map) if segment == "*" => {
+            map.values_mut().for_each(|v| redact_path(v, rest));
+        }
+        Value::Object(map) => {
+            if let Some(v) = map.get_mut(segment) {
+                redact_path(v, rest);
+            }
+        }
+        Value::Array(values) if segment == "*" => {
+            values.iter_mut().for_each(|v| redact_path(v, rest));
+        }
+        Value::Array(values) => {
+            if let Some(v) = segment.parse().ok().and_then(|i: usize| values.get_mut(i)) {
+                redact_path(v, rest);
+            }
+        }
+        _ => {}
+    }
+}
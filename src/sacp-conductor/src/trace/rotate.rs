@@ -0,0 +1,109 @@
+//! Size-based rotation of trace files.
+
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use super::{TraceEvent, WriteEvent};
+
+/// Writes trace events as newline-delimited JSON to a file, starting a fresh
+/// file whenever the next event would take it past a size limit.
+///
+/// Full files are kept alongside the current one with a number before the
+/// extension: `trace.jsons` is renamed to `trace.1.jsons`, which is renamed to
+/// `trace.2.jsons` on the next rotation, and so on up to the number of files
+/// to keep. Event timestamps continue across files.
+pub struct RotatingWriter {
+    path: PathBuf,
+    max_bytes: u64,
+    keep: usize,
+    file: BufWriter<File>,
+    written: u64,
+}
+
+impl RotatingWriter {
+    /// Create `path` (truncating it if it exists), rotating it once it would
+    /// exceed `max_bytes`. Four rotated files are kept by default.
+    pub fn new(path: impl Into<PathBuf>, max_bytes: u64) -> std::io::Result<Self> {
+        let path = path.into();
+        let file = create(&path)?;
+        Ok(Self {
+            path,
+            max_bytes,
+            keep: 4,
+            file,
+            written: 0,
+        })
+    }
+
+    /// Keep this many rotated files besides the current one; older ones are
+    /// deleted. With zero, the current file is simply started over.
+    pub fn keep(mut self, files: usize) -> Self {
+        self.keep = files;
+        self
+    }
+
+    /// Path of the `n`th most recently rotated file.
+    pub fn rotated_path(&self, n: usize) -> PathBuf {
+        let stem = self.path.file_stem().unwrap_or_default().to_string_lossy();
+        let name = match self.path.extension() {
+            Some(ext) => format!("{stem}.{n}.{}", ext.to_string_lossy()),
+            None => format!("{stem}.{n}"),
+        };
+        self.path.with_file_name(name)
+    }
+
+    fn rotate(&mut self) -> std::io::Result<()> {
+        self.file.flush()?;
+        if self.keep > 0 {
+            remove_if_exists(&self.rotated_path(self.keep))?;
+            for n in (1..self.keep).rev() {
+                rename_if_exists(&self.rotated_path(n), &self.rotated_path(n + 1))?;
+            }
+            std::fs::rename(&self.path, self.rotated_path(1))?;
+        }
+        self.file = create(&self.path)?;
+        self.written = 0;
+        Ok(())
+    }
+}
+
+impl WriteEvent for RotatingWriter {
+    fn write_event(&mut self, event: &TraceEvent) -> std::io::Result<()> {
+        let mut line = serde_json::to_vec(event).map_err(std::io::Error::other)?;
+        line.push(b'\n');
+        let len = line.len() as u64;
+
+        // An event larger than the limit still gets a file of its own.
+        if self.written > 0 && self.written + len > self.max_bytes {
+            self.rotate()?;
+        }
+        self.file.write_all(&line)?;
+        self.file.flush()?;
+        self.written += len;
+        Ok(())
+    }
+}
+
+fn create(path: &Path) -> std::io::Result<BufWriter<File>> {
+    let file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(true)
+        .open(path)?;
+    Ok(BufWriter::new(file))
+}
+
+fn remove_if_exists(path: &Path) -> std::io::Result<()> {
+    match std::fs::remove_file(path) {
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        result => result,
+    }
+}
+
+fn rename_if_exists(from: &Path, to: &Path) -> std::io::Result<()> {
+    match std::fs::rename(from, to) {
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        result => result,
+    }
+}
@@ -0,0 +1,166 @@
+//! Tests for recording component and conductor logs in traces.
+
+use futures::StreamExt;
+use futures::channel::mpsc;
+use sacp::Component;
+use sacp::link::{AgentToClient, ProxyToConductor};
+use sacp::schema::{AgentCapabilities, InitializeRequest, InitializeResponse};
+use sacp_conductor::trace::{TraceEvent, TraceLayer, TraceLevel, TraceLogEvent, TraceWriter};
+use sacp_conductor::{Conductor, ProxiesAndAgent};
+use serde_json::json;
+use tokio::io::duplex;
+use tokio_util::compat::{TokioAsyncReadCompatExt, TokioAsyncWriteCompatExt};
+use tracing::instrument::WithSubscriber;
+use tracing_subscriber::Layer;
+use tracing_subscriber::filter::Targets;
+use tracing_subscriber::layer::SubscriberExt;
+
+/// A proxy that forwards everything, logging when it starts and sending its
+/// logs to the conductor.
+struct LoggingProxy;
+
+impl Component<ProxyToConductor> for LoggingProxy {
+    async fn serve(
+        self,
+        client: impl Component<sacp::link::ConductorToProxy>,
+    ) -> Result<(), sacp::Error> {
+        let (layer, forwarder) = sacp::log_forwarding::forward_logs();
+        let subscriber = tracing_subscriber::registry().with(
+            layer.with_filter(Targets::new().with_target("log_forwarding", tracing::Level::INFO)),
+        );
+
+        ProxyToConductor::builder()
+            .name("logging-proxy")
+            .with_spawned(async move |cx| {
+                tracing::info!(answer = 42, "proxy started");
+                tracing::debug!("filtered out");
+                forwarder.run(cx).await
+            })
+            .connect_to(client)?
+            .serve()
+            .with_subscriber(subscriber)
+            .await
+    }
+}
+
+struct MockAgent;
+
+impl Component<AgentToClient> for MockAgent {
+    async fn serve(
+        self,
+        client: impl Component<sacp::link::ClientToAgent>,
+    ) -> Result<(), sacp::Error> {
+        AgentToClient::builder()
+            .name("mock-agent")
+            .on_receive_request(
+                async |request: InitializeRequest, request_cx, _cx| {
+                    request_cx.respond(
+                        InitializeResponse::new(request.protocol_version)
+                            .agent_capabilities(AgentCapabilities::new()),
+                    )
+                },
+                sacp::on_receive_request!(),
+            )
+            .connect_to(client)?
+            .serve()
+            .await
+    }
+}
+
+#[tokio::test]
+async fn test_proxy_logs_are_traced() -> Result<(), sacp::Error> {
+    let (trace_tx, trace_rx) = mpsc::unbounded();
+    let (editor_write, conductor_read) = duplex(8192);
+    let (conductor_write, editor_read) = duplex(8192);
+
+    let conductor_handle = tokio::spawn(async move {
+        Conductor::new_agent(
+            "conductor".to_string(),
+            ProxiesAndAgent::new(MockAgent).proxy(LoggingProxy),
+            Default::default(),
+        )
+        .trace_to(trace_tx)
+        .run(sacp::ByteStreams::new(
+            conductor_write.compat_write(),
+            conductor_read.compat(),
+        ))
+        .await
+    });
+
+    sacp::ClientToAgent::builder()
+        .name("editor")
+        .run_until(
+            sacp::ByteStreams::new(editor_write.compat_write(), editor_read.compat()),
+            async |cx| {
+                cx.send_request(InitializeRequest::new(
+                    sacp::schema::ProtocolVersion::LATEST,
+                ))
+                .block_task()
+                .await?;
+                Ok(())
+            },
+        )
+        .await?;
+
+    // Let the forwarder deliver the log before shutting down.
+    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    conductor_handle.abort();
+    let events: Vec<TraceEvent> = trace_rx.collect().await;
+
+    let logs: Vec<_> = events
+        .iter()
+        .filter_map(|event| match event {
+            TraceEvent::Trace(log) => Some(log),
+            _ => None,
+        })
+        .collect();
+    let [log] = &logs[..] else {
+        panic!("expected one log, got {logs:#?}");
+    };
+    assert_eq!(
+        (log.component.as_str(), log.level, log.message.as_str()),
+        ("proxy:0", TraceLevel::Info, "proxy started")
+    );
+    assert_eq!(
+        serde_json::Value::Object(log.fields.clone().unwrap()),
+        json!({"answer": 42, "target": "log_forwarding"})
+    );
+
+    // The log notification is consumed by the conductor rather than passed on.
+    assert!(!events.iter().any(|event| matches!(
+        event,
+        TraceEvent::Notification(n) if n.method == "_proxy/log"
+    )));
+    Ok(())
+}
+
+#[test]
+fn test_trace_layer() {
+    let (tx, rx) = mpsc::unbounded();
+    let subscriber = tracing_subscriber::registry().with(TraceLayer::new(TraceWriter::new(tx)));
+    tracing::subscriber::with_default(subscriber, || {
+        tracing::warn!(attempt = 2, retry = true, "reconnecting to {}", "agent");
+    });
+
+    let events: Vec<_> = futures::executor::block_on(rx.collect());
+    let [
+        TraceEvent::Trace(TraceLogEvent {
+            component,
+            level,
+            message,
+            fields,
+            ..
+        }),
+    ] = &events[..]
+    else {
+        panic!("expected one log, got {events:#?}");
+    };
+    assert_eq!(
+        (component.as_str(), *level, message.as_str()),
+        ("conductor", TraceLevel::Warn, "reconnecting to agent")
+    );
+    assert_eq!(
+        serde_json::Value::Object(fields.clone().unwrap()),
+        json!({"attempt": 2, "retry": true, "target": "log_forwarding"})
+    );
+}
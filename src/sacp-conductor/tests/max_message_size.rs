@@ -0,0 +1,45 @@
+//! Integration test for `--max-message-size` on messages from the client.
+//!
+//! Run `just prep-tests` before running this test.
+
+use sacp_test::test_binaries::{conductor_binary, elizacp_binary};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+fn initialize(id: u64, padding: usize) -> String {
+    format!(
+        r#"{{"jsonrpc":"2.0","id":{id},"method":"initialize","params":{{"protocolVersion":1,"_padding":"{}"}}}}"#,
+        "y".repeat(padding)
+    )
+}
+
+#[tokio::test]
+async fn test_conductor_limits_client_messages() {
+    let mut conductor = tokio::process::Command::new(conductor_binary())
+        .args(["--max-message-size", "1024", "agent"])
+        .arg(elizacp_binary())
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .unwrap();
+    let mut stdin = conductor.stdin.take().unwrap();
+    let mut stdout = BufReader::new(conductor.stdout.take().unwrap()).lines();
+
+    for line in [initialize(1, 4096), initialize(2, 0)] {
+        stdin.write_all(line.as_bytes()).await.unwrap();
+        stdin.write_all(b"\n").await.unwrap();
+    }
+
+    // The oversized request is refused...
+    let response: serde_json::Value =
+        serde_json::from_str(&stdout.next_line().await.unwrap().unwrap()).unwrap();
+    assert_eq!(response["id"], 1);
+    assert_eq!(response["error"]["code"], -32600);
+    assert_eq!(response["error"]["data"]["max_message_size"], 1024);
+
+    // ...and the conductor carries on with the next one.
+    let response: serde_json::Value =
+        serde_json::from_str(&stdout.next_line().await.unwrap().unwrap()).unwrap();
+    assert_eq!(response["id"], 2);
+    assert_eq!(response["result"]["protocolVersion"], 1);
+}
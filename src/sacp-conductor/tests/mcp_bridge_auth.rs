@@ -0,0 +1,135 @@
+//! Integration tests for authentication of MCP bridge endpoints.
+//!
+//! Bridges listen on localhost, so the conductor hands each agent a secret
+//! with the rewritten MCP server and rejects connections that lack it.
+
+mod mcp_integration;
+
+use std::time::Duration;
+
+use mcp_integration::agent::McpTestAgent;
+use sacp::link::ClientToAgent;
+use sacp::schema::{InitializeRequest, McpServer, NewSessionRequest, ProtocolVersion};
+use sacp_conductor::{Conductor, McpBridgeMode, ProxiesAndAgent};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+/// Start a session through a proxy that provides an `acp:` MCP server,
+/// and run `check` on the bridged server that the agent receives.
+async fn with_bridged_server(
+    mode: McpBridgeMode,
+    check: impl AsyncFnOnce(McpServer) -> Result<(), sacp::Error>,
+) -> Result<(), sacp::Error> {
+    let agent = McpTestAgent::new();
+    let conductor = Conductor::new_agent(
+        "test-conductor".to_string(),
+        ProxiesAndAgent::new(agent.clone()).proxy(mcp_integration::proxy::ProxyComponent),
+        mode,
+    );
+
+    ClientToAgent::builder()
+        .name("test-client")
+        .run_until(conductor, async |cx| {
+            cx.send_request(InitializeRequest::new(ProtocolVersion::LATEST))
+                .block_task()
+                .await?;
+            cx.send_request(NewSessionRequest::new("/"))
+                .block_task()
+                .await?;
+
+            let mcp_servers = agent.mcp_servers();
+            let [mcp_server] = &mcp_servers[..] else {
+                panic!("expected a single bridged server, got {mcp_servers:?}");
+            };
+            check(mcp_server.clone()).await
+        })
+        .await
+}
+
+/// Send a bare HTTP request to `localhost:port`, returning the status line of the response.
+async fn http_status(port: u16, authorization: Option<&str>) -> String {
+    let mut stream = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+    let body = r#"{"jsonrpc":"2.0","id":1,"method":"ping"}"#;
+    let authorization = authorization
+        .map(|value| format!("Authorization: {value}\r\n"))
+        .unwrap_or_default();
+    let request = format!(
+        "POST / HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\n\
+         Accept: application/json, text/event-stream\r\n{authorization}\
+         Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    stream.write_all(request.as_bytes()).await.unwrap();
+
+    let mut response = vec![0; 64];
+    let n = stream.read(&mut response).await.unwrap();
+    let response = String::from_utf8_lossy(&response[..n]);
+    response.lines().next().unwrap_or_default().to_string()
+}
+
+#[tokio::test]
+async fn test_http_bridge_rejects_requests_without_token() -> Result<(), sacp::Error> {
+    with_bridged_server(McpBridgeMode::Http, async |mcp_server| {
+        let McpServer::Http(http) = mcp_server else {
+            panic!("expected an HTTP server, got {mcp_server:?}");
+        };
+        let port: u16 = http.url.rsplit(':').next().unwrap().parse().unwrap();
+
+        // The rewritten server carries the token as a bearer header...
+        let [header] = &http.headers[..] else {
+            panic!("expected a single header, got {:?}", http.headers);
+        };
+        assert_eq!(header.name, "Authorization");
+        assert!(header.value.starts_with("Bearer "));
+
+        // ...without which (or with the wrong one) requests are refused.
+        assert_eq!(http_status(port, None).await, "HTTP/1.1 401 Unauthorized");
+        assert_eq!(
+            http_status(port, Some("Bearer not-the-token")).await,
+            "HTTP/1.1 401 Unauthorized"
+        );
+        assert_ne!(
+            http_status(port, Some(&header.value)).await,
+            "HTTP/1.1 401 Unauthorized"
+        );
+
+        Ok(())
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_stdio_bridge_rejects_connections_without_token() -> Result<(), sacp::Error> {
+    with_bridged_server(
+        McpBridgeMode::Stdio {
+            conductor_command: vec!["sacp-conductor".to_string()],
+        },
+        async |mcp_server| {
+            let McpServer::Stdio(stdio) = mcp_server else {
+                panic!("expected a stdio server, got {mcp_server:?}");
+            };
+            let port: u16 = stdio.args.last().unwrap().parse().unwrap();
+
+            // The token is passed to the bridge process through its environment.
+            let [env] = &stdio.env[..] else {
+                panic!(
+                    "expected a single environment variable, got {:?}",
+                    stdio.env
+                );
+            };
+            assert!(!env.value.is_empty());
+
+            // A client presenting the wrong token is disconnected.
+            let mut stream = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+            stream.write_all(b"not-the-token\n").await.unwrap();
+            let mut buf = [0; 1];
+            let read = tokio::time::timeout(Duration::from_secs(5), stream.read(&mut buf))
+                .await
+                .expect("connection was not closed");
+            assert!(matches!(read, Ok(0) | Err(_)), "unexpected read: {read:?}");
+
+            Ok(())
+        },
+    )
+    .await
+}
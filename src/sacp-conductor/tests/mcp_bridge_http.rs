@@ -0,0 +1,230 @@
+//! Integration tests for the streamable-HTTP behavior of the HTTP MCP bridge.
+//!
+//! The bridge assigns an `Mcp-Session-Id` in response to `initialize`, tags
+//! each SSE event with an id so that a broken stream can be resumed with
+//! `Last-Event-ID`, and ends the session on `DELETE`, after which a new
+//! `initialize` starts a fresh one.
+
+mod mcp_integration;
+
+use std::time::Duration;
+
+use mcp_integration::agent::McpTestAgent;
+use reqwest::StatusCode;
+use reqwest::header::{ACCEPT, CONTENT_TYPE};
+use sacp::link::ClientToAgent;
+use sacp::schema::{
+    InitializeRequest, McpServer, McpServerHttp, NewSessionRequest, ProtocolVersion,
+};
+use sacp_conductor::{Conductor, McpBridgeMode, ProxiesAndAgent};
+use serde_json::json;
+
+/// A minimal streamable-HTTP MCP client talking to a bridge.
+struct BridgeClient {
+    http: reqwest::Client,
+    server: McpServerHttp,
+}
+
+impl BridgeClient {
+    fn request(
+        &self,
+        method: reqwest::Method,
+        mcp_session_id: Option<&str>,
+    ) -> reqwest::RequestBuilder {
+        let mut request = self
+            .http
+            .request(method, &self.server.url)
+            .header(ACCEPT, "application/json, text/event-stream");
+        for header in &self.server.headers {
+            request = request.header(&header.name, &header.value);
+        }
+        if let Some(mcp_session_id) = mcp_session_id {
+            request = request.header("Mcp-Session-Id", mcp_session_id);
+        }
+        request
+    }
+
+    async fn post(
+        &self,
+        mcp_session_id: Option<&str>,
+        message: serde_json::Value,
+    ) -> reqwest::Response {
+        self.request(reqwest::Method::POST, mcp_session_id)
+            .header(CONTENT_TYPE, "application/json")
+            .body(message.to_string())
+            .send()
+            .await
+            .unwrap()
+    }
+}
+
+/// Read the next SSE event from `response`, returning its id and JSON data.
+async fn next_event(response: &mut reqwest::Response) -> (String, serde_json::Value) {
+    let mut buffer = String::new();
+    let event = tokio::time::timeout(Duration::from_secs(10), async {
+        loop {
+            if let Some((event, _)) = buffer.split_once("\n\n") {
+                return event.to_string();
+            }
+            let chunk = response.chunk().await.unwrap().expect("SSE stream ended");
+            buffer.push_str(std::str::from_utf8(&chunk).unwrap());
+        }
+    })
+    .await
+    .expect("timed out waiting for SSE event");
+
+    let field = |name: &str| {
+        event
+            .lines()
+            .find_map(|line| line.strip_prefix(name))
+            .map(|value| value.trim_start().to_string())
+            .unwrap_or_else(|| panic!("no `{name}` in SSE event {event:?}"))
+    };
+    (field("id:"), serde_json::from_str(&field("data:")).unwrap())
+}
+
+#[tokio::test]
+async fn test_http_bridge_sessions_and_resumption() -> Result<(), sacp::Error> {
+    let agent = McpTestAgent::new();
+    let conductor = Conductor::new_agent(
+        "test-conductor".to_string(),
+        ProxiesAndAgent::new(agent.clone()).proxy(mcp_integration::proxy::ProxyComponent),
+        McpBridgeMode::Http,
+    );
+
+    ClientToAgent::builder()
+        .name("test-client")
+        .run_until(conductor, async |cx| {
+            cx.send_request(InitializeRequest::new(ProtocolVersion::LATEST))
+                .block_task()
+                .await?;
+            cx.send_request(NewSessionRequest::new("/"))
+                .block_task()
+                .await?;
+
+            let mcp_servers = agent.mcp_servers();
+            let [McpServer::Http(server)] = &mcp_servers[..] else {
+                panic!("expected a single bridged HTTP server, got {mcp_servers:?}");
+            };
+            let client = BridgeClient {
+                http: reqwest::Client::new(),
+                server: server.clone(),
+            };
+
+            // `initialize` assigns the session id.
+            let initialize = json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": "initialize",
+                "params": {
+                    "protocolVersion": "2025-03-26",
+                    "capabilities": {},
+                    "clientInfo": { "name": "test-client", "version": "0.0.0" },
+                },
+            });
+            let mut response = client.post(None, initialize.clone()).await;
+            assert_eq!(response.status(), StatusCode::OK);
+            let mcp_session_id = response
+                .headers()
+                .get("Mcp-Session-Id")
+                .expect("no Mcp-Session-Id header")
+                .to_str()
+                .unwrap()
+                .to_string();
+            let (_, initialized) = next_event(&mut response).await;
+            assert_eq!(initialized["id"], 1);
+
+            let response = client
+                .post(
+                    Some(&mcp_session_id),
+                    json!({ "jsonrpc": "2.0", "method": "notifications/initialized" }),
+                )
+                .await;
+            assert_eq!(response.status(), StatusCode::ACCEPTED);
+
+            // Later requests must carry it.
+            let list_tools = json!({ "jsonrpc": "2.0", "id": 2, "method": "tools/list" });
+            let response = client.post(None, list_tools.clone()).await;
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+            let response = client
+                .post(Some("not-the-session"), list_tools.clone())
+                .await;
+            assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+            let mut response = client.post(Some(&mcp_session_id), list_tools).await;
+            assert_eq!(response.status(), StatusCode::OK);
+            let (event_id, tools) = next_event(&mut response).await;
+            assert_eq!(tools["id"], 2);
+            assert_eq!(tools["result"]["tools"][0]["name"], "echo");
+
+            // Resuming the stream from just before that event replays it.
+            let (stream_id, seq) = event_id.split_once('/').unwrap();
+            let seq: u64 = seq.parse().unwrap();
+            let mut resumed = client
+                .request(reqwest::Method::GET, Some(&mcp_session_id))
+                .header("Last-Event-ID", format!("{stream_id}/{}", seq - 1))
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(resumed.status(), StatusCode::OK);
+            assert_eq!(next_event(&mut resumed).await, (event_id, tools));
+            drop(resumed);
+
+            // `DELETE` ends the session...
+            let response = client
+                .request(reqwest::Method::DELETE, Some(&mcp_session_id))
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+
+            let response = client
+                .request(reqwest::Method::GET, Some(&mcp_session_id))
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+            // ...but the bridge keeps serving, and `initialize` starts a new one.
+            let mut response = client.post(None, initialize.clone()).await;
+            assert_eq!(response.status(), StatusCode::OK);
+            let new_mcp_session_id = response
+                .headers()
+                .get("Mcp-Session-Id")
+                .expect("no Mcp-Session-Id header")
+                .to_str()
+                .unwrap()
+                .to_string();
+            assert_ne!(new_mcp_session_id, mcp_session_id);
+            let (_, initialized) = next_event(&mut response).await;
+            assert_eq!(initialized["id"], 1);
+
+            Ok(())
+        })
+        .await
+}
+
+#[tokio::test]
+async fn test_http_bridge_serves_sequential_clients() -> Result<(), sacp::Error> {
+    // Each connection is closed (with `DELETE`) before the next one is made.
+    let agent = McpTestAgent::new()
+        .echo_message("again")
+        .connections_per_prompt(2);
+
+    let result = yopo::prompt(
+        Conductor::new_agent(
+            "test-conductor".to_string(),
+            ProxiesAndAgent::new(agent.clone()).proxy(mcp_integration::proxy::ProxyComponent),
+            McpBridgeMode::Http,
+        ),
+        "call the echo tool twice",
+    )
+    .await?;
+
+    assert_eq!(
+        result,
+        r#"{"result":"Echo: again"}{"result":"Echo: again"}"#
+    );
+
+    Ok(())
+}
@@ -0,0 +1,220 @@
+//! Integration tests for the lifetime of MCP bridges.
+//!
+//! `acp:` MCP servers given in `session/load` must be bridged just like those
+//! in `session/new`, and a bridge must stop listening once no session uses it.
+
+mod mcp_integration;
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use mcp_integration::agent::McpTestAgent;
+use sacp::link::{AgentToClient, ClientToAgent};
+use sacp::schema::{
+    HttpHeader, InitializeRequest, InitializeResponse, LoadSessionRequest, McpServer,
+    McpServerHttp, ProtocolVersion, SessionId,
+};
+use sacp::{Component, JrConnectionCx};
+use sacp_conductor::{Conductor, McpBridgeMode, ProxiesAndAgent};
+
+/// Load `session_id` with the given MCP servers, returning the servers the agent saw.
+async fn load_session(
+    cx: &JrConnectionCx<ClientToAgent>,
+    agent: &McpTestAgent,
+    session_id: &str,
+    mcp_servers: Vec<McpServer>,
+) -> Result<Vec<McpServer>, sacp::Error> {
+    cx.send_request(LoadSessionRequest::new(session_id.to_string(), "/").mcp_servers(mcp_servers))
+        .block_task()
+        .await?;
+    Ok(agent.mcp_servers())
+}
+
+/// The port of the TCP bridge that a stdio MCP server connects to.
+fn bridge_port(mcp_servers: &[McpServer]) -> u16 {
+    let [McpServer::Stdio(stdio)] = mcp_servers else {
+        panic!("expected a single bridged stdio server, got {mcp_servers:?}");
+    };
+    stdio.args.last().unwrap().parse().unwrap()
+}
+
+/// True if something is listening on the given port.
+async fn is_listening(port: u16) -> bool {
+    tokio::net::TcpStream::connect(("127.0.0.1", port))
+        .await
+        .is_ok()
+}
+
+/// Wait (briefly) for the given port to stop listening.
+async fn wait_until_closed(port: u16) -> bool {
+    for _ in 0..50 {
+        if !is_listening(port).await {
+            return true;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    false
+}
+
+#[tokio::test]
+async fn test_bridge_closed_when_last_session_stops_using_it() -> Result<(), sacp::Error> {
+    let agent = McpTestAgent::new().load_session();
+    let conductor = Conductor::new_agent(
+        "test-conductor".to_string(),
+        ProxiesAndAgent::new(agent.clone()),
+        McpBridgeMode::Stdio {
+            conductor_command: vec!["sacp-conductor".to_string()],
+        },
+    );
+
+    ClientToAgent::builder()
+        .name("test-client")
+        .run_until(conductor, async |cx| {
+            cx.send_request(InitializeRequest::new(ProtocolVersion::LATEST))
+                .block_task()
+                .await?;
+
+            let acp_server = McpServer::Http(McpServerHttp::new("shared", "acp:shared-server"));
+
+            // Both sessions are loaded with the same `acp:` server, which is
+            // bridged rather than passed to the agent unchanged.
+            let servers_1 =
+                load_session(&cx, &agent, "session-1", vec![acp_server.clone()]).await?;
+            let servers_2 =
+                load_session(&cx, &agent, "session-2", vec![acp_server.clone()]).await?;
+            let port = bridge_port(&servers_1);
+            assert_eq!(port, bridge_port(&servers_2));
+            assert!(is_listening(port).await);
+
+            // Reloading one session without the server leaves the bridge open for the other...
+            load_session(&cx, &agent, "session-1", vec![]).await?;
+            assert!(is_listening(port).await);
+
+            // ...but once no session uses it, the bridge stops listening.
+            load_session(&cx, &agent, "session-2", vec![]).await?;
+            assert!(
+                wait_until_closed(port).await,
+                "bridge on port {port} still open"
+            );
+
+            Ok(())
+        })
+        .await
+}
+
+#[tokio::test]
+async fn test_bridge_closed_when_session_fails_to_load() -> Result<(), sacp::Error> {
+    // An agent that rejects every `session/load`, recording the bridge port it was given.
+    struct NoLoadAgent(Arc<Mutex<Option<u16>>>);
+
+    impl Component<AgentToClient> for NoLoadAgent {
+        async fn serve(self, client: impl Component<ClientToAgent>) -> Result<(), sacp::Error> {
+            AgentToClient::builder()
+                .name("no-load-agent")
+                .on_receive_request(
+                    async |initialize: InitializeRequest, request_cx, _cx| {
+                        request_cx.respond(InitializeResponse::new(initialize.protocol_version))
+                    },
+                    sacp::on_receive_request!(),
+                )
+                .on_receive_request(
+                    async |request: LoadSessionRequest, request_cx, _cx| {
+                        *self.0.lock().unwrap() = Some(bridge_port(&request.mcp_servers));
+                        request_cx.respond_with_error(sacp::Error::method_not_found())
+                    },
+                    sacp::on_receive_request!(),
+                )
+                .connect_to(client)?
+                .serve()
+                .await
+        }
+    }
+
+    let port = Arc::new(Mutex::new(None));
+    let conductor = Conductor::new_agent(
+        "test-conductor".to_string(),
+        ProxiesAndAgent::new(NoLoadAgent(port.clone())),
+        McpBridgeMode::Stdio {
+            conductor_command: vec!["sacp-conductor".to_string()],
+        },
+    );
+
+    ClientToAgent::builder()
+        .name("test-client")
+        .run_until(conductor, async |cx| {
+            cx.send_request(InitializeRequest::new(ProtocolVersion::LATEST))
+                .block_task()
+                .await?;
+
+            let acp_server = McpServer::Http(McpServerHttp::new("server", "acp:some-server"));
+            let result = cx
+                .send_request(
+                    LoadSessionRequest::new(SessionId::new("session"), "/")
+                        .mcp_servers(vec![acp_server]),
+                )
+                .block_task()
+                .await;
+            assert!(result.is_err());
+
+            let port = port.lock().unwrap().expect("agent saw a bridged server");
+            assert!(
+                wait_until_closed(port).await,
+                "bridge on port {port} still open"
+            );
+
+            Ok(())
+        })
+        .await
+}
+
+#[tokio::test]
+async fn test_bridge_released_when_later_server_is_rejected() -> Result<(), sacp::Error> {
+    let agent = McpTestAgent::new().load_session();
+    let conductor = Conductor::new_agent(
+        "test-conductor".to_string(),
+        ProxiesAndAgent::new(agent.clone()),
+        McpBridgeMode::Stdio {
+            conductor_command: vec!["sacp-conductor".to_string()],
+        },
+    );
+
+    ClientToAgent::builder()
+        .name("test-client")
+        .run_until(conductor, async |cx| {
+            cx.send_request(InitializeRequest::new(ProtocolVersion::LATEST))
+                .block_task()
+                .await?;
+
+            // The first server is bridged before the second, with headers, is rejected.
+            let first = McpServer::Http(McpServerHttp::new("first", "acp:first-server"));
+            let with_headers = McpServer::Http(
+                McpServerHttp::new("second", "acp:second-server")
+                    .headers(vec![HttpHeader::new("Authorization", "Bearer token")]),
+            );
+            let error = cx
+                .send_request(
+                    LoadSessionRequest::new(SessionId::new("session-1"), "/")
+                        .mcp_servers(vec![first.clone(), with_headers]),
+                )
+                .block_task()
+                .await
+                .expect_err("headers on an `acp:` server should be rejected");
+            assert!(
+                format!("{error:?}").contains("headers on `acp:` servers are not supported"),
+                "{error:?}"
+            );
+
+            // Had the rejected request kept its hold on the first bridge, the bridge
+            // would stay open after the only session using it moves on.
+            let servers = load_session(&cx, &agent, "session-1", vec![first]).await?;
+            let port = bridge_port(&servers);
+            load_session(&cx, &agent, "session-1", vec![]).await?;
+            assert!(
+                wait_until_closed(port).await,
+                "bridge on port {port} still open"
+            );
+
+            Ok(())
+        })
+        .await
+}
@@ -0,0 +1,75 @@
+//! Integration test for the Unix socket variant of the stdio MCP bridge.
+//!
+//! The agent receives a stdio MCP server running `conductor mcp --socket $path`,
+//! which connects back to the conductor over a Unix domain socket that only the
+//! current user can access.
+
+#![cfg(unix)]
+
+mod mcp_integration;
+
+use std::os::unix::fs::PermissionsExt;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use mcp_integration::agent::McpTestAgent;
+use sacp::schema::McpServer;
+use sacp_conductor::{Conductor, McpBridgeMode, ProxiesAndAgent};
+use sacp_test::test_binaries;
+
+/// The socket that a bridged stdio server connects to.
+fn socket_path(mcp_server: &McpServer) -> PathBuf {
+    let McpServer::Stdio(stdio) = mcp_server else {
+        panic!("expected a stdio server, got {mcp_server:?}");
+    };
+    let [.., flag, path] = &stdio.args[..] else {
+        panic!("unexpected args: {:?}", stdio.args);
+    };
+    assert_eq!(flag, "--socket");
+    PathBuf::from(path)
+}
+
+#[tokio::test]
+async fn test_unix_socket_bridge() -> Result<(), sacp::Error> {
+    // The permissions of the bridge's socket, observed while it is in use.
+    let socket_mode = Arc::new(Mutex::new(None));
+    let agent = McpTestAgent::new().echo_message("unix").on_connect({
+        let socket_mode = socket_mode.clone();
+        move |mcp_server| {
+            let mode = std::fs::metadata(socket_path(mcp_server))
+                .unwrap()
+                .permissions()
+                .mode();
+            *socket_mode.lock().unwrap() = Some(mode & 0o777);
+        }
+    });
+
+    let result = yopo::prompt(
+        Conductor::new_agent(
+            "test-conductor".to_string(),
+            ProxiesAndAgent::new(agent.clone()).proxy(mcp_integration::proxy::ProxyComponent),
+            McpBridgeMode::UnixSocket {
+                conductor_command: vec![
+                    test_binaries::conductor_binary()
+                        .to_string_lossy()
+                        .to_string(),
+                ],
+            },
+        ),
+        "call the echo tool",
+    )
+    .await?;
+
+    // The tool call went through the socket...
+    assert_eq!(result, r#"{"result":"Echo: unix"}"#);
+
+    // ...which only we could connect to...
+    assert_eq!(*socket_mode.lock().unwrap(), Some(0o600));
+
+    // ...and which is removed once the conductor is done with it.
+    let socket = socket_path(&agent.mcp_servers()[0]);
+    assert!(!socket.exists(), "{} still exists", socket.display());
+    assert!(!socket.parent().unwrap().exists());
+
+    Ok(())
+}
@@ -0,0 +1,173 @@
+//! Agent component that records the MCP servers it is given and calls their tools
+
+use std::sync::{Arc, Mutex};
+
+use sacp::link::{AgentToClient, ClientToAgent};
+use sacp::mcp_client::McpClient;
+use sacp::schema::{
+    AgentCapabilities, ContentChunk, InitializeRequest, InitializeResponse, LoadSessionRequest,
+    LoadSessionResponse, McpServer, NewSessionRequest, NewSessionResponse, PromptRequest,
+    PromptResponse, SessionNotification, SessionUpdate, StopReason,
+};
+use sacp::{Component, McpAcpTransport, MetaCapabilityExt};
+
+/// An agent that records the MCP servers it receives in `session/new` and
+/// `session/load` and, when prompted, calls the `echo` tool of each of them,
+/// sending back the tool's output as an agent message.
+#[derive(Clone)]
+pub struct McpTestAgent {
+    /// The MCP servers received in the most recent `session/new` or `session/load`.
+    pub mcp_servers: Arc<Mutex<Vec<McpServer>>>,
+
+    /// The message passed to the `echo` tool.
+    message: String,
+
+    /// How many times each server is connected to (and its tool called) per prompt.
+    connections: usize,
+
+    /// Whether to advertise support for `session/load`.
+    load_session: bool,
+
+    /// Whether to advertise the `mcp_acp_transport` capability.
+    mcp_acp_transport: bool,
+
+    /// Invoked with each MCP server right before connecting to it.
+    on_connect: Option<OnConnect>,
+}
+
+/// Callback invoked with each MCP server right before connecting to it.
+type OnConnect = Arc<dyn Fn(&McpServer) + Send + Sync>;
+
+impl McpTestAgent {
+    pub fn new() -> Self {
+        Self {
+            mcp_servers: Default::default(),
+            message: "test".to_string(),
+            connections: 1,
+            load_session: false,
+            mcp_acp_transport: false,
+            on_connect: None,
+        }
+    }
+
+    /// Pass `message` to the `echo` tool.
+    pub fn echo_message(mut self, message: impl ToString) -> Self {
+        self.message = message.to_string();
+        self
+    }
+
+    /// Connect to each server `connections` times in a row, with a fresh client each time.
+    pub fn connections_per_prompt(mut self, connections: usize) -> Self {
+        self.connections = connections;
+        self
+    }
+
+    /// Advertise support for `session/load`.
+    pub fn load_session(mut self) -> Self {
+        self.load_session = true;
+        self
+    }
+
+    /// Advertise the `mcp_acp_transport` capability.
+    pub fn mcp_acp_transport(mut self) -> Self {
+        self.mcp_acp_transport = true;
+        self
+    }
+
+    /// Invoke `f` with each MCP server right before connecting to it.
+    pub fn on_connect(mut self, f: impl Fn(&McpServer) + Send + Sync + 'static) -> Self {
+        self.on_connect = Some(Arc::new(f));
+        self
+    }
+
+    /// The MCP servers received in the most recent `session/new` or `session/load`.
+    pub fn mcp_servers(&self) -> Vec<McpServer> {
+        self.mcp_servers.lock().unwrap().clone()
+    }
+}
+
+impl Component<AgentToClient> for McpTestAgent {
+    async fn serve(self, client: impl Component<ClientToAgent>) -> Result<(), sacp::Error> {
+        AgentToClient::builder()
+            .name("mcp-test-agent")
+            .on_receive_request(
+                {
+                    let agent = self.clone();
+                    async move |initialize: InitializeRequest, request_cx, _cx| {
+                        let mut response = InitializeResponse::new(initialize.protocol_version)
+                            .agent_capabilities(
+                                AgentCapabilities::new().load_session(agent.load_session),
+                            );
+                        if agent.mcp_acp_transport {
+                            response = response.add_meta_capability(McpAcpTransport);
+                        }
+                        request_cx.respond(response)
+                    }
+                },
+                sacp::on_receive_request!(),
+            )
+            .on_receive_request(
+                {
+                    let mcp_servers = self.mcp_servers.clone();
+                    async move |request: NewSessionRequest, request_cx, _cx| {
+                        *mcp_servers.lock().unwrap() = request.mcp_servers;
+                        request_cx.respond(NewSessionResponse::new("session"))
+                    }
+                },
+                sacp::on_receive_request!(),
+            )
+            .on_receive_request(
+                {
+                    let mcp_servers = self.mcp_servers.clone();
+                    async move |request: LoadSessionRequest, request_cx, _cx| {
+                        *mcp_servers.lock().unwrap() = request.mcp_servers;
+                        request_cx.respond(LoadSessionResponse::new())
+                    }
+                },
+                sacp::on_receive_request!(),
+            )
+            .on_receive_request(
+                {
+                    let agent = self.clone();
+                    async move |request: PromptRequest, request_cx, cx| {
+                        let agent = agent.clone();
+                        let cx_clone = cx.clone();
+                        cx.spawn(async move {
+                            for mcp_server in &agent.mcp_servers() {
+                                for _ in 0..agent.connections {
+                                    if let Some(on_connect) = &agent.on_connect {
+                                        on_connect(mcp_server);
+                                    }
+
+                                    let client = McpClient::connect(mcp_server, &cx_clone).await?;
+                                    let result = client
+                                        .call_tool(
+                                            "echo",
+                                            serde_json::json!({ "message": agent.message }),
+                                        )
+                                        .await?;
+                                    client.close().await?;
+
+                                    let text = result
+                                        .structured_content
+                                        .map(|content| content.to_string())
+                                        .unwrap_or_default();
+                                    cx_clone.send_notification(SessionNotification::new(
+                                        request.session_id.clone(),
+                                        SessionUpdate::AgentMessageChunk(ContentChunk::new(
+                                            text.into(),
+                                        )),
+                                    ))?;
+                                }
+                            }
+                            request_cx.respond(PromptResponse::new(StopReason::EndTurn))
+                        })
+                    }
+                },
+                sacp::on_receive_request!(),
+            )
+            .connect_to(client)?
+            .serve()
+            .await
+    }
+}
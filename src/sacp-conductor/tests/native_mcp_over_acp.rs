@@ -0,0 +1,42 @@
+//! Integration test for agents that speak MCP-over-ACP natively.
+//!
+//! When the agent advertises the `mcp_acp_transport` capability, the conductor
+//! must pass `acp:` MCP servers through unchanged (rather than bridging them
+//! over localhost) and route the agent's `_mcp/*` messages to the proxy that
+//! owns the server.
+
+mod mcp_integration;
+
+use mcp_integration::agent::McpTestAgent;
+use sacp::schema::McpServer;
+use sacp_conductor::{Conductor, ProxiesAndAgent};
+
+#[tokio::test]
+async fn test_native_mcp_over_acp_passthrough() -> Result<(), sacp::Error> {
+    let agent = McpTestAgent::new()
+        .mcp_acp_transport()
+        .echo_message("native");
+
+    let result = yopo::prompt(
+        Conductor::new_agent(
+            "test-conductor".to_string(),
+            ProxiesAndAgent::new(agent.clone()).proxy(mcp_integration::proxy::ProxyComponent),
+            Default::default(),
+        ),
+        "call the echo tool",
+    )
+    .await?;
+
+    // The agent saw the proxy's `acp:` URL rather than a localhost bridge...
+    let mcp_servers = agent.mcp_servers();
+    assert_eq!(mcp_servers.len(), 1);
+    let McpServer::Http(http) = &mcp_servers[0] else {
+        panic!("expected an HTTP MCP server, got {mcp_servers:?}");
+    };
+    assert!(http.url.starts_with("acp:"), "unexpected URL: {}", http.url);
+
+    // ...and its MCP-over-ACP messages reached the proxy's server.
+    assert_eq!(result, r#"{"result":"Echo: native"}"#);
+
+    Ok(())
+}
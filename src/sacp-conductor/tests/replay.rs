@@ -0,0 +1,151 @@
+//! Tests for replaying a recorded agent from a conductor trace.
+//!
+//! Eliza is recorded behind the arrow proxy, then the recording stands in for
+//! Eliza while the same and a different prompt are sent through the proxy.
+
+use futures::StreamExt;
+use futures::channel::mpsc;
+use sacp::Component;
+use sacp::link::{AgentToClient, ProxyToConductor};
+use sacp_conductor::replay::{ReplayAgent, ReplayProgress};
+use sacp_conductor::trace::TraceEvent;
+use sacp_conductor::{Conductor, ProxiesAndAgent};
+use tokio::io::duplex;
+use tokio_util::compat::{TokioAsyncReadCompatExt, TokioAsyncWriteCompatExt};
+
+struct MockEliza;
+
+impl Component<AgentToClient> for MockEliza {
+    async fn serve(
+        self,
+        client: impl Component<sacp::link::ClientToAgent>,
+    ) -> Result<(), sacp::Error> {
+        Component::<AgentToClient>::serve(elizacp::ElizaAgent::new(), client).await
+    }
+}
+
+struct ArrowProxy;
+
+impl Component<ProxyToConductor> for ArrowProxy {
+    async fn serve(
+        self,
+        client: impl Component<sacp::link::ConductorToProxy>,
+    ) -> Result<(), sacp::Error> {
+        sacp_test::arrow_proxy::run_arrow_proxy(client).await
+    }
+}
+
+/// Send `prompt` through the arrow proxy to `agent`, returning the reply and the
+/// trace of the run.
+async fn run(
+    agent: impl Component<AgentToClient> + 'static,
+    prompt: &str,
+) -> (Result<String, sacp::Error>, Vec<TraceEvent>) {
+    let (trace_tx, trace_rx) = mpsc::unbounded();
+    let (editor_write, conductor_read) = duplex(8192);
+    let (conductor_write, editor_read) = duplex(8192);
+
+    let conductor_handle = tokio::spawn(async move {
+        Conductor::new_agent(
+            "conductor".to_string(),
+            ProxiesAndAgent::new(agent).proxy(ArrowProxy),
+            Default::default(),
+        )
+        .trace_to(trace_tx)
+        .run(sacp::ByteStreams::new(
+            conductor_write.compat_write(),
+            conductor_read.compat(),
+        ))
+        .await
+    });
+
+    let result = tokio::time::timeout(
+        std::time::Duration::from_secs(30),
+        yopo::prompt(
+            sacp::ByteStreams::new(editor_write.compat_write(), editor_read.compat()),
+            prompt,
+        ),
+    )
+    .await
+    .expect("Test timed out");
+
+    conductor_handle.abort();
+    (result, trace_rx.collect().await)
+}
+
+async fn record(prompt: &str) -> (String, Vec<TraceEvent>) {
+    let (result, events) = run(MockEliza, prompt).await;
+    (result.expect("recording failed"), events)
+}
+
+#[tokio::test]
+async fn test_replay_matches_recording() {
+    let (recorded, events) = record("Hello").await;
+
+    let agent = ReplayAgent::new(events);
+    let progress = agent.progress();
+    let (replayed, _) = run(agent, "Hello").await;
+
+    assert_eq!(replayed.expect("replay failed"), recorded);
+    assert!(progress.is_finished(), "{progress:?}");
+}
+
+#[tokio::test]
+async fn test_replay_reports_divergence() {
+    let (_, events) = record("Hello").await;
+
+    let agent = ReplayAgent::new(events);
+    let progress = agent.progress();
+    let (replayed, _) = run(agent, "Goodbye").await;
+
+    assert!(replayed.is_err());
+    assert_divergence(
+        &progress,
+        "expected request `session/prompt`, got params that differ at `/prompt/0/text`: \
+         \"Goodbye\" instead of \"Hello\"",
+    );
+}
+
+#[tokio::test]
+async fn test_replay_proxy_as_agent() {
+    // The recorded proxy's replies include its `>` prefix; replaying it behind
+    // another arrow proxy adds a second one.
+    let (recorded, events) = record("Hello").await;
+
+    let agent = ReplayAgent::new(events).component("proxy:0");
+    let progress = agent.progress();
+    let (replayed, _) = run(agent, "Hello").await;
+
+    assert_eq!(replayed.expect("replay failed"), format!(">{recorded}"));
+    assert!(progress.is_finished(), "{progress:?}");
+}
+
+#[tokio::test]
+async fn test_replay_from_path() {
+    let (recorded, events) = record("Hello").await;
+    let trace_path = std::env::temp_dir().join(format!("replay_test_{}.jsons", std::process::id()));
+    let lines: Vec<_> = events
+        .iter()
+        .map(|event| serde_json::to_string(event).unwrap())
+        .collect();
+    // The last line was still being written when the trace was copied.
+    std::fs::write(
+        &trace_path,
+        format!("{}\n{{\"type\":\"request\",\"ts\":", lines.join("\n")),
+    )
+    .unwrap();
+
+    let agent = ReplayAgent::from_path(&trace_path).unwrap();
+    let _ = std::fs::remove_file(&trace_path);
+    let (replayed, _) = run(agent, "Hello").await;
+    assert_eq!(replayed.expect("replay failed"), recorded);
+}
+
+fn assert_divergence(progress: &ReplayProgress, expected: &str) {
+    let divergence = progress.divergence().expect("expected a divergence");
+    assert!(
+        divergence.starts_with("replay of `agent` diverged at trace event #")
+            && divergence.ends_with(expected),
+        "{divergence}"
+    );
+}
@@ -5,7 +5,15 @@
 
 use rmcp::{ClientHandler, ServiceExt, model::ClientInfo};
 use sacp::{
-    ByteStreams, Component, mcp::McpServerToClient, mcp_server::McpServer, util::run_until,
+    ByteStreams, Component,
+    mcp::McpServerToClient,
+    mcp_server::{
+        CallToolResult, Content, CreateElicitationRequestParam, CreateElicitationResult,
+        CreateMessageRequestParam, ElicitationAction, ElicitationSchema, Icon,
+        McpClientRequestHandler, McpContext, McpServer, Meta, Role, SamplingMessage,
+        ToolAnnotations,
+    },
+    util::run_until,
 };
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
@@ -206,6 +214,52 @@ async fn test_standalone_server_call_add_tool() -> Result<(), sacp::Error> {
     .await
 }
 
+#[tokio::test]
+async fn test_standalone_server_invalid_tool_arguments() -> Result<(), sacp::Error> {
+    let (server_stream, client_stream) = tokio::io::duplex(8192);
+    let (server_read, server_write) = tokio::io::split(server_stream);
+    let (client_read, client_write) = tokio::io::split(client_stream);
+
+    let server = create_test_server();
+    let client_as_component = ByteStreams::new(client_write.compat_write(), client_read.compat());
+
+    run_until(
+        Component::<McpServerToClient>::serve(server, client_as_component),
+        async move {
+            let client = MinimalClientHandler
+                .serve((server_read, server_write))
+                .await
+                .map_err(sacp::util::internal_error)?;
+
+            // `a` has the wrong type and `b` is missing
+            let result = client
+                .call_tool(rmcp::model::CallToolRequestParam {
+                    name: "add".into(),
+                    arguments: Some(
+                        serde_json::json!({ "a": "five" })
+                            .as_object()
+                            .unwrap()
+                            .clone(),
+                    ),
+                })
+                .await
+                .map_err(sacp::util::internal_error)?;
+
+            // The model sees a tool error naming the offending fields
+            assert_eq!(result.is_error, Some(true));
+            let text = result.content[0].raw.as_text().expect("Expected text content");
+            assert_eq!(
+                text.text,
+                "Invalid arguments for tool `add`:\n- `b`: missing required field\n- `a`: expected integer, got string"
+            );
+
+            client.cancel().await.map_err(sacp::util::internal_error)?;
+            Ok(())
+        },
+    )
+    .await
+}
+
 #[tokio::test]
 async fn test_standalone_server_tool_not_found() -> Result<(), sacp::Error> {
     let (server_stream, client_stream) = tokio::io::duplex(8192);
@@ -307,3 +361,391 @@ async fn test_standalone_server_with_disabled_tools() -> Result<(), sacp::Error>
     )
     .await
 }
+
+#[tokio::test]
+async fn test_standalone_server_rich_tool_results() -> Result<(), sacp::Error> {
+    let (server_stream, client_stream) = tokio::io::duplex(8192);
+    let (server_read, server_write) = tokio::io::split(server_stream);
+    let (client_read, client_write) = tokio::io::split(client_stream);
+
+    // Tools returning `CallToolResult` control the full result
+    let server = McpServer::builder("test-server")
+        .tool_fn(
+            "screenshot",
+            "Return an image and a caption",
+            async |_input: EchoInput, _cx| {
+                Ok(CallToolResult::success(vec![
+                    Content::image("aGVsbG8=", "image/png"),
+                    Content::text("a caption"),
+                ]))
+            },
+            sacp::tool_fn!(),
+        )
+        .tool_fn(
+            "fail",
+            "Return an error result the model can see",
+            async |input: EchoInput, _cx| {
+                Ok(CallToolResult::error(vec![Content::text(format!(
+                    "cannot handle {}",
+                    input.message
+                ))]))
+            },
+            sacp::tool_fn!(),
+        )
+        .build();
+
+    let client_as_component = ByteStreams::new(client_write.compat_write(), client_read.compat());
+
+    run_until(
+        Component::<McpServerToClient>::serve(server, client_as_component),
+        async move {
+            let client = MinimalClientHandler
+                .serve((server_read, server_write))
+                .await
+                .map_err(sacp::util::internal_error)?;
+
+            // Tools with rich results do not declare an output schema
+            let tools_result = client
+                .list_tools(None)
+                .await
+                .map_err(sacp::util::internal_error)?;
+            assert!(tools_result.tools.iter().all(|t| t.output_schema.is_none()));
+
+            let arguments = Some(
+                serde_json::json!({ "message": "test" })
+                    .as_object()
+                    .unwrap()
+                    .clone(),
+            );
+
+            // Multiple content blocks, including an image, are passed through
+            let result = client
+                .call_tool(rmcp::model::CallToolRequestParam {
+                    name: "screenshot".into(),
+                    arguments: arguments.clone(),
+                })
+                .await
+                .map_err(sacp::util::internal_error)?;
+            assert_eq!(result.content.len(), 2);
+            let image = result.content[0].raw.as_image().expect("Expected image");
+            assert_eq!(image.mime_type, "image/png");
+            assert_eq!(image.data, "aGVsbG8=");
+            assert_eq!(
+                result.content[1].raw.as_text().map(|t| t.text.as_str()),
+                Some("a caption")
+            );
+            assert!(!result.is_error.unwrap_or(false));
+
+            // Error results are tool results, not protocol errors
+            let result = client
+                .call_tool(rmcp::model::CallToolRequestParam {
+                    name: "fail".into(),
+                    arguments,
+                })
+                .await
+                .map_err(sacp::util::internal_error)?;
+            assert_eq!(result.is_error, Some(true));
+            assert_eq!(
+                result.content[0].raw.as_text().map(|t| t.text.as_str()),
+                Some("cannot handle test")
+            );
+
+            client.cancel().await.map_err(sacp::util::internal_error)?;
+            Ok(())
+        },
+    )
+    .await
+}
+
+#[tokio::test]
+async fn test_standalone_server_tool_annotations() -> Result<(), sacp::Error> {
+    let (server_stream, client_stream) = tokio::io::duplex(8192);
+    let (server_read, server_write) = tokio::io::split(server_stream);
+    let (client_read, client_write) = tokio::io::split(client_stream);
+
+    let mut meta = Meta::new();
+    meta.insert("policy".to_string(), serde_json::json!("auto-approve"));
+
+    let server = McpServer::builder("test-server")
+        .tool_fn(
+            "echo",
+            "Echo a message back",
+            async |input: EchoInput, _cx| Ok(format!("Echo: {}", input.message)),
+            sacp::tool_fn!(),
+        )
+        .tool_fn(
+            "add",
+            "Add two numbers",
+            async |input: AddInput, _cx| {
+                Ok(AddOutput {
+                    result: input.a + input.b,
+                })
+            },
+            sacp::tool_fn!(),
+        )
+        .tool_annotations(
+            "echo",
+            ToolAnnotations::new()
+                .read_only(true)
+                .destructive(false)
+                .idempotent(true)
+                .open_world(false),
+        )?
+        .tool_icons(
+            "echo",
+            vec![Icon {
+                src: "https://example.com/echo.svg".to_string(),
+                mime_type: Some("image/svg+xml".to_string()),
+                sizes: None,
+            }],
+        )?
+        .tool_meta("echo", meta)?
+        .build();
+
+    let client_as_component = ByteStreams::new(client_write.compat_write(), client_read.compat());
+
+    run_until(
+        Component::<McpServerToClient>::serve(server, client_as_component),
+        async move {
+            let client = MinimalClientHandler
+                .serve((server_read, server_write))
+                .await
+                .map_err(sacp::util::internal_error)?;
+
+            let tools_result = client
+                .list_tools(None)
+                .await
+                .map_err(sacp::util::internal_error)?;
+
+            let echo = tools_result
+                .tools
+                .iter()
+                .find(|t| t.name == "echo")
+                .expect("echo tool");
+            let annotations = echo.annotations.as_ref().expect("annotations");
+            assert_eq!(annotations.read_only_hint, Some(true));
+            assert_eq!(annotations.destructive_hint, Some(false));
+            assert_eq!(annotations.idempotent_hint, Some(true));
+            assert_eq!(annotations.open_world_hint, Some(false));
+            assert_eq!(echo.icons.as_ref().map(|i| i.len()), Some(1));
+            assert_eq!(
+                echo.meta.as_ref().and_then(|m| m.get("policy")),
+                Some(&serde_json::json!("auto-approve"))
+            );
+
+            // Tools without annotations advertise none
+            let add = tools_result
+                .tools
+                .iter()
+                .find(|t| t.name == "add")
+                .expect("add tool");
+            assert!(add.annotations.is_none());
+            assert!(add.icons.is_none());
+            assert!(add.meta.is_none());
+
+            client.cancel().await.map_err(sacp::util::internal_error)?;
+            Ok(())
+        },
+    )
+    .await
+}
+
+#[test]
+fn test_annotating_unknown_tool_fails() {
+    let result = McpServer::<McpServerToClient, _>::builder("test-server")
+        .tool_annotations("missing", ToolAnnotations::new().read_only(true));
+    assert!(result.is_err());
+}
+
+/// Client handler that answers sampling requests by reversing the prompt
+#[derive(Debug, Clone, Default)]
+struct SamplingClientHandler;
+
+impl ClientHandler for SamplingClientHandler {
+    fn get_info(&self) -> ClientInfo {
+        ClientInfo {
+            capabilities: rmcp::model::ClientCapabilities::builder()
+                .enable_sampling()
+                .build(),
+            ..ClientInfo::default()
+        }
+    }
+
+    async fn create_message(
+        &self,
+        params: CreateMessageRequestParam,
+        _context: rmcp::service::RequestContext<rmcp::RoleClient>,
+    ) -> Result<rmcp::model::CreateMessageResult, rmcp::ErrorData> {
+        let prompt = params.messages[0].content.as_text().unwrap().text.clone();
+        Ok(rmcp::model::CreateMessageResult {
+            model: "reverser".to_string(),
+            stop_reason: Some(rmcp::model::CreateMessageResult::STOP_REASON_END_TURN.to_string()),
+            message: SamplingMessage {
+                role: Role::Assistant,
+                content: Content::text(prompt.chars().rev().collect::<String>()),
+            },
+        })
+    }
+}
+
+/// Client request handler that accepts every elicitation with a fixed answer
+struct AlwaysAccept;
+
+impl McpClientRequestHandler<McpServerToClient> for AlwaysAccept {
+    async fn create_elicitation(
+        &self,
+        params: CreateElicitationRequestParam,
+        _cx: McpContext<McpServerToClient>,
+    ) -> Result<CreateElicitationResult, sacp::Error> {
+        Ok(CreateElicitationResult {
+            action: ElicitationAction::Accept,
+            content: Some(serde_json::json!({ "answer": params.message })),
+        })
+    }
+}
+
+/// Create a server whose tools make sampling and elicitation requests
+fn create_client_request_server()
+-> McpServer<McpServerToClient, impl sacp::JrResponder<McpServerToClient>> {
+    McpServer::builder("test-server")
+        .tool_fn(
+            "sample",
+            "Ask the client's model to complete a message",
+            async |input: EchoInput, cx| {
+                let result = cx
+                    .create_message(CreateMessageRequestParam {
+                        messages: vec![SamplingMessage {
+                            role: Role::User,
+                            content: Content::text(input.message),
+                        }],
+                        model_preferences: None,
+                        system_prompt: None,
+                        include_context: None,
+                        temperature: None,
+                        max_tokens: 100,
+                        stop_sequences: None,
+                        metadata: None,
+                    })
+                    .await?;
+                Ok(result.message.content.as_text().unwrap().text.clone())
+            },
+            sacp::tool_fn!(),
+        )
+        .tool_fn(
+            "confirm",
+            "Ask the user a question",
+            async |input: EchoInput, cx| {
+                let result = cx
+                    .create_elicitation(CreateElicitationRequestParam {
+                        message: input.message,
+                        requested_schema: ElicitationSchema::builder().build().unwrap(),
+                    })
+                    .await?;
+                Ok(format!("{:?} {:?}", result.action, result.content))
+            },
+            sacp::tool_fn!(),
+        )
+        .client_request_handler(AlwaysAccept)
+        .build()
+}
+
+#[tokio::test]
+async fn test_standalone_server_client_requests() -> Result<(), sacp::Error> {
+    let (server_stream, client_stream) = tokio::io::duplex(8192);
+    let (server_read, server_write) = tokio::io::split(server_stream);
+    let (client_read, client_write) = tokio::io::split(client_stream);
+
+    let server = create_client_request_server();
+    let client_as_component = ByteStreams::new(client_write.compat_write(), client_read.compat());
+
+    run_until(
+        Component::<McpServerToClient>::serve(server, client_as_component),
+        async move {
+            let client = SamplingClientHandler
+                .serve((server_read, server_write))
+                .await
+                .map_err(sacp::util::internal_error)?;
+
+            // Sampling is not handled by `AlwaysAccept`, so it goes to the MCP client
+            let result = client
+                .call_tool(rmcp::model::CallToolRequestParam {
+                    name: "sample".into(),
+                    arguments: Some(
+                        serde_json::json!({ "message": "hello" })
+                            .as_object()
+                            .unwrap()
+                            .clone(),
+                    ),
+                })
+                .await
+                .map_err(sacp::util::internal_error)?;
+            assert_eq!(
+                result.content[0].raw.as_text().map(|t| t.text.as_str()),
+                Some(r#""olleh""#)
+            );
+
+            // Elicitation is answered by the installed handler
+            let result = client
+                .call_tool(rmcp::model::CallToolRequestParam {
+                    name: "confirm".into(),
+                    arguments: Some(
+                        serde_json::json!({ "message": "proceed?" })
+                            .as_object()
+                            .unwrap()
+                            .clone(),
+                    ),
+                })
+                .await
+                .map_err(sacp::util::internal_error)?;
+            assert_eq!(
+                result.content[0].raw.as_text().map(|t| t.text.as_str()),
+                Some(r#""Accept Some(Object {\"answer\": String(\"proceed?\")})""#)
+            );
+
+            client.cancel().await.map_err(sacp::util::internal_error)?;
+            Ok(())
+        },
+    )
+    .await
+}
+
+#[tokio::test]
+async fn test_standalone_server_sampling_unsupported_by_client() -> Result<(), sacp::Error> {
+    let (server_stream, client_stream) = tokio::io::duplex(8192);
+    let (server_read, server_write) = tokio::io::split(server_stream);
+    let (client_read, client_write) = tokio::io::split(client_stream);
+
+    let server = create_client_request_server();
+    let client_as_component = ByteStreams::new(client_write.compat_write(), client_read.compat());
+
+    run_until(
+        Component::<McpServerToClient>::serve(server, client_as_component),
+        async move {
+            // This client does not advertise sampling
+            let client = MinimalClientHandler
+                .serve((server_read, server_write))
+                .await
+                .map_err(sacp::util::internal_error)?;
+
+            let result = client
+                .call_tool(rmcp::model::CallToolRequestParam {
+                    name: "sample".into(),
+                    arguments: Some(
+                        serde_json::json!({ "message": "hello" })
+                            .as_object()
+                            .unwrap()
+                            .clone(),
+                    ),
+                })
+                .await;
+            assert!(
+                result.is_err(),
+                "Expected error when sampling is unsupported"
+            );
+
+            client.cancel().await.map_err(sacp::util::internal_error)?;
+            Ok(())
+        },
+    )
+    .await
+}
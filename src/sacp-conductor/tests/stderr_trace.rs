@@ -0,0 +1,84 @@
+//! Integration test for recording the stderr of spawned components in traces.
+//!
+//! Run `just prep-tests` before running this test.
+
+#![cfg(unix)]
+
+use sacp_conductor::trace::{TraceEvent, TraceLevel};
+use sacp_test::test_binaries::{conductor_binary, elizacp_binary};
+use sacp_tokio::AcpAgent;
+
+#[tokio::test]
+async fn test_agent_stderr_is_traced() -> Result<(), sacp::Error> {
+    let trace_path =
+        std::env::temp_dir().join(format!("stderr_trace_test_{}.jsons", std::process::id()));
+
+    // An agent that warns on stderr before starting eliza.
+    let agent = format!(
+        "sh -c 'echo warning from the agent >&2; exec {}'",
+        elizacp_binary().display()
+    );
+    let conductor = AcpAgent::from_args([
+        conductor_binary().display().to_string(),
+        "--trace".to_string(),
+        trace_path.display().to_string(),
+        "agent".to_string(),
+        agent,
+    ])?;
+
+    let result = yopo::prompt(conductor, "Hello").await?;
+    assert!(!result.is_empty());
+
+    let trace = std::fs::read_to_string(&trace_path).expect("Failed to read trace file");
+    let _ = std::fs::remove_file(&trace_path);
+
+    let logs: Vec<_> = trace
+        .lines()
+        .map(|line| serde_json::from_str::<TraceEvent>(line).expect("Invalid trace event"))
+        .filter_map(|event| match event {
+            TraceEvent::Trace(log) => Some(log),
+            _ => None,
+        })
+        .collect();
+    assert!(
+        logs.iter().any(|log| log.component == "agent"
+            && log.level == TraceLevel::Info
+            && log.message == "warning from the agent"),
+        "{logs:#?}"
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_agent_stderr_is_traced_once() -> Result<(), sacp::Error> {
+    let trace_path = std::env::temp_dir().join(format!(
+        "stderr_trace_once_test_{}.jsons",
+        std::process::id()
+    ));
+
+    let agent = format!(
+        "sh -c 'echo first stderr line >&2; echo second stderr line >&2; exec {}'",
+        elizacp_binary().display()
+    );
+    let conductor = AcpAgent::from_args([
+        conductor_binary().display().to_string(),
+        "--trace".to_string(),
+        trace_path.display().to_string(),
+        "agent".to_string(),
+        agent,
+    ])?;
+
+    let result = yopo::prompt(conductor, "Hello").await?;
+    assert!(!result.is_empty());
+
+    let trace = std::fs::read_to_string(&trace_path).expect("Failed to read trace file");
+    let _ = std::fs::remove_file(&trace_path);
+
+    for expected in ["first stderr line", "second stderr line"] {
+        let copies = trace.lines().filter(|line| line.contains(expected)).count();
+        assert_eq!(copies, 1, "{expected:?} traced {copies} times:\n{trace}");
+    }
+
+    Ok(())
+}
@@ -0,0 +1,95 @@
+//! Tests for analyzing the trace files that the conductor writes.
+//!
+//! The trace viewer's own tests build traces by hand; these run a conductor
+//! and analyze what it actually wrote, so that the two agree on the format.
+
+mod mcp_integration;
+
+use elizacp::ElizaAgent;
+use sacp_conductor::{Conductor, ProxiesAndAgent};
+
+/// Prompt eliza once through a proxy, returning the events of the conductor's trace.
+async fn traced_prompt(name: &str) -> Result<Vec<serde_json::Value>, sacp::Error> {
+    let trace_path = std::env::temp_dir().join(format!(
+        "trace_analysis_{name}_{}.jsons",
+        std::process::id()
+    ));
+
+    let conductor = Conductor::new_agent(
+        "conductor".to_string(),
+        ProxiesAndAgent::new(ElizaAgent::new()).proxy(mcp_integration::proxy::ProxyComponent),
+        Default::default(),
+    )
+    .trace_to_path(&trace_path)
+    .map_err(sacp::Error::into_internal_error)?;
+    yopo::prompt(conductor, "Hello").await?;
+
+    let events = sacp_trace_viewer::read_trace(&trace_path);
+    std::fs::remove_file(&trace_path).ok();
+    events.map_err(sacp::Error::into_internal_error)
+}
+
+#[tokio::test]
+async fn test_sessions_of_conductor_trace() -> Result<(), sacp::Error> {
+    let events = traced_prompt("sessions").await?;
+
+    let sessions = sacp_trace_viewer::sessions(&events);
+    let [session] = &sessions[..] else {
+        panic!("expected a single session, got {sessions:?}");
+    };
+
+    // The prompt went through the proxy, but is one turn.
+    let [turn] = &session.turns[..] else {
+        panic!("expected a single turn, got {:?}", session.turns);
+    };
+    assert_eq!(turn.prompt, "Hello");
+    assert_eq!(turn.stop_reason.as_deref(), Some("end_turn"));
+    assert!(!turn.is_error);
+    assert!(turn.updates > 0, "no updates in {turn:?}");
+    assert_eq!(session.errors, 0);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_query_conductor_trace_by_session() -> Result<(), sacp::Error> {
+    use sacp_trace_viewer::TraceQuery;
+
+    let events = traced_prompt("query").await?;
+    let session = events
+        .iter()
+        .find_map(|event| event["params"]["sessionId"].as_str())
+        .expect("no session in trace")
+        .to_string();
+
+    // The session's prompts, forwarded through the proxy, are selected...
+    let prompts = TraceQuery {
+        session: Some(session),
+        method: Some("session/prompt".into()),
+        ..Default::default()
+    }
+    .apply(&events);
+    let hops: Vec<_> = prompts
+        .events
+        .iter()
+        .filter(|m| m.event["type"] == "request")
+        .map(|m| (m.event["from"].as_str(), m.event["to"].as_str()))
+        .collect();
+    assert_eq!(
+        hops,
+        [
+            (Some("client"), Some("proxy:0")),
+            (Some("proxy:0"), Some("agent"))
+        ]
+    );
+
+    // ...and another session's are not.
+    let other = TraceQuery {
+        session: Some("no-such-session".into()),
+        ..Default::default()
+    }
+    .apply(&events);
+    assert_eq!(other.total, 0);
+
+    Ok(())
+}
@@ -0,0 +1,225 @@
+//! Tests for the trace assertions of `sacp_conductor::test_support`.
+
+use std::time::Duration;
+
+use expect_test::expect;
+use sacp::Component;
+use sacp::ProxyToConductor;
+use sacp::mcp_server::McpServer;
+use sacp_conductor::ProxiesAndAgent;
+use sacp_conductor::test_support::{Trace, TracedChain};
+use sacp_conductor::trace::{Protocol, RequestEvent, ResponseEvent, TraceEvent};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+struct GreetInput {
+    name: String,
+}
+
+/// A proxy providing an MCP server with a `greet` tool.
+struct GreetProxy;
+
+impl Component<ProxyToConductor> for GreetProxy {
+    async fn serve(
+        self,
+        client: impl Component<sacp::link::ConductorToProxy>,
+    ) -> Result<(), sacp::Error> {
+        let mcp_server = McpServer::builder("greet_server".to_string())
+            .tool_fn(
+                "greet",
+                "Greet someone by name",
+                async |input: GreetInput, _context| Ok(format!("Hello, {}!", input.name)),
+                sacp::tool_fn!(),
+            )
+            .build();
+        ProxyToConductor::builder()
+            .name("greet-proxy")
+            .with_mcp_server(mcp_server)
+            .serve(client)
+            .await
+    }
+}
+
+#[tokio::test]
+async fn test_tool_call_assertions() -> Result<(), sacp::Error> {
+    let (reply, trace) =
+        TracedChain::new(ProxiesAndAgent::new(elizacp::ElizaAgent::new()).proxy(GreetProxy))
+            .run(async |conductor| {
+                yopo::prompt(
+                    conductor,
+                    r#"Use tool greet_server::greet with {"name": "World"}"#,
+                )
+                .await
+            })
+            .await?;
+    assert!(reply.contains("Hello, World!"), "{reply}");
+
+    let prompt = trace
+        .request("session/prompt")
+        .from("client")
+        .to("proxy:0")
+        .exists();
+    let forwarded = trace
+        .request("session/prompt")
+        .from("proxy:0")
+        .to("agent")
+        .exists();
+    let greet = trace
+        .tool_call("greet")
+        .from("agent")
+        .to("proxy:0")
+        .params(json!({"arguments": {"name": "World"}}))
+        .exists()
+        .responded_within(Duration::from_secs(30));
+    assert_eq!(greet.succeeded()["isError"], json!(false));
+    prompt.before(&forwarded).before(&greet);
+    forwarded.responded();
+
+    trace.tool_call("greet").times(1);
+    trace.tool_call("farewell").absent();
+    trace.request("tools/call").protocol(Protocol::Acp).absent();
+    trace
+        .notification("session/update")
+        .from("agent")
+        .at_least(1);
+    Ok(())
+}
+
+fn request(ts: f64, id: serde_json::Value, method: &str, params: serde_json::Value) -> TraceEvent {
+    TraceEvent::Request(RequestEvent {
+        ts,
+        protocol: Protocol::Acp,
+        from: "client".to_string(),
+        to: "agent".to_string(),
+        id,
+        method: method.to_string(),
+        session: params["sessionId"].as_str().map(str::to_string),
+        params,
+    })
+}
+
+fn response(
+    ts: f64,
+    id: serde_json::Value,
+    is_error: bool,
+    payload: serde_json::Value,
+) -> TraceEvent {
+    TraceEvent::Response(ResponseEvent {
+        ts,
+        from: "agent".to_string(),
+        to: "client".to_string(),
+        id,
+        is_error,
+        payload,
+    })
+}
+
+#[test]
+fn test_timing_and_failures() {
+    let trace = Trace::new([
+        request(0.0, json!(1), "session/new", json!({"cwd": "/"})),
+        response(0.01, json!(1), false, json!({"sessionId": "s1"})),
+        request(0.02, json!(2), "session/prompt", json!({"sessionId": "s1"})),
+        response(0.52, json!(2), true, json!({"code": -32603})),
+    ]);
+
+    trace.request("session/new").exists().succeeded();
+    let prompt = trace.request("session/prompt").session("s1").exists();
+    assert_eq!(prompt.failed(), &json!({"code": -32603}));
+    prompt.responded_within(Duration::from_millis(600));
+
+    let panic = std::panic::catch_unwind(|| {
+        prompt.responded_within(Duration::from_millis(100));
+    })
+    .unwrap_err();
+    let message = panic.downcast_ref::<String>().unwrap();
+    assert!(
+        message.starts_with("#2: responded after 500.0ms, more than 100ms\ntrace:\n"),
+        "{message}"
+    );
+
+    let panic = std::panic::catch_unwind(|| {
+        trace.request("session/cancel").from("client").exists();
+    })
+    .unwrap_err();
+    expect![[r#"
+        no matching message: request session/cancel from client
+        trace:
+           0    0.000s client -> agent acp session/new (id 1) {"cwd":"/"}
+           1    0.010s agent -> client response (id 1) {"sessionId":"s1"}
+           2    0.020s client -> agent acp session/prompt (id 2) {"sessionId":"s1"}
+           3    0.520s agent -> client error (id 2) {"code":-32603}
+    "#]]
+    .assert_eq(panic.downcast_ref::<String>().unwrap());
+}
+
+#[test]
+fn test_normalized() {
+    let session = "0b5f6c1e-8f2a-4a5e-9c3d-2f1e0d9c8b7a";
+    let trace = Trace::new([
+        request(0.1, json!("abc"), "session/new", json!({})),
+        response(0.2, json!("abc"), false, json!({"sessionId": session})),
+        request(
+            0.3,
+            json!(7),
+            "session/prompt",
+            json!({
+                "sessionId": session,
+                "prompt": [{"text": format!("acp:7d9e1f20-3a4b-4c5d-8e6f-7a8b9c0d1e2f in {session}")}],
+            }),
+        ),
+    ]);
+
+    expect![[r#"
+        [
+            Request(
+                RequestEvent {
+                    ts: 0.0,
+                    protocol: Acp,
+                    from: "client",
+                    to: "agent",
+                    id: String("id:0"),
+                    method: "session/new",
+                    session: None,
+                    params: Object {},
+                },
+            ),
+            Response(
+                ResponseEvent {
+                    ts: 0.0,
+                    from: "agent",
+                    to: "client",
+                    id: String("id:0"),
+                    is_error: false,
+                    payload: Object {
+                        "sessionId": String("session:0"),
+                    },
+                },
+            ),
+            Request(
+                RequestEvent {
+                    ts: 0.0,
+                    protocol: Acp,
+                    from: "client",
+                    to: "agent",
+                    id: String("id:1"),
+                    method: "session/prompt",
+                    session: Some(
+                        "session:0",
+                    ),
+                    params: Object {
+                        "prompt": Array [
+                            Object {
+                                "text": String("acp:uuid:0 in session:0"),
+                            },
+                        ],
+                        "sessionId": String("session:0"),
+                    },
+                },
+            ),
+        ]
+    "#]]
+    .assert_debug_eq(&trace.normalized());
+}
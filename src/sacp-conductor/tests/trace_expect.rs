@@ -35,7 +35,7 @@ fn strip_timestamps(events: Vec<TraceEvent>) -> Vec<TraceEvent> {
 #[tokio::test]
 async fn test_trace_events() {
     let (tx, rx) = mpsc::unbounded();
-    let mut writer = TraceWriter::new(tx);
+    let writer = TraceWriter::new(tx);
 
     // Write some events
     writer.request(
@@ -0,0 +1,205 @@
+//! Tests for redacting trace events and rotating trace files.
+
+use futures::StreamExt;
+use futures::channel::mpsc;
+use sacp_conductor::trace::{
+    Protocol, Redaction, RotatingWriter, TraceEvent, TraceLevel, TraceWriter, WriteEvent,
+};
+use serde_json::{Value, json};
+
+/// Write events through a writer with `redaction`, returning what reached the
+/// destination as JSON.
+async fn redacted(redaction: Redaction, write: impl FnOnce(&TraceWriter)) -> Vec<Value> {
+    let (tx, rx) = mpsc::unbounded();
+    let writer = TraceWriter::new(tx).with_redaction(redaction);
+    write(&writer);
+    drop(writer);
+    rx.map(|event| {
+        let mut value = serde_json::to_value(event).unwrap();
+        value.as_object_mut().unwrap().remove("ts");
+        value
+    })
+    .collect()
+    .await
+}
+
+#[tokio::test]
+async fn test_path_rules() {
+    let redaction = Redaction::new()
+        .redact("/mcpServers/*/env/*/value")
+        .redact_method("fs/read_text_file", "/content");
+
+    let events = redacted(redaction, |writer| {
+        writer.request(
+            Protocol::Acp,
+            "client",
+            "agent",
+            json!(1),
+            "session/new",
+            None,
+            json!({"mcpServers": [
+                {"name": "gh", "env": [{"name": "GH_TOKEN", "value": "ghp_secret"}]},
+                {"name": "plain", "env": []},
+            ]}),
+        );
+        writer.request(
+            Protocol::Acp,
+            "agent",
+            "client",
+            json!(1),
+            "fs/read_text_file",
+            None,
+            json!({"path": "/home/me/.env"}),
+        );
+        writer.response(
+            "client",
+            "agent",
+            json!(1),
+            false,
+            json!({"content": "KEY=1"}),
+        );
+        // Same id, but answering the other request.
+        writer.response(
+            "agent",
+            "client",
+            json!(1),
+            false,
+            json!({"sessionId": "s1", "content": "kept"}),
+        );
+    })
+    .await;
+
+    assert_eq!(
+        events[0]["params"]["mcpServers"],
+        json!([
+            {"name": "gh", "env": [{"name": "GH_TOKEN", "value": "[REDACTED]"}]},
+            {"name": "plain", "env": []},
+        ])
+    );
+    assert_eq!(events[1]["params"], json!({"path": "/home/me/.env"}));
+    assert_eq!(events[2]["payload"], json!({"content": "[REDACTED]"}));
+    assert_eq!(
+        events[3]["payload"],
+        json!({"sessionId": "s1", "content": "kept"})
+    );
+}
+
+#[tokio::test]
+async fn test_scrubbing_and_truncation() {
+    let redaction = Redaction::new()
+        .scrub(
+            regex::Regex::new(r"token=(\w)\w*").unwrap(),
+            "token=${1}[REDACTED]",
+        )
+        .max_string_len(8);
+
+    let events = redacted(redaction, |writer| {
+        writer.notification(
+            Protocol::Acp,
+            "agent",
+            "client",
+            "session/update",
+            None,
+            json!({"text": "token=abcdef", "short": "héllo", "long": "ééééé"}),
+        );
+        let fields = json!({"url": "https://x/?token=abc"});
+        writer.trace_log(
+            "agent",
+            TraceLevel::Warn,
+            "retrying with token=abc",
+            fields.as_object().cloned(),
+        );
+    })
+    .await;
+
+    // Scrubbing happens before truncation, and truncation respects character
+    // boundaries.
+    assert_eq!(
+        events[0]["params"],
+        json!({
+            "text": "token=a[…[truncated 9 bytes]",
+            "short": "héllo",
+            "long": "éééé…[truncated 2 bytes]",
+        })
+    );
+    assert_eq!(events[1]["message"], "retrying…[truncated 23 bytes]");
+    assert_eq!(events[1]["fields"]["url"], "https://…[truncated 20 bytes]");
+}
+
+#[tokio::test]
+async fn test_recommended() {
+    let events = redacted(Redaction::recommended(), |writer| {
+        writer.request(
+            Protocol::Acp,
+            "client",
+            "agent",
+            json!(1),
+            "session/new",
+            None,
+            json!({"cwd": "/work", "mcpServers": [{"name": "api", "type": "http",
+                "url": "https://api", "headers": [{"name": "Authorization",
+                "value": "Bearer abc.def"}]}]}),
+        );
+        writer.notification(
+            Protocol::Acp,
+            "agent",
+            "client",
+            "session/update",
+            None,
+            json!({"text": "use sk-abcdefghijklmnopqrstuvwxyz or Authorization: bearer xyz"}),
+        );
+    })
+    .await;
+
+    assert_eq!(
+        events[0]["params"]["mcpServers"][0]["headers"][0]["value"],
+        "[REDACTED]"
+    );
+    assert_eq!(events[0]["params"]["cwd"], "/work");
+    assert_eq!(
+        events[1]["params"]["text"],
+        "use sk-[REDACTED] or Authorization: Bearer [REDACTED]"
+    );
+}
+
+#[test]
+fn test_rotation() {
+    let dir = std::env::temp_dir().join(format!("trace_rotation_test_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("trace.jsons");
+
+    let event = |n: usize| {
+        TraceEvent::Trace(sacp_conductor::trace::TraceLogEvent {
+            ts: n as f64,
+            component: "agent".to_string(),
+            level: TraceLevel::Info,
+            message: format!("event {n}"),
+            fields: None,
+        })
+    };
+    let line_len = serde_json::to_string(&event(0)).unwrap().len() as u64 + 1;
+
+    // Two events per file, keeping two rotated files.
+    let mut writer = RotatingWriter::new(&path, 2 * line_len).unwrap().keep(2);
+    for n in 0..7 {
+        writer.write_event(&event(n)).unwrap();
+    }
+    assert_eq!(writer.rotated_path(2), dir.join("trace.2.jsons"));
+
+    let messages = |path: std::path::PathBuf| -> Vec<String> {
+        std::fs::read_to_string(path)
+            .unwrap()
+            .lines()
+            .map(|line| match serde_json::from_str(line).unwrap() {
+                TraceEvent::Trace(log) => log.message,
+                event => panic!("unexpected event {event:?}"),
+            })
+            .collect()
+    };
+    assert_eq!(messages(path.clone()), ["event 6"]);
+    assert_eq!(messages(dir.join("trace.1.jsons")), ["event 4", "event 5"]);
+    assert_eq!(messages(dir.join("trace.2.jsons")), ["event 2", "event 3"]);
+    assert!(!dir.join("trace.3.jsons").exists());
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
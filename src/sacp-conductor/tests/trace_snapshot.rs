@@ -8,127 +8,13 @@
 use expect_test::expect;
 use futures::StreamExt;
 use futures::channel::mpsc;
-use sacp_conductor::trace::TraceEvent;
+use sacp_conductor::test_support::Trace;
 use sacp_conductor::{Conductor, ProxiesAndAgent};
 use sacp_test::test_binaries::{arrow_proxy_example, elizacp_binary};
 use sacp_tokio::AcpAgent;
-use std::collections::HashMap;
 use tokio::io::duplex;
 use tokio_util::compat::{TokioAsyncReadCompatExt, TokioAsyncWriteCompatExt};
 
-/// Normalize events for stable snapshot testing.
-///
-/// - Strips timestamps (set to 0.0)
-/// - Replaces UUIDs with sequential IDs (id:0, id:1, etc.)
-/// - Replaces session IDs with "session:0", etc.
-struct EventNormalizer {
-    id_map: HashMap<String, String>,
-    next_id: usize,
-    session_map: HashMap<String, String>,
-    next_session: usize,
-}
-
-impl EventNormalizer {
-    fn new() -> Self {
-        Self {
-            id_map: HashMap::new(),
-            next_id: 0,
-            session_map: HashMap::new(),
-            next_session: 0,
-        }
-    }
-
-    fn normalize_id(&mut self, id: serde_json::Value) -> serde_json::Value {
-        let id_str = match &id {
-            serde_json::Value::String(s) => s.clone(),
-            serde_json::Value::Number(n) => n.to_string(),
-            _ => return id,
-        };
-
-        let normalized = self.id_map.entry(id_str).or_insert_with(|| {
-            let n = format!("id:{}", self.next_id);
-            self.next_id += 1;
-            n
-        });
-
-        serde_json::Value::String(normalized.clone())
-    }
-
-    fn normalize_session(&mut self, session: Option<String>) -> Option<String> {
-        session.map(|s| self.normalize_session_id(&s))
-    }
-
-    fn normalize_session_id(&mut self, session: &str) -> String {
-        self.session_map
-            .entry(session.to_string())
-            .or_insert_with(|| {
-                let n = format!("session:{}", self.next_session);
-                self.next_session += 1;
-                n
-            })
-            .clone()
-    }
-
-    /// Recursively normalize session IDs in JSON values.
-    fn normalize_json(&mut self, value: serde_json::Value) -> serde_json::Value {
-        match value {
-            serde_json::Value::Object(map) => {
-                let normalized: serde_json::Map<String, serde_json::Value> = map
-                    .into_iter()
-                    .map(|(k, v)| {
-                        let v = if k == "sessionId" {
-                            if let serde_json::Value::String(s) = &v {
-                                serde_json::Value::String(self.normalize_session_id(s))
-                            } else {
-                                self.normalize_json(v)
-                            }
-                        } else {
-                            self.normalize_json(v)
-                        };
-                        (k, v)
-                    })
-                    .collect();
-                serde_json::Value::Object(normalized)
-            }
-            serde_json::Value::Array(arr) => {
-                serde_json::Value::Array(arr.into_iter().map(|v| self.normalize_json(v)).collect())
-            }
-            other => other,
-        }
-    }
-
-    fn normalize_events(&mut self, events: Vec<TraceEvent>) -> Vec<TraceEvent> {
-        events
-            .into_iter()
-            .map(|event| match event {
-                TraceEvent::Request(mut r) => {
-                    r.ts = 0.0;
-                    r.id = self.normalize_id(r.id);
-                    r.session = self.normalize_session(r.session);
-                    r.params = self.normalize_json(r.params);
-                    TraceEvent::Request(r)
-                }
-                TraceEvent::Response(mut r) => {
-                    r.ts = 0.0;
-                    r.id = self.normalize_id(r.id);
-                    r.payload = self.normalize_json(r.payload);
-                    TraceEvent::Response(r)
-                }
-                TraceEvent::Notification(mut n) => {
-                    n.ts = 0.0;
-                    n.session = self.normalize_session(n.session);
-                    n.params = self.normalize_json(n.params);
-                    TraceEvent::Notification(n)
-                }
-                TraceEvent::Trace(mut t) => {
-                    t.ts = 0.0;
-                    TraceEvent::Trace(t)
-                }
-            })
-            .collect()
-    }
-}
-
 #[tokio::test]
 async fn test_trace_snapshot() -> Result<(), sacp::Error> {
     // Create channel for collecting trace events
@@ -174,8 +60,7 @@ async fn test_trace_snapshot() -> Result<(), sacp::Error> {
     conductor_handle.abort();
 
     // Collect and normalize events
-    let mut normalizer = EventNormalizer::new();
-    let events = normalizer.normalize_events(rx.collect().await);
+    let events = Trace::new(rx.collect::<Vec<_>>().await).normalized();
 
     // Snapshot the trace events
     expect![[r#"
@@ -336,7 +221,9 @@ async fn test_trace_snapshot() -> Result<(), sacp::Error> {
                     to: "proxy:0",
                     id: String("id:4"),
                     method: "session/prompt",
-                    session: None,
+                    session: Some(
+                        "session:0",
+                    ),
                     params: Object {
                         "prompt": Array [
                             Object {
@@ -356,7 +243,9 @@ async fn test_trace_snapshot() -> Result<(), sacp::Error> {
                     to: "agent",
                     id: String("id:5"),
                     method: "session/prompt",
-                    session: None,
+                    session: Some(
+                        "session:0",
+                    ),
                     params: Object {
                         "prompt": Array [
                             Object {
@@ -375,7 +264,9 @@ async fn test_trace_snapshot() -> Result<(), sacp::Error> {
                     from: "agent",
                     to: "proxy:0",
                     method: "session/update",
-                    session: None,
+                    session: Some(
+                        "session:0",
+                    ),
                     params: Object {
                         "sessionId": String("session:0"),
                         "update": Object {
@@ -407,7 +298,9 @@ async fn test_trace_snapshot() -> Result<(), sacp::Error> {
                     from: "proxy:0",
                     to: "client",
                     method: "session/update",
-                    session: None,
+                    session: Some(
+                        "session:0",
+                    ),
                     params: Object {
                         "sessionId": String("session:0"),
                         "update": Object {
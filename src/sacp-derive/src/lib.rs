@@ -38,7 +38,7 @@
 
 use proc_macro::TokenStream;
 use quote::quote;
-use syn::{parse_macro_input, DeriveInput, Expr, Lit, Path, Type};
+use syn::{DeriveInput, Expr, Lit, Path, Type, parse_macro_input};
 
 /// Derive macro for implementing `JrRequest` and `JrMessage` traits.
 ///
@@ -6,11 +6,16 @@
 use anyhow::Result;
 use sacp::ProxyToConductor;
 use sacp::component::Component;
-use sacp::{Handled, JrMessageHandler, MessageCx};
+use sacp::schema::SuccessorMessage;
+use sacp::{Handled, JrMessage, JrMessageHandler, MessageCx};
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
 use tokio::sync::mpsc;
 
+pub mod trace;
+
 /// A JSON-RPC message representation for logging
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(untagged)]
@@ -28,22 +33,129 @@ pub enum JsonRpcMessage {
         id: serde_json::Value,
         result: serde_json::Value,
     },
+    /// An error response, with the full JSON-RPC error object.
+    Error {
+        id: serde_json::Value,
+        error: serde_json::Value,
+    },
+}
+
+impl JsonRpcMessage {
+    /// Parse a line of newline-delimited JSON-RPC, returning `None` if it is
+    /// not a JSON-RPC message.
+    pub fn from_line(line: &str) -> Option<Self> {
+        let mut object: serde_json::Map<String, serde_json::Value> =
+            serde_json::from_str(line).ok()?;
+        let id = object.remove("id");
+        if let Some(serde_json::Value::String(method)) = object.remove("method") {
+            let message = sacp::UntypedMessage {
+                method,
+                params: object.remove("params").unwrap_or_default(),
+            };
+            return Some(match id {
+                Some(id) => JsonRpcMessage::Request { id, message },
+                None => JsonRpcMessage::Notification { message },
+            });
+        }
+        let id = id?;
+        if let Some(error) = object.remove("error") {
+            return Some(JsonRpcMessage::Error { id, error });
+        }
+        let result = object.remove("result")?;
+        Some(JsonRpcMessage::Reply { id, result })
+    }
+
+    /// The id of a request or response.
+    fn id(&self) -> Option<&serde_json::Value> {
+        match self {
+            JsonRpcMessage::Request { id, .. }
+            | JsonRpcMessage::Reply { id, .. }
+            | JsonRpcMessage::Error { id, .. } => Some(id),
+            JsonRpcMessage::Notification { .. } => None,
+        }
+    }
+}
+
+/// Which way a message travels through the tee.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    /// From the client towards the agent.
+    Downstream,
+    /// From the agent towards the client.
+    Upstream,
+}
+
+impl Direction {
+    /// The direction replies to a request sent this way travel in.
+    pub fn reverse(self) -> Self {
+        match self {
+            Direction::Downstream => Direction::Upstream,
+            Direction::Upstream => Direction::Downstream,
+        }
+    }
+
+    /// The arrow raw mode prefixes lines with.
+    pub(crate) fn arrow(self) -> &'static str {
+        match self {
+            Direction::Downstream => "→",
+            Direction::Upstream => "←",
+        }
+    }
 }
 
 /// A log entry representing a message passing through the proxy
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LogEntry {
     pub timestamp: String,
-    pub direction: String,
+    pub direction: Direction,
     pub message: JsonRpcMessage,
+    /// For responses, the milliseconds since the request passed through.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<f64>,
 }
 
 impl LogEntry {
-    fn new(direction: &str, message: JsonRpcMessage) -> Self {
+    fn new(direction: Direction, message: JsonRpcMessage) -> Self {
         Self {
             timestamp: chrono::Utc::now().to_rfc3339(),
-            direction: direction.to_string(),
+            direction,
             message,
+            duration_ms: None,
+        }
+    }
+
+    fn with_duration(mut self, start: Instant) -> Self {
+        self.duration_ms = Some(start.elapsed().as_secs_f64() * 1000.0);
+        self
+    }
+}
+
+/// Start times of requests awaiting responses, for timing them.
+#[derive(Default)]
+struct RequestTimer {
+    pending: HashMap<(Direction, String), Instant>,
+}
+
+impl RequestTimer {
+    /// Make the log entry for a message, timing it if it answers a request.
+    fn entry(&mut self, direction: Direction, message: JsonRpcMessage) -> LogEntry {
+        let now = Instant::now();
+        let key = |direction, id: &serde_json::Value| (direction, id.to_string());
+        let start = match &message {
+            JsonRpcMessage::Request { id, .. } => {
+                self.pending.insert(key(direction, id), now);
+                None
+            }
+            JsonRpcMessage::Notification { .. } => None,
+            JsonRpcMessage::Reply { .. } | JsonRpcMessage::Error { .. } => message
+                .id()
+                .and_then(|id| self.pending.remove(&key(direction.reverse(), id))),
+        };
+        let entry = LogEntry::new(direction, message);
+        match start {
+            Some(start) => entry.with_duration(start),
+            None => entry,
         }
     }
 }
@@ -89,14 +201,16 @@ impl LogWriter {
 }
 
 /// Handler that logs messages passing through
+///
+/// Messages from the client arrive as they are; messages from the agent arrive
+/// wrapped in `_proxy/successor` envelopes, which are logged unwrapped.
 pub struct TeeHandler {
     log_tx: mpsc::UnboundedSender<LogEntry>,
-    next_id: u64,
 }
 
 impl TeeHandler {
     pub fn new(log_tx: mpsc::UnboundedSender<LogEntry>) -> Self {
-        Self { log_tx, next_id: 1 }
+        Self { log_tx }
     }
 
     fn log_entry(&self, entry: LogEntry) {
@@ -104,10 +218,15 @@ impl TeeHandler {
         let _ = self.log_tx.send(entry);
     }
 
-    fn allocate_id(&mut self) -> u64 {
-        let id = self.next_id;
-        self.next_id += 1;
-        id
+    /// The direction of a message and the message as sent by its originator.
+    fn unwrap_successor(message: &sacp::UntypedMessage) -> (Direction, sacp::UntypedMessage) {
+        match SuccessorMessage::<sacp::UntypedMessage>::parse_message(
+            &message.method,
+            &message.params,
+        ) {
+            Some(Ok(successor)) => (Direction::Upstream, successor.message),
+            _ => (Direction::Downstream, message.clone()),
+        }
     }
 }
 
@@ -125,33 +244,31 @@ impl JrMessageHandler for TeeHandler {
     ) -> Result<Handled<MessageCx>, sacp::Error> {
         match message {
             MessageCx::Request(request, request_cx) => {
-                // Allocate a synthetic ID for tracking this request/response pair
-                let synthetic_id = self.allocate_id();
-
-                // Log the outgoing request
-                let json_msg = JsonRpcMessage::Request {
-                    id: serde_json::json!(synthetic_id),
-                    message: request.clone(),
-                };
-                let entry = LogEntry::new("downstream", json_msg);
-                self.log_entry(entry);
+                let (direction, original) = Self::unwrap_successor(&request);
+                let id = request_cx.id();
+                self.log_entry(LogEntry::new(
+                    direction,
+                    JsonRpcMessage::Request {
+                        id: id.clone(),
+                        message: original,
+                    },
+                ));
 
                 // Wrap the request context to log the response when it comes back
                 let log_tx = self.log_tx.clone();
-
+                let start = Instant::now();
                 let wrapped_cx = request_cx.wrap_params(move |_method, result| {
-                    // Log the response
-                    let result_value = match &result {
-                        Ok(value) => serde_json::to_value(value).unwrap_or(serde_json::Value::Null),
-                        Err(e) => serde_json::json!({ "error": e.to_string() }),
-                    };
-
-                    let json_msg = JsonRpcMessage::Reply {
-                        id: serde_json::json!(synthetic_id),
-                        result: result_value,
+                    let json_msg = match &result {
+                        Ok(value) => JsonRpcMessage::Reply {
+                            id,
+                            result: serde_json::to_value(value).unwrap_or_default(),
+                        },
+                        Err(e) => JsonRpcMessage::Error {
+                            id,
+                            error: serde_json::to_value(e).unwrap_or_default(),
+                        },
                     };
-                    let entry = LogEntry::new("upstream", json_msg);
-
+                    let entry = LogEntry::new(direction.reverse(), json_msg).with_duration(start);
                     let _ = log_tx.send(entry);
 
                     result
@@ -164,12 +281,11 @@ impl JrMessageHandler for TeeHandler {
                 })
             }
             MessageCx::Notification(notification) => {
-                // Log the notification
-                let json_msg = JsonRpcMessage::Notification {
-                    message: notification.clone(),
-                };
-                let entry = LogEntry::new("downstream", json_msg);
-                self.log_entry(entry);
+                let (direction, original) = Self::unwrap_successor(&notification);
+                self.log_entry(LogEntry::new(
+                    direction,
+                    JsonRpcMessage::Notification { message: original },
+                ));
 
                 // Return unhandled so it continues down the chain
                 Ok(Handled::No {
@@ -219,35 +335,94 @@ impl Component<sacp::link::ProxyToConductor> for Tee {
 }
 
 /// Run the tee in raw mode - just log lines without parsing
-pub async fn run_raw(log_file: PathBuf, downstream: sacp_tokio::AcpAgent) -> Result<()> {
-    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
-
-    // Initialize tracing
-    tracing_subscriber::fmt()
-        .with_writer(std::io::stderr)
-        .with_env_filter(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("info")),
-        )
-        .init();
+///
+/// With a `trace_file`, JSON-RPC lines are also written to it as conductor
+/// trace events (see [`trace`]).
+pub async fn run_raw(
+    log_file: PathBuf,
+    trace_file: Option<PathBuf>,
+    downstream: sacp_tokio::AcpAgent,
+) -> Result<()> {
+    init_tracing();
 
     tracing::info!(
         "Starting sacp-tee in raw mode, logging to: {}",
         log_file.display()
     );
 
+    tee_lines(&log_file, trace_file, downstream, |direction, line| {
+        Some(format!("{} {line}\n", direction.arrow()))
+    })
+    .await
+}
+
+/// Run the tee in JSON mode - log each message as a [`LogEntry`], with its
+/// direction, its original id and, for responses, how long the request took
+///
+/// With a `trace_file`, messages are also written to it as conductor trace
+/// events (see [`trace`]).
+pub async fn run_json(
+    log_file: PathBuf,
+    trace_file: Option<PathBuf>,
+    downstream: sacp_tokio::AcpAgent,
+) -> Result<()> {
+    init_tracing();
+
+    tracing::info!(
+        "Starting sacp-tee in JSON mode, logging to: {}",
+        log_file.display()
+    );
+
+    let mut timer = RequestTimer::default();
+    tee_lines(&log_file, trace_file, downstream, |direction, line| {
+        let Some(message) = JsonRpcMessage::from_line(line) else {
+            tracing::warn!(?direction, "Not logging non-JSON-RPC line: {line}");
+            return None;
+        };
+        let entry = timer.entry(direction, message);
+        serde_json::to_string(&entry).ok().map(|json| json + "\n")
+    })
+    .await
+}
+
+/// Spawn the downstream process and copy lines between it and stdio, logging
+/// whatever `format` returns for each line and tracing JSON-RPC messages to
+/// `trace_file`.
+async fn tee_lines(
+    log_file: &Path,
+    trace_file: Option<PathBuf>,
+    downstream: sacp_tokio::AcpAgent,
+    mut format: impl FnMut(Direction, &str) -> Option<String>,
+) -> Result<()> {
+    use sacp_conductor::trace::TraceWriter;
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
     // Open log file
     let log_file = tokio::fs::OpenOptions::new()
         .create(true)
         .append(true)
-        .open(&log_file)
+        .open(log_file)
         .await?;
     let mut log_writer = tokio::io::BufWriter::new(log_file);
 
+    let trace_writer = trace_file.map(TraceWriter::from_path).transpose()?;
+    let trace = |direction, line: &str| {
+        if let Some(writer) = &trace_writer
+            && let Some(message) = JsonRpcMessage::from_line(line)
+        {
+            writer.write_event(&trace::trace_event(writer.elapsed(), direction, message));
+        }
+    };
+
     // Spawn the downstream process
-    let (mut child_stdin, child_stdout, _child_stderr, _child) = downstream.spawn_process()?;
+    let (mut child_stdin, child_stdout, mut child_stderr, _child) = downstream.spawn_process()?;
     let mut child_stdout = BufReader::new(child_stdout).lines();
 
+    // Pass the child's stderr through, so that it cannot fill the pipe and stall the child
+    tokio::spawn(async move {
+        let _ = tokio::io::copy(&mut child_stderr, &mut tokio::io::stderr()).await;
+    });
+
     // Get stdin/stdout
     let stdin = tokio::io::stdin();
     let mut stdin_lines = BufReader::new(stdin).lines();
@@ -265,10 +440,11 @@ pub async fn run_raw(log_file: PathBuf, downstream: sacp_tokio::AcpAgent) -> Res
                         child_stdin.flush().await?;
 
                         // Log outgoing
-                        log_writer.write_all(b"\xE2\x86\x92 ").await?; // → arrow
-                        log_writer.write_all(line.as_bytes()).await?;
-                        log_writer.write_all(b"\n").await?;
-                        log_writer.flush().await?;
+                        trace(Direction::Downstream, &line);
+                        if let Some(entry) = format(Direction::Downstream, &line) {
+                            log_writer.write_all(entry.as_bytes()).await?;
+                            log_writer.flush().await?;
+                        }
                     }
                     None => {
                         // stdin closed
@@ -287,10 +463,11 @@ pub async fn run_raw(log_file: PathBuf, downstream: sacp_tokio::AcpAgent) -> Res
                         stdout.flush().await?;
 
                         // Log incoming
-                        log_writer.write_all(b"\xE2\x86\x90 ").await?; // ← arrow
-                        log_writer.write_all(line.as_bytes()).await?;
-                        log_writer.write_all(b"\n").await?;
-                        log_writer.flush().await?;
+                        trace(Direction::Upstream, &line);
+                        if let Some(entry) = format(Direction::Upstream, &line) {
+                            log_writer.write_all(entry.as_bytes()).await?;
+                            log_writer.flush().await?;
+                        }
                     }
                     None => {
                         // child stdout closed
@@ -304,9 +481,7 @@ pub async fn run_raw(log_file: PathBuf, downstream: sacp_tokio::AcpAgent) -> Res
     Ok(())
 }
 
-/// Run the tee proxy as a standalone binary connected to stdio
-pub async fn run(log_file: PathBuf) -> Result<()> {
-    // Initialize tracing
+fn init_tracing() {
     tracing_subscriber::fmt()
         .with_writer(std::io::stderr)
         .with_env_filter(
@@ -314,6 +489,11 @@ pub async fn run(log_file: PathBuf) -> Result<()> {
                 .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("info")),
         )
         .init();
+}
+
+/// Run the tee proxy as a standalone binary connected to stdio
+pub async fn run(log_file: PathBuf) -> Result<()> {
+    init_tracing();
 
     tracing::info!("Starting sacp-tee, logging to: {}", log_file.display());
 
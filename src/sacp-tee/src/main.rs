@@ -3,9 +3,13 @@
 //! Usage:
 //!   sacp-tee --log-file debug.log -- python agent.py
 //!   sacp-tee --json --log-file debug.log -- python agent.py
+//!   sacp-conductor agent "sacp-tee --json --log-file debug.log" "python agent.py"
+//!   sacp-tee --trace debug.jsons -- python agent.py
+//!   sacp-tee --convert debug.log --trace debug.jsons
 
 use anyhow::Result;
 use clap::Parser;
+use sacp_conductor::trace::EventWriter;
 use sacp_tokio::AcpAgent;
 use std::path::PathBuf;
 use std::str::FromStr;
@@ -18,10 +22,21 @@ struct Args {
     #[arg(short, long, default_value = "sacp-tee.log")]
     log_file: PathBuf,
 
-    /// Use JSON structured logging instead of raw line-by-line logging
+    /// Use JSON structured logging instead of raw line-by-line logging.
+    /// Without a command, runs as a proxy in a conductor chain.
     #[arg(long)]
     json: bool,
 
+    /// Also write messages to this file as trace events for sacp-trace-viewer.
+    /// Requires a downstream command, or --convert.
+    #[arg(long, value_name = "PATH")]
+    trace: Option<PathBuf>,
+
+    /// Convert an existing sacp-tee log (raw or JSON) to trace events, writing them to
+    /// --trace or stdout, instead of running an agent
+    #[arg(long, value_name = "LOG", conflicts_with_all = ["json", "command"])]
+    convert: Option<PathBuf>,
+
     /// Command to run for the downstream agent
     #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
     command: Vec<String>,
@@ -31,23 +46,41 @@ struct Args {
 async fn main() -> Result<()> {
     let args = Args::parse();
 
+    if let Some(log) = &args.convert {
+        let input = std::io::BufReader::new(std::fs::File::open(log)?);
+        let count = match &args.trace {
+            Some(path) => {
+                let file = std::io::BufWriter::new(std::fs::File::create(path)?);
+                sacp_tee::trace::convert_log(input, &mut EventWriter::new(file))?
+            }
+            None => sacp_tee::trace::convert_log(input, &mut EventWriter::new(std::io::stdout()))?,
+        };
+        eprintln!("Converted {count} messages");
+        return Ok(());
+    }
+
+    if args.json && args.command.is_empty() {
+        // No downstream command: run as a proxy component in a conductor chain
+        if args.trace.is_some() {
+            eprintln!("Error: --trace requires a downstream command");
+            std::process::exit(1);
+        }
+        return sacp_tee::run(args.log_file).await;
+    }
+
+    if args.command.is_empty() {
+        eprintln!("Error: raw mode requires a downstream command");
+        eprintln!("Usage: sacp-tee --log-file debug.log -- python agent.py");
+        std::process::exit(1);
+    }
+
+    let command_str = args.command.join(" ");
+    let downstream = AcpAgent::from_str(&command_str)?;
     if args.json {
         // Use structured JSON logging mode
-        if !args.command.is_empty() {
-            eprintln!("Warning: --json mode does not support spawning downstream commands yet");
-            eprintln!("The downstream agent should connect via stdio");
-        }
-        sacp_tee::run(args.log_file).await
+        sacp_tee::run_json(args.log_file, args.trace, downstream).await
     } else {
         // Use raw line-by-line logging mode (default)
-        if args.command.is_empty() {
-            eprintln!("Error: raw mode requires a downstream command");
-            eprintln!("Usage: sacp-tee --log-file debug.log -- python agent.py");
-            std::process::exit(1);
-        }
-
-        let command_str = args.command.join(" ");
-        let downstream = AcpAgent::from_str(&command_str)?;
-        sacp_tee::run_raw(args.log_file, downstream).await
+        sacp_tee::run_raw(args.log_file, args.trace, downstream).await
     }
 }
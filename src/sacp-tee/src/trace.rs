@@ -0,0 +1,110 @@
+//! Converting tee output to conductor trace events.
+//!
+//! The trace viewer reads the `.jsons` traces written by `sacp-conductor`. A tee
+//! sees the same traffic between a client and an agent, so what it logs can be
+//! written in that format too, with the client and agent as the two components:
+//! either while it runs (`--trace`) or afterwards from its log (`--convert`).
+
+use std::io::BufRead;
+
+use sacp_conductor::trace::{
+    NotificationEvent, RequestEvent, ResponseEvent, TraceEvent, WriteEvent, traced_message,
+    traced_session,
+};
+
+use crate::{Direction, JsonRpcMessage, LogEntry};
+
+/// The trace event for a message seen by the tee `ts` seconds into the trace.
+///
+/// MCP-over-ACP messages are unwrapped into their MCP message, as the conductor
+/// does. Responses are from the component the matching request was sent to, so
+/// the viewer pairs them by id.
+pub fn trace_event(ts: f64, direction: Direction, message: JsonRpcMessage) -> TraceEvent {
+    let (from, to) = match direction {
+        Direction::Downstream => ("client", "agent"),
+        Direction::Upstream => ("agent", "client"),
+    };
+    let (from, to) = (from.to_string(), to.to_string());
+    match message {
+        JsonRpcMessage::Request { id, message } => {
+            let (protocol, method, params) = traced_message(message);
+            TraceEvent::Request(RequestEvent {
+                ts,
+                protocol,
+                from,
+                to,
+                id,
+                method,
+                session: traced_session(&params),
+                params,
+            })
+        }
+        JsonRpcMessage::Notification { message } => {
+            let (protocol, method, params) = traced_message(message);
+            TraceEvent::Notification(NotificationEvent {
+                ts,
+                protocol,
+                from,
+                to,
+                method,
+                session: traced_session(&params),
+                params,
+            })
+        }
+        JsonRpcMessage::Reply { id, result } => TraceEvent::Response(ResponseEvent {
+            ts,
+            from,
+            to,
+            id,
+            is_error: false,
+            payload: result,
+        }),
+        JsonRpcMessage::Error { id, error } => TraceEvent::Response(ResponseEvent {
+            ts,
+            from,
+            to,
+            id,
+            is_error: true,
+            payload: error,
+        }),
+    }
+}
+
+/// Convert a log written by the tee to trace events, returning how many were
+/// written.
+///
+/// Both JSON-mode entries and raw-mode lines are understood. Raw lines carry no
+/// timestamps, so each is placed a millisecond after the line before it. Lines
+/// that are neither, such as non-JSON-RPC output in a raw log, are skipped.
+pub fn convert_log(input: impl BufRead, output: &mut impl WriteEvent) -> std::io::Result<usize> {
+    let mut start = None;
+    let mut ts = 0.0;
+    let mut written = 0;
+    for line in input.lines() {
+        let line = line?;
+        let (direction, message) = if let Ok(entry) = serde_json::from_str::<LogEntry>(&line) {
+            if let Ok(time) = chrono::DateTime::parse_from_rfc3339(&entry.timestamp) {
+                let start = *start.get_or_insert(time);
+                ts = (time - start).as_seconds_f64();
+            }
+            (entry.direction, entry.message)
+        } else {
+            let parsed = [Direction::Downstream, Direction::Upstream]
+                .into_iter()
+                .find_map(|direction| {
+                    let rest = line.strip_prefix(direction.arrow())?.strip_prefix(' ')?;
+                    Some((direction, JsonRpcMessage::from_line(rest)?))
+                });
+            let Some(parsed) = parsed else {
+                continue;
+            };
+            if written > 0 {
+                ts += 0.001;
+            }
+            parsed
+        };
+        output.write_event(&trace_event(ts, direction, message))?;
+        written += 1;
+    }
+    Ok(written)
+}
@@ -0,0 +1,172 @@
+//! Tests for the structured log written in JSON mode.
+
+use sacp::Component;
+use sacp::link::AgentToClient;
+use sacp_conductor::{Conductor, ProxiesAndAgent};
+use sacp_tee::{Direction, JsonRpcMessage, LogEntry, Tee};
+use serde_json::json;
+use tokio::io::duplex;
+use tokio_util::compat::{TokioAsyncReadCompatExt, TokioAsyncWriteCompatExt};
+
+#[test]
+fn test_from_line() {
+    let request = JsonRpcMessage::from_line(
+        r#"{"jsonrpc":"2.0","id":"a","method":"session/new","params":{"cwd":"/"}}"#,
+    );
+    assert!(matches!(
+        request,
+        Some(JsonRpcMessage::Request { id, message })
+            if id == json!("a") && message.method == "session/new" && message.params == json!({"cwd": "/"})
+    ));
+
+    let notification = JsonRpcMessage::from_line(r#"{"jsonrpc":"2.0","method":"initialized"}"#);
+    assert!(matches!(
+        notification,
+        Some(JsonRpcMessage::Notification { message }) if message.params.is_null()
+    ));
+
+    let error = JsonRpcMessage::from_line(
+        r#"{"jsonrpc":"2.0","id":3,"error":{"code":-32601,"message":"Method not found","data":"x/y"}}"#,
+    );
+    assert!(matches!(
+        error,
+        Some(JsonRpcMessage::Error { id, error })
+            if id == json!(3) && error == json!({"code": -32601, "message": "Method not found", "data": "x/y"})
+    ));
+
+    let reply = JsonRpcMessage::from_line(r#"{"jsonrpc":"2.0","id":3,"result":null}"#);
+    assert!(matches!(reply, Some(JsonRpcMessage::Reply { result, .. }) if result.is_null()));
+
+    assert!(JsonRpcMessage::from_line("Listening on stdio").is_none());
+    assert!(JsonRpcMessage::from_line(r#"{"jsonrpc":"2.0"}"#).is_none());
+}
+
+struct MockEliza;
+
+impl Component<AgentToClient> for MockEliza {
+    async fn serve(
+        self,
+        client: impl Component<sacp::link::ClientToAgent>,
+    ) -> Result<(), sacp::Error> {
+        Component::<AgentToClient>::serve(elizacp::ElizaAgent::new(), client).await
+    }
+}
+
+#[tokio::test]
+async fn test_tee_proxy_logs_directions() -> Result<(), sacp::Error> {
+    let log_file = std::env::temp_dir().join(format!("sacp_tee_test_{}.log", std::process::id()));
+    let _ = std::fs::remove_file(&log_file);
+
+    let (editor_write, conductor_read) = duplex(8192);
+    let (conductor_write, editor_read) = duplex(8192);
+    let conductor_handle = tokio::spawn({
+        let log_file = log_file.clone();
+        async move {
+            Conductor::new_agent(
+                "conductor".to_string(),
+                ProxiesAndAgent::new(MockEliza).proxy(Tee::new(log_file)),
+                Default::default(),
+            )
+            .run(sacp::ByteStreams::new(
+                conductor_write.compat_write(),
+                conductor_read.compat(),
+            ))
+            .await
+        }
+    });
+
+    let reply = yopo::prompt(
+        sacp::ByteStreams::new(editor_write.compat_write(), editor_read.compat()),
+        "Hello",
+    )
+    .await?;
+    assert!(!reply.is_empty());
+
+    // Give the log writer a moment to catch up.
+    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    conductor_handle.abort();
+    let log = std::fs::read_to_string(&log_file).expect("Failed to read log file");
+    let _ = std::fs::remove_file(&log_file);
+    let entries: Vec<LogEntry> = log
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("Invalid log entry"))
+        .collect();
+
+    let method = |entry: &LogEntry| match &entry.message {
+        JsonRpcMessage::Request { message, .. } | JsonRpcMessage::Notification { message } => {
+            Some(message.method.clone())
+        }
+        _ => None,
+    };
+
+    // The prompt goes down to the agent and its reply comes back up, timed.
+    let prompt = entries
+        .iter()
+        .find(|e| method(e).as_deref() == Some("session/prompt"))
+        .expect("prompt not logged");
+    assert_eq!(prompt.direction, Direction::Downstream);
+    let JsonRpcMessage::Request { id: prompt_id, .. } = &prompt.message else {
+        unreachable!()
+    };
+    let reply = entries
+        .iter()
+        .find(|e| {
+            matches!(&e.message, JsonRpcMessage::Reply { id, result }
+            if id == prompt_id && result.get("stopReason").is_some())
+        })
+        .expect("prompt reply not logged");
+    assert_eq!(reply.direction, Direction::Upstream);
+    assert!(reply.duration_ms.is_some());
+
+    // Updates from the agent are logged unwrapped, going up.
+    let update = entries
+        .iter()
+        .find(|e| method(e).as_deref() == Some("session/update"))
+        .expect("update not logged");
+    assert_eq!(update.direction, Direction::Upstream);
+    assert!(
+        !entries
+            .iter()
+            .any(|e| method(e).as_deref() == Some("_proxy/successor"))
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_json_mode_drains_agent_stderr() {
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+    let log_file =
+        std::env::temp_dir().join(format!("sacp_tee_stderr_test_{}.log", std::process::id()));
+    let _ = std::fs::remove_file(&log_file);
+
+    // An agent that writes far more than a pipe buffer to stderr before echoing stdin.
+    let mut tee = tokio::process::Command::new(env!("CARGO_BIN_EXE_sacp-tee"))
+        .arg("--json")
+        .arg("--log-file")
+        .arg(&log_file)
+        .arg("--")
+        .arg("sh -c 'yes noise from the agent | head -n 50000 >&2; exec cat'")
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::null())
+        .kill_on_drop(true)
+        .spawn()
+        .unwrap();
+    let mut stdin = tee.stdin.take().unwrap();
+    let mut stdout = BufReader::new(tee.stdout.take().unwrap()).lines();
+
+    let request = r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{}}"#;
+    stdin.write_all(request.as_bytes()).await.unwrap();
+    stdin.write_all(b"\n").await.unwrap();
+
+    let echoed = tokio::time::timeout(std::time::Duration::from_secs(10), stdout.next_line())
+        .await
+        .expect("the agent stalled writing to stderr")
+        .unwrap();
+    assert_eq!(echoed.as_deref(), Some(request));
+
+    drop(stdin);
+    tee.wait().await.unwrap();
+    let _ = std::fs::remove_file(&log_file);
+}
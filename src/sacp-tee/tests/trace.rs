@@ -0,0 +1,101 @@
+//! Tests for converting tee logs to conductor trace events.
+
+use sacp_conductor::trace::{Protocol, TraceEvent};
+use serde_json::json;
+
+fn convert(log: &str) -> Vec<TraceEvent> {
+    let (tx, rx) = futures::channel::mpsc::unbounded();
+    let mut tx = tx;
+    let count = sacp_tee::trace::convert_log(log.as_bytes(), &mut tx).unwrap();
+    drop(tx);
+    let events: Vec<_> = futures::executor::block_on_stream(rx).collect();
+    assert_eq!(count, events.len());
+    events
+}
+
+#[test]
+fn test_convert_json_log() {
+    let log = [
+        json!({"timestamp": "2025-01-01T00:00:00Z", "direction": "downstream",
+               "message": {"id": 1, "method": "session/prompt",
+                           "params": {"sessionId": "s1", "prompt": []}}}),
+        json!({"timestamp": "2025-01-01T00:00:00.250Z", "direction": "upstream",
+               "message": {"id": 0, "method": "_mcp/message",
+                           "params": {"connectionId": "c1", "method": "tools/call",
+                                      "params": {"name": "search"}}}}),
+        json!({"timestamp": "2025-01-01T00:00:00.500Z", "direction": "downstream",
+               "message": {"id": 0, "error": {"code": -32602, "message": "no such tool"}},
+               "duration_ms": 250.0}),
+        json!({"timestamp": "2025-01-01T00:00:01Z", "direction": "upstream",
+               "message": {"id": 1, "result": {"stopReason": "end_turn"}},
+               "duration_ms": 1000.0}),
+    ]
+    .map(|entry| entry.to_string())
+    .join("\n");
+
+    let events = convert(&log);
+    let [
+        TraceEvent::Request(prompt),
+        TraceEvent::Request(tool),
+        TraceEvent::Response(tool_error),
+        TraceEvent::Response(reply),
+    ] = &events[..]
+    else {
+        panic!("unexpected events {events:#?}");
+    };
+
+    assert_eq!(
+        (prompt.from.as_str(), prompt.to.as_str(), prompt.ts),
+        ("client", "agent", 0.0)
+    );
+    assert_eq!(prompt.session.as_deref(), Some("s1"));
+
+    // The MCP call is unwrapped from its MCP-over-ACP envelope.
+    assert_eq!(tool.protocol, Protocol::Mcp);
+    assert_eq!(
+        (tool.method.as_str(), &tool.params),
+        ("tools/call", &json!({"name": "search"}))
+    );
+    assert_eq!((tool.from.as_str(), tool.ts), ("agent", 0.25));
+
+    assert!(tool_error.is_error);
+    assert_eq!(
+        (tool_error.from.as_str(), tool_error.to.as_str()),
+        ("client", "agent")
+    );
+    assert_eq!(
+        tool_error.payload,
+        json!({"code": -32602, "message": "no such tool"})
+    );
+
+    assert_eq!((reply.id.clone(), reply.ts), (json!(1), 1.0));
+    assert!(!reply.is_error);
+}
+
+#[test]
+fn test_convert_raw_log() {
+    let log = "\
+→ {\"jsonrpc\":\"2.0\",\"id\":1,\"method\":\"initialize\",\"params\":{}}
+← agent starting up
+← {\"jsonrpc\":\"2.0\",\"method\":\"session/update\",\"params\":{\"sessionId\":\"s1\"}}
+← {\"jsonrpc\":\"2.0\",\"id\":1,\"result\":{}}
+";
+    let events = convert(log);
+    let summary: Vec<_> = events
+        .iter()
+        .map(|event| match event {
+            TraceEvent::Request(e) => (e.ts, e.from.as_str(), e.method.as_str()),
+            TraceEvent::Notification(e) => (e.ts, e.from.as_str(), e.method.as_str()),
+            TraceEvent::Response(e) => (e.ts, e.from.as_str(), "response"),
+            TraceEvent::Trace(e) => panic!("unexpected log {e:?}"),
+        })
+        .collect();
+    assert_eq!(
+        summary,
+        [
+            (0.0, "client", "initialize"),
+            (0.001, "agent", "session/update"),
+            (0.002, "agent", "response"),
+        ]
+    );
+}
@@ -1,7 +1,7 @@
 //! Utilities for connecting to ACP agents and proxies.
 //!
 //! This module provides [`AcpAgent`], a convenient wrapper around [`sacp::schema::McpServer`]
-//! that can be parsed from either a command string or JSON configuration.
+//! that can be parsed from either a command string, an endpoint URL, or JSON configuration.
 
 use std::path::PathBuf;
 use std::str::FromStr;
@@ -10,6 +10,10 @@ use std::sync::Arc;
 use tokio::process::Child;
 use tokio_util::compat::{TokioAsyncReadCompatExt, TokioAsyncWriteCompatExt};
 
+use crate::AcpEndpoint;
+use crate::StderrPolicy;
+use crate::process::{ChildGuard, ProcessConfig};
+
 /// Direction of a line being sent or received.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum LineDirection {
@@ -21,6 +25,9 @@ pub enum LineDirection {
     Stderr,
 }
 
+/// Callback invoked with each line sent to or received from an agent.
+pub(crate) type DebugCallback = Arc<dyn Fn(&str, LineDirection) + Send + Sync + 'static>;
+
 /// A component representing an external ACP agent running in a separate process.
 ///
 /// `AcpAgent` implements the [`sacp::Component`] trait for spawning and communicating with
@@ -28,8 +35,16 @@ pub enum LineDirection {
 /// byte stream serialization automatically. This is the primary way to connect to agents
 /// that run as separate executables.
 ///
+/// An agent that is already running and listening on the network (see
+/// [`AcpListener`](crate::AcpListener)) is given by an HTTP server configuration whose URL
+/// is a `tcp://`, `unix://`, `ws://` or `wss://` [`AcpEndpoint`]; `AcpAgent` then connects
+/// to it rather than spawning anything.
+///
 /// This is a wrapper around [`sacp::schema::McpServer`] that provides convenient parsing
-/// from command-line strings or JSON configurations.
+/// from command-line strings or JSON configurations. How the process is spawned beyond
+/// that -- working directory, inherited environment, resource limits, process group --
+/// is set with [`with_process_config`](Self::with_process_config), and what happens to
+/// its stderr with [`with_stderr_policy`](Self::with_stderr_policy).
 ///
 /// # Use Cases
 ///
@@ -47,6 +62,13 @@ pub enum LineDirection {
 /// let agent = AcpAgent::from_str("python my_agent.py --verbose").unwrap();
 /// ```
 ///
+/// Parse from an endpoint URL:
+/// ```
+/// # use sacp_tokio::AcpAgent;
+/// # use std::str::FromStr;
+/// let agent = AcpAgent::from_str("tcp://localhost:4000").unwrap();
+/// ```
+///
 /// Parse from JSON:
 /// ```
 /// # use sacp_tokio::AcpAgent;
@@ -78,13 +100,21 @@ pub enum LineDirection {
 /// [`sacp_conductor::Conductor`]: https://docs.rs/sacp-conductor/latest/sacp_conductor/struct.Conductor.html
 pub struct AcpAgent {
     server: sacp::schema::McpServer,
-    debug_callback: Option<Arc<dyn Fn(&str, LineDirection) + Send + Sync + 'static>>,
+    process_config: ProcessConfig,
+    stderr_policy: StderrPolicy,
+    framing: sacp::Framing,
+    max_message_size: Option<usize>,
+    debug_callback: Option<DebugCallback>,
 }
 
 impl std::fmt::Debug for AcpAgent {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("AcpAgent")
             .field("server", &self.server)
+            .field("process_config", &self.process_config)
+            .field("stderr_policy", &self.stderr_policy)
+            .field("framing", &self.framing)
+            .field("max_message_size", &self.max_message_size)
             .field(
                 "debug_callback",
                 &self.debug_callback.as_ref().map(|_| "..."),
@@ -98,6 +128,10 @@ impl AcpAgent {
     pub fn new(server: sacp::schema::McpServer) -> Self {
         Self {
             server,
+            process_config: ProcessConfig::default(),
+            stderr_policy: StderrPolicy::default(),
+            framing: sacp::Framing::default(),
+            max_message_size: None,
             debug_callback: None,
         }
     }
@@ -105,20 +139,28 @@ impl AcpAgent {
     /// Create an ACP agent for Zed Industries' Claude Code tool.
     /// Just runs `npx -y @zed-industries/claude-code-acp@latest`.
     pub fn zed_claude_code() -> Self {
-        Self::from_str("npx -y @zed-industries/claude-code-acp@latest").expect("valid bash command")
+        Self::npx("npx -y @zed-industries/claude-code-acp@latest")
     }
 
     /// Create an ACP agent for Zed Industries' Codex tool.
     /// Just runs `npx -y @zed-industries/codex-acp@latest`.
     pub fn zed_codex() -> Self {
-        Self::from_str("npx -y @zed-industries/codex-acp@latest").expect("valid bash command")
+        Self::npx("npx -y @zed-industries/codex-acp@latest")
     }
 
     /// Create an ACP agent for Google's Gemini CLI.
     /// Just runs `npx -y -- @google/gemini-cli@latest --experimental-acp`.
     pub fn google_gemini() -> Self {
-        Self::from_str("npx -y -- @google/gemini-cli@latest --experimental-acp")
-            .expect("valid bash command")
+        Self::npx("npx -y -- @google/gemini-cli@latest --experimental-acp")
+    }
+
+    /// An agent launched through `npx`, which runs it as a grandchild,
+    /// so it gets its own process group to be cleaned up along with `npx`.
+    fn npx(command: &str) -> Self {
+        let agent = Self::from_str(command).expect("valid bash command");
+        #[cfg(unix)]
+        let agent = agent.with_process_config(ProcessConfig::new().process_group(true));
+        agent
     }
 
     /// Get the underlying [`sacp::schema::McpServer`] configuration.
@@ -131,6 +173,52 @@ impl AcpAgent {
         self.server
     }
 
+    /// Set how the agent process is spawned.
+    ///
+    /// See [`ProcessConfig`] for the available options. This has no effect on agents
+    /// that are reached over the network.
+    pub fn with_process_config(mut self, config: ProcessConfig) -> Self {
+        self.process_config = config;
+        self
+    }
+
+    /// Get the configuration for how the agent process is spawned.
+    pub fn process_config(&self) -> &ProcessConfig {
+        &self.process_config
+    }
+
+    /// Set what happens to the agent's stderr output.
+    ///
+    /// See [`StderrPolicy`] for the available options.
+    pub fn with_stderr_policy(mut self, policy: StderrPolicy) -> Self {
+        self.stderr_policy = policy;
+        self
+    }
+
+    /// Get the policy for the agent's stderr output.
+    pub fn stderr_policy(&self) -> &StderrPolicy {
+        &self.stderr_policy
+    }
+
+    /// Set how messages are delimited on the agent's stdin and stdout.
+    ///
+    /// Agents normally use newline-delimited JSON; use [`sacp::Framing::ContentLength`]
+    /// for tools that expect LSP-style `Content-Length` headers.
+    pub fn with_framing(mut self, framing: sacp::Framing) -> Self {
+        self.framing = framing;
+        self
+    }
+
+    /// Refuse messages from the agent that are larger than `bytes`.
+    ///
+    /// Oversized messages are skipped without being read into memory, and handled
+    /// as described in [`sacp::Lines::with_max_message_size`]: the agent gets an
+    /// error response to an oversized request, and the connection stays up.
+    pub fn with_max_message_size(mut self, bytes: usize) -> Self {
+        self.max_message_size = Some(bytes);
+        self
+    }
+
     /// Add a debug callback that will be invoked for each line sent/received.
     ///
     /// The callback receives the line content and the direction (stdin/stdout/stderr).
@@ -157,6 +245,9 @@ impl AcpAgent {
 
     /// Spawn the process and get stdio streams.
     /// Used internally by the Component trait implementation.
+    ///
+    /// The process is spawned according to the agent's [`ProcessConfig`], but the
+    /// caller is responsible for killing it (and its process group, if any).
     pub fn spawn_process(
         &self,
     ) -> Result<
@@ -172,6 +263,7 @@ impl AcpAgent {
             sacp::schema::McpServer::Stdio(stdio) => {
                 let mut cmd = tokio::process::Command::new(&stdio.command);
                 cmd.args(&stdio.args);
+                self.process_config.apply(&mut cmd);
                 for env_var in &stdio.env {
                     cmd.env(&env_var.name, &env_var.value);
                 }
@@ -196,6 +288,12 @@ impl AcpAgent {
 
                 Ok((child_stdin, child_stdout, child_stderr, child))
             }
+            sacp::schema::McpServer::Http(http) if AcpEndpoint::is_endpoint_url(&http.url) => {
+                Err(sacp::util::internal_error(format!(
+                    "AcpAgent connects to `{}` rather than spawning a process",
+                    http.url
+                )))
+            }
             sacp::schema::McpServer::Http(_) => Err(sacp::util::internal_error(
                 "HTTP transport not yet supported by AcpAgent",
             )),
@@ -209,31 +307,14 @@ impl AcpAgent {
     }
 }
 
-/// A wrapper around Child that kills the process when dropped.
-struct ChildGuard(Child);
-
-impl ChildGuard {
-    async fn wait(&mut self) -> std::io::Result<std::process::ExitStatus> {
-        self.0.wait().await
-    }
-}
-
-impl Drop for ChildGuard {
-    fn drop(&mut self) {
-        let _ = self.0.start_kill();
-    }
-}
-
 /// Waits for a child process and returns an error if it exits with non-zero status.
 ///
-/// The error message includes any stderr output collected by the background task.
-/// When dropped, the child process is killed.
+/// The error message includes the tail of the stderr output collected by the background task.
+/// When dropped, the child process (and its process group, if it has one) is killed.
 async fn monitor_child(
-    child: Child,
+    mut guard: ChildGuard,
     stderr_rx: tokio::sync::oneshot::Receiver<String>,
 ) -> Result<(), sacp::Error> {
-    let mut guard = ChildGuard(child);
-
     // Wait for the child to exit
     let status = guard
         .wait()
@@ -256,88 +337,103 @@ async fn monitor_child(
     }
 }
 
+/// Serve `client` over a pair of message streams, passing each message to the debug
+/// callback (if any) on its way and refusing those over `max_message_size`.
+async fn serve_lines<L: sacp::link::JrLink>(
+    debug_callback: Option<DebugCallback>,
+    max_message_size: Option<usize>,
+    outgoing: impl futures::Sink<String, Error = std::io::Error> + Send + 'static,
+    incoming: impl futures::Stream<Item = std::io::Result<String>> + Send + 'static,
+    client: impl sacp::Component<L::ConnectsTo>,
+) -> Result<(), sacp::Error> {
+    use futures::{SinkExt, StreamExt};
+
+    match debug_callback {
+        Some(callback) => {
+            let outgoing_callback = callback.clone();
+            let outgoing = outgoing.with(move |line: String| {
+                outgoing_callback(&line, LineDirection::Stdin);
+                futures::future::ready(Ok::<_, std::io::Error>(line))
+            });
+            let incoming = incoming.inspect(move |result| {
+                if let Ok(line) = result {
+                    callback(line, LineDirection::Stdout);
+                }
+            });
+            let lines = limited_lines(outgoing, incoming, max_message_size);
+            sacp::Component::<L>::serve(lines, client).await
+        }
+        None => {
+            let lines = limited_lines(outgoing, incoming, max_message_size);
+            sacp::Component::<L>::serve(lines, client).await
+        }
+    }
+}
+
+/// [`sacp::Lines`] over the given streams, refusing messages over `max_message_size`.
+pub(crate) fn limited_lines<O, I>(
+    outgoing: O,
+    incoming: I,
+    max_message_size: Option<usize>,
+) -> sacp::Lines<O, I>
+where
+    O: futures::Sink<String, Error = std::io::Error> + Send + 'static,
+    I: futures::Stream<Item = std::io::Result<String>> + Send + 'static,
+{
+    let lines = sacp::Lines::new(outgoing, incoming);
+    match max_message_size {
+        Some(bytes) => lines.with_max_message_size(bytes),
+        None => lines,
+    }
+}
+
 impl<L: sacp::link::JrLink> sacp::Component<L> for AcpAgent {
     async fn serve(self, client: impl sacp::Component<L::ConnectsTo>) -> Result<(), sacp::Error> {
-        use futures::AsyncBufReadExt;
-        use futures::AsyncWriteExt;
-        use futures::StreamExt;
-        use futures::io::BufReader;
+        if let sacp::schema::McpServer::Http(http) = &self.server
+            && AcpEndpoint::is_endpoint_url(&http.url)
+        {
+            let endpoint: AcpEndpoint = http.url.parse()?;
+            return self.serve_network::<L>(endpoint, client).await;
+        }
 
         let (child_stdin, child_stdout, child_stderr, child) = self.spawn_process()?;
+        let child = ChildGuard::new(child, &self.process_config);
 
         // Create a channel to collect stderr for error reporting
         let (stderr_tx, stderr_rx) = tokio::sync::oneshot::channel::<String>();
 
-        // Spawn a task to read stderr, optionally calling the debug callback
-        let debug_callback = self.debug_callback.clone();
+        // Spawn a task to read stderr, forwarding it according to the stderr policy
+        let stderr = self.stderr_policy.clone().capture(
+            child_stderr,
+            match &self.server {
+                sacp::schema::McpServer::Stdio(stdio) => stdio.name.clone(),
+                _ => "agent".to_string(),
+            },
+            self.debug_callback.clone(),
+        );
         tokio::spawn(async move {
-            let stderr_reader = BufReader::new(child_stderr.compat());
-            let mut stderr_lines = stderr_reader.lines();
-            let mut collected = String::new();
-            while let Some(line_result) = stderr_lines.next().await {
-                if let Ok(line) = line_result {
-                    // Call debug callback if present
-                    if let Some(ref callback) = debug_callback {
-                        callback(&line, LineDirection::Stderr);
-                    }
-                    // Always collect for error reporting
-                    if !collected.is_empty() {
-                        collected.push('\n');
-                    }
-                    collected.push_str(&line);
-                }
-            }
-            let _ = stderr_tx.send(collected);
+            let _ = stderr_tx.send(stderr.await);
         });
 
         // Create a future that monitors the child process for early exit
         let child_monitor = monitor_child(child, stderr_rx);
 
-        // Convert stdio to line streams with optional debug inspection
-        let incoming_lines = if let Some(callback) = self.debug_callback.clone() {
-            Box::pin(
-                BufReader::new(child_stdout.compat())
-                    .lines()
-                    .inspect(move |result| {
-                        if let Ok(line) = result {
-                            callback(line, LineDirection::Stdout);
-                        }
-                    }),
-            )
-                as std::pin::Pin<Box<dyn futures::Stream<Item = std::io::Result<String>> + Send>>
-        } else {
-            Box::pin(BufReader::new(child_stdout.compat()).lines())
-        };
-
-        // Create a sink that writes lines (with newlines) to stdin with optional debug logging
-        let outgoing_sink = if let Some(callback) = self.debug_callback.clone() {
-            Box::pin(futures::sink::unfold(
-                (child_stdin.compat_write(), callback),
-                async move |(mut writer, callback), line: String| {
-                    callback(&line, LineDirection::Stdin);
-                    let mut bytes = line.into_bytes();
-                    bytes.push(b'\n');
-                    writer.write_all(&bytes).await?;
-                    Ok::<_, std::io::Error>((writer, callback))
-                },
-            ))
-                as std::pin::Pin<Box<dyn futures::Sink<String, Error = std::io::Error> + Send>>
-        } else {
-            Box::pin(futures::sink::unfold(
-                child_stdin.compat_write(),
-                async move |mut writer, line: String| {
-                    let mut bytes = line.into_bytes();
-                    bytes.push(b'\n');
-                    writer.write_all(&bytes).await?;
-                    Ok::<_, std::io::Error>(writer)
-                },
-            ))
-        };
+        // Convert stdio to message streams, with optional debug inspection
+        let (outgoing, incoming) = self.framing.frame_limited(
+            child_stdin.compat_write(),
+            child_stdout.compat(),
+            self.max_message_size,
+        );
 
         // Race the protocol against child process exit
         // If the child exits early (e.g., with an error), we return that error
-        let protocol_future =
-            sacp::Component::<L>::serve(sacp::Lines::new(outgoing_sink, incoming_lines), client);
+        let protocol_future = serve_lines::<L>(
+            self.debug_callback.clone(),
+            self.max_message_size,
+            outgoing,
+            incoming,
+            client,
+        );
 
         tokio::select! {
             result = protocol_future => result,
@@ -347,6 +443,27 @@ impl<L: sacp::link::JrLink> sacp::Component<L> for AcpAgent {
 }
 
 impl AcpAgent {
+    /// Connect to an agent that is already listening at `endpoint`.
+    async fn serve_network<L: sacp::link::JrLink>(
+        self,
+        endpoint: AcpEndpoint,
+        client: impl sacp::Component<L::ConnectsTo>,
+    ) -> Result<(), sacp::Error> {
+        let (outgoing, incoming) = endpoint
+            .connect()
+            .await?
+            .into_lines(self.max_message_size)
+            .await?;
+        serve_lines::<L>(
+            self.debug_callback,
+            self.max_message_size,
+            outgoing,
+            incoming,
+            client,
+        )
+        .await
+    }
+
     /// Create an `AcpAgent` from an iterator of command-line arguments.
     ///
     /// Leading arguments of the form `NAME=value` are parsed as environment variables.
@@ -410,6 +527,10 @@ impl AcpAgent {
                     .args(cmd_args)
                     .env(env),
             ),
+            process_config: ProcessConfig::default(),
+            stderr_policy: StderrPolicy::default(),
+            framing: sacp::Framing::default(),
+            max_message_size: None,
             debug_callback: None,
         })
     }
@@ -451,10 +572,15 @@ impl FromStr for AcpAgent {
         if trimmed.starts_with('{') {
             let server: sacp::schema::McpServer = serde_json::from_str(trimmed)
                 .map_err(|e| sacp::util::internal_error(format!("Failed to parse JSON: {}", e)))?;
-            return Ok(Self {
-                server,
-                debug_callback: None,
-            });
+            return Ok(Self::new(server));
+        }
+
+        // If it is an endpoint URL, connect to an agent that is already running
+        if AcpEndpoint::is_endpoint_url(trimmed) {
+            let endpoint: AcpEndpoint = trimmed.parse()?;
+            return Ok(Self::new(sacp::schema::McpServer::Http(
+                sacp::schema::McpServerHttp::new(endpoint.to_string(), endpoint.to_string()),
+            )));
         }
 
         // Otherwise, parse as a command string
@@ -532,6 +658,18 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_endpoint_url() {
+        let agent = AcpAgent::from_str("ws://localhost:4000/acp").unwrap();
+        match agent.server {
+            sacp::schema::McpServer::Http(http) => {
+                assert_eq!(http.url, "ws://localhost:4000/acp");
+                assert!(http.headers.is_empty());
+            }
+            _ => panic!("Expected Http variant"),
+        }
+    }
+
     #[test]
     fn test_parse_json_http() {
         let json = r#"{
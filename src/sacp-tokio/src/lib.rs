@@ -1,24 +1,34 @@
 //! Tokio-based utilities for SACP
 //!
 //! This crate provides higher-level functionality for working with SACP
-//! that requires the Tokio async runtime, such as spawning agent processes
-//! and creating connections.
+//! that requires the Tokio async runtime, such as spawning agent processes,
+//! connecting to agents over the network, and creating connections.
 
 mod acp_agent;
+mod network;
+mod process;
+mod stderr;
 
 pub use acp_agent::{AcpAgent, LineDirection};
+pub use network::{AcpEndpoint, AcpListener, AcpStream};
+pub use process::ProcessConfig;
+#[cfg(unix)]
+pub use process::Resource;
 use sacp::{ByteStreams, Component};
 use std::sync::Arc;
+pub use stderr::{StderrLine, StderrPolicy};
 use tokio_util::compat::{TokioAsyncReadCompatExt, TokioAsyncWriteCompatExt};
 
 pub struct Stdio {
     debug_callback: Option<Arc<dyn Fn(&str, LineDirection) + Send + Sync + 'static>>,
+    max_message_size: Option<usize>,
 }
 
 impl Stdio {
     pub fn new() -> Self {
         Self {
             debug_callback: None,
+            max_message_size: None,
         }
     }
 
@@ -29,6 +39,14 @@ impl Stdio {
         self.debug_callback = Some(Arc::new(callback));
         self
     }
+
+    /// Refuse messages on stdin larger than `bytes`, without reading them into memory.
+    ///
+    /// Oversized messages are handled as described in [`sacp::Lines::with_max_message_size`].
+    pub fn with_max_message_size(mut self, bytes: usize) -> Self {
+        self.max_message_size = Some(bytes);
+        self
+    }
 }
 
 impl Default for Stdio {
@@ -40,50 +58,41 @@ impl Default for Stdio {
 impl<L: sacp::link::JrLink> Component<L> for Stdio {
     async fn serve(self, client: impl Component<L::ConnectsTo>) -> Result<(), sacp::Error> {
         if let Some(callback) = self.debug_callback {
-            use futures::AsyncBufReadExt;
-            use futures::AsyncWriteExt;
-            use futures::StreamExt;
-            use futures::io::BufReader;
+            use futures::{SinkExt, StreamExt};
 
             // With debug: use Lines with interception
-            let stdin = tokio::io::stdin();
-            let stdout = tokio::io::stdout();
+            let (outgoing, incoming) = sacp::Framing::Newline.frame_limited(
+                tokio::io::stdout().compat_write(),
+                tokio::io::stdin().compat(),
+                self.max_message_size,
+            );
 
-            // Convert stdio to line streams with debug inspection
             let incoming_callback = callback.clone();
-            let incoming_lines = Box::pin(BufReader::new(stdin.compat()).lines().inspect(
-                move |result| {
-                    if let Ok(line) = result {
-                        incoming_callback(line, LineDirection::Stdin);
-                    }
-                },
-            ))
-                as std::pin::Pin<Box<dyn futures::Stream<Item = std::io::Result<String>> + Send>>;
-
-            // Create a sink that writes lines with debug logging
-            let outgoing_sink = Box::pin(futures::sink::unfold(
-                (stdout.compat_write(), callback),
-                async move |(mut writer, callback), line: String| {
-                    callback(&line, LineDirection::Stdout);
-                    let mut bytes = line.into_bytes();
-                    bytes.push(b'\n');
-                    writer.write_all(&bytes).await?;
-                    Ok::<_, std::io::Error>((writer, callback))
-                },
-            ))
-                as std::pin::Pin<Box<dyn futures::Sink<String, Error = std::io::Error> + Send>>;
+            let incoming = incoming.inspect(move |result| {
+                if let Ok(line) = result {
+                    incoming_callback(line, LineDirection::Stdin);
+                }
+            });
+            let outgoing = outgoing.with(move |line: String| {
+                callback(&line, LineDirection::Stdout);
+                futures::future::ready(Ok::<_, std::io::Error>(line))
+            });
 
-            Component::<L>::serve(sacp::Lines::new(outgoing_sink, incoming_lines), client).await
-        } else {
-            // Without debug: use simple ByteStreams
             Component::<L>::serve(
-                ByteStreams::new(
-                    tokio::io::stdout().compat_write(),
-                    tokio::io::stdin().compat(),
-                ),
+                acp_agent::limited_lines(outgoing, incoming, self.max_message_size),
                 client,
             )
             .await
+        } else {
+            // Without debug: use simple ByteStreams
+            let mut byte_streams = ByteStreams::new(
+                tokio::io::stdout().compat_write(),
+                tokio::io::stdin().compat(),
+            );
+            if let Some(bytes) = self.max_message_size {
+                byte_streams = byte_streams.with_max_message_size(bytes);
+            }
+            Component::<L>::serve(byte_streams, client).await
         }
     }
 }
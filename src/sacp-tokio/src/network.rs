@@ -0,0 +1,430 @@
+//! Components for talking to ACP agents over the network.
+//!
+//! An agent running in a long-lived process (say, in a container) can listen for
+//! connections with [`AcpListener`], and editors or conductors can attach to it
+//! with an [`AcpEndpoint`] or [`AcpAgent`](crate::AcpAgent) URL. TCP and Unix
+//! sockets carry newline-delimited JSON-RPC, as stdio does; WebSockets carry one
+//! JSON-RPC message per text frame.
+
+use std::fmt;
+#[cfg(unix)]
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::str::FromStr;
+use std::time::Duration;
+
+use futures::{SinkExt, StreamExt};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::{TcpListener, TcpStream};
+#[cfg(unix)]
+use tokio::net::{UnixListener, UnixStream};
+use tokio_tungstenite::WebSocketStream;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_util::compat::{TokioAsyncReadCompatExt, TokioAsyncWriteCompatExt};
+
+type LineSink = Pin<Box<dyn futures::Sink<String, Error = std::io::Error> + Send>>;
+type LineStream = Pin<Box<dyn futures::Stream<Item = std::io::Result<String>> + Send>>;
+
+/// How long an accepted connection may take to complete the WebSocket upgrade.
+const WEBSOCKET_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Where an ACP agent listens for connections.
+///
+/// Parsed from (and displayed as) a URL:
+///
+/// - `tcp://host:port` for a TCP socket
+/// - `unix:///path/to/socket` for a Unix domain socket
+/// - `ws://host:port/path` or `wss://host:port/path` for a WebSocket
+///
+/// As a [`Component`](sacp::Component), an endpoint connects to the agent when served.
+///
+/// # Example
+///
+/// ```no_run
+/// # use sacp::link::ClientToAgent;
+/// # use sacp_tokio::AcpEndpoint;
+/// # async fn example() -> Result<(), sacp::Error> {
+/// let agent: AcpEndpoint = "tcp://localhost:4000".parse()?;
+///
+/// ClientToAgent::builder()
+///     .run_until(agent, async |cx| {
+///         // Talk to the agent
+///         Ok(())
+///     })
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AcpEndpoint {
+    /// A TCP socket, as `host:port`
+    Tcp(String),
+
+    /// A Unix domain socket
+    #[cfg(unix)]
+    Unix(PathBuf),
+
+    /// A WebSocket, as a `ws://` or `wss://` URL
+    WebSocket(String),
+}
+
+impl AcpEndpoint {
+    /// True if `s` looks like an endpoint URL rather than, say, a command.
+    pub fn is_endpoint_url(s: &str) -> bool {
+        ["tcp://", "unix://", "ws://", "wss://"]
+            .iter()
+            .any(|scheme| s.starts_with(scheme))
+    }
+
+    /// Connect to the agent listening at this endpoint.
+    pub async fn connect(&self) -> Result<AcpStream, sacp::Error> {
+        let kind = match self {
+            AcpEndpoint::Tcp(addr) => StreamKind::Tcp(
+                TcpStream::connect(addr)
+                    .await
+                    .map_err(sacp::Error::into_internal_error)?,
+            ),
+
+            #[cfg(unix)]
+            AcpEndpoint::Unix(path) => StreamKind::Unix(
+                UnixStream::connect(path)
+                    .await
+                    .map_err(sacp::Error::into_internal_error)?,
+            ),
+
+            AcpEndpoint::WebSocket(url) => {
+                let (websocket, _response) = tokio_tungstenite::connect_async(url.as_str())
+                    .await
+                    .map_err(sacp::Error::into_internal_error)?;
+                StreamKind::WebSocketClient(Box::new(websocket))
+            }
+        };
+        Ok(AcpStream::new(kind))
+    }
+}
+
+impl FromStr for AcpEndpoint {
+    type Err = sacp::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(addr) = s.strip_prefix("tcp://") {
+            return Ok(AcpEndpoint::Tcp(addr.trim_end_matches('/').to_string()));
+        }
+
+        if let Some(path) = s.strip_prefix("unix://") {
+            #[cfg(unix)]
+            return Ok(AcpEndpoint::Unix(PathBuf::from(path)));
+
+            #[cfg(not(unix))]
+            return Err(sacp::util::internal_error(format!(
+                "Unix sockets are not supported on this platform: `{path}`"
+            )));
+        }
+
+        if s.starts_with("ws://") || s.starts_with("wss://") {
+            return Ok(AcpEndpoint::WebSocket(s.to_string()));
+        }
+
+        Err(sacp::util::internal_error(format!(
+            "`{s}` is not a tcp://, unix://, ws:// or wss:// URL"
+        )))
+    }
+}
+
+impl fmt::Display for AcpEndpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AcpEndpoint::Tcp(addr) => write!(f, "tcp://{addr}"),
+            #[cfg(unix)]
+            AcpEndpoint::Unix(path) => write!(f, "unix://{}", path.display()),
+            AcpEndpoint::WebSocket(url) => write!(f, "{url}"),
+        }
+    }
+}
+
+impl<L: sacp::link::JrLink> sacp::Component<L> for AcpEndpoint {
+    async fn serve(self, client: impl sacp::Component<L::ConnectsTo>) -> Result<(), sacp::Error> {
+        let stream = self.connect().await?;
+        sacp::Component::<L>::serve(stream, client).await
+    }
+}
+
+/// A connection to (or from) an ACP peer over the network.
+///
+/// Obtained from [`AcpEndpoint::connect`] or [`AcpListener::accept`], and used as a
+/// [`Component`](sacp::Component) just like [`sacp::ByteStreams`].
+pub struct AcpStream {
+    kind: StreamKind,
+    max_message_size: Option<usize>,
+}
+
+enum StreamKind {
+    Tcp(TcpStream),
+    #[cfg(unix)]
+    Unix(UnixStream),
+    WebSocketClient(Box<WebSocketStream<tokio_tungstenite::MaybeTlsStream<TcpStream>>>),
+    /// An accepted connection whose WebSocket upgrade has not happened yet.
+    WebSocketServer(TcpStream),
+}
+
+impl fmt::Debug for AcpStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match &self.kind {
+            StreamKind::Tcp(_) => "tcp",
+            #[cfg(unix)]
+            StreamKind::Unix(_) => "unix",
+            StreamKind::WebSocketClient(_) | StreamKind::WebSocketServer(_) => "websocket",
+        };
+        f.debug_struct("AcpStream")
+            .field("kind", &kind)
+            .field("max_message_size", &self.max_message_size)
+            .finish()
+    }
+}
+
+impl AcpStream {
+    fn new(kind: StreamKind) -> Self {
+        Self {
+            kind,
+            max_message_size: None,
+        }
+    }
+
+    /// Refuse incoming messages larger than `bytes`.
+    ///
+    /// Oversized messages are handled as described in [`sacp::Lines::with_max_message_size`].
+    /// On TCP and Unix sockets they are skipped without being read into memory;
+    /// WebSocket messages are checked once received.
+    pub fn with_max_message_size(mut self, bytes: usize) -> Self {
+        self.max_message_size = Some(bytes);
+        self
+    }
+
+    /// Split the connection into a sink of outgoing and a stream of incoming JSON-RPC messages.
+    ///
+    /// On byte streams, incoming lines longer than `max_message_size` are skipped
+    /// rather than read into memory. Connections accepted by a WebSocket listener
+    /// are upgraded here, failing if the peer does not complete the upgrade in time.
+    pub(crate) async fn into_lines(
+        self,
+        max_message_size: Option<usize>,
+    ) -> Result<(LineSink, LineStream), sacp::Error> {
+        Ok(match self.kind {
+            StreamKind::Tcp(stream) => byte_stream_lines(stream, max_message_size),
+            #[cfg(unix)]
+            StreamKind::Unix(stream) => byte_stream_lines(stream, max_message_size),
+            StreamKind::WebSocketClient(websocket) => websocket_lines(*websocket),
+            StreamKind::WebSocketServer(stream) => {
+                let websocket = tokio::time::timeout(
+                    WEBSOCKET_HANDSHAKE_TIMEOUT,
+                    tokio_tungstenite::accept_async(stream),
+                )
+                .await
+                .map_err(|_| {
+                    sacp::util::internal_error("timed out waiting for the WebSocket handshake")
+                })?
+                .map_err(sacp::Error::into_internal_error)?;
+                websocket_lines(websocket)
+            }
+        })
+    }
+}
+
+impl<L: sacp::link::JrLink> sacp::Component<L> for AcpStream {
+    async fn serve(self, client: impl sacp::Component<L::ConnectsTo>) -> Result<(), sacp::Error> {
+        let max_message_size = self.max_message_size;
+        let (outgoing, incoming) = self.into_lines(max_message_size).await?;
+        let lines = crate::acp_agent::limited_lines(outgoing, incoming, max_message_size);
+        sacp::Component::<L>::serve(lines, client).await
+    }
+}
+
+/// Newline-delimited messages over a byte stream.
+fn byte_stream_lines(
+    stream: impl AsyncRead + AsyncWrite + Send + 'static,
+    max_message_size: Option<usize>,
+) -> (LineSink, LineStream) {
+    let (read_half, write_half) = tokio::io::split(stream);
+    let (outgoing, incoming) = sacp::Framing::Newline.frame_limited(
+        write_half.compat_write(),
+        read_half.compat(),
+        max_message_size,
+    );
+    (Box::pin(outgoing), Box::pin(incoming))
+}
+
+/// One message per text frame over a WebSocket.
+fn websocket_lines<S>(websocket: WebSocketStream<S>) -> (LineSink, LineStream)
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let (sink, stream) = websocket.split();
+
+    let outgoing = Box::pin(
+        sink.sink_map_err(std::io::Error::other)
+            .with(async |line: String| Ok::<_, std::io::Error>(Message::text(line))),
+    );
+
+    let incoming = Box::pin(
+        stream
+            .take_while(|message| std::future::ready(!matches!(message, Ok(Message::Close(_)))))
+            .filter_map(async |message| match message {
+                Ok(Message::Text(text)) => Some(Ok(text.to_string())),
+                Ok(Message::Binary(bytes)) => Some(
+                    String::from_utf8(bytes.to_vec())
+                        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e)),
+                ),
+                // Control frames are handled by tungstenite itself
+                Ok(_) => None,
+                Err(e) => Some(Err(std::io::Error::other(e))),
+            }),
+    );
+
+    (outgoing, incoming)
+}
+
+/// Listens for connections from ACP peers, typically editors or conductors
+/// attaching to a long-running agent.
+///
+/// As a [`Component`](sacp::Component), a listener accepts a single connection and
+/// serves it. To serve many connections, [`accept`](Self::accept) them in a loop:
+///
+/// ```no_run
+/// # use sacp::Component;
+/// # use sacp::link::AgentToClient;
+/// # use sacp_tokio::{AcpEndpoint, AcpListener};
+/// # fn my_agent() -> impl Component<AgentToClient> + Send + 'static { sacp::link::AgentToClient::builder() }
+/// # async fn example() -> Result<(), sacp::Error> {
+/// let listener = AcpListener::bind(&"tcp://0.0.0.0:4000".parse()?).await?;
+/// loop {
+///     let connection = listener.accept().await?;
+///     tokio::spawn(Component::<AgentToClient>::serve(my_agent(), connection));
+/// }
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct AcpListener {
+    kind: ListenerKind,
+    endpoint: AcpEndpoint,
+}
+
+#[derive(Debug)]
+enum ListenerKind {
+    Tcp(TcpListener),
+    #[cfg(unix)]
+    Unix(UnixListener),
+    WebSocket(TcpListener),
+}
+
+impl AcpListener {
+    /// Start listening at `endpoint`.
+    ///
+    /// For TCP and WebSocket endpoints, port 0 picks a free port; see [`local_endpoint`](Self::local_endpoint).
+    /// Listening on `wss://` is not supported; terminate TLS in front of the agent instead.
+    pub async fn bind(endpoint: &AcpEndpoint) -> Result<Self, sacp::Error> {
+        match endpoint {
+            AcpEndpoint::Tcp(addr) => {
+                let listener = TcpListener::bind(addr)
+                    .await
+                    .map_err(sacp::Error::into_internal_error)?;
+                let local_addr = listener
+                    .local_addr()
+                    .map_err(sacp::Error::into_internal_error)?;
+                Ok(AcpListener {
+                    kind: ListenerKind::Tcp(listener),
+                    endpoint: AcpEndpoint::Tcp(local_addr.to_string()),
+                })
+            }
+
+            #[cfg(unix)]
+            AcpEndpoint::Unix(path) => {
+                let listener =
+                    UnixListener::bind(path).map_err(sacp::Error::into_internal_error)?;
+                Ok(AcpListener {
+                    kind: ListenerKind::Unix(listener),
+                    endpoint: endpoint.clone(),
+                })
+            }
+
+            AcpEndpoint::WebSocket(url) => {
+                let Some(rest) = url.strip_prefix("ws://") else {
+                    return Err(sacp::util::internal_error(format!(
+                        "cannot listen on `{url}`: only ws:// URLs are supported"
+                    )));
+                };
+                let (addr, path) = match rest.find('/') {
+                    Some(index) => rest.split_at(index),
+                    None => (rest, ""),
+                };
+
+                let listener = TcpListener::bind(addr)
+                    .await
+                    .map_err(sacp::Error::into_internal_error)?;
+                let local_addr = listener
+                    .local_addr()
+                    .map_err(sacp::Error::into_internal_error)?;
+                Ok(AcpListener {
+                    kind: ListenerKind::WebSocket(listener),
+                    endpoint: AcpEndpoint::WebSocket(format!("ws://{local_addr}{path}")),
+                })
+            }
+        }
+    }
+
+    /// The endpoint that peers can connect to, with the actual port if port 0 was requested.
+    pub fn local_endpoint(&self) -> &AcpEndpoint {
+        &self.endpoint
+    }
+
+    /// Wait for the next peer to connect.
+    ///
+    /// For WebSocket endpoints the upgrade happens once the connection is served,
+    /// so a slow or misbehaving peer only fails its own connection.
+    pub async fn accept(&self) -> Result<AcpStream, sacp::Error> {
+        let kind = match &self.kind {
+            ListenerKind::Tcp(listener) => {
+                let (stream, _addr) = listener
+                    .accept()
+                    .await
+                    .map_err(sacp::Error::into_internal_error)?;
+                StreamKind::Tcp(stream)
+            }
+
+            #[cfg(unix)]
+            ListenerKind::Unix(listener) => {
+                let (stream, _addr) = listener
+                    .accept()
+                    .await
+                    .map_err(sacp::Error::into_internal_error)?;
+                StreamKind::Unix(stream)
+            }
+
+            ListenerKind::WebSocket(listener) => {
+                let (stream, _addr) = listener
+                    .accept()
+                    .await
+                    .map_err(sacp::Error::into_internal_error)?;
+                StreamKind::WebSocketServer(stream)
+            }
+        };
+        Ok(AcpStream::new(kind))
+    }
+}
+
+#[cfg(unix)]
+impl Drop for AcpListener {
+    fn drop(&mut self) {
+        if let ListenerKind::Unix(_) = self.kind
+            && let AcpEndpoint::Unix(path) = &self.endpoint
+        {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+impl<L: sacp::link::JrLink> sacp::Component<L> for AcpListener {
+    async fn serve(self, client: impl sacp::Component<L::ConnectsTo>) -> Result<(), sacp::Error> {
+        let stream = self.accept().await?;
+        sacp::Component::<L>::serve(stream, client).await
+    }
+}
@@ -0,0 +1,237 @@
+//! Configuration for how [`AcpAgent`](crate::AcpAgent) spawns agent processes.
+//!
+//! The command, arguments and environment variables come from the agent's
+//! [`McpServerStdio`](sacp::schema::McpServerStdio) configuration. [`ProcessConfig`]
+//! covers everything else about the process: its working directory, which parts of
+//! our own environment it inherits, resource limits, and whether it runs in its own
+//! process group.
+
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+/// How an agent process is spawned, beyond its command line and environment variables.
+///
+/// By default the process inherits our working directory and environment and is
+/// the only process killed when the agent is dropped.
+///
+/// # Example
+///
+/// ```no_run
+/// # use sacp_tokio::{AcpAgent, ProcessConfig};
+/// # use std::str::FromStr;
+/// let agent = AcpAgent::from_str("npx -y my-agent")
+///     .unwrap()
+///     .with_process_config(
+///         ProcessConfig::new()
+///             .current_dir("/path/to/project")
+///             .env_clear()
+///             .inherit_env("PATH")
+///             .inherit_env("HOME")
+///             .process_group(true),
+///     );
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct ProcessConfig {
+    current_dir: Option<PathBuf>,
+    env_clear: bool,
+    inherit_env: Vec<OsString>,
+    #[cfg(unix)]
+    rlimits: Vec<(Resource, u64, u64)>,
+    #[cfg(unix)]
+    process_group: bool,
+}
+
+impl ProcessConfig {
+    /// Create a configuration that spawns processes the same way [`std::process::Command`] does.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Run the process in `dir` rather than in our current working directory.
+    pub fn current_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.current_dir = Some(dir.into());
+        self
+    }
+
+    /// Don't pass our environment on to the process.
+    ///
+    /// The process then only sees the variables from its server configuration
+    /// plus any named with [`inherit_env`](Self::inherit_env).
+    pub fn env_clear(mut self) -> Self {
+        self.env_clear = true;
+        self
+    }
+
+    /// Pass our value of the variable `name` on to the process, even after
+    /// [`env_clear`](Self::env_clear).
+    ///
+    /// Variables from the server configuration take precedence.
+    pub fn inherit_env(mut self, name: impl Into<OsString>) -> Self {
+        self.inherit_env.push(name.into());
+        self
+    }
+
+    /// Limit the process (and anything it spawns) to `soft` units of `resource`,
+    /// which it may raise up to `hard`.
+    #[cfg(unix)]
+    pub fn rlimit(mut self, resource: Resource, soft: u64, hard: u64) -> Self {
+        self.rlimits.push((resource, soft, hard));
+        self
+    }
+
+    /// Run the process in a new process group, and kill the whole group when the
+    /// agent is dropped.
+    ///
+    /// Enable this for agents launched through wrappers such as `npx`, where the
+    /// process we spawn is not the agent itself, so that the agent does not outlive us.
+    #[cfg(unix)]
+    pub fn process_group(mut self, enabled: bool) -> Self {
+        self.process_group = enabled;
+        self
+    }
+
+    /// Whether processes are spawned in their own process group.
+    pub(crate) fn uses_process_group(&self) -> bool {
+        #[cfg(unix)]
+        {
+            self.process_group
+        }
+        #[cfg(not(unix))]
+        {
+            false
+        }
+    }
+
+    /// Apply this configuration to `cmd`.
+    ///
+    /// Must be called before the server's own environment variables are set,
+    /// since clearing the environment would discard them.
+    pub(crate) fn apply(&self, cmd: &mut tokio::process::Command) {
+        if let Some(dir) = &self.current_dir {
+            cmd.current_dir(dir);
+        }
+
+        if self.env_clear {
+            cmd.env_clear();
+            for name in &self.inherit_env {
+                if let Some(value) = std::env::var_os(name) {
+                    cmd.env(name, value);
+                }
+            }
+        }
+
+        #[cfg(unix)]
+        {
+            if self.process_group {
+                cmd.process_group(0);
+            }
+
+            if !self.rlimits.is_empty() {
+                let rlimits = self.rlimits.clone();
+                // SAFETY: `setrlimit` is async-signal-safe, and the closure neither
+                // allocates nor takes locks.
+                unsafe {
+                    cmd.pre_exec(move || {
+                        for &(resource, soft, hard) in &rlimits {
+                            let limit = libc::rlimit {
+                                rlim_cur: soft as libc::rlim_t,
+                                rlim_max: hard as libc::rlim_t,
+                            };
+                            if libc::setrlimit(resource.as_raw() as _, &limit) != 0 {
+                                return Err(std::io::Error::last_os_error());
+                            }
+                        }
+                        Ok(())
+                    });
+                }
+            }
+        }
+    }
+}
+
+/// A resource that can be limited with [`ProcessConfig::rlimit`].
+#[cfg(unix)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Resource {
+    /// CPU time, in seconds (`RLIMIT_CPU`).
+    CpuTime,
+    /// Size of the virtual address space, in bytes (`RLIMIT_AS`).
+    AddressSpace,
+    /// Size of the data segment, in bytes (`RLIMIT_DATA`).
+    Data,
+    /// Size of the stack, in bytes (`RLIMIT_STACK`).
+    Stack,
+    /// Size of core dumps, in bytes (`RLIMIT_CORE`).
+    CoreFileSize,
+    /// Size of files the process may create, in bytes (`RLIMIT_FSIZE`).
+    FileSize,
+    /// Number of open file descriptors (`RLIMIT_NOFILE`).
+    OpenFiles,
+    /// Number of processes for the user (`RLIMIT_NPROC`).
+    Processes,
+}
+
+#[cfg(unix)]
+impl Resource {
+    fn as_raw(self) -> i32 {
+        let raw = match self {
+            Resource::CpuTime => libc::RLIMIT_CPU,
+            Resource::AddressSpace => libc::RLIMIT_AS,
+            Resource::Data => libc::RLIMIT_DATA,
+            Resource::Stack => libc::RLIMIT_STACK,
+            Resource::CoreFileSize => libc::RLIMIT_CORE,
+            Resource::FileSize => libc::RLIMIT_FSIZE,
+            Resource::OpenFiles => libc::RLIMIT_NOFILE,
+            Resource::Processes => libc::RLIMIT_NPROC,
+        };
+        raw as i32
+    }
+}
+
+/// A spawned agent process, killed when dropped.
+///
+/// If the process leads its own process group, the whole group is killed, taking
+/// any processes it spawned along with it -- even once the leader itself has exited.
+pub(crate) struct ChildGuard {
+    child: tokio::process::Child,
+    #[cfg(unix)]
+    process_group: Option<i32>,
+}
+
+impl ChildGuard {
+    pub(crate) fn new(child: tokio::process::Child, config: &ProcessConfig) -> Self {
+        #[cfg(unix)]
+        let process_group = if config.uses_process_group() {
+            child.id().map(|pid| pid as i32)
+        } else {
+            None
+        };
+        #[cfg(not(unix))]
+        let _ = config;
+
+        Self {
+            child,
+            #[cfg(unix)]
+            process_group,
+        }
+    }
+
+    pub(crate) async fn wait(&mut self) -> std::io::Result<std::process::ExitStatus> {
+        self.child.wait().await
+    }
+}
+
+impl Drop for ChildGuard {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        if let Some(pgid) = self.process_group {
+            // SAFETY: `killpg` has no memory-safety preconditions.
+            unsafe {
+                libc::killpg(pgid, libc::SIGKILL);
+            }
+        }
+
+        let _ = self.child.start_kill();
+    }
+}
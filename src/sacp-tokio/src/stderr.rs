@@ -0,0 +1,219 @@
+//! What happens to the stderr output of spawned agent processes.
+//!
+//! Agents write their logs and warnings to stderr. [`StderrPolicy`] decides where
+//! those lines go: to `tracing`, to a callback (the conductor uses this to put them in
+//! its trace), and into a bounded buffer whose contents are reported if the process
+//! exits with an error.
+
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use futures::io::BufReader;
+use futures::{AsyncBufReadExt, StreamExt};
+use tokio_util::compat::TokioAsyncReadCompatExt;
+
+use crate::LineDirection;
+use crate::acp_agent::DebugCallback;
+
+/// Callback receiving each line written to stderr.
+type LineCallback = Arc<dyn Fn(StderrLine<'_>) + Send + Sync + 'static>;
+
+/// A line written to stderr by an agent process, as passed to [`StderrPolicy::on_line`].
+#[derive(Debug, Clone, Copy)]
+pub struct StderrLine<'a> {
+    /// The name of the component that wrote the line.
+    pub component: &'a str,
+
+    /// The level the line is reported at.
+    pub level: tracing::Level,
+
+    /// The line itself, without its trailing newline.
+    pub text: &'a str,
+}
+
+/// How the stderr output of an agent process is captured and forwarded.
+///
+/// By default, each line is emitted as a `tracing` event at `INFO` level with a
+/// `component` field naming the agent, and the last 100 lines are kept to be
+/// included in the error reported if the process exits unsuccessfully.
+///
+/// # Example
+///
+/// ```no_run
+/// # use sacp_tokio::{AcpAgent, StderrPolicy};
+/// # use std::str::FromStr;
+/// let agent = AcpAgent::from_str("python my_agent.py")
+///     .unwrap()
+///     .with_stderr_policy(
+///         StderrPolicy::new()
+///             .component("my-agent")
+///             .level(tracing::Level::WARN)
+///             .on_line(|line| eprintln!("[{}] {}", line.component, line.text)),
+///     );
+/// ```
+#[derive(Clone)]
+pub struct StderrPolicy {
+    component: Option<String>,
+    level: tracing::Level,
+    tracing: bool,
+    buffer_lines: usize,
+    on_line: Option<LineCallback>,
+}
+
+impl std::fmt::Debug for StderrPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StderrPolicy")
+            .field("component", &self.component)
+            .field("level", &self.level)
+            .field("tracing", &self.tracing)
+            .field("buffer_lines", &self.buffer_lines)
+            .field("on_line", &self.on_line.as_ref().map(|_| "..."))
+            .finish()
+    }
+}
+
+impl Default for StderrPolicy {
+    fn default() -> Self {
+        Self {
+            component: None,
+            level: tracing::Level::INFO,
+            tracing: true,
+            buffer_lines: 100,
+            on_line: None,
+        }
+    }
+}
+
+impl StderrPolicy {
+    /// Create the default policy.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Name the component whose stderr this is.
+    ///
+    /// Defaults to the name from the agent's server configuration.
+    pub fn component(mut self, name: impl Into<String>) -> Self {
+        self.component = Some(name.into());
+        self
+    }
+
+    /// Report lines at `level`.
+    pub fn level(mut self, level: tracing::Level) -> Self {
+        self.level = level;
+        self
+    }
+
+    /// Whether to emit each line as a `tracing` event.
+    pub fn tracing(mut self, enabled: bool) -> Self {
+        self.tracing = enabled;
+        self
+    }
+
+    /// Keep the last `lines` lines for the error reported when the process fails.
+    ///
+    /// With zero, the error only carries the exit status.
+    pub fn buffer_lines(mut self, lines: usize) -> Self {
+        self.buffer_lines = lines;
+        self
+    }
+
+    /// Call `callback` with each line as it is written.
+    pub fn on_line<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(StderrLine<'_>) + Send + Sync + 'static,
+    {
+        self.on_line = Some(Arc::new(callback));
+        self
+    }
+
+    /// Forward `line` to wherever this policy sends it.
+    fn forward(&self, component: &str, line: &str) {
+        if self.tracing {
+            match self.level {
+                tracing::Level::ERROR => tracing::error!(component, "{line}"),
+                tracing::Level::WARN => tracing::warn!(component, "{line}"),
+                tracing::Level::INFO => tracing::info!(component, "{line}"),
+                tracing::Level::DEBUG => tracing::debug!(component, "{line}"),
+                tracing::Level::TRACE => tracing::trace!(component, "{line}"),
+            }
+        }
+
+        if let Some(on_line) = &self.on_line {
+            on_line(StderrLine {
+                component,
+                level: self.level,
+                text: line,
+            });
+        }
+    }
+
+    /// Read `stderr` to the end, forwarding each line, and return the tail kept
+    /// for error reporting.
+    pub(crate) async fn capture(
+        self,
+        stderr: tokio::process::ChildStderr,
+        default_component: String,
+        debug_callback: Option<DebugCallback>,
+    ) -> String {
+        let component = self.component.clone().unwrap_or(default_component);
+        let mut tail = StderrTail::new(self.buffer_lines);
+
+        let mut lines = BufReader::new(stderr.compat()).lines();
+        while let Some(line) = lines.next().await {
+            let Ok(line) = line else {
+                continue;
+            };
+            if let Some(callback) = &debug_callback {
+                callback(&line, LineDirection::Stderr);
+            }
+            self.forward(&component, &line);
+            tail.push(line);
+        }
+
+        tail.into_string()
+    }
+}
+
+/// The last few lines of stderr, kept for reporting errors.
+struct StderrTail {
+    capacity: usize,
+    lines: VecDeque<String>,
+    dropped: usize,
+}
+
+impl StderrTail {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            lines: VecDeque::new(),
+            dropped: 0,
+        }
+    }
+
+    fn push(&mut self, line: String) {
+        if self.capacity == 0 {
+            self.dropped += 1;
+            return;
+        }
+        if self.lines.len() == self.capacity {
+            self.lines.pop_front();
+            self.dropped += 1;
+        }
+        self.lines.push_back(line);
+    }
+
+    fn into_string(self) -> String {
+        let mut text = String::new();
+        if self.dropped > 0 && !self.lines.is_empty() {
+            text = format!("({} earlier lines omitted)", self.dropped);
+        }
+        for line in self.lines {
+            if !text.is_empty() {
+                text.push('\n');
+            }
+            text.push_str(&line);
+        }
+        text
+    }
+}
@@ -0,0 +1,201 @@
+//! Integration tests for connecting to ACP agents over the network.
+
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+
+use sacp::Component;
+use sacp::link::{AgentToClient, ClientToAgent};
+use sacp::schema::{InitializeRequest, InitializeResponse, ProtocolVersion};
+use sacp_tokio::{AcpAgent, AcpEndpoint, AcpListener, LineDirection};
+
+/// An agent that only answers `initialize`.
+fn agent() -> impl Component<AgentToClient> {
+    AgentToClient::builder()
+        .name("network-agent")
+        .on_receive_request(
+            async |initialize: InitializeRequest, request_cx, _cx| {
+                request_cx.respond(InitializeResponse::new(initialize.protocol_version))
+            },
+            sacp::on_receive_request!(),
+        )
+}
+
+/// Listen at `endpoint`, serve the agent to one connection, and initialize it
+/// through an `AcpAgent` parsed from the listener's URL.
+async fn initialize_over(endpoint: &str) -> Result<Vec<LineDirection>, sacp::Error> {
+    let listener = AcpListener::bind(&endpoint.parse()?).await?;
+    let url = listener.local_endpoint().to_string();
+    let server = tokio::spawn(Component::<AgentToClient>::serve(agent(), listener));
+
+    let directions = Arc::new(Mutex::new(vec![]));
+    let agent = AcpAgent::from_str(&url)?.with_debug({
+        let directions = directions.clone();
+        move |_line, direction| directions.lock().unwrap().push(direction)
+    });
+
+    ClientToAgent::builder()
+        .name("network-client")
+        .run_until(agent, async |cx| {
+            let response = cx
+                .send_request(InitializeRequest::new(ProtocolVersion::LATEST))
+                .block_task()
+                .await?;
+            assert_eq!(response.protocol_version, ProtocolVersion::LATEST);
+            Ok(())
+        })
+        .await?;
+
+    server.abort();
+    let directions = directions.lock().unwrap().clone();
+    Ok(directions)
+}
+
+#[tokio::test]
+async fn test_tcp() -> Result<(), sacp::Error> {
+    let directions = initialize_over("tcp://127.0.0.1:0").await?;
+    assert_eq!(directions, [LineDirection::Stdin, LineDirection::Stdout]);
+    Ok(())
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_unix_socket() -> Result<(), sacp::Error> {
+    let path = std::env::temp_dir().join(format!("sacp-tokio-test-{}.sock", std::process::id()));
+    let directions = initialize_over(&format!("unix://{}", path.display())).await?;
+    assert_eq!(directions, [LineDirection::Stdin, LineDirection::Stdout]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_websocket() -> Result<(), sacp::Error> {
+    let directions = initialize_over("ws://127.0.0.1:0/acp").await?;
+    assert_eq!(directions, [LineDirection::Stdin, LineDirection::Stdout]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_listener_serves_many_connections() -> Result<(), sacp::Error> {
+    let listener = AcpListener::bind(&"tcp://127.0.0.1:0".parse()?).await?;
+    let endpoint = listener.local_endpoint().clone();
+    let server = tokio::spawn(async move {
+        loop {
+            let connection = listener.accept().await?;
+            tokio::spawn(Component::<AgentToClient>::serve(agent(), connection));
+        }
+        #[allow(unreachable_code)]
+        Ok::<_, sacp::Error>(())
+    });
+
+    for _ in 0..3 {
+        ClientToAgent::builder()
+            .run_until(endpoint.clone(), async |cx| {
+                cx.send_request(InitializeRequest::new(ProtocolVersion::LATEST))
+                    .block_task()
+                    .await?;
+                Ok(())
+            })
+            .await?;
+    }
+
+    server.abort();
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_stalled_websocket_handshake_does_not_block_listener() -> Result<(), sacp::Error> {
+    use tokio::io::AsyncWriteExt;
+
+    let listener = AcpListener::bind(&"ws://127.0.0.1:0/acp".parse()?).await?;
+    let endpoint = listener.local_endpoint().clone();
+    let addr = endpoint
+        .to_string()
+        .trim_start_matches("ws://")
+        .trim_end_matches("/acp")
+        .to_string();
+    let server = tokio::spawn(async move {
+        loop {
+            let connection = listener.accept().await?;
+            tokio::spawn(Component::<AgentToClient>::serve(agent(), connection));
+        }
+        #[allow(unreachable_code)]
+        Ok::<_, sacp::Error>(())
+    });
+
+    // One peer never starts the upgrade, another sends garbage instead.
+    let _stalled = tokio::net::TcpStream::connect(&addr).await.unwrap();
+    let mut garbage = tokio::net::TcpStream::connect(&addr).await.unwrap();
+    garbage.write_all(b"not a websocket\r\n\r\n").await.unwrap();
+
+    tokio::time::timeout(
+        std::time::Duration::from_secs(5),
+        ClientToAgent::builder().run_until(endpoint, async |cx| {
+            cx.send_request(InitializeRequest::new(ProtocolVersion::LATEST))
+                .block_task()
+                .await?;
+            Ok(())
+        }),
+    )
+    .await
+    .expect("a stalled handshake blocked the listener")?;
+
+    assert!(!server.is_finished(), "the listener stopped accepting");
+    server.abort();
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_accepted_stream_limits_message_size() -> Result<(), sacp::Error> {
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+    let listener = AcpListener::bind(&"tcp://127.0.0.1:0".parse()?).await?;
+    let AcpEndpoint::Tcp(addr) = listener.local_endpoint().clone() else {
+        unreachable!()
+    };
+    let server = tokio::spawn(async move {
+        let connection = listener.accept().await?.with_max_message_size(256);
+        Component::<AgentToClient>::serve(agent(), connection).await
+    });
+
+    let stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+    let (read, mut write) = tokio::io::split(stream);
+    let mut read = BufReader::new(read).lines();
+    let initialize = |id: u64, padding: usize| {
+        format!(
+            r#"{{"jsonrpc":"2.0","id":{id},"method":"initialize","params":{{"protocolVersion":1,"_padding":"{}"}}}}"#,
+            "y".repeat(padding)
+        )
+    };
+    for line in [initialize(1, 4096), initialize(2, 0)] {
+        write.write_all(line.as_bytes()).await.unwrap();
+        write.write_all(b"\n").await.unwrap();
+    }
+
+    // The oversized request is refused, and the next one answered.
+    let response: serde_json::Value =
+        serde_json::from_str(&read.next_line().await.unwrap().unwrap()).unwrap();
+    assert_eq!(response["id"], 1);
+    assert_eq!(response["error"]["data"]["max_message_size"], 256);
+    let response: serde_json::Value =
+        serde_json::from_str(&read.next_line().await.unwrap().unwrap()).unwrap();
+    assert_eq!(response["id"], 2);
+    assert!(response.get("result").is_some(), "{response}");
+
+    server.abort();
+    Ok(())
+}
+
+#[test]
+fn test_endpoint_urls() {
+    for url in [
+        "tcp://localhost:4000",
+        "unix:///tmp/agent.sock",
+        "ws://localhost:4000/acp",
+        "wss://example.com/acp",
+    ] {
+        let endpoint = AcpEndpoint::from_str(url).unwrap();
+        assert_eq!(endpoint.to_string(), url);
+    }
+
+    assert!(AcpEndpoint::from_str("http://localhost:4000").is_err());
+    assert!(AcpEndpoint::from_str("python agent.py").is_err());
+}
@@ -0,0 +1,110 @@
+//! Integration tests for how `AcpAgent` spawns agent processes.
+//!
+//! The "agents" here are shell scripts that report what they observe on stderr
+//! and then fail, so that the report comes back in the error from serving them.
+
+#![cfg(unix)]
+
+use std::time::Duration;
+
+use sacp::link::ClientToAgent;
+use sacp_tokio::{AcpAgent, ProcessConfig, Resource};
+
+/// Serve `agent` until it exits, returning what it wrote to stderr.
+async fn stderr_of(agent: AcpAgent) -> String {
+    let error = ClientToAgent::builder()
+        .run_until(agent, async |_cx| {
+            futures::future::pending::<()>().await;
+            Ok(())
+        })
+        .await
+        .expect_err("the script always fails");
+    format!("{error:?}")
+}
+
+fn script(script: &str) -> AcpAgent {
+    AcpAgent::from_args(["sh", "-c", &format!("{script}; exit 1")]).unwrap()
+}
+
+#[tokio::test]
+async fn test_current_dir() {
+    let dir = std::env::temp_dir().canonicalize().unwrap();
+    let agent = script("echo cwd=$(pwd -P) >&2")
+        .with_process_config(ProcessConfig::new().current_dir(&dir));
+    let stderr = stderr_of(agent).await;
+    assert!(
+        stderr.contains(&format!("cwd={}", dir.display())),
+        "{stderr}"
+    );
+}
+
+#[tokio::test]
+async fn test_env_clear_and_inherit() {
+    // SAFETY: no other test in this binary reads or writes the environment concurrently.
+    unsafe {
+        std::env::set_var("SACP_TOKIO_TEST_INHERITED", "kept");
+        std::env::set_var("SACP_TOKIO_TEST_DROPPED", "dropped");
+    }
+
+    let agent = AcpAgent::from_args([
+        "SACP_TOKIO_TEST_CONFIGURED=configured",
+        "/bin/sh",
+        "-c",
+        "echo \"env=[$SACP_TOKIO_TEST_INHERITED,$SACP_TOKIO_TEST_DROPPED,$SACP_TOKIO_TEST_CONFIGURED]\" >&2; exit 1",
+    ])
+    .unwrap()
+    .with_process_config(
+        ProcessConfig::new()
+            .env_clear()
+            .inherit_env("SACP_TOKIO_TEST_INHERITED"),
+    );
+    let stderr = stderr_of(agent).await;
+    assert!(stderr.contains("env=[kept,,configured]"), "{stderr}");
+}
+
+#[tokio::test]
+async fn test_rlimit() {
+    let agent = script("echo nofile=$(ulimit -n) >&2")
+        .with_process_config(ProcessConfig::new().rlimit(Resource::OpenFiles, 64, 64));
+    let stderr = stderr_of(agent).await;
+    assert!(stderr.contains("nofile=64"), "{stderr}");
+}
+
+/// Whether `pid` is still running (rather than gone or a zombie).
+#[cfg(target_os = "linux")]
+fn is_running(pid: u32) -> bool {
+    match std::fs::read_to_string(format!("/proc/{pid}/stat")) {
+        Ok(stat) => {
+            let state = stat.rsplit(')').next().unwrap().trim_start();
+            !state.starts_with('Z') && !state.starts_with('X')
+        }
+        Err(_) => false,
+    }
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+async fn test_process_group_is_killed() {
+    // The script leaves a grandchild behind, detached from our pipes.
+    let agent = script("sleep 1000 </dev/null >/dev/null 2>&1 & echo grandchild=$! >&2")
+        .with_process_config(ProcessConfig::new().process_group(true));
+    let stderr = stderr_of(agent).await;
+    let pid: u32 = stderr
+        .split("grandchild=")
+        .nth(1)
+        .and_then(|rest| {
+            rest.split(|c: char| !c.is_ascii_digit())
+                .next()?
+                .parse()
+                .ok()
+        })
+        .unwrap_or_else(|| panic!("no pid in {stderr}"));
+
+    for _ in 0..50 {
+        if !is_running(pid) {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("grandchild {pid} outlived the agent");
+}
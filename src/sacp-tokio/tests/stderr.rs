@@ -0,0 +1,78 @@
+//! Integration tests for capturing the stderr of agent processes.
+
+#![cfg(unix)]
+
+use std::sync::{Arc, Mutex};
+
+use sacp::link::ClientToAgent;
+use sacp_tokio::{AcpAgent, StderrPolicy};
+
+/// An "agent" that writes three lines to stderr and fails.
+fn failing_agent() -> AcpAgent {
+    AcpAgent::from_args([
+        "sh",
+        "-c",
+        "echo first >&2; echo second >&2; echo third >&2; exit 3",
+    ])
+    .unwrap()
+}
+
+/// Serve `agent` until it exits, returning the error it failed with.
+async fn serve(agent: AcpAgent) -> String {
+    let error = ClientToAgent::builder()
+        .run_until(agent, async |_cx| {
+            futures::future::pending::<()>().await;
+            Ok(())
+        })
+        .await
+        .expect_err("the agent always fails");
+    format!("{error:?}")
+}
+
+#[tokio::test]
+async fn test_lines_are_forwarded() {
+    let lines = Arc::new(Mutex::new(vec![]));
+    let agent = failing_agent().with_stderr_policy(
+        StderrPolicy::new()
+            .component("my-agent")
+            .level(tracing::Level::WARN)
+            .on_line({
+                let lines = lines.clone();
+                move |line| {
+                    lines
+                        .lock()
+                        .unwrap()
+                        .push(format!("{} {} {}", line.component, line.level, line.text))
+                }
+            }),
+    );
+
+    let error = serve(agent).await;
+    assert!(error.contains("first\\nsecond\\nthird"), "{error}");
+    assert_eq!(
+        *lines.lock().unwrap(),
+        [
+            "my-agent WARN first",
+            "my-agent WARN second",
+            "my-agent WARN third"
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_crash_report_keeps_the_tail() {
+    let agent = failing_agent().with_stderr_policy(StderrPolicy::new().buffer_lines(2));
+    let error = serve(agent).await;
+    assert!(
+        error.contains("(1 earlier lines omitted)\\nsecond\\nthird"),
+        "{error}"
+    );
+}
+
+#[tokio::test]
+async fn test_crash_report_without_buffer() {
+    let agent = failing_agent().with_stderr_policy(StderrPolicy::new().buffer_lines(0));
+    let error = serve(agent).await;
+    assert!(error.contains("exit status: 3"), "{error}");
+    assert!(!error.contains("third"), "{error}");
+}
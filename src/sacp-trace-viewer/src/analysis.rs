@@ -0,0 +1,388 @@
+//! Summaries of a whole trace, for triaging it without the viewer.
+//!
+//! Most of these pair each request with its response, which gives how long the
+//! request took and whether it failed. They back the `sacp-trace` command.
+
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::query::{exchange, is_error, kind};
+
+/// Read all the events of a `.jsons` trace file.
+///
+/// Lines that are not valid JSON, such as one that was still being written, are
+/// skipped.
+pub fn read_trace(path: &Path) -> std::io::Result<Vec<serde_json::Value>> {
+    let text = std::fs::read_to_string(path)?;
+    Ok(text
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+/// A request and the response to it, if one was sent.
+#[derive(Clone, Debug)]
+pub struct Exchange<'a> {
+    /// The position of the request in the trace.
+    pub index: usize,
+
+    /// The request event.
+    pub request: &'a serde_json::Value,
+
+    /// The response event, or `None` if the request was never answered.
+    pub response: Option<&'a serde_json::Value>,
+}
+
+impl<'a> Exchange<'a> {
+    /// The request's method.
+    pub fn method(&self) -> &'a str {
+        str_field(self.request, "method").unwrap_or("")
+    }
+
+    /// The request's protocol: `acp` or `mcp`.
+    pub fn protocol(&self) -> &'a str {
+        str_field(self.request, "protocol").unwrap_or("")
+    }
+
+    /// The component that sent the request.
+    pub fn from(&self) -> &'a str {
+        str_field(self.request, "from").unwrap_or("")
+    }
+
+    /// The component that handled the request.
+    pub fn to(&self) -> &'a str {
+        str_field(self.request, "to").unwrap_or("")
+    }
+
+    /// The ACP session the request belongs to, if known.
+    pub fn session(&self) -> Option<&'a str> {
+        str_field(self.request, "session")
+    }
+
+    /// When the request was sent, in seconds since the trace started.
+    pub fn start(&self) -> f64 {
+        ts(self.request)
+    }
+
+    /// How long the request took to be answered, in seconds.
+    pub fn duration(&self) -> Option<f64> {
+        self.response.map(|response| ts(response) - self.start())
+    }
+
+    /// Whether the request was answered with an error.
+    pub fn is_error(&self) -> bool {
+        self.response.is_some_and(is_error)
+    }
+}
+
+/// Pair every request in `events` with its response, in the order the requests
+/// were sent.
+pub fn exchanges(events: &[serde_json::Value]) -> Vec<Exchange<'_>> {
+    let mut exchanges = Vec::new();
+
+    // Requests awaiting a response, by exchange key.
+    let mut pending: HashMap<String, usize> = HashMap::new();
+
+    for (index, event) in events.iter().enumerate() {
+        match kind(event) {
+            "request" => {
+                if let Some(key) = exchange(event, "to") {
+                    pending.insert(key, exchanges.len());
+                }
+                exchanges.push(Exchange {
+                    index,
+                    request: event,
+                    response: None,
+                });
+            }
+            "response" => {
+                if let Some(i) = exchange(event, "from").and_then(|key| pending.remove(&key)) {
+                    exchanges[i].response = Some(event);
+                }
+            }
+            _ => {}
+        }
+    }
+    exchanges
+}
+
+/// Counts and latencies for one method.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MethodStats {
+    /// The method name.
+    pub method: String,
+
+    /// The protocol the method belongs to: `acp` or `mcp`.
+    pub protocol: String,
+
+    /// How many requests were sent, counting each hop through a proxy.
+    pub requests: usize,
+
+    /// How many notifications were sent, counting each hop through a proxy.
+    pub notifications: usize,
+
+    /// How many requests were answered with an error.
+    pub errors: usize,
+
+    /// How many requests were never answered.
+    pub unanswered: usize,
+
+    /// Latency of the answered requests, if there were any.
+    pub latency: Option<Latency>,
+}
+
+/// Latency percentiles, in seconds.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Latency {
+    /// The median.
+    pub p50: f64,
+
+    /// The 90th percentile.
+    pub p90: f64,
+
+    /// The 99th percentile.
+    pub p99: f64,
+
+    /// The slowest.
+    pub max: f64,
+}
+
+impl Latency {
+    /// The percentiles of `durations`, or `None` if there are none.
+    pub fn of(mut durations: Vec<f64>) -> Option<Self> {
+        if durations.is_empty() {
+            return None;
+        }
+        durations.sort_by(f64::total_cmp);
+
+        // Nearest-rank percentile.
+        let percentile = |p: f64| {
+            let rank = (p / 100.0 * durations.len() as f64).ceil() as usize;
+            durations[rank.clamp(1, durations.len()) - 1]
+        };
+        Some(Self {
+            p50: percentile(50.0),
+            p90: percentile(90.0),
+            p99: percentile(99.0),
+            max: durations[durations.len() - 1],
+        })
+    }
+}
+
+/// Per-method counts and latencies, busiest method first.
+pub fn method_stats(events: &[serde_json::Value]) -> Vec<MethodStats> {
+    #[derive(Default)]
+    struct Tally {
+        requests: usize,
+        notifications: usize,
+        errors: usize,
+        unanswered: usize,
+        durations: Vec<f64>,
+    }
+
+    let mut tallies: HashMap<(&str, &str), Tally> = HashMap::new();
+    for exchange in exchanges(events) {
+        let tally = tallies
+            .entry((exchange.method(), exchange.protocol()))
+            .or_default();
+        tally.requests += 1;
+        tally.errors += usize::from(exchange.is_error());
+        match exchange.duration() {
+            Some(duration) => tally.durations.push(duration),
+            None => tally.unanswered += 1,
+        }
+    }
+    for event in events.iter().filter(|event| kind(event) == "notification") {
+        let method = str_field(event, "method").unwrap_or("");
+        let protocol = str_field(event, "protocol").unwrap_or("");
+        tallies.entry((method, protocol)).or_default().notifications += 1;
+    }
+
+    let mut stats: Vec<_> = tallies
+        .into_iter()
+        .map(|((method, protocol), tally)| MethodStats {
+            method: method.to_string(),
+            protocol: protocol.to_string(),
+            requests: tally.requests,
+            notifications: tally.notifications,
+            errors: tally.errors,
+            unanswered: tally.unanswered,
+            latency: Latency::of(tally.durations),
+        })
+        .collect();
+    stats.sort_by(|a, b| {
+        (b.requests + b.notifications)
+            .cmp(&(a.requests + a.notifications))
+            .then_with(|| a.method.cmp(&b.method))
+            .then_with(|| a.protocol.cmp(&b.protocol))
+    });
+    stats
+}
+
+/// The requests that were answered with an error, in the order they were sent.
+pub fn failed_requests(events: &[serde_json::Value]) -> Vec<Exchange<'_>> {
+    exchanges(events)
+        .into_iter()
+        .filter(Exchange::is_error)
+        .collect()
+}
+
+/// The `limit` answered requests that took longest, slowest first.
+pub fn slowest_requests(events: &[serde_json::Value], limit: usize) -> Vec<Exchange<'_>> {
+    let mut answered: Vec<_> = exchanges(events)
+        .into_iter()
+        .filter(|exchange| exchange.response.is_some())
+        .collect();
+    answered.sort_by(|a, b| {
+        b.duration()
+            .unwrap_or(0.0)
+            .total_cmp(&a.duration().unwrap_or(0.0))
+    });
+    answered.truncate(limit);
+    answered
+}
+
+/// What happened in one ACP session.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SessionSummary {
+    /// The session id.
+    pub session: String,
+
+    /// When the session's first event happened, in seconds since the trace started.
+    pub start: f64,
+
+    /// When the session's last event happened.
+    pub end: f64,
+
+    /// The prompts the client sent, in order.
+    pub turns: Vec<Turn>,
+
+    /// How many requests in the session were answered with an error,
+    /// counting each hop through a proxy.
+    pub errors: usize,
+}
+
+/// One `session/prompt` turn of a session.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Turn {
+    /// The position of the prompt request in the trace.
+    pub index: usize,
+
+    /// When the prompt was sent.
+    pub start: f64,
+
+    /// How long the turn took, or `None` if it never finished.
+    pub duration: Option<f64>,
+
+    /// The text of the prompt.
+    pub prompt: String,
+
+    /// Why the turn ended (e.g. `end_turn` or `cancelled`), if it ended without an error.
+    pub stop_reason: Option<String>,
+
+    /// Whether the prompt was answered with an error.
+    pub is_error: bool,
+
+    /// How many `session/update` notifications the client received during the turn.
+    pub updates: usize,
+}
+
+/// A summary of each session in the trace, in the order the sessions started.
+///
+/// A turn is a `session/prompt` request coming into the chain of components, so
+/// a prompt forwarded through proxies counts once.
+pub fn sessions(events: &[serde_json::Value]) -> Vec<SessionSummary> {
+    let mut summaries: Vec<SessionSummary> = Vec::new();
+    let mut positions: HashMap<&str, usize> = HashMap::new();
+    for event in events {
+        let Some(session) = str_field(event, "session") else {
+            continue;
+        };
+        let position = *positions.entry(session).or_insert_with(|| {
+            summaries.push(SessionSummary {
+                session: session.to_string(),
+                start: ts(event),
+                end: ts(event),
+                turns: Vec::new(),
+                errors: 0,
+            });
+            summaries.len() - 1
+        });
+        let summary = &mut summaries[position];
+        summary.end = summary.end.max(ts(event));
+    }
+
+    let exchanges = exchanges(events);
+
+    // Prompts sent by a component that was itself prompted were forwarded.
+    let prompted: HashSet<(&str, &str)> = exchanges
+        .iter()
+        .filter(|exchange| exchange.method() == "session/prompt")
+        .filter_map(|exchange| Some((exchange.session()?, exchange.to())))
+        .collect();
+
+    for exchange in &exchanges {
+        let Some(position) = exchange.session().and_then(|s| positions.get(s)) else {
+            continue;
+        };
+        let summary = &mut summaries[*position];
+        if let Some(response) = exchange.response {
+            summary.end = summary.end.max(ts(response));
+        }
+        summary.errors += usize::from(exchange.is_error());
+
+        if exchange.method() != "session/prompt"
+            || prompted.contains(&(summary.session.as_str(), exchange.from()))
+        {
+            continue;
+        }
+        let end = exchange.response.map_or(f64::INFINITY, ts);
+        let updates = events
+            .iter()
+            .filter(|event| {
+                kind(event) == "notification"
+                    && str_field(event, "method") == Some("session/update")
+                    && str_field(event, "session") == exchange.session()
+                    && str_field(event, "to") == Some(exchange.from())
+                    && (exchange.start()..=end).contains(&ts(event))
+            })
+            .count();
+        summary.turns.push(Turn {
+            index: exchange.index,
+            start: exchange.start(),
+            duration: exchange.duration(),
+            prompt: prompt_text(&exchange.request["params"]),
+            stop_reason: exchange
+                .response
+                .filter(|response| !is_error(response))
+                .and_then(|response| str_field(&response["payload"], "stopReason"))
+                .map(str::to_string),
+            is_error: exchange.is_error(),
+            updates,
+        });
+    }
+    summaries
+}
+
+/// The text blocks of a `session/prompt` request, joined by spaces.
+fn prompt_text(params: &serde_json::Value) -> String {
+    params
+        .get("prompt")
+        .and_then(|prompt| prompt.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|block| block.get("text")?.as_str())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn str_field<'a>(event: &'a serde_json::Value, name: &str) -> Option<&'a str> {
+    event.get(name)?.as_str()
+}
+
+fn ts(event: &serde_json::Value) -> f64 {
+    event.get("ts").and_then(|ts| ts.as_f64()).unwrap_or(0.0)
+}
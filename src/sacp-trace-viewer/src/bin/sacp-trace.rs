@@ -0,0 +1,375 @@
+//! SACP Trace
+//!
+//! Summarizes SACP trace files in the terminal, for triaging a trace without
+//! opening the viewer.
+//!
+//! Usage:
+//! ```bash
+//! sacp-trace stats ./trace.jsons
+//! sacp-trace errors ./trace.jsons
+//! sacp-trace slowest ./trace.jsons --limit 20
+//! sacp-trace sessions ./trace.jsons
+//! sacp-trace export ./trace.jsons --format mermaid --session <ID>
+//! ```
+
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use clap::{Parser, Subcommand, ValueEnum};
+use sacp_trace_viewer::{Exchange, Latency, TraceQuery};
+
+#[derive(Parser, Debug)]
+#[command(author, version, about = "Summarize SACP trace files")]
+struct Args {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Count messages and measure request latency per method
+    Stats {
+        /// Path to the trace file (.jsons)
+        trace_file: PathBuf,
+    },
+
+    /// List the requests that failed, with their params
+    Errors {
+        /// Path to the trace file (.jsons)
+        trace_file: PathBuf,
+    },
+
+    /// List the requests that took longest
+    Slowest {
+        /// Path to the trace file (.jsons)
+        trace_file: PathBuf,
+
+        /// How many requests to list
+        #[arg(short = 'n', long, default_value_t = 10)]
+        limit: usize,
+    },
+
+    /// Summarize the turns of each session
+    Sessions {
+        /// Path to the trace file (.jsons)
+        trace_file: PathBuf,
+    },
+
+    /// Render the trace, or the events matching the filters, in another format
+    Export {
+        /// Path to the trace file (.jsons)
+        trace_file: PathBuf,
+
+        /// Output format
+        #[arg(short, long, value_enum)]
+        format: ExportFormat,
+
+        /// Write to this file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        #[command(flatten)]
+        filter: Filter,
+    },
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum ExportFormat {
+    /// A Mermaid sequenceDiagram
+    Mermaid,
+    /// A PlantUML sequence diagram
+    Plantuml,
+    /// OpenTelemetry spans as OTLP JSON
+    Otlp,
+}
+
+/// Which events to export; see `TraceQuery`.
+#[derive(clap::Args, Debug)]
+struct Filter {
+    /// Only events for this ACP session
+    #[arg(long)]
+    session: Option<String>,
+
+    /// Only events sent, received or logged by this component (e.g. `proxy:0`)
+    #[arg(long)]
+    component: Option<String>,
+
+    /// Only messages of this protocol (`acp` or `mcp`)
+    #[arg(long)]
+    protocol: Option<String>,
+
+    /// Only messages whose method matches this glob (e.g. `session/*`)
+    #[arg(long)]
+    method: Option<String>,
+
+    /// Only events at or after this many seconds into the trace
+    #[arg(long)]
+    since: Option<f64>,
+
+    /// Only events at or before this many seconds into the trace
+    #[arg(long)]
+    until: Option<f64>,
+
+    /// Only failed requests and their error responses
+    #[arg(long)]
+    errors: bool,
+}
+
+impl From<Filter> for TraceQuery {
+    fn from(filter: Filter) -> Self {
+        TraceQuery {
+            session: filter.session,
+            component: filter.component,
+            protocol: filter.protocol,
+            method: filter.method,
+            since: filter.since,
+            until: filter.until,
+            errors: filter.errors,
+            ..Default::default()
+        }
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    let args = Args::parse();
+    match args.command {
+        Command::Stats { trace_file } => stats(&read(&trace_file)?),
+        Command::Errors { trace_file } => errors(&read(&trace_file)?),
+        Command::Slowest { trace_file, limit } => slowest(&read(&trace_file)?, limit),
+        Command::Sessions { trace_file } => sessions(&read(&trace_file)?),
+        Command::Export {
+            trace_file,
+            format,
+            output,
+            filter,
+        } => export(&trace_file, format, output, filter)?,
+    }
+    Ok(())
+}
+
+fn read(path: &Path) -> anyhow::Result<Vec<serde_json::Value>> {
+    sacp_trace_viewer::read_trace(path)
+        .map_err(|e| anyhow::anyhow!("Failed to read trace file {}: {}", path.display(), e))
+}
+
+fn stats(events: &[serde_json::Value]) {
+    let stats = sacp_trace_viewer::method_stats(events);
+    let width = column_width(stats.iter().map(|s| s.method.as_str()), "METHOD");
+    println!(
+        "{:width$}  {:5}  {:>8}  {:>8}  {:>6}  {:>10}  {:>9}  {:>9}  {:>9}  {:>9}",
+        "METHOD",
+        "PROTO",
+        "REQUESTS",
+        "NOTIFIES",
+        "ERRORS",
+        "UNANSWERED",
+        "P50",
+        "P90",
+        "P99",
+        "MAX",
+    );
+    for s in &stats {
+        let (p50, p90, p99, max) = match s.latency {
+            Some(Latency { p50, p90, p99, max }) => (
+                format_duration(p50),
+                format_duration(p90),
+                format_duration(p99),
+                format_duration(max),
+            ),
+            None => Default::default(),
+        };
+        println!(
+            "{:width$}  {:5}  {:>8}  {:>8}  {:>6}  {:>10}  {:>9}  {:>9}  {:>9}  {:>9}",
+            s.method,
+            s.protocol,
+            s.requests,
+            s.notifications,
+            s.errors,
+            s.unanswered,
+            p50,
+            p90,
+            p99,
+            max,
+        );
+    }
+}
+
+fn errors(events: &[serde_json::Value]) {
+    let failed = sacp_trace_viewer::failed_requests(events);
+    if failed.is_empty() {
+        println!("No failed requests.");
+        return;
+    }
+    for (i, exchange) in failed.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        println!("{}", describe(exchange));
+        println!("  params: {}", indent(&exchange.request["params"]));
+        if let Some(response) = exchange.response {
+            println!("  error: {}", indent(&response["payload"]));
+        }
+    }
+}
+
+fn slowest(events: &[serde_json::Value], limit: usize) {
+    for exchange in sacp_trace_viewer::slowest_requests(events, limit) {
+        println!("{}", describe(&exchange));
+    }
+}
+
+fn sessions(events: &[serde_json::Value]) {
+    let sessions = sacp_trace_viewer::sessions(events);
+    if sessions.is_empty() {
+        println!("No sessions.");
+        return;
+    }
+    for (i, session) in sessions.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        println!(
+            "session {}: {} turn{}, {} error{}, {} to {}",
+            session.session,
+            session.turns.len(),
+            plural(session.turns.len()),
+            session.errors,
+            plural(session.errors),
+            format_time(session.start),
+            format_time(session.end),
+        );
+        for (n, turn) in session.turns.iter().enumerate() {
+            let outcome = if turn.is_error {
+                "error".to_string()
+            } else {
+                match (&turn.stop_reason, turn.duration) {
+                    (Some(reason), _) => reason.clone(),
+                    (None, Some(_)) => "done".to_string(),
+                    (None, None) => "unfinished".to_string(),
+                }
+            };
+            println!(
+                "  turn {:<3} #{:<6} at {:>9}  took {:>9}  {:<12} {:>4} update{}  {}",
+                n + 1,
+                turn.index,
+                format_time(turn.start),
+                turn.duration.map(format_duration).unwrap_or_default(),
+                outcome,
+                turn.updates,
+                plural(turn.updates),
+                preview(&turn.prompt, 60),
+            );
+        }
+    }
+}
+
+fn export(
+    trace_file: &Path,
+    format: ExportFormat,
+    output: Option<PathBuf>,
+    filter: Filter,
+) -> anyhow::Result<()> {
+    let events: Vec<_> = TraceQuery::from(filter)
+        .apply(&read(trace_file)?)
+        .events
+        .into_iter()
+        .map(|m| m.event)
+        .collect();
+
+    let rendered = match format {
+        ExportFormat::Mermaid => sacp_trace_viewer::to_mermaid(&events),
+        ExportFormat::Plantuml => sacp_trace_viewer::to_plantuml(&events),
+        ExportFormat::Otlp => {
+            let start = trace_start_unix_nanos(trace_file, &events)?;
+            serde_json::to_string_pretty(&sacp_trace_viewer::to_otlp(&events, start))? + "\n"
+        }
+    };
+
+    match output {
+        Some(path) => std::fs::write(&path, rendered)
+            .map_err(|e| anyhow::anyhow!("Failed to write {}: {}", path.display(), e)),
+        None => {
+            print!("{rendered}");
+            Ok(())
+        }
+    }
+}
+
+/// When the trace started, taking the trace file to have last been written at
+/// its last event.
+fn trace_start_unix_nanos(trace_file: &Path, events: &[serde_json::Value]) -> anyhow::Result<u64> {
+    let modified = std::fs::metadata(trace_file)?
+        .modified()?
+        .duration_since(UNIX_EPOCH)?;
+    let last = events
+        .iter()
+        .filter_map(|event| event.get("ts")?.as_f64())
+        .fold(0.0, f64::max);
+    Ok(modified
+        .saturating_sub(std::time::Duration::from_secs_f64(last))
+        .as_nanos() as u64)
+}
+
+/// One line describing a request: where it is in the trace, who sent it to whom,
+/// and how long it took.
+fn describe(exchange: &Exchange<'_>) -> String {
+    let mut line = format!(
+        "#{} at {}: {} -> {} {}",
+        exchange.index,
+        format_time(exchange.start()),
+        exchange.from(),
+        exchange.to(),
+        exchange.method(),
+    );
+    if exchange.protocol() == "mcp" {
+        line.push_str(" (mcp)");
+    }
+    if let Some(session) = exchange.session() {
+        line.push_str(&format!(" in session {session}"));
+    }
+    match exchange.duration() {
+        Some(duration) => line.push_str(&format!(", took {}", format_duration(duration))),
+        None => line.push_str(", unanswered"),
+    }
+    line
+}
+
+fn column_width<'a>(values: impl Iterator<Item = &'a str>, header: &str) -> usize {
+    values
+        .map(str::len)
+        .chain([header.len()])
+        .max()
+        .unwrap_or(0)
+}
+
+/// Pretty-print JSON, indenting continuation lines to sit under a label.
+fn indent(value: &serde_json::Value) -> String {
+    serde_json::to_string_pretty(value)
+        .unwrap_or_default()
+        .replace('\n', "\n  ")
+}
+
+/// The first `max` characters of `text` on one line.
+fn preview(text: &str, max: usize) -> String {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    match text.char_indices().nth(max) {
+        Some((end, _)) => format!("{:?}", format!("{}...", &text[..end])),
+        None => format!("{text:?}"),
+    }
+}
+
+fn format_duration(seconds: f64) -> String {
+    if seconds < 1.0 {
+        format!("{:.1}ms", seconds * 1000.0)
+    } else {
+        format!("{:.2}s", seconds)
+    }
+}
+
+fn format_time(seconds: f64) -> String {
+    format!("{:.3}s", seconds)
+}
+
+fn plural(n: usize) -> &'static str {
+    if n == 1 { "" } else { "s" }
+}
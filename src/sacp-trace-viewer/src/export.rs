@@ -0,0 +1,434 @@
+//! Rendering traces in other tools' formats.
+//!
+//! Sequence diagrams come out as Mermaid or PlantUML source, for pasting into
+//! documentation, and request/response pairs come out as OpenTelemetry spans in
+//! OTLP JSON, for loading into Jaeger and similar tools. Each exporter takes a
+//! slice of events, so a trace can be narrowed with a [`TraceQuery`](crate::TraceQuery)
+//! first.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use crate::analysis::{Exchange, exchanges};
+use crate::query::{exchange, is_error, kind};
+
+/// Render `events` as a Mermaid `sequenceDiagram`.
+///
+/// Requests are solid arrows that activate the receiving component until it
+/// responds, responses are dashed arrows, notifications are open arrows and log
+/// lines are notes.
+pub fn to_mermaid(events: &[serde_json::Value]) -> String {
+    let participants = participants(events);
+    let id = |component: &str| participants[component].clone();
+
+    let mut out = String::from("sequenceDiagram\n");
+    for (component, alias) in ordered(&participants) {
+        if component == alias {
+            writeln!(out, "    participant {alias}").unwrap();
+        } else {
+            writeln!(
+                out,
+                "    participant {alias} as {}",
+                mermaid_text(component)
+            )
+            .unwrap();
+        }
+    }
+
+    let mut open = HashMap::new();
+    for event in events {
+        let Some(message) = Message::of(event, &mut open) else {
+            continue;
+        };
+        match message {
+            Message::Request { from, to, label } => {
+                writeln!(
+                    out,
+                    "    {}->>+{}: {}",
+                    id(from),
+                    id(to),
+                    mermaid_text(&label)
+                )
+            }
+            Message::Response {
+                from,
+                to,
+                label,
+                is_error,
+                answers,
+            } => {
+                let label = if is_error {
+                    format!("{label} (error)")
+                } else {
+                    label
+                };
+                let deactivate = if answers { "-" } else { "" };
+                writeln!(
+                    out,
+                    "    {}-->>{deactivate}{}: {}",
+                    id(from),
+                    id(to),
+                    mermaid_text(&label)
+                )
+            }
+            Message::Notification { from, to, label } => {
+                writeln!(
+                    out,
+                    "    {}-){}: {}",
+                    id(from),
+                    id(to),
+                    mermaid_text(&label)
+                )
+            }
+            Message::Log { component, text } => {
+                writeln!(
+                    out,
+                    "    Note over {}: {}",
+                    id(component),
+                    mermaid_text(&text)
+                )
+            }
+        }
+        .unwrap();
+    }
+    out
+}
+
+/// Render `events` as a PlantUML sequence diagram.
+///
+/// The diagram has the same shape as [`to_mermaid`]'s, with error responses in red.
+pub fn to_plantuml(events: &[serde_json::Value]) -> String {
+    let participants = participants(events);
+    let id = |component: &str| participants[component].clone();
+
+    let mut out = String::from("@startuml\n");
+    for (component, alias) in ordered(&participants) {
+        writeln!(out, "participant {component:?} as {alias}").unwrap();
+    }
+
+    let mut open = HashMap::new();
+    for event in events {
+        let Some(message) = Message::of(event, &mut open) else {
+            continue;
+        };
+        match message {
+            Message::Request { from, to, label } => {
+                writeln!(
+                    out,
+                    "{} -> {} : {}",
+                    id(from),
+                    id(to),
+                    plantuml_text(&label)
+                )
+                .unwrap();
+                writeln!(out, "activate {}", id(to)).unwrap();
+            }
+            Message::Response {
+                from,
+                to,
+                label,
+                is_error,
+                answers,
+            } => {
+                let arrow = if is_error { "-[#red]->" } else { "-->" };
+                writeln!(
+                    out,
+                    "{} {arrow} {} : {}",
+                    id(from),
+                    id(to),
+                    plantuml_text(&label)
+                )
+                .unwrap();
+                if answers {
+                    writeln!(out, "deactivate {}", id(from)).unwrap();
+                }
+            }
+            Message::Notification { from, to, label } => writeln!(
+                out,
+                "{} ->> {} : {}",
+                id(from),
+                id(to),
+                plantuml_text(&label)
+            )
+            .unwrap(),
+            Message::Log { component, text } => writeln!(
+                out,
+                "note over {} : {}",
+                id(component),
+                plantuml_text(&text)
+            )
+            .unwrap(),
+        }
+    }
+    out.push_str("@enduml\n");
+    out
+}
+
+/// Render the requests in `events` as OpenTelemetry spans, in the OTLP JSON
+/// encoding of an `ExportTraceServiceRequest`.
+///
+/// Each request and its response become one span, reported by the component that
+/// handled the request as its `service.name`. A request sent while its sender was
+/// handling another request, such as a proxy forwarding a prompt, is a child of
+/// that request's span, so a prompt and everything it caused form one trace.
+/// Unanswered requests end with the last event.
+///
+/// Event timestamps are relative to the start of the trace; `start_unix_nanos`
+/// anchors them in time. It also seeds the trace ids, so that exports of
+/// different traces do not collide.
+pub fn to_otlp(events: &[serde_json::Value], start_unix_nanos: u64) -> serde_json::Value {
+    let exchanges = exchanges(events);
+    let end_of_trace = events.iter().map(ts).fold(0.0, f64::max);
+    let nanos =
+        |seconds: f64| (start_unix_nanos + (seconds.max(0.0) * 1e9).round() as u64).to_string();
+
+    // For each span, its parent and the root of its tree, as indices into `exchanges`.
+    let mut parents: Vec<Option<usize>> = Vec::with_capacity(exchanges.len());
+    let mut roots: Vec<usize> = Vec::with_capacity(exchanges.len());
+    for (i, exchange) in exchanges.iter().enumerate() {
+        // The latest request the sender had received and not yet answered.
+        let parent = (0..i).rev().find(|&j| {
+            let candidate = &exchanges[j];
+            candidate.to() == exchange.from()
+                && candidate
+                    .response
+                    .is_none_or(|response| ts(response) >= exchange.start())
+        });
+        parents.push(parent);
+        roots.push(parent.map_or(i, |p| roots[p]));
+    }
+
+    let trace_id =
+        |root: usize| format!("{start_unix_nanos:016x}{:016x}", exchanges[root].index + 1);
+    let span_id = |i: usize| format!("{:016x}", exchanges[i].index + 1);
+
+    // Spans grouped by the component that handled them.
+    let mut services: HashMap<&str, Vec<serde_json::Value>> = HashMap::new();
+    for (i, exchange) in exchanges.iter().enumerate() {
+        let end = exchange.response.map_or(end_of_trace, ts);
+        let mut span = serde_json::json!({
+            "traceId": trace_id(roots[i]),
+            "spanId": span_id(i),
+            "name": exchange.method(),
+            "kind": 2, // SPAN_KIND_SERVER
+            "startTimeUnixNano": nanos(exchange.start()),
+            "endTimeUnixNano": nanos(end),
+            "attributes": span_attributes(exchange),
+            "status": span_status(exchange),
+        });
+        if let Some(parent) = parents[i] {
+            span["parentSpanId"] = span_id(parent).into();
+        }
+        services.entry(exchange.to()).or_default().push(span);
+    }
+
+    let mut services: Vec<_> = services.into_iter().collect();
+    services.sort_by_key(|&(service, _)| service);
+    serde_json::json!({
+        "resourceSpans": services
+            .into_iter()
+            .map(|(service, spans)| serde_json::json!({
+                "resource": {
+                    "attributes": [string_attribute("service.name", service)],
+                },
+                "scopeSpans": [{
+                    "scope": {"name": "sacp-trace-viewer", "version": env!("CARGO_PKG_VERSION")},
+                    "spans": spans,
+                }],
+            }))
+            .collect::<Vec<_>>(),
+    })
+}
+
+fn span_attributes(exchange: &Exchange<'_>) -> Vec<serde_json::Value> {
+    let mut attributes = vec![
+        string_attribute("rpc.system", "jsonrpc"),
+        string_attribute("rpc.method", exchange.method()),
+        string_attribute("rpc.jsonrpc.request_id", &id_text(&exchange.request["id"])),
+        string_attribute("sacp.protocol", exchange.protocol()),
+        string_attribute("sacp.from", exchange.from()),
+        string_attribute("sacp.to", exchange.to()),
+    ];
+    if let Some(session) = exchange.session() {
+        attributes.push(string_attribute("sacp.session", session));
+    }
+    if exchange.response.is_none() {
+        attributes
+            .push(serde_json::json!({"key": "sacp.unanswered", "value": {"boolValue": true}}));
+    }
+    attributes
+}
+
+fn span_status(exchange: &Exchange<'_>) -> serde_json::Value {
+    match exchange.response {
+        Some(response) if is_error(response) => {
+            let message = response["payload"]
+                .get("message")
+                .and_then(|m| m.as_str())
+                .unwrap_or("");
+            serde_json::json!({"code": 2, "message": message}) // STATUS_CODE_ERROR
+        }
+        Some(_) => serde_json::json!({"code": 1}), // STATUS_CODE_OK
+        None => serde_json::json!({}),
+    }
+}
+
+fn string_attribute(key: &str, value: &str) -> serde_json::Value {
+    serde_json::json!({"key": key, "value": {"stringValue": value}})
+}
+
+/// One arrow or note of a sequence diagram.
+enum Message<'a> {
+    Request {
+        from: &'a str,
+        to: &'a str,
+        label: String,
+    },
+    Response {
+        from: &'a str,
+        to: &'a str,
+        label: String,
+        is_error: bool,
+
+        /// Whether the request being answered is in the diagram, so its
+        /// activation can end.
+        answers: bool,
+    },
+    Notification {
+        from: &'a str,
+        to: &'a str,
+        label: String,
+    },
+    Log {
+        component: &'a str,
+        text: String,
+    },
+}
+
+impl<'a> Message<'a> {
+    /// The diagram element for `event`, where `open` holds the labels of the
+    /// requests that have been drawn and not yet answered.
+    fn of(event: &'a serde_json::Value, open: &mut HashMap<String, String>) -> Option<Self> {
+        let from = str_field(event, "from").unwrap_or("");
+        let to = str_field(event, "to").unwrap_or("");
+        let method = || {
+            let method = str_field(event, "method").unwrap_or("");
+            match str_field(event, "protocol") {
+                Some("mcp") => format!("mcp: {method}"),
+                _ => method.to_string(),
+            }
+        };
+        match kind(event) {
+            "request" => {
+                let label = method();
+                if let Some(key) = exchange(event, "to") {
+                    open.insert(key, label.clone());
+                }
+                Some(Message::Request { from, to, label })
+            }
+            "response" => {
+                // Label the response with the method it answers, if that is known.
+                let request = exchange(event, "from").and_then(|key| open.remove(&key));
+                Some(Message::Response {
+                    from,
+                    to,
+                    answers: request.is_some(),
+                    label: request.unwrap_or_else(|| format!("response {}", id_text(&event["id"]))),
+                    is_error: is_error(event),
+                })
+            }
+            "notification" => Some(Message::Notification {
+                from,
+                to,
+                label: method(),
+            }),
+            "trace" => Some(Message::Log {
+                component: str_field(event, "component")?,
+                text: str_field(event, "message").unwrap_or("").to_string(),
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// Diagram identifiers for each component in `events`.
+fn participants(events: &[serde_json::Value]) -> HashMap<String, String> {
+    let mut participants = HashMap::new();
+    for event in events {
+        for name in ["from", "to", "component"] {
+            if let Some(component) = str_field(event, name) {
+                participants
+                    .entry(component.to_string())
+                    .or_insert_with(|| {
+                        component
+                            .chars()
+                            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+                            .collect()
+                    });
+            }
+        }
+    }
+    participants
+}
+
+/// Components in diagram order: the client first, the agent last, as in the viewer.
+fn ordered(participants: &HashMap<String, String>) -> Vec<(&str, &str)> {
+    let mut ordered: Vec<_> = participants
+        .iter()
+        .map(|(component, alias)| (component.as_str(), alias.as_str()))
+        .collect();
+    ordered.sort_by_key(|&(component, _)| {
+        let rank = match component {
+            "client" => 0,
+            "agent" => 2,
+            _ => 1,
+        };
+        (rank, component)
+    });
+    ordered
+}
+
+/// Message text safe for a Mermaid label, which ends at `;` and treats `#` as
+/// the start of an entity code.
+fn mermaid_text(text: &str) -> String {
+    let mut escaped = String::new();
+    for c in one_line(text).chars() {
+        match c {
+            '#' => escaped.push_str("#35;"),
+            ';' => escaped.push_str("#59;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Message text safe for a PlantUML label.
+fn plantuml_text(text: &str) -> String {
+    one_line(text)
+}
+
+/// `text` on one line, shortened to keep the diagram readable.
+fn one_line(text: &str) -> String {
+    const MAX: usize = 80;
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    match text.char_indices().nth(MAX) {
+        Some((end, _)) => format!("{}...", &text[..end]),
+        None => text,
+    }
+}
+
+/// A request id as it appears in JSON-RPC, without quotes for string ids.
+fn id_text(id: &serde_json::Value) -> String {
+    match id {
+        serde_json::Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn str_field<'a>(event: &'a serde_json::Value, name: &str) -> Option<&'a str> {
+    event.get(name)?.as_str()
+}
+
+fn ts(event: &serde_json::Value) -> f64 {
+    event.get("ts").and_then(|ts| ts.as_f64()).unwrap_or(0.0)
+}
@@ -4,54 +4,118 @@
 //! Can serve events from memory (for live viewing) or from a file.
 
 use std::path::PathBuf;
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
+use std::time::Duration;
 
 use axum::{
-    Router,
-    extract::State,
-    http::StatusCode,
-    response::{Html, IntoResponse, Response},
+    Json, Router,
+    extract::{Query, State},
+    http::{HeaderMap, StatusCode},
+    response::{
+        Html, IntoResponse, Response,
+        sse::{Event, KeepAlive, Sse},
+    },
     routing::get,
 };
+use futures::StreamExt;
+use serde::Deserialize;
 use tokio::net::TcpListener;
 
+use crate::log::{Changes, EventLog, FileTail};
+
+mod analysis;
+mod export;
+mod log;
+mod query;
+
+pub use analysis::{
+    Exchange, Latency, MethodStats, SessionSummary, Turn, exchanges, failed_requests, method_stats,
+    read_trace, sessions, slowest_requests,
+};
+pub use export::{to_mermaid, to_otlp, to_plantuml};
+pub use query::{QueryMatch, QueryResult, TraceQuery};
+
 /// The HTML viewer page (embedded at compile time).
 pub const VIEWER_HTML: &str = include_str!("viewer.html");
 
+/// How often a trace file is checked for new events while it is being streamed.
+const FILE_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
 /// Source of trace events for the viewer.
 #[derive(Clone)]
 pub enum TraceSource {
-    /// Read events from a file (re-reads on each request for live updates).
+    /// Read events from a file, following it as it grows.
     File(PathBuf),
-    /// Read events from shared memory.
-    Memory(Arc<Mutex<Vec<serde_json::Value>>>),
+    /// Read events pushed through a [`TraceHandle`].
+    Memory(TraceHandle),
 }
 
 /// Handle to push events when using memory-backed trace source.
 #[derive(Clone)]
 pub struct TraceHandle {
-    events: Arc<Mutex<Vec<serde_json::Value>>>,
+    events: Arc<EventLog>,
 }
 
 impl TraceHandle {
+    /// Create an empty trace, to be served with [`TraceSource::Memory`].
+    pub fn new() -> Self {
+        Self {
+            events: Arc::new(EventLog::new()),
+        }
+    }
+
     /// Push a new event to the trace.
+    ///
+    /// Viewers connected to the stream receive it right away.
     pub fn push(&self, event: serde_json::Value) {
-        self.events.lock().unwrap().push(event);
+        self.events.push(event);
     }
 
     /// Get the current number of events.
     pub fn len(&self) -> usize {
-        self.events.lock().unwrap().len()
+        self.events.len()
     }
 
     /// Check if empty.
     pub fn is_empty(&self) -> bool {
-        self.events.lock().unwrap().is_empty()
+        self.len() == 0
+    }
+}
+
+impl Default for TraceHandle {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
 struct AppState {
-    source: TraceSource,
+    events: Arc<EventLog>,
+
+    /// The file the events are read from, for file-backed sources.
+    tail: Option<FileTail>,
+}
+
+impl AppState {
+    fn new(source: TraceSource) -> Self {
+        match source {
+            TraceSource::File(path) => Self {
+                events: Arc::new(EventLog::new()),
+                tail: Some(FileTail::new(path)),
+            },
+            TraceSource::Memory(handle) => Self {
+                events: handle.events,
+                tail: None,
+            },
+        }
+    }
+
+    /// Bring the events up to date with the trace file, if there is one.
+    async fn refresh(&self) -> std::io::Result<()> {
+        match &self.tail {
+            Some(tail) => tail.refresh(&self.events).await,
+            None => Ok(()),
+        }
+    }
 }
 
 /// Configuration for the trace viewer server.
@@ -74,7 +138,7 @@ impl Default for TraceViewerConfig {
 /// Start the trace viewer server with a memory-backed event source.
 ///
 /// Returns a handle to push events and a future that runs the server.
-/// The server will poll for new events automatically.
+/// Pushed events are streamed to open viewers as they arrive.
 ///
 /// # Example
 ///
@@ -96,11 +160,8 @@ pub async fn serve_memory(
     TraceHandle,
     impl std::future::Future<Output = anyhow::Result<()>>,
 )> {
-    let events = Arc::new(Mutex::new(Vec::new()));
-    let handle = TraceHandle {
-        events: events.clone(),
-    };
-    let source = TraceSource::Memory(events);
+    let handle = TraceHandle::new();
+    let source = TraceSource::Memory(handle.clone());
 
     let server = serve_impl(source, config);
     Ok((handle, server))
@@ -108,7 +169,8 @@ pub async fn serve_memory(
 
 /// Start the trace viewer server with a file-backed event source.
 ///
-/// The file is re-read on each request, allowing live updates as the file grows.
+/// The file is followed as it grows: only newly written events are read, and they
+/// are streamed to open viewers.
 ///
 /// # Example
 ///
@@ -124,12 +186,7 @@ pub async fn serve_file(path: PathBuf, config: TraceViewerConfig) -> anyhow::Res
 }
 
 async fn serve_impl(source: TraceSource, config: TraceViewerConfig) -> anyhow::Result<()> {
-    let state = Arc::new(AppState { source });
-
-    let app = Router::new()
-        .route("/", get(serve_viewer))
-        .route("/events", get(serve_events))
-        .with_state(state);
+    let app = router(source);
 
     let listener = TcpListener::bind(format!("127.0.0.1:{}", config.port)).await?;
     let addr = listener.local_addr()?;
@@ -147,55 +204,139 @@ async fn serve_impl(source: TraceSource, config: TraceViewerConfig) -> anyhow::R
     Ok(())
 }
 
+/// Build the viewer's routes for `source`.
+///
+/// [`serve_memory`] and [`serve_file`] serve this router on a local port; use it
+/// directly to mount the viewer in another axum application.
+///
+/// - `GET /` - the viewer page
+/// - `GET /events?from=N` - the events from index `N` (default 0) as a JSON array
+/// - `GET /events/stream?from=N` - server-sent events, one per trace event from
+///   index `N` onwards, as they are written. Each event's `id` is its index, so a
+///   reconnecting `EventSource` resumes where it left off. An event named `reset`
+///   means the trace was rewritten and should be reloaded.
+/// - `GET /query?...` - the events selected by a [`TraceQuery`], whose fields are
+///   the query parameters, as a [`QueryResult`]. For example,
+///   `/query?method=session/*&errors=true&limit=100`.
+pub fn router(source: TraceSource) -> Router {
+    Router::new()
+        .route("/", get(serve_viewer))
+        .route("/events", get(serve_events))
+        .route("/events/stream", get(stream_events))
+        .route("/query", get(query_events))
+        .with_state(Arc::new(AppState::new(source)))
+}
+
 /// Serve the main viewer HTML page.
 async fn serve_viewer() -> Html<&'static str> {
     Html(VIEWER_HTML)
 }
 
+#[derive(Deserialize)]
+struct EventsQuery {
+    /// Index of the first event to return.
+    #[serde(default)]
+    from: usize,
+}
+
 /// Serve the trace events as JSON.
-async fn serve_events(State(state): State<Arc<AppState>>) -> Response {
-    match &state.source {
-        TraceSource::File(path) => serve_events_from_file(path).await,
-        TraceSource::Memory(events) => serve_events_from_memory(events),
+async fn serve_events(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<EventsQuery>,
+) -> Response {
+    if let Err(e) = state.refresh().await {
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to read trace file: {}", e),
+        )
+            .into_response();
     }
+    Json(state.events.events_from(query.from)).into_response()
 }
 
-async fn serve_events_from_file(path: &PathBuf) -> Response {
-    match tokio::fs::read_to_string(path).await {
-        Ok(content) => {
-            let events: Vec<serde_json::Value> = content
-                .lines()
-                .filter(|line| !line.trim().is_empty())
-                .filter_map(|line| serde_json::from_str(line).ok())
-                .collect();
-
-            match serde_json::to_string(&events) {
-                Ok(json) => {
-                    (StatusCode::OK, [("content-type", "application/json")], json).into_response()
-                }
-                Err(e) => (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    format!("Failed to serialize events: {}", e),
-                )
-                    .into_response(),
-            }
-        }
-        Err(e) => (
+/// Serve the trace events selected by a query.
+async fn query_events(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<TraceQuery>,
+) -> Response {
+    if let Err(e) = state.refresh().await {
+        return (
             StatusCode::INTERNAL_SERVER_ERROR,
             format!("Failed to read trace file: {}", e),
         )
-            .into_response(),
+            .into_response();
     }
+    Json(state.events.read(|events| query.apply(events))).into_response()
 }
 
-fn serve_events_from_memory(events: &Arc<Mutex<Vec<serde_json::Value>>>) -> Response {
-    let events = events.lock().unwrap();
-    match serde_json::to_string(&*events) {
-        Ok(json) => (StatusCode::OK, [("content-type", "application/json")], json).into_response(),
-        Err(e) => (
+/// Stream trace events as they are written.
+async fn stream_events(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<EventsQuery>,
+    headers: HeaderMap,
+) -> Response {
+    if let Err(e) = state.refresh().await {
+        return (
             StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Failed to serialize events: {}", e),
+            format!("Failed to read trace file: {}", e),
         )
-            .into_response(),
+            .into_response();
     }
+
+    // A reconnecting EventSource tells us the last event it saw.
+    let from = headers
+        .get("last-event-id")
+        .and_then(|id| id.to_str().ok()?.parse::<usize>().ok())
+        .map_or(query.from, |last| last + 1);
+
+    let changed = state.events.subscribe();
+    let generation = state.events.generation();
+    let stream = futures::stream::unfold(
+        (state, changed, generation, from),
+        async |(state, mut changed, mut generation, mut seen)| {
+            loop {
+                changed.mark_unchanged();
+                let batch = match state.events.changes(generation, seen) {
+                    Changes::Appended(new) if new.is_empty() => None,
+                    Changes::Appended(new) => {
+                        let batch: Vec<_> = new
+                            .iter()
+                            .enumerate()
+                            .map(|(i, event)| {
+                                Event::default().id((seen + i).to_string()).json_data(event)
+                            })
+                            .collect();
+                        seen += new.len();
+                        Some(batch)
+                    }
+                    Changes::Reset => {
+                        generation = state.events.generation();
+                        seen = 0;
+                        Some(vec![Ok(Event::default().event("reset").data(""))])
+                    }
+                };
+                if let Some(batch) = batch {
+                    return Some((
+                        futures::stream::iter(batch),
+                        (state, changed, generation, seen),
+                    ));
+                }
+
+                // Wait for events to be pushed, or look for them in the trace file.
+                if state.tail.is_some() {
+                    tokio::time::sleep(FILE_POLL_INTERVAL).await;
+                    if let Err(e) = state.refresh().await {
+                        eprintln!("Failed to read trace file: {}", e);
+                    }
+                } else {
+                    changed.changed().await.ok()?;
+                }
+            }
+        },
+    )
+    .flatten();
+
+    Sse::new(stream)
+        .keep_alive(KeepAlive::default())
+        .into_response()
 }
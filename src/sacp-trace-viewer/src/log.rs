@@ -0,0 +1,194 @@
+//! The in-memory log of trace events that the viewer serves.
+//!
+//! Both trace sources end up here: a [`TraceHandle`](crate::TraceHandle) appends to
+//! the log directly, and a trace file is tailed into it, reading only what has been
+//! written since the last look. Readers can wait for the log to change, which is
+//! what the streaming endpoint does.
+
+use std::io::SeekFrom;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use tokio::sync::watch;
+
+/// Trace events in the order they were written.
+pub(crate) struct EventLog {
+    inner: Mutex<Inner>,
+
+    /// Bumped whenever the log changes.
+    version: watch::Sender<u64>,
+}
+
+struct Inner {
+    events: Vec<serde_json::Value>,
+
+    /// Bumped when the log is cleared, so readers know to start over.
+    generation: u64,
+}
+
+/// What has happened to the log since a reader last looked.
+pub(crate) enum Changes {
+    /// These events were appended.
+    Appended(Vec<serde_json::Value>),
+
+    /// The log was cleared and the reader should start over.
+    Reset,
+}
+
+impl EventLog {
+    pub(crate) fn new() -> Self {
+        Self {
+            inner: Mutex::new(Inner {
+                events: Vec::new(),
+                generation: 0,
+            }),
+            version: watch::Sender::new(0),
+        }
+    }
+
+    pub(crate) fn push(&self, event: serde_json::Value) {
+        self.extend(std::iter::once(event));
+    }
+
+    pub(crate) fn extend(&self, events: impl IntoIterator<Item = serde_json::Value>) {
+        let mut inner = self.inner.lock().unwrap();
+        let len = inner.events.len();
+        inner.events.extend(events);
+        if inner.events.len() != len {
+            self.version.send_modify(|version| *version += 1);
+        }
+    }
+
+    pub(crate) fn clear(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.events.clear();
+        inner.generation += 1;
+        self.version.send_modify(|version| *version += 1);
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.inner.lock().unwrap().events.len()
+    }
+
+    pub(crate) fn generation(&self) -> u64 {
+        self.inner.lock().unwrap().generation
+    }
+
+    /// Look at all the events.
+    pub(crate) fn read<R>(&self, f: impl FnOnce(&[serde_json::Value]) -> R) -> R {
+        f(&self.inner.lock().unwrap().events)
+    }
+
+    /// Copy out the events from index `from` onwards.
+    pub(crate) fn events_from(&self, from: usize) -> Vec<serde_json::Value> {
+        let inner = self.inner.lock().unwrap();
+        inner.events.get(from..).unwrap_or_default().to_vec()
+    }
+
+    /// The events appended since a reader that has seen `seen` events of
+    /// `generation` last looked.
+    pub(crate) fn changes(&self, generation: u64, seen: usize) -> Changes {
+        let inner = self.inner.lock().unwrap();
+        if inner.generation != generation || inner.events.len() < seen {
+            return Changes::Reset;
+        }
+        Changes::Appended(inner.events[seen..].to_vec())
+    }
+
+    /// Subscribe to changes of the log.
+    pub(crate) fn subscribe(&self) -> watch::Receiver<u64> {
+        self.version.subscribe()
+    }
+}
+
+/// Follows a `.jsons` trace file as it grows.
+pub(crate) struct FileTail {
+    path: PathBuf,
+    state: tokio::sync::Mutex<TailState>,
+}
+
+#[derive(Default)]
+struct TailState {
+    /// Which file we have been reading, if known.
+    file_id: Option<FileId>,
+
+    /// How far into the file we have read.
+    offset: u64,
+
+    /// The start of a line whose end has not been written yet.
+    partial: Vec<u8>,
+}
+
+impl FileTail {
+    pub(crate) fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            state: Default::default(),
+        }
+    }
+
+    /// Read whatever has been written since the last call into `log`.
+    ///
+    /// If the file has shrunk or been replaced by another (as when a trace is
+    /// rotated), it is read again from the start.
+    pub(crate) async fn refresh(&self, log: &EventLog) -> std::io::Result<()> {
+        let mut state = self.state.lock().await;
+
+        let mut file = tokio::fs::File::open(&self.path).await?;
+        let metadata = file.metadata().await?;
+        let len = metadata.len();
+        let file_id = FileId::of(&metadata);
+        let replaced = state.file_id.is_some() && state.file_id != file_id;
+        if replaced || len < state.offset {
+            *state = TailState::default();
+            log.clear();
+        }
+        state.file_id = file_id;
+        if len == state.offset {
+            return Ok(());
+        }
+
+        file.seek(SeekFrom::Start(state.offset)).await?;
+        let mut bytes = std::mem::take(&mut state.partial);
+        let read = file.read_to_end(&mut bytes).await?;
+        state.offset += read as u64;
+
+        // Keep an unfinished last line for next time.
+        let complete = bytes.iter().rposition(|&b| b == b'\n').map_or(0, |i| i + 1);
+        state.partial = bytes.split_off(complete);
+
+        log.extend(
+            bytes
+                .split(|&b| b == b'\n')
+                .filter(|line| !line.trim_ascii().is_empty())
+                .filter_map(|line| serde_json::from_slice(line).ok()),
+        );
+        Ok(())
+    }
+}
+
+/// Identifies a file independently of its path.
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct FileId {
+    dev: u64,
+    ino: u64,
+}
+
+impl FileId {
+    /// The identity of the file `metadata` describes, where the platform tells us.
+    /// Elsewhere a replaced file is only noticed once it is shorter than the old one.
+    #[cfg(unix)]
+    fn of(metadata: &std::fs::Metadata) -> Option<Self> {
+        use std::os::unix::fs::MetadataExt;
+        Some(Self {
+            dev: metadata.dev(),
+            ino: metadata.ino(),
+        })
+    }
+
+    #[cfg(not(unix))]
+    fn of(_metadata: &std::fs::Metadata) -> Option<Self> {
+        None
+    }
+}
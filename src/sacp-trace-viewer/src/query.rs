@@ -0,0 +1,261 @@
+//! Filtering trace events on the server.
+//!
+//! A [`TraceQuery`] selects events by session, component, protocol, method, time
+//! and outcome, and pages through the result. Responses carry none of these
+//! fields themselves (apart from the time), so a response is selected when the
+//! request it answers is.
+
+use std::collections::{HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+
+/// Which trace events to select, as accepted by `GET /query`.
+///
+/// All fields are optional; an empty query selects everything.
+///
+/// # Example
+///
+/// ```
+/// # use sacp_trace_viewer::TraceQuery;
+/// let events = vec![
+///     serde_json::json!({"type": "request", "ts": 0.1, "protocol": "acp", "from": "client",
+///                        "to": "agent", "id": 1, "method": "session/prompt", "params": {}}),
+///     serde_json::json!({"type": "response", "ts": 0.5, "from": "agent", "to": "client",
+///                        "id": 1, "is_error": true, "payload": {}}),
+/// ];
+/// let query = TraceQuery {
+///     method: Some("session/*".into()),
+///     errors: true,
+///     ..Default::default()
+/// };
+/// assert_eq!(query.apply(&events).total, 2);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TraceQuery {
+    /// Only events for this ACP session.
+    pub session: Option<String>,
+
+    /// Only events sent or received by this component (e.g. `agent` or `proxy:0`),
+    /// or logged by it.
+    pub component: Option<String>,
+
+    /// Only messages of this protocol: `acp` or `mcp`.
+    pub protocol: Option<String>,
+
+    /// Only messages whose method matches this glob, where `*` matches any
+    /// run of characters and `?` any single character (e.g. `session/*`).
+    pub method: Option<String>,
+
+    /// Only events at or after this time, in seconds since the trace started.
+    pub since: Option<f64>,
+
+    /// Only events at or before this time, in seconds since the trace started.
+    pub until: Option<f64>,
+
+    /// Only error responses, and the requests they answer.
+    pub errors: bool,
+
+    /// Skip this many of the selected events.
+    pub offset: usize,
+
+    /// Return at most this many of the selected events.
+    pub limit: Option<usize>,
+}
+
+/// A page of the events selected by a [`TraceQuery`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct QueryResult {
+    /// How many events were selected in all.
+    pub total: usize,
+
+    /// The offset of this page within the selected events.
+    pub offset: usize,
+
+    /// The selected events on this page.
+    pub events: Vec<QueryMatch>,
+}
+
+/// An event selected by a [`TraceQuery`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct QueryMatch {
+    /// The position of the event in the whole trace.
+    pub index: usize,
+
+    /// The event itself.
+    pub event: serde_json::Value,
+}
+
+impl TraceQuery {
+    /// Select events from `events`, which are in trace order.
+    pub fn apply(&self, events: &[serde_json::Value]) -> QueryResult {
+        let selected = self.select(events);
+        let limit = self.limit.unwrap_or(usize::MAX);
+        QueryResult {
+            total: selected.len(),
+            offset: self.offset,
+            events: selected
+                .into_iter()
+                .skip(self.offset)
+                .take(limit)
+                .map(|index| QueryMatch {
+                    index,
+                    event: events[index].clone(),
+                })
+                .collect(),
+        }
+    }
+
+    /// The indices of the selected events.
+    fn select(&self, events: &[serde_json::Value]) -> Vec<usize> {
+        // Requests answered with an error, when only those are wanted.
+        let failed: HashSet<String> = if self.errors {
+            events
+                .iter()
+                .filter(|event| kind(event) == "response" && is_error(event))
+                .filter_map(|event| exchange(event, "from"))
+                .collect()
+        } else {
+            HashSet::new()
+        };
+
+        // Whether the latest request of each exchange was selected.
+        let mut requests: HashMap<String, bool> = HashMap::new();
+
+        let mut selected = Vec::new();
+        for (index, event) in events.iter().enumerate() {
+            let keep = match kind(event) {
+                "request" => {
+                    let key = exchange(event, "to");
+                    let keep = self.matches(event)
+                        && (!self.errors || key.as_ref().is_some_and(|key| failed.contains(key)));
+                    if let Some(key) = key {
+                        requests.insert(key, keep);
+                    }
+                    keep
+                }
+                "response" => {
+                    exchange(event, "from")
+                        .and_then(|key| requests.get(&key).copied())
+                        .unwrap_or(false)
+                        && (!self.errors || is_error(event))
+                        && self.in_time_range(event)
+                }
+                _ => !self.errors && self.matches(event),
+            };
+            if keep {
+                selected.push(index);
+            }
+        }
+        selected
+    }
+
+    /// Whether a request, notification or log event matches the query.
+    fn matches(&self, event: &serde_json::Value) -> bool {
+        let field = |name: &str| event.get(name).and_then(|value| value.as_str());
+
+        if let Some(session) = &self.session
+            && field("session") != Some(session)
+        {
+            return false;
+        }
+
+        if let Some(component) = &self.component
+            && !["from", "to", "component"]
+                .iter()
+                .any(|name| field(name) == Some(component))
+        {
+            return false;
+        }
+
+        if let Some(protocol) = &self.protocol
+            && !field("protocol").is_some_and(|p| p.eq_ignore_ascii_case(protocol))
+        {
+            return false;
+        }
+
+        if let Some(method) = &self.method
+            && !field("method").is_some_and(|m| glob_match(method, m))
+        {
+            return false;
+        }
+
+        self.in_time_range(event)
+    }
+
+    fn in_time_range(&self, event: &serde_json::Value) -> bool {
+        let ts = event.get("ts").and_then(|ts| ts.as_f64()).unwrap_or(0.0);
+        self.since.is_none_or(|since| ts >= since) && self.until.is_none_or(|until| ts <= until)
+    }
+}
+
+pub(crate) fn kind(event: &serde_json::Value) -> &str {
+    event.get("type").and_then(|t| t.as_str()).unwrap_or("")
+}
+
+pub(crate) fn is_error(event: &serde_json::Value) -> bool {
+    event
+        .get("is_error")
+        .and_then(|e| e.as_bool())
+        .unwrap_or(false)
+}
+
+/// Identifies a request/response exchange by the component handling the request
+/// (`to` of the request, `from` of the response) and the request id.
+pub(crate) fn exchange(event: &serde_json::Value, component: &str) -> Option<String> {
+    let component = event.get(component)?.as_str()?;
+    Some(format!("{component}:{}", event.get("id")?))
+}
+
+/// Match `text` against a glob `pattern` with `*` and `?` wildcards.
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+
+    let (mut p, mut t) = (0, 0);
+    // Where the last `*` was, and the text position it is currently matched up to.
+    let mut star: Option<(usize, usize)> = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, t));
+                p += 1;
+            }
+            Some('?') => {
+                p += 1;
+                t += 1;
+            }
+            Some(&c) if c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match star {
+                // Let the last `*` swallow one more character and try again.
+                Some((star_p, star_t)) => {
+                    star = Some((star_p, star_t + 1));
+                    p = star_p + 1;
+                    t = star_t + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::glob_match;
+
+    #[test]
+    fn globs() {
+        assert!(glob_match("session/*", "session/prompt"));
+        assert!(glob_match("*/prompt", "session/prompt"));
+        assert!(glob_match("session/?rompt", "session/prompt"));
+        assert!(glob_match("*", ""));
+        assert!(glob_match("a*b*c", "aXbYbZc"));
+        assert!(!glob_match("session/*", "fs/read_text_file"));
+        assert!(!glob_match("session", "session/prompt"));
+        assert!(!glob_match("a*b*c", "aXbYbZ"));
+    }
+}
@@ -0,0 +1,233 @@
+//! Tests for whole-trace summaries.
+
+use std::io::Write;
+
+use sacp_trace_viewer::Latency;
+use serde_json::{Value, json};
+
+fn request(ts: f64, from: &str, to: &str, id: u64, method: &str, params: Value) -> Value {
+    json!({"type": "request", "ts": ts, "protocol": "acp", "from": from, "to": to, "id": id,
+           "method": method, "session": params.get("sessionId"), "params": params})
+}
+
+fn response(ts: f64, from: &str, to: &str, id: u64, is_error: bool, payload: Value) -> Value {
+    json!({"type": "response", "ts": ts, "from": from, "to": to, "id": id,
+           "is_error": is_error, "payload": payload})
+}
+
+fn update(ts: f64, from: &str, to: &str) -> Value {
+    json!({"type": "notification", "ts": ts, "protocol": "acp", "from": from, "to": to,
+           "method": "session/update", "session": "s1", "params": {"sessionId": "s1"}})
+}
+
+fn prompt(text: &str) -> Value {
+    json!({"sessionId": "s1", "prompt": [{"type": "text", "text": text}]})
+}
+
+/// A client prompting an agent twice through one proxy; the second turn fails.
+fn trace() -> Vec<Value> {
+    vec![
+        request(0.0, "client", "proxy:0", 1, "session/new", json!({})), // 0
+        request(0.1, "proxy:0", "agent", 1, "session/new", json!({})),  // 1
+        response(
+            0.2,
+            "agent",
+            "proxy:0",
+            1,
+            false,
+            json!({"sessionId": "s1"}),
+        ),
+        response(
+            0.3,
+            "proxy:0",
+            "client",
+            1,
+            false,
+            json!({"sessionId": "s1"}),
+        ),
+        request(
+            1.0,
+            "client",
+            "proxy:0",
+            2,
+            "session/prompt",
+            prompt("hello"),
+        ), // 4
+        request(
+            1.1,
+            "proxy:0",
+            "agent",
+            2,
+            "session/prompt",
+            prompt("hello"),
+        ), // 5
+        update(1.2, "agent", "proxy:0"),
+        update(1.25, "proxy:0", "client"),
+        update(1.3, "agent", "proxy:0"),
+        update(1.35, "proxy:0", "client"),
+        response(
+            1.5,
+            "agent",
+            "proxy:0",
+            2,
+            false,
+            json!({"stopReason": "end_turn"}),
+        ),
+        response(
+            1.6,
+            "proxy:0",
+            "client",
+            2,
+            false,
+            json!({"stopReason": "end_turn"}),
+        ),
+        request(
+            2.0,
+            "client",
+            "proxy:0",
+            3,
+            "session/prompt",
+            prompt("again"),
+        ), // 12
+        request(
+            2.1,
+            "proxy:0",
+            "agent",
+            3,
+            "session/prompt",
+            prompt("again"),
+        ), // 13
+        response(4.1, "agent", "proxy:0", 3, true, json!({"code": -32603})),
+        response(4.2, "proxy:0", "client", 3, true, json!({"code": -32603})),
+        request(
+            5.0,
+            "client",
+            "proxy:0",
+            4,
+            "session/cancel",
+            json!({"sessionId": "s1"}),
+        ), // 16
+    ]
+}
+
+#[test]
+fn test_exchanges_pair_requests_with_responses() {
+    let events = trace();
+    let exchanges = sacp_trace_viewer::exchanges(&events);
+    assert_eq!(exchanges.len(), 7);
+
+    let forwarded = &exchanges[3];
+    assert_eq!(forwarded.index, 5);
+    assert_eq!((forwarded.from(), forwarded.to()), ("proxy:0", "agent"));
+    assert_eq!(forwarded.response, Some(&events[10]));
+    assert!((forwarded.duration().unwrap() - 0.4).abs() < 1e-9);
+
+    assert!(exchanges[4].is_error());
+    assert_eq!(exchanges[6].response, None);
+}
+
+#[test]
+fn test_method_stats() {
+    let stats = sacp_trace_viewer::method_stats(&trace());
+    let methods: Vec<_> = stats.iter().map(|s| s.method.as_str()).collect();
+    assert_eq!(
+        methods,
+        [
+            "session/prompt",
+            "session/update",
+            "session/new",
+            "session/cancel"
+        ]
+    );
+
+    let prompt = &stats[0];
+    assert_eq!(
+        (prompt.requests, prompt.errors, prompt.unanswered),
+        (4, 2, 0)
+    );
+    let latency = prompt.latency.unwrap();
+    assert!((latency.p50 - 0.6).abs() < 1e-9);
+    assert!((latency.max - 2.2).abs() < 1e-9);
+
+    assert_eq!(stats[1].notifications, 4);
+    assert_eq!(stats[1].latency, None);
+    assert_eq!((stats[3].requests, stats[3].unanswered), (1, 1));
+}
+
+#[test]
+fn test_latency_percentiles() {
+    let durations = (1..=100).map(f64::from).collect();
+    assert_eq!(
+        Latency::of(durations),
+        Some(Latency {
+            p50: 50.0,
+            p90: 90.0,
+            p99: 99.0,
+            max: 100.0
+        })
+    );
+    assert_eq!(Latency::of(vec![]), None);
+}
+
+#[test]
+fn test_failed_and_slowest_requests() {
+    let events = trace();
+    let failed: Vec<_> = sacp_trace_viewer::failed_requests(&events)
+        .iter()
+        .map(|e| e.index)
+        .collect();
+    assert_eq!(failed, [12, 13]);
+
+    let slowest: Vec<_> = sacp_trace_viewer::slowest_requests(&events, 3)
+        .iter()
+        .map(|e| e.index)
+        .collect();
+    assert_eq!(slowest, [12, 13, 4]);
+}
+
+#[test]
+fn test_sessions() {
+    let sessions = sacp_trace_viewer::sessions(&trace());
+    assert_eq!(sessions.len(), 1);
+
+    let session = &sessions[0];
+    assert_eq!(session.session, "s1");
+    assert_eq!((session.start, session.end), (1.0, 5.0));
+    assert_eq!(session.errors, 2);
+    let durations: Vec<_> = session.turns.iter().map(|t| t.duration.unwrap()).collect();
+    assert!((durations[0] - 0.6).abs() < 1e-9 && (durations[1] - 2.2).abs() < 1e-9);
+
+    let [first, second] = &session.turns[..] else {
+        panic!("expected two turns, got {:?}", session.turns);
+    };
+    assert_eq!(
+        (
+            first.index,
+            first.prompt.as_str(),
+            first.stop_reason.as_deref()
+        ),
+        (4, "hello", Some("end_turn"))
+    );
+    assert_eq!((first.is_error, first.updates), (false, 2));
+    assert_eq!(
+        (
+            second.index,
+            second.prompt.as_str(),
+            second.stop_reason.as_deref()
+        ),
+        (12, "again", None)
+    );
+    assert_eq!((second.is_error, second.updates), (true, 0));
+}
+
+#[test]
+fn test_read_trace_skips_partial_lines() {
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    for event in trace() {
+        writeln!(file, "{event}").unwrap();
+    }
+    write!(file, "{{\"type\": \"tr").unwrap();
+    file.flush().unwrap();
+
+    assert_eq!(sacp_trace_viewer::read_trace(file.path()).unwrap(), trace());
+}
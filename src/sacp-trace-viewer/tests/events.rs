@@ -0,0 +1,165 @@
+//! Tests for the trace viewer's event endpoints.
+
+use std::io::Write;
+use std::time::Duration;
+
+use sacp_trace_viewer::{TraceHandle, TraceSource};
+use serde_json::json;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+/// Serve the viewer for `source` on a local port.
+async fn serve(source: TraceSource) -> std::net::SocketAddr {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(axum::serve(listener, sacp_trace_viewer::router(source)).into_future());
+    addr
+}
+
+/// Send a GET request, returning the connection to read the response from.
+async fn get(addr: std::net::SocketAddr, path: &str) -> TcpStream {
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream
+        .write_all(
+            format!("GET {path} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+                .as_bytes(),
+        )
+        .await
+        .unwrap();
+    stream
+}
+
+/// Fetch a JSON body.
+async fn get_json(addr: std::net::SocketAddr, path: &str) -> serde_json::Value {
+    let mut response = String::new();
+    get(addr, path)
+        .await
+        .read_to_string(&mut response)
+        .await
+        .unwrap();
+    let (_, body) = response.split_once("\r\n\r\n").unwrap();
+    serde_json::from_str(body).unwrap()
+}
+
+/// Read from `stream` until `needle` has been received.
+async fn read_until(stream: &mut TcpStream, received: &mut String, needle: &str) {
+    tokio::time::timeout(Duration::from_secs(10), async {
+        while !received.contains(needle) {
+            let mut buf = [0; 4096];
+            let n = stream.read(&mut buf).await.unwrap();
+            assert!(n > 0, "stream closed before {needle:?}; got {received:?}");
+            received.push_str(&String::from_utf8_lossy(&buf[..n]));
+        }
+    })
+    .await
+    .unwrap_or_else(|_| panic!("timed out waiting for {needle:?}; got {received:?}"));
+}
+
+#[tokio::test]
+async fn test_file_events_from_offset() {
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    for i in 0..3 {
+        writeln!(file, "{}", json!({"type": "trace", "n": i})).unwrap();
+    }
+    // An unfinished line is not an event yet.
+    write!(file, "{{\"type\": \"tr").unwrap();
+    file.flush().unwrap();
+
+    let addr = serve(TraceSource::File(file.path().to_owned())).await;
+    assert_eq!(
+        get_json(addr, "/events?from=1").await,
+        json!([{"type": "trace", "n": 1}, {"type": "trace", "n": 2}])
+    );
+
+    writeln!(file, "ace\", \"n\": 3}}").unwrap();
+    file.flush().unwrap();
+    assert_eq!(
+        get_json(addr, "/events?from=3").await,
+        json!([{"type": "trace", "n": 3}])
+    );
+}
+
+#[tokio::test]
+async fn test_stream_follows_file() {
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    writeln!(file, "{}", json!({"type": "trace", "n": 0})).unwrap();
+    file.flush().unwrap();
+
+    let addr = serve(TraceSource::File(file.path().to_owned())).await;
+    let mut stream = get(addr, "/events/stream").await;
+    let mut received = String::new();
+    read_until(&mut stream, &mut received, r#""n":0"#).await;
+    assert!(received.contains("id: 0"));
+
+    writeln!(file, "{}", json!({"type": "trace", "n": 1})).unwrap();
+    file.flush().unwrap();
+    read_until(&mut stream, &mut received, r#""n":1"#).await;
+    assert!(received.contains("id: 1"));
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_file_rotated_past_old_offset() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("trace.jsons");
+    let write_events = |events: &[serde_json::Value]| {
+        let mut file = std::fs::File::create(&path).unwrap();
+        for event in events {
+            writeln!(file, "{event}").unwrap();
+        }
+    };
+
+    let old: Vec<_> = (0..2)
+        .map(|n| json!({"type": "trace", "generation": "old", "n": n}))
+        .collect();
+    write_events(&old);
+    let addr = serve(TraceSource::File(path.clone())).await;
+    assert_eq!(get_json(addr, "/events").await, json!(old));
+
+    // Rotate, and write more to the new file than was read from the old one
+    // before the viewer looks again.
+    std::fs::rename(&path, dir.path().join("trace.1.jsons")).unwrap();
+    let new: Vec<_> = (0..5)
+        .map(
+            |n| json!({"type": "trace", "generation": "new", "n": n, "padding": "x".repeat(n * 7)}),
+        )
+        .collect();
+    write_events(&new);
+    assert_eq!(get_json(addr, "/events").await, json!(new));
+}
+
+#[tokio::test]
+async fn test_stream_pushed_events() {
+    let handle = TraceHandle::new();
+    handle.push(json!({"type": "trace", "n": 0}));
+    handle.push(json!({"type": "trace", "n": 1}));
+
+    let addr = serve(TraceSource::Memory(handle.clone())).await;
+    let mut stream = get(addr, "/events/stream?from=1").await;
+    let mut received = String::new();
+    read_until(&mut stream, &mut received, r#""n":1"#).await;
+    assert!(!received.contains(r#""n":0"#));
+
+    handle.push(json!({"type": "trace", "n": 2}));
+    read_until(&mut stream, &mut received, r#""n":2"#).await;
+    assert!(received.contains("id: 2"));
+}
+
+#[tokio::test]
+async fn test_query_endpoint() {
+    let handle = TraceHandle::new();
+    for (i, method) in ["session/new", "session/prompt", "fs/read_text_file"]
+        .into_iter()
+        .enumerate()
+    {
+        handle.push(json!({"type": "notification", "ts": i, "protocol": "acp",
+                           "from": "agent", "to": "client", "method": method, "params": {}}));
+    }
+
+    let addr = serve(TraceSource::Memory(handle)).await;
+    let result = get_json(addr, "/query?method=session/*&offset=1&limit=5").await;
+    assert_eq!(result["total"], 2);
+    assert_eq!(result["offset"], 1);
+    assert_eq!(result["events"][0]["index"], 1);
+    assert_eq!(result["events"][0]["event"]["method"], "session/prompt");
+}
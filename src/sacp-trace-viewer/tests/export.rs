@@ -0,0 +1,149 @@
+//! Tests for exporting traces to other formats.
+
+use serde_json::{Value, json};
+
+fn request(ts: f64, protocol: &str, from: &str, to: &str, id: u64, method: &str) -> Value {
+    json!({"type": "request", "ts": ts, "protocol": protocol, "from": from, "to": to,
+           "id": id, "method": method, "session": "s1", "params": {}})
+}
+
+fn response(ts: f64, from: &str, to: &str, id: u64, payload: Value) -> Value {
+    let is_error = payload.get("code").is_some();
+    json!({"type": "response", "ts": ts, "from": from, "to": to, "id": id,
+           "is_error": is_error, "payload": payload})
+}
+
+/// A prompt forwarded through a proxy, during which the agent calls one of the
+/// proxy's tools and it fails.
+fn trace() -> Vec<Value> {
+    vec![
+        request(1.0, "acp", "client", "proxy:0", 1, "session/prompt"),
+        request(1.1, "acp", "proxy:0", "agent", 7, "session/prompt"),
+        request(1.2, "mcp", "agent", "proxy:0", 3, "tools/call"),
+        json!({"type": "trace", "ts": 1.25, "component": "proxy:0", "level": "warn",
+               "message": "no such tool #2; giving up"}),
+        response(
+            1.3,
+            "proxy:0",
+            "agent",
+            3,
+            json!({"code": -32602, "message": "no such tool"}),
+        ),
+        json!({"type": "notification", "ts": 1.4, "protocol": "acp", "from": "agent",
+               "to": "proxy:0", "method": "session/update", "session": "s1", "params": {}}),
+        response(
+            1.5,
+            "agent",
+            "proxy:0",
+            7,
+            json!({"stopReason": "end_turn"}),
+        ),
+        response(
+            1.6,
+            "proxy:0",
+            "client",
+            1,
+            json!({"stopReason": "end_turn"}),
+        ),
+    ]
+}
+
+#[test]
+fn test_mermaid() {
+    expect_lines(
+        &sacp_trace_viewer::to_mermaid(&trace()),
+        &[
+            "sequenceDiagram",
+            "    participant client",
+            "    participant proxy_0 as proxy:0",
+            "    participant agent",
+            "    client->>+proxy_0: session/prompt",
+            "    proxy_0->>+agent: session/prompt",
+            "    agent->>+proxy_0: mcp: tools/call",
+            "    Note over proxy_0: no such tool #35;2#59; giving up",
+            "    proxy_0-->>-agent: mcp: tools/call (error)",
+            "    agent-)proxy_0: session/update",
+            "    agent-->>-proxy_0: session/prompt",
+            "    proxy_0-->>-client: session/prompt",
+        ],
+    );
+}
+
+#[test]
+fn test_plantuml() {
+    expect_lines(
+        &sacp_trace_viewer::to_plantuml(&trace()),
+        &[
+            "@startuml",
+            "participant \"client\" as client",
+            "participant \"proxy:0\" as proxy_0",
+            "participant \"agent\" as agent",
+            "client -> proxy_0 : session/prompt",
+            "activate proxy_0",
+            "proxy_0 -> agent : session/prompt",
+            "activate agent",
+            "agent -> proxy_0 : mcp: tools/call",
+            "activate proxy_0",
+            "note over proxy_0 : no such tool #2; giving up",
+            "proxy_0 -[#red]-> agent : mcp: tools/call",
+            "deactivate proxy_0",
+            "agent ->> proxy_0 : session/update",
+            "agent --> proxy_0 : session/prompt",
+            "deactivate agent",
+            "proxy_0 --> client : session/prompt",
+            "deactivate proxy_0",
+            "@enduml",
+        ],
+    );
+}
+
+#[test]
+fn test_response_without_request() {
+    // A slice that starts after the request was sent cannot end its activation.
+    let diagram = sacp_trace_viewer::to_mermaid(&trace()[6..]);
+    assert!(diagram.contains("    agent-->>proxy_0: response 7\n"));
+}
+
+#[test]
+fn test_otlp_spans_link_across_proxies() {
+    let start = 1_700_000_000_000_000_000;
+    let otlp = sacp_trace_viewer::to_otlp(&trace(), start);
+
+    let mut spans = Vec::new();
+    for resource in otlp["resourceSpans"].as_array().unwrap() {
+        let service = resource["resource"]["attributes"][0]["value"]["stringValue"].clone();
+        for span in resource["scopeSpans"][0]["spans"].as_array().unwrap() {
+            spans.push((service.clone(), span.clone()));
+        }
+    }
+    let span = |name: &str, service: &str| {
+        spans
+            .iter()
+            .find(|(s, span)| span["name"] == name && s == service)
+            .map(|(_, span)| span.clone())
+            .unwrap_or_else(|| panic!("no {name} span in {service}"))
+    };
+
+    let prompt = span("session/prompt", "proxy:0");
+    let forwarded = span("session/prompt", "agent");
+    let tool = span("tools/call", "proxy:0");
+
+    // The forwarded prompt is a child of the client's prompt, and the tool call
+    // a child of the forwarded prompt, all in one trace.
+    assert!(prompt.get("parentSpanId").is_none());
+    assert_eq!(forwarded["parentSpanId"], prompt["spanId"]);
+    assert_eq!(tool["parentSpanId"], forwarded["spanId"]);
+    assert_eq!(tool["traceId"], prompt["traceId"]);
+
+    assert_eq!(prompt["startTimeUnixNano"], "1700000001000000000");
+    assert_eq!(prompt["endTimeUnixNano"], "1700000001600000000");
+    assert_eq!(
+        tool["status"],
+        json!({"code": 2, "message": "no such tool"})
+    );
+    assert_eq!(prompt["status"], json!({"code": 1}));
+}
+
+fn expect_lines(actual: &str, expected: &[&str]) {
+    assert_eq!(actual.lines().collect::<Vec<_>>(), expected, "{actual}");
+}
@@ -0,0 +1,121 @@
+//! Tests for server-side trace queries.
+
+use sacp_trace_viewer::TraceQuery;
+use serde_json::{Value, json};
+
+fn request(ts: f64, protocol: &str, to: &str, id: u64, method: &str, session: &str) -> Value {
+    json!({"type": "request", "ts": ts, "protocol": protocol, "from": "client", "to": to,
+           "id": id, "method": method, "session": session, "params": {}})
+}
+
+fn response(ts: f64, from: &str, id: u64, is_error: bool) -> Value {
+    json!({"type": "response", "ts": ts, "from": from, "to": "client", "id": id,
+           "is_error": is_error, "payload": {}})
+}
+
+/// A small trace of two sessions with an MCP call and a log line.
+fn trace() -> Vec<Value> {
+    vec![
+        request(0.0, "acp", "agent", 1, "session/prompt", "s1"), // 0
+        request(0.1, "acp", "agent", 2, "session/prompt", "s2"), // 1
+        request(0.2, "mcp", "proxy:0", 1, "tools/call", "s1"),   // 2
+        response(0.3, "proxy:0", 1, true),                       // 3
+        json!({"type": "notification", "ts": 0.4, "protocol": "acp", "from": "agent",
+               "to": "client", "method": "session/update", "session": "s1", "params": {}}), // 4
+        json!({"type": "trace", "ts": 0.45, "component": "agent", "level": "info",
+               "message": "thinking"}), // 5
+        response(0.5, "agent", 2, false),                        // 6
+        response(0.6, "agent", 1, false),                        // 7
+    ]
+}
+
+fn indices(query: TraceQuery) -> Vec<usize> {
+    query
+        .apply(&trace())
+        .events
+        .into_iter()
+        .map(|m| m.index)
+        .collect()
+}
+
+#[test]
+fn test_empty_query_selects_everything() {
+    assert_eq!(indices(TraceQuery::default()), (0..8).collect::<Vec<_>>());
+}
+
+#[test]
+fn test_responses_follow_their_requests() {
+    let session = TraceQuery {
+        session: Some("s2".into()),
+        ..Default::default()
+    };
+    assert_eq!(indices(session), [1, 6]);
+
+    let mcp = TraceQuery {
+        protocol: Some("mcp".into()),
+        ..Default::default()
+    };
+    assert_eq!(indices(mcp), [2, 3]);
+}
+
+#[test]
+fn test_component_and_method() {
+    let proxy = TraceQuery {
+        component: Some("proxy:0".into()),
+        ..Default::default()
+    };
+    assert_eq!(indices(proxy), [2, 3]);
+
+    let agent = TraceQuery {
+        component: Some("agent".into()),
+        method: Some("session/*".into()),
+        ..Default::default()
+    };
+    assert_eq!(indices(agent), [0, 1, 4, 6, 7]);
+}
+
+#[test]
+fn test_time_range_and_errors() {
+    let window = TraceQuery {
+        since: Some(0.15),
+        until: Some(0.45),
+        ..Default::default()
+    };
+    assert_eq!(indices(window), [2, 3, 4, 5]);
+
+    let errors = TraceQuery {
+        errors: true,
+        ..Default::default()
+    };
+    assert_eq!(indices(errors), [2, 3]);
+}
+
+#[test]
+fn test_pagination() {
+    let page = TraceQuery {
+        offset: 2,
+        limit: Some(3),
+        ..Default::default()
+    }
+    .apply(&trace());
+    assert_eq!(page.total, 8);
+    assert_eq!(page.offset, 2);
+    let indices: Vec<_> = page.events.iter().map(|m| m.index).collect();
+    assert_eq!(indices, [2, 3, 4]);
+    assert_eq!(page.events[0].event["method"], "tools/call");
+}
+
+#[test]
+fn test_query_from_url_parameters() {
+    let query: TraceQuery =
+        serde_json::from_value(json!({"method": "tools/*", "errors": true, "limit": 10})).unwrap();
+    assert_eq!(
+        query,
+        TraceQuery {
+            method: Some("tools/*".into()),
+            errors: true,
+            limit: Some(10),
+            ..Default::default()
+        }
+    );
+}
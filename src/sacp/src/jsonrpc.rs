@@ -17,14 +17,17 @@ use futures::future::{self, BoxFuture, Either};
 use futures::{AsyncRead, AsyncWrite, StreamExt};
 
 mod dynamic_handler;
+mod framing;
 pub(crate) mod handlers;
 mod incoming_actor;
+mod message_size;
 mod outgoing_actor;
 pub(crate) mod responder;
 mod task_actor;
 mod transport_actor;
 
 use crate::jsonrpc::dynamic_handler::DynamicHandlerMessage;
+pub use crate::jsonrpc::framing::Framing;
 pub use crate::jsonrpc::handlers::NullHandler;
 use crate::jsonrpc::handlers::{ChainedHandler, NamedHandler};
 use crate::jsonrpc::handlers::{MessageHandler, NotificationHandler, RequestHandler};
@@ -543,6 +546,9 @@ pub struct JrConnectionBuilder<H: JrMessageHandler, R: JrResponder<H::Link> = Nu
 
     /// Responder for background tasks.
     responder: R,
+
+    /// Incoming messages larger than this are refused.
+    max_message_size: Option<usize>,
 }
 
 impl<Link: JrLink> JrConnectionBuilder<NullHandler<Link>, NullResponder> {
@@ -554,6 +560,7 @@ impl<Link: JrLink> JrConnectionBuilder<NullHandler<Link>, NullResponder> {
             name: Default::default(),
             handler: NullHandler::new(role),
             responder: NullResponder,
+            max_message_size: None,
         }
     }
 }
@@ -565,6 +572,7 @@ impl<H: JrMessageHandler> JrConnectionBuilder<H, NullResponder> {
             name: Default::default(),
             handler,
             responder: NullResponder,
+            max_message_size: None,
         }
     }
 }
@@ -576,6 +584,22 @@ impl<H: JrMessageHandler, R: JrResponder<H::Link>> JrConnectionBuilder<H, R> {
         self
     }
 
+    /// Refuse incoming messages whose JSON is larger than `bytes`.
+    ///
+    /// An oversized request is answered with an `invalid_request` error without
+    /// reaching any handler, an oversized response is delivered to whoever awaits it
+    /// as that same error, and an oversized notification is logged and dropped. The
+    /// connection itself carries on.
+    ///
+    /// This applies whatever the transport, but by the time the connection sees a
+    /// message the transport has already parsed it. To also avoid reading oversized
+    /// messages into memory, set a limit on the transport as well, e.g. with
+    /// [`ByteStreams::with_max_message_size`].
+    pub fn max_message_size(mut self, bytes: usize) -> Self {
+        self.max_message_size = Some(bytes);
+        self
+    }
+
     /// Merge another [`JrConnectionBuilder`] into this one.
     ///
     /// Prefer [`Self::on_receive_request`] or [`Self::on_receive_notification`].
@@ -595,6 +619,10 @@ impl<H: JrMessageHandler, R: JrResponder<H::Link>> JrConnectionBuilder<H, R> {
                 NamedHandler::new(other.name, other.handler),
             ),
             responder: ChainResponder::new(self.responder, other.responder),
+            max_message_size: match (self.max_message_size, other.max_message_size) {
+                (Some(mine), Some(theirs)) => Some(mine.min(theirs)),
+                (mine, theirs) => mine.or(theirs),
+            },
         }
     }
 
@@ -613,6 +641,7 @@ impl<H: JrMessageHandler, R: JrResponder<H::Link>> JrConnectionBuilder<H, R> {
             name: self.name,
             handler: ChainedHandler::new(self.handler, handler),
             responder: self.responder,
+            max_message_size: self.max_message_size,
         }
     }
 
@@ -628,6 +657,7 @@ impl<H: JrMessageHandler, R: JrResponder<H::Link>> JrConnectionBuilder<H, R> {
             name: self.name,
             handler: self.handler,
             responder: ChainResponder::new(self.responder, responder),
+            max_message_size: self.max_message_size,
         }
     }
 
@@ -1044,6 +1074,7 @@ impl<H: JrMessageHandler, R: JrResponder<H::Link>> JrConnectionBuilder<H, R> {
             name,
             handler,
             responder,
+            max_message_size,
         } = self;
 
         let (outgoing_tx, outgoing_rx) = mpsc::unbounded();
@@ -1073,6 +1104,7 @@ impl<H: JrMessageHandler, R: JrResponder<H::Link>> JrConnectionBuilder<H, R> {
             dynamic_handler_rx,
             handler,
             responder,
+            max_message_size,
         })
     }
 
@@ -1218,6 +1250,7 @@ pub struct JrConnection<H: JrMessageHandler, R: JrResponder<H::Link> = NullRespo
     dynamic_handler_rx: mpsc::UnboundedReceiver<DynamicHandlerMessage<H::Link>>,
     handler: H,
     responder: R,
+    max_message_size: Option<usize>,
 }
 
 impl<H: JrMessageHandler, R: JrResponder<H::Link>> JrConnection<H, R> {
@@ -1338,6 +1371,7 @@ impl<H: JrMessageHandler, R: JrResponder<H::Link>> JrConnection<H, R> {
             transport_outgoing_tx,
             transport_incoming_rx,
             dynamic_handler_rx,
+            max_message_size,
         } = self;
         let (reply_tx, reply_rx) = mpsc::unbounded();
 
@@ -1357,6 +1391,7 @@ impl<H: JrMessageHandler, R: JrResponder<H::Link>> JrConnection<H, R> {
                         dynamic_handler_rx,
                         reply_rx,
                         handler,
+                        max_message_size,
                     ),
                     task_actor::task_actor(new_task_rx, &cx),
                     responder.run(cx.clone()),
@@ -2988,6 +3023,8 @@ pub struct Lines<OutgoingSink, IncomingStream> {
     pub outgoing: OutgoingSink,
     /// Incoming line stream (where we read and parse JSON-RPC messages)
     pub incoming: IncomingStream,
+    /// Lines longer than this many bytes are not parsed (see [`Lines::with_max_message_size`])
+    max_message_size: Option<usize>,
 }
 
 impl<OutgoingSink, IncomingStream> Lines<OutgoingSink, IncomingStream>
@@ -2997,7 +3034,25 @@ where
 {
     /// Create a new line stream transport.
     pub fn new(outgoing: OutgoingSink, incoming: IncomingStream) -> Self {
-        Self { outgoing, incoming }
+        Self {
+            outgoing,
+            incoming,
+            max_message_size: None,
+        }
+    }
+
+    /// Refuse incoming messages longer than `bytes`.
+    ///
+    /// An oversized request is answered with an `invalid_request` error, an oversized
+    /// response is delivered to its waiter as that same error, and an oversized
+    /// notification is logged and dropped. Either way the connection stays up.
+    ///
+    /// The line has already been read into memory by the time `Lines` sees it; to
+    /// bound memory use as well, use [`ByteStreams::with_max_message_size`] or
+    /// [`Framing::frame_limited`].
+    pub fn with_max_message_size(mut self, bytes: usize) -> Self {
+        self.max_message_size = Some(bytes);
+        self
     }
 }
 
@@ -3015,7 +3070,11 @@ where
     }
 
     fn into_server(self) -> (Channel, BoxFuture<'static, Result<(), crate::Error>>) {
-        let Self { outgoing, incoming } = self;
+        let Self {
+            outgoing,
+            incoming,
+            max_message_size,
+        } = self;
 
         // Create a channel pair for the client to use
         let (channel_for_caller, channel_for_lines) = Channel::duplex();
@@ -3024,9 +3083,20 @@ where
         let server_future = Box::pin(async move {
             let Channel { rx, tx } = channel_for_lines;
 
+            // Error responses to oversized requests are sent by the transport itself
+            let (reply_tx, reply_rx) = mpsc::unbounded();
+
             // Run both actors concurrently
-            let outgoing_future = transport_actor::transport_outgoing_lines_actor(rx, outgoing);
-            let incoming_future = transport_actor::transport_incoming_lines_actor(incoming, tx);
+            let outgoing_future = transport_actor::transport_outgoing_lines_actor(
+                futures::stream::select(rx, reply_rx),
+                outgoing,
+            );
+            let incoming_future = transport_actor::transport_incoming_lines_actor(
+                incoming,
+                tx,
+                reply_tx,
+                max_message_size,
+            );
 
             // Wait for both to complete
             futures::try_join!(outgoing_future, incoming_future)?;
@@ -3044,6 +3114,9 @@ where
 /// streams, handling serialization of JSON-RPC messages to/from newline-delimited JSON.
 /// This is the standard way to communicate with external processes or network connections.
 ///
+/// Peers that delimit messages with `Content-Length` headers instead are supported
+/// by choosing a different [`Framing`] with [`with_framing`](Self::with_framing).
+///
 /// # Use Cases
 ///
 /// - **Stdio communication**: Connect to agents or proxies via stdin/stdout
@@ -3075,12 +3148,31 @@ where
 /// # }
 /// ```
 ///
+/// Connecting to a process that uses `Content-Length` framing:
+///
+/// ```no_run
+/// # use sacp::{ByteStreams, Framing};
+/// use tokio_util::compat::{TokioAsyncReadCompatExt, TokioAsyncWriteCompatExt};
+///
+/// # fn example(child: &mut tokio::process::Child) {
+/// let component = ByteStreams::new(
+///     child.stdin.take().unwrap().compat_write(),
+///     child.stdout.take().unwrap().compat(),
+/// )
+/// .with_framing(Framing::ContentLength);
+/// # }
+/// ```
+///
 /// [`Component`]: crate::Component
 pub struct ByteStreams<OB, IB> {
     /// Outgoing byte stream (where we write serialized messages)
     pub outgoing: OB,
     /// Incoming byte stream (where we read and parse messages)
     pub incoming: IB,
+    /// How messages are delimited on both streams (see [`ByteStreams::with_framing`])
+    framing: Framing,
+    /// Incoming messages larger than this many bytes are skipped (see [`ByteStreams::with_max_message_size`])
+    max_message_size: Option<usize>,
 }
 
 impl<OB, IB> ByteStreams<OB, IB>
@@ -3088,9 +3180,28 @@ where
     OB: AsyncWrite + Send + 'static,
     IB: AsyncRead + Send + 'static,
 {
-    /// Create a new byte stream transport.
+    /// Create a new byte stream transport using newline-delimited JSON.
     pub fn new(outgoing: OB, incoming: IB) -> Self {
-        Self { outgoing, incoming }
+        Self {
+            outgoing,
+            incoming,
+            framing: Framing::default(),
+            max_message_size: None,
+        }
+    }
+
+    /// Delimit messages with `framing` rather than newlines.
+    pub fn with_framing(mut self, framing: Framing) -> Self {
+        self.framing = framing;
+        self
+    }
+
+    /// Refuse incoming messages larger than `bytes`, without reading them into memory.
+    ///
+    /// Oversized messages are handled as described in [`Lines::with_max_message_size`].
+    pub fn with_max_message_size(mut self, bytes: usize) -> Self {
+        self.max_message_size = Some(bytes);
+        self
     }
 }
 
@@ -3108,27 +3219,23 @@ where
     }
 
     fn into_server(self) -> (Channel, BoxFuture<'static, Result<(), crate::Error>>) {
-        use futures::AsyncBufReadExt;
-        use futures::AsyncWriteExt;
-        use futures::io::BufReader;
-        let Self { outgoing, incoming } = self;
-
-        // Convert byte streams to line streams
-        // Box both streams to satisfy Unpin requirements
-        let incoming_lines = Box::pin(BufReader::new(incoming).lines());
-
-        // Create a sink that writes lines (with newlines) to the outgoing byte stream
-        // We need to Box the writer since it may not be Unpin
-        let outgoing_sink =
-            futures::sink::unfold(Box::pin(outgoing), async move |mut writer, line: String| {
-                let mut bytes = line.into_bytes();
-                bytes.push(b'\n');
-                writer.write_all(&bytes).await?;
-                Ok::<_, std::io::Error>(writer)
-            });
+        let Self {
+            outgoing,
+            incoming,
+            framing,
+            max_message_size,
+        } = self;
+
+        // Convert byte streams to streams of messages, one JSON string each
+        let (outgoing_sink, incoming_messages) =
+            framing.frame_limited(outgoing, incoming, max_message_size);
 
         // Delegate to Lines component
-        Component::<L>::into_server(Lines::new(outgoing_sink, incoming_lines))
+        Component::<L>::into_server(Lines {
+            outgoing: outgoing_sink,
+            incoming: incoming_messages,
+            max_message_size,
+        })
     }
 }
 
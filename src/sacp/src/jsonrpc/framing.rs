@@ -0,0 +1,373 @@
+//! Framing of JSON-RPC messages on byte streams.
+//!
+//! ACP peers normally send one JSON message per line, but many JSON-RPC tools
+//! (LSP servers, some MCP servers and agents) instead precede each message with
+//! a `Content-Length` header. [`Framing`] converts between a byte stream and the
+//! individual message strings in either style, so that [`ByteStreams`] and
+//! [`Lines`] can talk to both.
+//!
+//! [`ByteStreams`]: crate::ByteStreams
+//! [`Lines`]: crate::Lines
+
+use std::io;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use futures::io::BufReader;
+use futures::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use crate::jsonrpc::message_size::{OversizedMessage, Skimmer};
+
+/// The header that precedes each message with [`Framing::ContentLength`].
+const CONTENT_LENGTH: &str = "content-length";
+
+/// The most bytes of headers we read before a message body.
+///
+/// Headers are buffered whatever the maximum message size, so they are limited
+/// separately; real headers are a few dozen bytes.
+const MAX_HEADER_SIZE: usize = 8 * 1024;
+
+/// How JSON-RPC messages are delimited on a byte stream.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Framing {
+    /// One message per line (newline-delimited JSON). This is what ACP uses.
+    #[default]
+    Newline,
+
+    /// Each message is preceded by headers, as in the Language Server Protocol:
+    ///
+    /// ```text
+    /// Content-Length: 52\r\n
+    /// \r\n
+    /// {"jsonrpc":"2.0","id":1,"method":"initialize",...}
+    /// ```
+    ContentLength,
+
+    /// Detect the framing from the first bytes received: a message starting with
+    /// a header means [`ContentLength`](Self::ContentLength), anything else means
+    /// [`Newline`](Self::Newline).
+    ///
+    /// Outgoing messages use the detected framing, and are newline-delimited until
+    /// something has been received. This suits the side of a connection that
+    /// waits for its peer to speak first, such as an agent; a client connecting
+    /// to a `Content-Length` peer should ask for that framing explicitly.
+    Auto,
+}
+
+/// The framing detected by [`Framing::Auto`], shared between reader and writer.
+#[derive(Clone, Default)]
+struct Detected(Arc<AtomicU8>);
+
+impl Detected {
+    const UNKNOWN: u8 = 0;
+    const NEWLINE: u8 = 1;
+    const CONTENT_LENGTH: u8 = 2;
+
+    fn get(&self) -> Option<Framing> {
+        match self.0.load(Ordering::Acquire) {
+            Self::NEWLINE => Some(Framing::Newline),
+            Self::CONTENT_LENGTH => Some(Framing::ContentLength),
+            _ => None,
+        }
+    }
+
+    fn set(&self, framing: Framing) {
+        let value = match framing {
+            Framing::Newline => Self::NEWLINE,
+            Framing::ContentLength => Self::CONTENT_LENGTH,
+            Framing::Auto => Self::UNKNOWN,
+        };
+        self.0.store(value, Ordering::Release);
+    }
+}
+
+impl Framing {
+    /// Split a pair of byte streams into a sink of outgoing messages and a stream
+    /// of incoming ones, each message being a single JSON string.
+    ///
+    /// The results can be given to [`Lines`](crate::Lines), optionally after
+    /// inspecting or transforming the messages.
+    pub fn frame(
+        self,
+        outgoing: impl AsyncWrite + Send + 'static,
+        incoming: impl AsyncRead + Send + 'static,
+    ) -> (
+        impl futures::Sink<String, Error = io::Error> + Send + 'static,
+        impl futures::Stream<Item = io::Result<String>> + Send + 'static,
+    ) {
+        self.frame_limited(outgoing, incoming, None)
+    }
+
+    /// Like [`frame`](Self::frame), but incoming messages larger than
+    /// `max_message_size` bytes are not buffered.
+    ///
+    /// The stream yields an [`io::ErrorKind::InvalidData`] error in place of each
+    /// oversized message and carries on with the next one. [`Lines`](crate::Lines)
+    /// recognizes these errors and answers or drops the message, as described in
+    /// [`Lines::with_max_message_size`](crate::Lines::with_max_message_size).
+    ///
+    /// With [`Framing::ContentLength`], the headers of each message are limited
+    /// to 8 KiB regardless of `max_message_size`; longer headers end the stream
+    /// with an [`io::ErrorKind::InvalidData`] error.
+    pub fn frame_limited(
+        self,
+        outgoing: impl AsyncWrite + Send + 'static,
+        incoming: impl AsyncRead + Send + 'static,
+        max_message_size: Option<usize>,
+    ) -> (
+        impl futures::Sink<String, Error = io::Error> + Send + 'static,
+        impl futures::Stream<Item = io::Result<String>> + Send + 'static,
+    ) {
+        let detected = Detected::default();
+        (
+            self.message_sink(outgoing, detected.clone()),
+            self.message_stream(incoming, detected, max_message_size.unwrap_or(usize::MAX)),
+        )
+    }
+
+    fn message_sink(
+        self,
+        outgoing: impl AsyncWrite + Send + 'static,
+        detected: Detected,
+    ) -> impl futures::Sink<String, Error = io::Error> + Send + 'static {
+        futures::sink::unfold(Box::pin(outgoing), move |mut writer, message: String| {
+            let framing = match self {
+                Framing::Auto => detected.get().unwrap_or(Framing::Newline),
+                framing => framing,
+            };
+            async move {
+                let mut bytes = match framing {
+                    Framing::ContentLength => {
+                        format!("Content-Length: {}\r\n\r\n", message.len()).into_bytes()
+                    }
+                    _ => Vec::with_capacity(message.len() + 1),
+                };
+                bytes.extend_from_slice(message.as_bytes());
+                if framing != Framing::ContentLength {
+                    bytes.push(b'\n');
+                }
+                writer.write_all(&bytes).await?;
+                writer.flush().await?;
+                Ok::<_, io::Error>(writer)
+            }
+        })
+    }
+
+    fn message_stream(
+        self,
+        incoming: impl AsyncRead + Send + 'static,
+        detected: Detected,
+        max_message_size: usize,
+    ) -> impl futures::Stream<Item = io::Result<String>> + Send + 'static {
+        let reader = Box::pin(BufReader::new(incoming));
+        futures::stream::unfold(
+            (reader, self, detected, false),
+            move |(mut reader, mut framing, detected, failed)| async move {
+                if failed {
+                    return None;
+                }
+
+                if framing == Framing::Auto {
+                    match detect(&mut reader).await {
+                        Ok(Some(found)) => {
+                            detected.set(found);
+                            framing = found;
+                        }
+                        Ok(None) => return None,
+                        Err(error) => return Some((Err(error), (reader, framing, detected, true))),
+                    }
+                }
+
+                let message = match framing {
+                    Framing::ContentLength => {
+                        read_content_length_message(&mut reader, max_message_size).await
+                    }
+                    _ => read_line_message(&mut reader, max_message_size).await,
+                };
+                match message {
+                    Ok(Some(message)) => Some((Ok(message), (reader, framing, detected, false))),
+                    Ok(None) => None,
+                    // The oversized message has been skipped; the next one can be read.
+                    Err(error) if OversizedMessage::from_io_error(&error).is_some() => {
+                        Some((Err(error), (reader, framing, detected, false)))
+                    }
+                    Err(error) => Some((Err(error), (reader, framing, detected, true))),
+                }
+            },
+        )
+    }
+}
+
+/// Work out the framing from the first non-whitespace byte: JSON messages start
+/// with `{` or `[`, headers with a letter. Returns `None` at end of stream.
+async fn detect(reader: &mut (impl AsyncBufReadExt + Unpin)) -> io::Result<Option<Framing>> {
+    loop {
+        let buf = reader.fill_buf().await?;
+        let Some(&first) = buf.first() else {
+            return Ok(None);
+        };
+        if first.is_ascii_whitespace() {
+            reader.consume_unpin(1);
+            continue;
+        }
+        return Ok(Some(if first.is_ascii_alphabetic() {
+            Framing::ContentLength
+        } else {
+            Framing::Newline
+        }));
+    }
+}
+
+/// Read one newline-delimited message, without its line ending.
+///
+/// A line longer than `max_message_size` is skimmed rather than buffered, and
+/// reported as an [`OversizedMessage`].
+async fn read_line_message(
+    reader: &mut (impl AsyncBufReadExt + Unpin),
+    max_message_size: usize,
+) -> io::Result<Option<String>> {
+    let mut line = Vec::new();
+    let mut skimmer: Option<Skimmer> = None;
+    let mut read_any = false;
+    loop {
+        let buf = reader.fill_buf().await?;
+        if buf.is_empty() {
+            break;
+        }
+        read_any = true;
+
+        let (content, consumed, complete) = match buf.iter().position(|&byte| byte == b'\n') {
+            Some(newline) => (&buf[..newline], newline + 1, true),
+            None => (buf, buf.len(), false),
+        };
+        match &mut skimmer {
+            Some(skimmer) => skimmer.feed(content),
+            None => {
+                line.extend_from_slice(content);
+                if line.len() > max_message_size {
+                    let mut skimming = Skimmer::new();
+                    skimming.feed(&std::mem::take(&mut line));
+                    skimmer = Some(skimming);
+                }
+            }
+        }
+        reader.consume_unpin(consumed);
+        if complete {
+            break;
+        }
+    }
+
+    if !read_any {
+        return Ok(None);
+    }
+    if let Some(skimmer) = skimmer {
+        return Err(skimmer.finish(max_message_size).into_io_error());
+    }
+    if line.ends_with(b"\r") {
+        line.pop();
+    }
+    String::from_utf8(line)
+        .map(Some)
+        .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+}
+
+/// Read one message preceded by a `Content-Length` header.
+///
+/// A body longer than `max_message_size` is skimmed rather than buffered, and
+/// reported as an [`OversizedMessage`]. Headers longer than [`MAX_HEADER_SIZE`]
+/// in total are an error.
+async fn read_content_length_message(
+    reader: &mut (impl AsyncBufReadExt + AsyncReadExt + Unpin),
+    max_message_size: usize,
+) -> io::Result<Option<String>> {
+    let mut content_length = None;
+    let mut seen_header = false;
+    let mut header_size = 0;
+    loop {
+        let headers_too_long = || {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("message headers exceed {MAX_HEADER_SIZE} bytes"),
+            )
+        };
+        let remaining = MAX_HEADER_SIZE - header_size;
+        if remaining == 0 {
+            return Err(headers_too_long());
+        }
+
+        let mut line = String::new();
+        let read = (&mut *reader)
+            .take(remaining as u64)
+            .read_line(&mut line)
+            .await?;
+        header_size += read;
+        if read == remaining && !line.ends_with('\n') {
+            return Err(headers_too_long());
+        }
+        if read == 0 {
+            if seen_header {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "stream ended in the middle of message headers",
+                ));
+            }
+            return Ok(None);
+        }
+
+        let line = line.trim_end_matches(['\r', '\n']);
+        if line.is_empty() {
+            if seen_header {
+                break;
+            }
+            // Tolerate blank lines between messages.
+            continue;
+        }
+        seen_header = true;
+
+        let Some((name, value)) = line.split_once(':') else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("malformed message header `{line}`"),
+            ));
+        };
+        if name.trim().eq_ignore_ascii_case(CONTENT_LENGTH) {
+            let length = value.trim().parse::<usize>().map_err(|_| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("invalid Content-Length `{}`", value.trim()),
+                )
+            })?;
+            content_length = Some(length);
+        }
+    }
+
+    let Some(length) = content_length else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "message headers have no Content-Length",
+        ));
+    };
+
+    if length > max_message_size {
+        let mut skimmer = Skimmer::new();
+        let mut remaining = length;
+        while remaining > 0 {
+            let buf = reader.fill_buf().await?;
+            if buf.is_empty() {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+            let chunk = buf.len().min(remaining);
+            skimmer.feed(&buf[..chunk]);
+            reader.consume_unpin(chunk);
+            remaining -= chunk;
+        }
+        return Err(skimmer.finish(max_message_size).into_io_error());
+    }
+
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).await?;
+    String::from_utf8(body)
+        .map(Some)
+        .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+}
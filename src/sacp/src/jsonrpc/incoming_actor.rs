@@ -19,6 +19,7 @@ use crate::jsonrpc::dynamic_handler::DynamicHandlerMessage;
 use crate::link::JrLink;
 
 use super::Handled;
+use super::message_size::{Disposition, OversizedMessage};
 
 /// Incoming protocol actor: The central dispatch loop for a connection.
 ///
@@ -35,6 +36,7 @@ pub(super) async fn incoming_protocol_actor<Link: JrLink>(
     dynamic_handler_rx: mpsc::UnboundedReceiver<DynamicHandlerMessage<Link>>,
     reply_rx: mpsc::UnboundedReceiver<ReplyMessage>,
     mut handler: impl JrMessageHandler<Link = Link>,
+    max_message_size: Option<usize>,
 ) -> Result<(), crate::Error> {
     let mut my_rx = transport_rx
         .map(IncomingProtocolMsg::Transport)
@@ -97,8 +99,9 @@ pub(super) async fn incoming_protocol_actor<Link: JrLink>(
             },
 
             IncomingProtocolMsg::Transport(message) => match message {
-                Ok(message) => match message {
-                    jsonrpcmsg::Message::Request(request) => {
+                Ok(message) => match limit_message_size(json_rpc_cx, message, max_message_size)? {
+                    None => {}
+                    Some(jsonrpcmsg::Message::Request(request)) => {
                         tracing::trace!(method = %request.method, id = ?request.id, "Handling request");
                         dispatch_request(
                             json_rpc_cx,
@@ -110,7 +113,7 @@ pub(super) async fn incoming_protocol_actor<Link: JrLink>(
                         )
                         .await?
                     }
-                    jsonrpcmsg::Message::Response(response) => {
+                    Some(jsonrpcmsg::Message::Response(response)) => {
                         tracing::trace!(id = ?response.id, has_result = response.result.is_some(), has_error = response.error.is_some(), "Handling response");
                         if let Some(id) = response.id {
                             let result = if let Some(value) = response.result {
@@ -170,6 +173,37 @@ pub(super) async fn incoming_protocol_actor<Link: JrLink>(
     Ok(())
 }
 
+/// Apply the connection's maximum message size to an incoming message.
+///
+/// Returns the message to handle, which for an oversized response is an error
+/// response in its place. Oversized requests are answered with an error here
+/// and, like oversized notifications, yield `None`.
+fn limit_message_size<Link: JrLink>(
+    json_rpc_cx: &JrConnectionCx<Link>,
+    message: jsonrpcmsg::Message,
+    max_message_size: Option<usize>,
+) -> Result<Option<jsonrpcmsg::Message>, crate::Error> {
+    let Some(oversized) = max_message_size.and_then(|max| OversizedMessage::check(&message, max))
+    else {
+        return Ok(Some(message));
+    };
+
+    let error = oversized.error();
+    match oversized.disposition() {
+        Disposition::Reply(_) => {
+            let jsonrpcmsg::Message::Request(request) = message else {
+                unreachable!("only requests are replied to")
+            };
+            let id = request.id.expect("requests that are replied to have an id");
+            JrRequestCx::new(json_rpc_cx.message_tx.clone(), request.method, id)
+                .respond_with_error(error)?;
+            Ok(None)
+        }
+        Disposition::Deliver(response) => Ok(Some(response)),
+        Disposition::Drop => Ok(None),
+    }
+}
+
 #[derive(Debug)]
 enum IncomingProtocolMsg<Link: JrLink> {
     Transport(Result<jsonrpcmsg::Message, crate::Error>),
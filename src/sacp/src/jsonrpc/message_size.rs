@@ -0,0 +1,371 @@
+//! Limits on the size of incoming messages.
+//!
+//! A peer that sends an arbitrarily large message should not be able to exhaust
+//! our memory, nor to take the whole connection down. Transports stop buffering a
+//! message once it goes over the limit and skim the rest of it with a [`Skimmer`],
+//! which only remembers the top-level `id` and `method`. That is enough to decide
+//! what to do with it:
+//!
+//! - an oversized request gets an error response, so the peer is not left waiting;
+//! - an oversized response is replaced by an error response, so that whoever is
+//!   waiting for it gets an error;
+//! - an oversized notification is logged and dropped.
+
+use std::io;
+
+/// Identifiers and values longer than this are not worth remembering.
+const MAX_CAPTURE: usize = 1024;
+
+/// A message that was larger than the maximum message size.
+#[derive(Debug, Clone)]
+pub(crate) struct OversizedMessage {
+    /// The size of the message in bytes, if it is known.
+    pub(crate) size: Option<usize>,
+
+    /// The maximum message size it went over.
+    pub(crate) max_message_size: usize,
+
+    /// The `id` of the message, if it had one we could read.
+    pub(crate) id: Option<jsonrpcmsg::Id>,
+
+    /// The `method` of the message, if it had one we could read.
+    pub(crate) method: Option<String>,
+
+    /// Whether the message had a `result` or an `error`.
+    pub(crate) is_response: bool,
+}
+
+/// What to do with an [`OversizedMessage`].
+#[derive(Debug)]
+pub(crate) enum Disposition {
+    /// Send this error response back to the peer.
+    Reply(jsonrpcmsg::Message),
+
+    /// Handle this error response in place of the oversized response.
+    Deliver(jsonrpcmsg::Message),
+
+    /// Nothing to do.
+    Drop,
+}
+
+impl OversizedMessage {
+    /// Check whether `message` serializes to more than `max_message_size` bytes.
+    ///
+    /// Serialization stops as soon as the limit is passed.
+    pub(crate) fn check(message: &jsonrpcmsg::Message, max_message_size: usize) -> Option<Self> {
+        struct Counter {
+            count: usize,
+            max: usize,
+        }
+
+        impl io::Write for Counter {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.count += buf.len();
+                if self.count > self.max {
+                    return Err(io::Error::other("message size limit exceeded"));
+                }
+                Ok(buf.len())
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let mut counter = Counter {
+            count: 0,
+            max: max_message_size,
+        };
+        if serde_json::to_writer(&mut counter, message).is_ok() || counter.count <= max_message_size
+        {
+            return None;
+        }
+
+        let (id, method, is_response) = match message {
+            jsonrpcmsg::Message::Request(request) => {
+                (request.id.clone(), Some(request.method.clone()), false)
+            }
+            jsonrpcmsg::Message::Response(response) => (response.id.clone(), None, true),
+        };
+        Some(Self {
+            size: None,
+            max_message_size,
+            id,
+            method,
+            is_response,
+        })
+    }
+
+    /// Find the oversized message an I/O error from [`Framing`](crate::Framing) is about.
+    pub(crate) fn from_io_error(error: &io::Error) -> Option<&Self> {
+        error.get_ref()?.downcast_ref()
+    }
+
+    /// Wrap this in an I/O error, for transports that report errors that way.
+    pub(crate) fn into_io_error(self) -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidData, self)
+    }
+
+    /// The error reported to the peer, or to whoever waits for an oversized response.
+    pub(crate) fn error(&self) -> crate::Error {
+        crate::Error::invalid_request().data(serde_json::json!({
+            "reason": "message too large",
+            "message_size": self.size,
+            "max_message_size": self.max_message_size,
+        }))
+    }
+
+    /// Decide what to do with this message, logging it.
+    pub(crate) fn disposition(self) -> Disposition {
+        let error = self.error();
+        let error = jsonrpcmsg::Error {
+            code: error.code.into(),
+            message: error.message,
+            data: error.data,
+        };
+
+        match (self.id, self.method) {
+            (Some(id), Some(method)) => {
+                tracing::warn!(
+                    ?id,
+                    method,
+                    size = self.size,
+                    max_message_size = self.max_message_size,
+                    "Rejecting oversized request"
+                );
+                Disposition::Reply(jsonrpcmsg::Message::Response(jsonrpcmsg::Response::error(
+                    error,
+                    Some(id),
+                )))
+            }
+            (Some(id), None) if self.is_response => {
+                tracing::warn!(
+                    ?id,
+                    size = self.size,
+                    max_message_size = self.max_message_size,
+                    "Dropping oversized response"
+                );
+                Disposition::Deliver(jsonrpcmsg::Message::Response(jsonrpcmsg::Response::error(
+                    error,
+                    Some(id),
+                )))
+            }
+            (id, method) => {
+                tracing::warn!(
+                    ?id,
+                    method,
+                    size = self.size,
+                    max_message_size = self.max_message_size,
+                    "Dropping oversized message"
+                );
+                Disposition::Drop
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for OversizedMessage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.size {
+            Some(size) => write!(f, "message of {size} bytes")?,
+            None => write!(f, "message")?,
+        }
+        write!(
+            f,
+            " exceeds the maximum message size of {} bytes",
+            self.max_message_size
+        )
+    }
+}
+
+impl std::error::Error for OversizedMessage {}
+
+/// Scans a JSON message without keeping it, remembering only the top-level `id`
+/// and `method` and whether it looks like a response.
+///
+/// The scan is forgiving: malformed JSON just means nothing useful is found.
+#[derive(Debug, Default)]
+pub(crate) struct Skimmer {
+    size: usize,
+    depth: usize,
+    in_string: bool,
+    escaped: bool,
+
+    /// The next string at the top level of the object is a key.
+    expecting_key: bool,
+    reading_key: bool,
+    key: Vec<u8>,
+
+    /// The top-level value being read, if its key is one we care about.
+    value: Option<Vec<u8>>,
+
+    id: Option<jsonrpcmsg::Id>,
+    method: Option<String>,
+    is_response: bool,
+}
+
+impl Skimmer {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Scan the next part of the message.
+    pub(crate) fn feed(&mut self, bytes: &[u8]) {
+        self.size += bytes.len();
+        for &byte in bytes {
+            self.feed_byte(byte);
+        }
+    }
+
+    fn feed_byte(&mut self, byte: u8) {
+        if self.in_string {
+            let closing = if self.escaped {
+                self.escaped = false;
+                false
+            } else if byte == b'\\' {
+                self.escaped = true;
+                false
+            } else {
+                byte == b'"'
+            };
+
+            if closing {
+                self.in_string = false;
+            }
+            if self.reading_key {
+                if closing {
+                    self.reading_key = false;
+                } else if self.key.len() < MAX_CAPTURE {
+                    self.key.push(byte);
+                }
+            } else {
+                self.capture(byte);
+            }
+            return;
+        }
+
+        match byte {
+            b'"' if self.depth == 1 && self.expecting_key => {
+                self.expecting_key = false;
+                self.reading_key = true;
+                self.in_string = true;
+                self.key.clear();
+            }
+            b'"' => {
+                self.in_string = true;
+                self.capture(byte);
+            }
+            b'{' | b'[' => {
+                self.depth += 1;
+                if self.depth == 1 {
+                    self.expecting_key = byte == b'{';
+                } else {
+                    self.capture(byte);
+                }
+            }
+            b'}' | b']' => {
+                if self.depth == 1 {
+                    self.end_value();
+                } else {
+                    self.capture(byte);
+                }
+                self.depth = self.depth.saturating_sub(1);
+            }
+            b',' if self.depth == 1 => {
+                self.end_value();
+                self.expecting_key = true;
+            }
+            b':' if self.depth == 1 => self.start_value(),
+            _ => self.capture(byte),
+        }
+    }
+
+    fn capture(&mut self, byte: u8) {
+        if let Some(value) = &mut self.value {
+            if value.len() < MAX_CAPTURE {
+                value.push(byte);
+            } else {
+                self.value = None;
+            }
+        }
+    }
+
+    fn start_value(&mut self) {
+        self.value = None;
+        match self.key.as_slice() {
+            b"id" | b"method" => self.value = Some(Vec::new()),
+            b"result" | b"error" => self.is_response = true,
+            _ => {}
+        }
+    }
+
+    fn end_value(&mut self) {
+        let Some(value) = self.value.take() else {
+            return;
+        };
+        match self.key.as_slice() {
+            b"id" => self.id = serde_json::from_slice(&value).ok(),
+            b"method" => self.method = serde_json::from_slice(&value).ok(),
+            _ => {}
+        }
+    }
+
+    /// Finish scanning a message that went over `max_message_size`.
+    pub(crate) fn finish(self, max_message_size: usize) -> OversizedMessage {
+        OversizedMessage {
+            size: Some(self.size),
+            max_message_size,
+            id: self.id,
+            method: self.method,
+            is_response: self.is_response,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn skim(message: &str) -> OversizedMessage {
+        let mut skimmer = Skimmer::new();
+        for chunk in message.as_bytes().chunks(3) {
+            skimmer.feed(chunk);
+        }
+        skimmer.finish(1)
+    }
+
+    #[test]
+    fn skims_request() {
+        let message = skim(
+            r#"{"params":{"id":7,"method":"nested","text":"a \"quoted\" }"},"method":"fs/write_text_file","jsonrpc":"2.0","id":"abc"}"#,
+        );
+        assert_eq!(message.id, Some(jsonrpcmsg::Id::String("abc".into())));
+        assert_eq!(message.method.as_deref(), Some("fs/write_text_file"));
+        assert!(!message.is_response);
+    }
+
+    #[test]
+    fn skims_response() {
+        let message = skim(r#"{ "jsonrpc" : "2.0", "id" : 12, "result" : {"content":"xxxx"} }"#);
+        assert_eq!(message.id, Some(jsonrpcmsg::Id::Number(12)));
+        assert_eq!(message.method, None);
+        assert!(message.is_response);
+    }
+
+    #[test]
+    fn checks_parsed_messages() {
+        let request = jsonrpcmsg::Message::Request(jsonrpcmsg::Request::new_v2(
+            "session/prompt".into(),
+            Some(jsonrpcmsg::Params::Object(
+                [("text".to_string(), serde_json::json!("x".repeat(100)))]
+                    .into_iter()
+                    .collect(),
+            )),
+            Some(jsonrpcmsg::Id::Number(1)),
+        ));
+        assert!(OversizedMessage::check(&request, 1000).is_none());
+        let oversized = OversizedMessage::check(&request, 50).unwrap();
+        assert_eq!(oversized.method.as_deref(), Some("session/prompt"));
+        assert!(matches!(oversized.disposition(), Disposition::Reply(_)));
+    }
+}
@@ -4,6 +4,8 @@ use std::pin::pin;
 use futures::StreamExt as _;
 use futures::channel::mpsc;
 
+use crate::jsonrpc::message_size::{Disposition, OversizedMessage, Skimmer};
+
 /// Transport outgoing actor for line streams: Serializes jsonrpcmsg::Message and yields lines.
 ///
 /// This is a line-based variant of `transport_outgoing_actor` that works with a Sink<String>
@@ -18,10 +20,11 @@ use futures::channel::mpsc;
 ///
 /// This is the transport layer - it has no knowledge of protocol semantics (IDs, correlation, etc.).
 pub(super) async fn transport_outgoing_lines_actor(
-    mut transport_rx: mpsc::UnboundedReceiver<Result<jsonrpcmsg::Message, crate::Error>>,
+    transport_rx: impl futures::Stream<Item = Result<jsonrpcmsg::Message, crate::Error>>,
     outgoing_lines: impl futures::Sink<String, Error = std::io::Error>,
 ) -> Result<(), crate::Error> {
     use futures::SinkExt;
+    let mut transport_rx = pin!(transport_rx);
     let mut outgoing_lines = pin!(outgoing_lines);
 
     while let Some(message_result) = transport_rx.next().await {
@@ -86,15 +89,44 @@ pub(super) async fn transport_outgoing_lines_actor(
 /// - Reads lines from the stream
 /// - Parses to jsonrpcmsg::Message
 /// - Handles parse errors
+/// - Skips lines longer than `max_message_size`, sending error responses to
+///   oversized requests through `reply_tx`
 ///
 /// This is the transport layer - it has no knowledge of protocol semantics.
 pub(super) async fn transport_incoming_lines_actor(
     incoming_lines: impl futures::Stream<Item = std::io::Result<String>>,
     transport_tx: mpsc::UnboundedSender<Result<jsonrpcmsg::Message, crate::Error>>,
+    reply_tx: mpsc::UnboundedSender<Result<jsonrpcmsg::Message, crate::Error>>,
+    max_message_size: Option<usize>,
 ) -> Result<(), crate::Error> {
     let mut incoming_lines = pin!(incoming_lines);
     while let Some(line_result) = incoming_lines.next().await {
-        let line = line_result.map_err(crate::Error::into_internal_error)?;
+        let oversized = match line_result {
+            Ok(line) => match max_message_size {
+                Some(max) if line.len() > max => {
+                    let mut skimmer = Skimmer::new();
+                    skimmer.feed(line.as_bytes());
+                    Err(skimmer.finish(max))
+                }
+                _ => Ok(line),
+            },
+            Err(error) => match OversizedMessage::from_io_error(&error) {
+                Some(oversized) => Err(oversized.clone()),
+                None => return Err(crate::Error::into_internal_error(error)),
+            },
+        };
+        let line = match oversized {
+            Ok(line) => line,
+            Err(oversized) => {
+                match oversized.disposition() {
+                    Disposition::Reply(reply) => reply_tx.unbounded_send(Ok(reply)),
+                    Disposition::Deliver(response) => transport_tx.unbounded_send(Ok(response)),
+                    Disposition::Drop => Ok(()),
+                }
+                .map_err(crate::Error::into_internal_error)?;
+                continue;
+            }
+        };
         tracing::trace!(message = %line, "Received JSON-RPC message");
 
         let message: Result<jsonrpcmsg::Message, _> = serde_json::from_str(&line);
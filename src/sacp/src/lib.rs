@@ -90,8 +90,13 @@ pub mod handler;
 mod jsonrpc;
 /// Link types for JSON-RPC connections
 pub mod link;
+/// Forwarding `tracing` events to the conductor
+#[cfg(feature = "log-forwarding")]
+pub mod log_forwarding;
 /// MCP declarations (minimal)
 pub mod mcp;
+/// MCP client support for consuming MCP servers over ACP
+pub mod mcp_client;
 /// MCP server support for providing MCP tools over ACP
 pub mod mcp_server;
 /// Peer types for JSON-RPC connections
@@ -115,9 +120,9 @@ pub mod jsonrpcmsg {
 }
 
 pub use jsonrpc::{
-    ByteStreams, Channel, Handled, IntoHandled, JrConnection, JrConnectionBuilder, JrConnectionCx,
-    JrMessage, JrMessageHandler, JrNotification, JrRequest, JrRequestCx, JrResponse,
-    JrResponsePayload, Lines, MessageCx, NullHandler, UntypedMessage,
+    ByteStreams, Channel, Framing, Handled, IntoHandled, JrConnection, JrConnectionBuilder,
+    JrConnectionCx, JrMessage, JrMessageHandler, JrNotification, JrRequest, JrRequestCx,
+    JrResponse, JrResponsePayload, Lines, MessageCx, NullHandler, UntypedMessage,
     responder::{ChainResponder, JrResponder, NullResponder},
 };
 
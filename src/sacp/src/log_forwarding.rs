@@ -0,0 +1,88 @@
+//! Forwarding `tracing` events to the conductor.
+//!
+//! A component run by `sacp-conductor` usually logs to its own file or to
+//! stderr, separately from the messages it exchanges. Forwarding its events as
+//! [`LogNotification`]s instead lets a conductor recording a trace interleave
+//! them with those messages, so the trace shows what the component was doing
+//! when it sent each one.
+//!
+//! [`forward_logs`] returns a [`tracing_subscriber::Layer`] to install in the
+//! component's subscriber and a [`LogForwarder`] to run alongside its
+//! connection:
+//!
+//! ```no_run
+//! use sacp::ProxyToConductor;
+//! use tracing_subscriber::layer::SubscriberExt;
+//! use tracing_subscriber::util::SubscriberInitExt;
+//!
+//! # async fn run(transport: impl sacp::Component<sacp::link::ConductorToProxy>) -> Result<(), sacp::Error> {
+//! let (layer, forwarder) = sacp::log_forwarding::forward_logs();
+//! tracing_subscriber::registry().with(layer).init();
+//!
+//! ProxyToConductor::builder()
+//!     .name("my-proxy")
+//!     .with_spawned(async move |cx| forwarder.run(cx).await)
+//!     .serve(transport)
+//!     .await
+//! # }
+//! ```
+//!
+//! Events from sacp itself are not forwarded: the messages they describe are
+//! already in the trace, and forwarding would log the forwarding.
+
+use futures::StreamExt;
+use futures::channel::mpsc;
+use tracing_subscriber::layer::Context;
+
+use crate::schema::LogNotification;
+use crate::{ClientPeer, HasPeer, JrConnectionCx, JrLink};
+
+/// Create a layer that captures `tracing` events and a forwarder that sends
+/// them to the conductor.
+///
+/// Events captured before the forwarder runs are queued until it does.
+pub fn forward_logs() -> (ForwardLogsLayer, LogForwarder) {
+    let (tx, rx) = mpsc::unbounded();
+    (ForwardLogsLayer { tx }, LogForwarder { rx })
+}
+
+/// A [`tracing_subscriber::Layer`] queueing events for a [`LogForwarder`].
+///
+/// Combine it with a filter (such as `tracing_subscriber::EnvFilter`) to choose
+/// which events are forwarded.
+pub struct ForwardLogsLayer {
+    tx: mpsc::UnboundedSender<LogNotification>,
+}
+
+impl<S: tracing::Subscriber> tracing_subscriber::Layer<S> for ForwardLogsLayer {
+    fn on_event(&self, event: &tracing::Event<'_>, _cx: Context<'_, S>) {
+        let target = event.metadata().target();
+        if target == "sacp" || target.starts_with("sacp::") {
+            return;
+        }
+        // Once the forwarder is gone there is nowhere to send events.
+        let _ = self.tx.unbounded_send(LogNotification::from_event(event));
+    }
+}
+
+/// Sends the events captured by a [`ForwardLogsLayer`] to the conductor.
+pub struct LogForwarder {
+    rx: mpsc::UnboundedReceiver<LogNotification>,
+}
+
+impl LogForwarder {
+    /// Send captured events over `cx` until the connection closes.
+    ///
+    /// For proxies, `cx` is the connection to the conductor; for an agent, it
+    /// is the connection to its client, which is the conductor when the agent
+    /// runs in a chain. Clients that are not a conductor ignore the events.
+    pub async fn run<Link>(mut self, cx: JrConnectionCx<Link>) -> Result<(), crate::Error>
+    where
+        Link: JrLink + HasPeer<ClientPeer>,
+    {
+        while let Some(log) = self.rx.next().await {
+            cx.send_notification_to(ClientPeer, log)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,256 @@
+use rmcp::{
+    RoleClient, ServiceError, ServiceExt,
+    model::{
+        CallToolRequestParam, CallToolResult, GetPromptRequestParam, GetPromptResult, JsonObject,
+        Prompt, ReadResourceRequestParam, ReadResourceResult, Resource, Tool,
+    },
+    service::{Peer, RunningService},
+};
+use tokio_util::compat::{TokioAsyncReadCompatExt, TokioAsyncWriteCompatExt};
+
+use crate::{
+    ByteStreams, ClientPeer, Component, HasPeer, JrConnectionCx, JrLink,
+    mcp::{McpClientToServer, McpServerToClient},
+    mcp_client::McpOverAcpServer,
+};
+
+/// A connection to an MCP server, offering typed access to its tools, resources, and prompts.
+///
+/// Create one with [`McpClient::connect`] for a server from a `session/new` request,
+/// or with [`McpClient::connect_component`] for any MCP server component.
+///
+/// The connection stays open until [`close`](Self::close) is called or the client is dropped.
+pub struct McpClient {
+    service: RunningService<RoleClient, ()>,
+}
+
+impl McpClient {
+    /// Connect to an MCP server given to us by the ACP client (e.g., in `session/new`).
+    ///
+    /// Servers with `acp:` URLs are reached through `acp_cx` using MCP-over-ACP;
+    /// other HTTP servers use the streamable HTTP transport and stdio servers are
+    /// spawned as child processes. Those two need the `mcp-client-http` and
+    /// `mcp-client-stdio` features respectively.
+    ///
+    /// This waits for responses from the ACP client, so it must not be called
+    /// from within a message handler; call it from a spawned task instead
+    /// (see [`JrConnectionCx::spawn`]).
+    pub async fn connect<Link>(
+        server: &crate::schema::McpServer,
+        acp_cx: &JrConnectionCx<Link>,
+    ) -> Result<Self, crate::Error>
+    where
+        Link: JrLink + HasPeer<ClientPeer>,
+    {
+        match server {
+            crate::schema::McpServer::Http(http) if http.url.starts_with("acp:") => {
+                Self::connect_component(McpOverAcpServer::new(&http.url, acp_cx.clone())).await
+            }
+
+            #[cfg(feature = "mcp-client-http")]
+            crate::schema::McpServer::Http(http) => {
+                use rmcp::transport::{
+                    StreamableHttpClientTransport,
+                    streamable_http_client::StreamableHttpClientTransportConfig,
+                };
+
+                let mut config = StreamableHttpClientTransportConfig::with_uri(http.url.as_str());
+                for header in &http.headers {
+                    if header.name.eq_ignore_ascii_case("authorization") {
+                        config = config.auth_header(
+                            header
+                                .value
+                                .strip_prefix("Bearer ")
+                                .unwrap_or(&header.value)
+                                .to_string(),
+                        );
+                    } else {
+                        return Err(crate::Error::invalid_params().data(format!(
+                            "unsupported header `{}` for MCP server `{}`",
+                            header.name, http.name
+                        )));
+                    }
+                }
+                Self::serve(StreamableHttpClientTransport::from_config(config)).await
+            }
+
+            #[cfg(not(feature = "mcp-client-http"))]
+            crate::schema::McpServer::Http(http) => {
+                Err(crate::Error::invalid_params().data(format!(
+                    "HTTP MCP servers require the `mcp-client-http` feature (server `{}`)",
+                    http.name
+                )))
+            }
+
+            #[cfg(feature = "mcp-client-stdio")]
+            crate::schema::McpServer::Stdio(stdio) => {
+                use rmcp::transport::{ConfigureCommandExt, TokioChildProcess};
+
+                let transport = TokioChildProcess::new(
+                    tokio::process::Command::new(&stdio.command).configure(|cmd| {
+                        cmd.args(&stdio.args);
+                        for env_var in &stdio.env {
+                            cmd.env(&env_var.name, &env_var.value);
+                        }
+                    }),
+                )
+                .map_err(crate::Error::into_internal_error)?;
+                Self::serve(transport).await
+            }
+
+            #[cfg(not(feature = "mcp-client-stdio"))]
+            crate::schema::McpServer::Stdio(stdio) => {
+                Err(crate::Error::invalid_params().data(format!(
+                    "stdio MCP servers require the `mcp-client-stdio` feature (server `{}`)",
+                    stdio.name
+                )))
+            }
+
+            crate::schema::McpServer::Sse(sse) => Err(crate::Error::invalid_params().data(
+                format!("SSE MCP servers are not supported (server `{}`)", sse.name),
+            )),
+
+            _ => Err(crate::Error::invalid_params().data("unknown MCP server type")),
+        }
+    }
+
+    /// Connect to an MCP server component, such as an
+    /// [`McpServer`](`crate::mcp_server::McpServer`) or an [`McpOverAcpServer`].
+    pub async fn connect_component(
+        server: impl Component<McpServerToClient>,
+    ) -> Result<Self, crate::Error> {
+        // Create tokio byte streams that rmcp expects
+        let (mcp_client_stream, mcp_server_stream) = tokio::io::duplex(8192);
+        let (mcp_server_read, mcp_server_write) = tokio::io::split(mcp_server_stream);
+
+        // Create ByteStreams component for the server side
+        let byte_streams =
+            ByteStreams::new(mcp_server_write.compat_write(), mcp_server_read.compat());
+
+        // Spawn task to connect byte_streams to the provided server
+        tokio::spawn(async move {
+            if let Err(err) = Component::<McpClientToServer>::serve(byte_streams, server).await {
+                tracing::warn!(?err, "MCP server connection failed");
+            }
+        });
+
+        Self::serve(tokio::io::split(mcp_client_stream)).await
+    }
+
+    /// Run the rmcp client over the given transport, performing the MCP handshake.
+    async fn serve<T, E, A>(transport: T) -> Result<Self, crate::Error>
+    where
+        T: rmcp::transport::IntoTransport<RoleClient, E, A>,
+        E: std::error::Error + Send + Sync + 'static,
+    {
+        let service = ().serve(transport).await.map_err(crate::Error::into_internal_error)?;
+        Ok(Self { service })
+    }
+
+    /// The underlying rmcp peer, for MCP requests not covered by this API.
+    pub fn peer(&self) -> &Peer<RoleClient> {
+        self.service.peer()
+    }
+
+    /// List all tools offered by the server.
+    pub async fn list_tools(&self) -> Result<Vec<Tool>, crate::Error> {
+        self.service
+            .list_all_tools()
+            .await
+            .map_err(from_service_error)
+    }
+
+    /// Call the tool `name` with the given arguments, which must be a JSON object or null.
+    ///
+    /// A tool that fails in a way the model should see returns `Ok` with
+    /// [`CallToolResult::is_error`] set; `Err` is reserved for protocol errors.
+    pub async fn call_tool(
+        &self,
+        name: impl ToString,
+        arguments: serde_json::Value,
+    ) -> Result<CallToolResult, crate::Error> {
+        let arguments = json_object(arguments)?;
+        self.service
+            .call_tool(CallToolRequestParam {
+                name: name.to_string().into(),
+                arguments,
+            })
+            .await
+            .map_err(from_service_error)
+    }
+
+    /// List all resources offered by the server.
+    pub async fn list_resources(&self) -> Result<Vec<Resource>, crate::Error> {
+        self.service
+            .list_all_resources()
+            .await
+            .map_err(from_service_error)
+    }
+
+    /// Read the resource with the given URI.
+    pub async fn read_resource(
+        &self,
+        uri: impl ToString,
+    ) -> Result<ReadResourceResult, crate::Error> {
+        self.service
+            .read_resource(ReadResourceRequestParam {
+                uri: uri.to_string(),
+            })
+            .await
+            .map_err(from_service_error)
+    }
+
+    /// List all prompts offered by the server.
+    pub async fn list_prompts(&self) -> Result<Vec<Prompt>, crate::Error> {
+        self.service
+            .list_all_prompts()
+            .await
+            .map_err(from_service_error)
+    }
+
+    /// Get the prompt `name` with the given arguments, which must be a JSON object or null.
+    pub async fn get_prompt(
+        &self,
+        name: impl ToString,
+        arguments: serde_json::Value,
+    ) -> Result<GetPromptResult, crate::Error> {
+        let arguments = json_object(arguments)?;
+        self.service
+            .get_prompt(GetPromptRequestParam {
+                name: name.to_string(),
+                arguments,
+            })
+            .await
+            .map_err(from_service_error)
+    }
+
+    /// Close the connection to the server.
+    pub async fn close(self) -> Result<(), crate::Error> {
+        self.service
+            .cancel()
+            .await
+            .map(|_quit_reason| ())
+            .map_err(crate::Error::into_internal_error)
+    }
+}
+
+/// Convert MCP arguments into the optional JSON object that MCP expects.
+fn json_object(value: serde_json::Value) -> Result<Option<JsonObject>, crate::Error> {
+    match value {
+        serde_json::Value::Null => Ok(None),
+        serde_json::Value::Object(object) => Ok(Some(object)),
+        value => Err(crate::Error::invalid_params().data(format!(
+            "expected MCP arguments to be an object, got `{value}`"
+        ))),
+    }
+}
+
+/// Convert an rmcp [`ServiceError`] into a [`crate::Error`], preserving MCP error responses.
+pub(crate) fn from_service_error(error: ServiceError) -> crate::Error {
+    match error {
+        ServiceError::McpError(error) => {
+            crate::Error::new(error.code.0, error.message).data(error.data)
+        }
+        error => crate::Error::into_internal_error(error),
+    }
+}
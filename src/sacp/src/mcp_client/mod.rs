@@ -0,0 +1,47 @@
+//! MCP client support for consuming MCP servers over ACP.
+//!
+//! This module is the counterpart to [`mcp_server`](`crate::mcp_server`): it lets an
+//! agent (or proxy) connect to the MCP servers that its client passed in
+//! `session/new` and use their tools, resources, and prompts.
+//!
+//! ## Quick Start
+//!
+//! ```rust,ignore
+//! use sacp::mcp_client::McpClient;
+//!
+//! // Inside a spawned task of an agent, for a server from `NewSessionRequest::mcp_servers`
+//! let client = McpClient::connect(&mcp_server, &cx).await?;
+//!
+//! for tool in client.list_tools().await? {
+//!     println!("{}", tool.name);
+//! }
+//!
+//! let result = client
+//!     .call_tool("echo", serde_json::json!({ "message": "hi" }))
+//!     .await?;
+//!
+//! client.close().await?;
+//! ```
+//!
+//! ## Transports
+//!
+//! [`McpClient::connect`] picks the transport based on the server entry:
+//!
+//! - HTTP servers whose URL starts with `acp:` are reached over the ACP connection
+//!   itself, using `_mcp/connect` and `_mcp/message` (see [`McpOverAcpServer`]).
+//! - Other HTTP servers are reached using the streamable HTTP transport
+//!   (requires the `mcp-client-http` feature).
+//! - Stdio servers are spawned as child processes (requires the `mcp-client-stdio` feature).
+//!
+//! Any [`Component<McpServerToClient>`](`crate::Component`) can also be used directly
+//! with [`McpClient::connect_component`].
+
+mod client;
+mod over_acp;
+
+pub use client::McpClient;
+pub(crate) use client::from_service_error;
+pub use over_acp::McpOverAcpServer;
+
+// Re-export the MCP types returned by the client.
+pub use rmcp::model::{GetPromptResult, Prompt, ReadResourceResult, Resource, Tool};
@@ -0,0 +1,185 @@
+use futures::channel::mpsc;
+use futures::{SinkExt, StreamExt};
+
+use crate::mcp::{McpClientPeer, McpClientToServer, McpServerToClient};
+use crate::schema::{
+    McpConnectRequest, McpConnectResponse, McpDisconnectNotification, McpOverAcpMessage,
+};
+use crate::util::MatchMessageFrom;
+use crate::{
+    ClientPeer, Component, Handled, HasPeer, JrConnectionCx, JrLink, JrMessageHandler, MessageCx,
+    UntypedMessage,
+};
+
+/// An MCP server reached over an ACP connection using an `acp:` URL.
+///
+/// When served, this sends a [`McpConnectRequest`] to the ACP client and then relays
+/// MCP messages in both directions as `_mcp/message` requests and notifications.
+/// When the MCP client disconnects, a [`McpDisconnectNotification`] is sent.
+///
+/// This is the client half of the protocol implemented by
+/// [`McpServer`](`crate::mcp_server::McpServer`); most users will go through
+/// [`McpClient::connect`](`super::McpClient::connect`) rather than use it directly.
+pub struct McpOverAcpServer<Link> {
+    acp_url: String,
+    acp_cx: JrConnectionCx<Link>,
+}
+
+impl<Link: JrLink> McpOverAcpServer<Link>
+where
+    Link: HasPeer<ClientPeer>,
+{
+    /// Create a component for the MCP server with the given `acp:` URL,
+    /// reached through the ACP connection `acp_cx`.
+    pub fn new(acp_url: impl ToString, acp_cx: JrConnectionCx<Link>) -> Self {
+        Self {
+            acp_url: acp_url.to_string(),
+            acp_cx,
+        }
+    }
+}
+
+impl<Link: JrLink> Component<McpServerToClient> for McpOverAcpServer<Link>
+where
+    Link: HasPeer<ClientPeer>,
+{
+    async fn serve(self, client: impl Component<McpClientToServer>) -> Result<(), crate::Error> {
+        let Self { acp_url, acp_cx } = self;
+
+        // Ask the ACP client to start a connection to the MCP server
+        let McpConnectResponse { connection_id, .. } = acp_cx
+            .send_request_to(
+                ClientPeer,
+                McpConnectRequest {
+                    acp_url,
+                    meta: None,
+                },
+            )
+            .block_task()
+            .await?;
+
+        // Intercept messages from the MCP server for this connection
+        let (mcp_client_tx, mut mcp_client_rx) = mpsc::channel(128);
+        let registration = acp_cx.add_dynamic_handler(McpOverAcpClientHandler {
+            link: Link::default(),
+            connection_id: connection_id.clone(),
+            mcp_client_tx,
+        })?;
+
+        let result = McpServerToClient::builder()
+            .on_receive_message(
+                {
+                    let connection_id = connection_id.clone();
+                    let acp_cx = acp_cx.clone();
+                    async move |message: MessageCx, _mcp_cx| {
+                        // Wrap the message in McpOverAcp{Request,Notification} and send to the ACP client
+                        let wrapped = message.map(
+                            |request, request_cx| {
+                                (
+                                    McpOverAcpMessage {
+                                        connection_id: connection_id.clone(),
+                                        message: request,
+                                        meta: None,
+                                    },
+                                    request_cx,
+                                )
+                            },
+                            |notification| McpOverAcpMessage {
+                                connection_id: connection_id.clone(),
+                                message: notification,
+                                meta: None,
+                            },
+                        );
+                        acp_cx.send_proxied_message_to(ClientPeer, wrapped)
+                    }
+                },
+                crate::on_receive_message!(),
+            )
+            .with_spawned(move |mcp_cx| async move {
+                // Messages we pull off this channel were sent by the MCP server.
+                // Forward them to the MCP client.
+                while let Some(msg) = mcp_client_rx.next().await {
+                    mcp_cx.send_proxied_message_to(McpClientPeer, msg)?;
+                }
+                Ok(())
+            })
+            .serve(client)
+            .await;
+
+        drop(registration);
+        acp_cx.send_notification_to(
+            ClientPeer,
+            McpDisconnectNotification {
+                connection_id,
+                meta: None,
+            },
+        )?;
+
+        result
+    }
+}
+
+/// Dynamic handler that routes MCP-over-ACP messages for one connection
+/// back to the MCP client.
+struct McpOverAcpClientHandler<Link> {
+    #[expect(dead_code)]
+    link: Link,
+    connection_id: String,
+    mcp_client_tx: mpsc::Sender<MessageCx>,
+}
+
+impl<Link: JrLink> JrMessageHandler for McpOverAcpClientHandler<Link>
+where
+    Link: HasPeer<ClientPeer>,
+{
+    type Link = Link;
+
+    fn describe_chain(&self) -> impl std::fmt::Debug {
+        format!("McpOverAcpClient({})", self.connection_id)
+    }
+
+    async fn handle_message(
+        &mut self,
+        message: MessageCx,
+        connection_cx: JrConnectionCx<Link>,
+    ) -> Result<Handled<MessageCx>, crate::Error> {
+        MatchMessageFrom::new(message, &connection_cx)
+            // MCP server-to-client requests come from the Client direction
+            .if_request_from(
+                ClientPeer,
+                async |request: McpOverAcpMessage<UntypedMessage>, request_cx| {
+                    if request.connection_id != self.connection_id {
+                        return Ok(Handled::No {
+                            message: (request, request_cx),
+                            retry: false,
+                        });
+                    }
+                    self.mcp_client_tx
+                        .send(MessageCx::Request(request.message, request_cx))
+                        .await
+                        .map_err(crate::Error::into_internal_error)?;
+                    Ok(Handled::Yes)
+                },
+            )
+            .await
+            // MCP server-to-client notifications come from the Client direction
+            .if_notification_from(
+                ClientPeer,
+                async |notification: McpOverAcpMessage<UntypedMessage>| {
+                    if notification.connection_id != self.connection_id {
+                        return Ok(Handled::No {
+                            message: notification,
+                            retry: false,
+                        });
+                    }
+                    self.mcp_client_tx
+                        .send(MessageCx::Notification(notification.message))
+                        .await
+                        .map_err(crate::Error::into_internal_error)?;
+                    Ok(Handled::Yes)
+                },
+            )
+            .await
+            .done()
+    }
+}
@@ -112,10 +112,9 @@ where
         };
 
         // Get the MCP server component
-        let spawned_server = self.mcp_connect.connect(McpContext {
-            acp_url: request.acp_url.clone(),
-            connection_cx: outer_cx.clone(),
-        });
+        let spawned_server = self
+            .mcp_connect
+            .connect(McpContext::new(request.acp_url.clone(), outer_cx.clone()));
 
         // Spawn both sides of the connection
         let spawn_results = outer_cx
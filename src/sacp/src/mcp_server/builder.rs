@@ -1,6 +1,6 @@
 //! MCP server builder for creating MCP servers.
 
-use std::{collections::HashSet, pin::pin, sync::Arc};
+use std::{any::Any, collections::HashSet, pin::pin, sync::Arc};
 
 use futures::{
     SinkExt,
@@ -39,19 +39,21 @@ impl EnabledTools {
 use rmcp::{
     ErrorData, ServerHandler,
     handler::server::tool::{schema_for_output, schema_for_type},
-    model::{CallToolResult, ListToolsResult, Tool},
+    model::{CallToolResult, Content, JsonObject, ListToolsResult, Tool},
 };
 use schemars::JsonSchema;
 use serde::{Serialize, de::DeserializeOwned};
 use tokio_util::compat::{TokioAsyncReadCompatExt, TokioAsyncWriteCompatExt};
 
-use super::{McpContext, McpTool};
+use super::{Icon, McpContext, McpTool, Meta, ToolAnnotations};
 use crate::{
     ByteStreams, Component, DynComponent, JrLink,
     jsonrpc::responder::{ChainResponder, JrResponder, NullResponder},
     mcp_server::{
-        McpServer, McpServerConnect,
+        McpClientRequestHandler, McpServer, McpServerConnect,
+        client_requests::ErasedMcpClientRequestHandler,
         responder::{ToolCall, ToolFnMutResponder, ToolFnResponder},
+        validate::validate_arguments,
     },
 };
 
@@ -86,13 +88,12 @@ struct McpServerData<Link: JrLink> {
     tool_models: Vec<rmcp::model::Tool>,
     tools: FxHashMap<String, RegisteredTool<Link>>,
     enabled_tools: EnabledTools,
+    client_requests: Option<Arc<dyn ErasedMcpClientRequestHandler<Link>>>,
 }
 
 /// A registered tool with its metadata.
 struct RegisteredTool<Link: JrLink> {
     tool: Arc<dyn ErasedMcpTool<Link>>,
-    /// Whether this tool returns structured output (i.e., has an output_schema).
-    has_structured_output: bool,
 }
 
 impl<Link: JrLink> Default for McpServerData<Link> {
@@ -102,6 +103,7 @@ impl<Link: JrLink> Default for McpServerData<Link> {
             tool_models: Vec::new(),
             tools: FxHashMap::default(),
             enabled_tools: EnabledTools::default(),
+            client_requests: None,
         }
     }
 }
@@ -124,16 +126,25 @@ impl<Link: JrLink, Responder: JrResponder<Link>> McpServerBuilder<Link, Responde
         self
     }
 
+    /// Set the handler that answers sampling and elicitation requests made by tools
+    /// through [`McpContext::create_message`] and [`McpContext::create_elicitation`].
+    ///
+    /// Without a handler, these requests are sent to the MCP client.
+    pub fn client_request_handler(mut self, handler: impl McpClientRequestHandler<Link>) -> Self {
+        self.data.client_requests = Some(Arc::new(handler));
+        self
+    }
+
     /// Add a tool to the server.
     pub fn tool(mut self, tool: impl McpTool<Link> + 'static) -> Self {
         let tool_model = make_tool_model(&tool);
         let has_structured_output = tool_model.output_schema.is_some();
+        let input_schema = tool_model.input_schema.clone();
         self.data.tool_models.push(tool_model);
         self.data.tools.insert(
             tool.name(),
             RegisteredTool {
-                tool: make_erased_mcp_tool(tool),
-                has_structured_output,
+                tool: make_erased_mcp_tool(tool, input_schema, has_structured_output),
             },
         );
         self
@@ -189,6 +200,48 @@ impl<Link: JrLink, Responder: JrResponder<Link>> McpServerBuilder<Link, Responde
         Ok(self)
     }
 
+    /// Set the behavior hints (read-only, destructive, idempotent, open-world)
+    /// advertised for a tool.
+    ///
+    /// This is most useful for tools defined with [`tool_fn`](Self::tool_fn) or
+    /// [`tool_fn_mut`](Self::tool_fn_mut); tools defined as structs can implement
+    /// [`McpTool::annotations`] instead. Overrides any annotations the tool provided.
+    ///
+    /// Returns an error if the tool is not registered.
+    pub fn tool_annotations(
+        mut self,
+        name: &str,
+        annotations: ToolAnnotations,
+    ) -> Result<Self, crate::Error> {
+        self.tool_model_mut(name)?.annotations = Some(annotations);
+        Ok(self)
+    }
+
+    /// Set the icons advertised for a tool. Overrides any icons the tool provided.
+    ///
+    /// Returns an error if the tool is not registered.
+    pub fn tool_icons(mut self, name: &str, icons: Vec<Icon>) -> Result<Self, crate::Error> {
+        self.tool_model_mut(name)?.icons = Some(icons);
+        Ok(self)
+    }
+
+    /// Set the `_meta` advertised for a tool. Overrides any metadata the tool provided.
+    ///
+    /// Returns an error if the tool is not registered.
+    pub fn tool_meta(mut self, name: &str, meta: Meta) -> Result<Self, crate::Error> {
+        self.tool_model_mut(name)?.meta = Some(meta);
+        Ok(self)
+    }
+
+    /// Private fn: find the model advertised for the tool `name`.
+    fn tool_model_mut(&mut self, name: &str) -> Result<&mut Tool, crate::Error> {
+        self.data
+            .tool_models
+            .iter_mut()
+            .find(|t| t.name == name)
+            .ok_or_else(|| crate::Error::invalid_request().data(format!("unknown tool: {}", name)))
+    }
+
     /// Private fn: adds the tool but also adds a responder that will be
     /// run while the MCP server is active.
     fn tool_with_responder<R: JrResponder<Link>>(
@@ -414,28 +467,22 @@ impl<Link: JrLink> ServerHandler for McpServerConnection<Link> {
         // Convert input into JSON
         let serde_value = serde_json::to_value(request.arguments).expect("valid json");
 
+        // Give the tool a way to make requests of the client that invoked it
+        let mcp_cx = McpContext {
+            client_peer: Some(context.peer.clone()),
+            client_requests: self.data.client_requests.clone(),
+            ..self.mcp_cx.clone()
+        };
+
         // Execute the user's tool, unless cancellation occurs
-        let has_structured_output = registered.has_structured_output;
         match futures::future::select(
-            registered.tool.call_tool(serde_value, self.mcp_cx.clone()),
+            registered.tool.call_tool(serde_value, mcp_cx),
             pin!(context.ct.cancelled()),
         )
         .await
         {
             // If completed successfully
-            Either::Left((m, _)) => match m {
-                Ok(result) => {
-                    // Use structured output only if the tool declared an output_schema
-                    if has_structured_output {
-                        Ok(CallToolResult::structured(result))
-                    } else {
-                        Ok(CallToolResult::success(vec![rmcp::model::Content::text(
-                            result.to_string(),
-                        )]))
-                    }
-                }
-                Err(error) => Err(to_rmcp_error(error)),
-            },
+            Either::Left((m, _)) => m.map_err(to_rmcp_error),
 
             // If cancelled
             Either::Right(((), _)) => {
@@ -479,7 +526,7 @@ trait ErasedMcpTool<Link: JrLink>: Send + Sync {
         &self,
         input: serde_json::Value,
         context: McpContext<Link>,
-    ) -> BoxFuture<'_, Result<serde_json::Value, crate::Error>>;
+    ) -> BoxFuture<'_, Result<CallToolResult, crate::Error>>;
 }
 
 /// Create an `rmcp` tool model from our [`McpTool`] trait.
@@ -492,19 +539,61 @@ fn make_tool_model<Link: JrLink, M: McpTool<Link>>(tool: &M) -> Tool {
         // schema_for_output returns Err for non-object types (strings, integers, etc.)
         // since MCP structured output requires JSON objects. We use .ok() to set
         // output_schema to None for these tools, signaling unstructured output.
-        output_schema: schema_for_output::<M::Output>().ok(),
-        annotations: None,
-        icons: None,
-        meta: None,
+        // Tools that return a `CallToolResult` describe their own content, so they
+        // never declare an output schema.
+        output_schema: if is_call_tool_result::<M::Output>() {
+            None
+        } else {
+            schema_for_output::<M::Output>().ok()
+        },
+        annotations: tool.annotations(),
+        icons: tool.icons(),
+        meta: tool.meta(),
+    }
+}
+
+/// True if the tool output type `O` is a [`CallToolResult`] that the tool built itself.
+fn is_call_tool_result<O: 'static>() -> bool {
+    std::any::TypeId::of::<O>() == std::any::TypeId::of::<CallToolResult>()
+}
+
+/// Convert the output of a tool into the [`CallToolResult`] sent to the client.
+///
+/// Tools returning a [`CallToolResult`] have it passed through unchanged.
+/// Other outputs are serialized, either as structured content (if the tool
+/// declared an output schema) or as a single text block.
+fn into_call_tool_result<O: Serialize + 'static>(
+    output: O,
+    has_structured_output: bool,
+) -> Result<CallToolResult, crate::Error> {
+    let output: Box<dyn Any> = Box::new(output);
+    let output = match output.downcast::<CallToolResult>() {
+        Ok(result) => return Ok(*result),
+        Err(output) => output
+            .downcast::<O>()
+            .expect("output has type `O` if it is not a `CallToolResult`"),
+    };
+
+    let value = serde_json::to_value(*output).map_err(crate::util::internal_error)?;
+    if has_structured_output {
+        Ok(CallToolResult::structured(value))
+    } else {
+        Ok(CallToolResult::success(vec![Content::text(
+            value.to_string(),
+        )]))
     }
 }
 
 /// Create a [`ErasedMcpTool`] from a [`McpTool`], erasing the type details.
 fn make_erased_mcp_tool<'s, Link: JrLink, M: McpTool<Link> + 's>(
     tool: M,
+    input_schema: Arc<JsonObject>,
+    has_structured_output: bool,
 ) -> Arc<dyn ErasedMcpTool<Link> + 's> {
     struct ErasedMcpToolImpl<M> {
         tool: M,
+        input_schema: Arc<JsonObject>,
+        has_structured_output: bool,
     }
 
     impl<Link, M> ErasedMcpTool<Link> for ErasedMcpToolImpl<M>
@@ -516,16 +605,60 @@ fn make_erased_mcp_tool<'s, Link: JrLink, M: McpTool<Link> + 's>(
             &self,
             input: serde_json::Value,
             context: McpContext<Link>,
-        ) -> BoxFuture<'_, Result<serde_json::Value, crate::Error>> {
+        ) -> BoxFuture<'_, Result<CallToolResult, crate::Error>> {
             Box::pin(async move {
-                let input = serde_json::from_value(input).map_err(crate::util::internal_error)?;
-                serde_json::to_value(self.tool.call_tool(input, context).await?)
-                    .map_err(crate::util::internal_error)
+                // Omitted arguments are equivalent to an empty object
+                let input = match input {
+                    serde_json::Value::Null
+                        if self.input_schema.get("type")
+                            == Some(&serde_json::Value::from("object")) =>
+                    {
+                        serde_json::Value::Object(Default::default())
+                    }
+                    input => input,
+                };
+
+                // Report malformed arguments to the model as a tool error,
+                // so that it can correct its call.
+                let invalid_arguments = validate_arguments(&self.input_schema, &input);
+                if !invalid_arguments.is_empty() {
+                    let problems: Vec<String> = invalid_arguments
+                        .iter()
+                        .map(|invalid| format!("- {invalid}"))
+                        .collect();
+                    return Ok(invalid_arguments_result(
+                        &self.tool.name(),
+                        &problems.join("\n"),
+                    ));
+                }
+                let input = match serde_json::from_value(input) {
+                    Ok(input) => input,
+                    Err(error) => {
+                        return Ok(invalid_arguments_result(
+                            &self.tool.name(),
+                            &error.to_string(),
+                        ));
+                    }
+                };
+
+                let output = self.tool.call_tool(input, context).await?;
+                into_call_tool_result(output, self.has_structured_output)
             })
         }
     }
 
-    Arc::new(ErasedMcpToolImpl { tool })
+    Arc::new(ErasedMcpToolImpl {
+        tool,
+        input_schema,
+        has_structured_output,
+    })
+}
+
+/// The tool-level error returned when a tool is called with invalid arguments.
+fn invalid_arguments_result(tool_name: &str, problems: &str) -> CallToolResult {
+    CallToolResult::error(vec![Content::text(format!(
+        "Invalid arguments for tool `{tool_name}`:\n{problems}"
+    ))])
 }
 
 /// Convert a [`crate::Error`] into an [`rmcp::ErrorData`].
@@ -0,0 +1,174 @@
+//! Handlers for requests an MCP server makes of its client (sampling and elicitation).
+
+use futures::future::BoxFuture;
+use rmcp::model::{
+    CreateElicitationRequestParam, CreateElicitationResult, CreateMessageRequestParam,
+    CreateMessageResult, ElicitationAction,
+};
+
+use crate::{
+    ClientPeer, HasPeer, JrLink,
+    mcp_server::McpContext,
+    schema::{
+        PermissionOption, PermissionOptionKind, RequestPermissionOutcome, RequestPermissionRequest,
+        SessionId, ToolCallUpdate, ToolCallUpdateFields,
+    },
+};
+
+/// Decides how requests from an MCP server to its client are answered.
+///
+/// Tools make these requests through [`McpContext::create_message`] and
+/// [`McpContext::create_elicitation`]. Without a handler, they are sent to the
+/// MCP client, which is usually the agent. Install a handler with
+/// [`McpServerBuilder::client_request_handler`](`super::McpServerBuilder::client_request_handler`)
+/// to answer them some other way, for example by asking the user through the
+/// ACP client (see [`ElicitViaRequestPermission`]).
+///
+/// Each method defaults to forwarding the request to the MCP client. Handlers
+/// must not call [`McpContext::create_message`] or [`McpContext::create_elicitation`]
+/// themselves, as that would recurse; use the `send_*_to_client` methods instead.
+pub trait McpClientRequestHandler<Link: JrLink>: Send + Sync + 'static {
+    /// Answer a sampling request.
+    fn create_message(
+        &self,
+        params: CreateMessageRequestParam,
+        cx: McpContext<Link>,
+    ) -> impl Future<Output = Result<CreateMessageResult, crate::Error>> + Send {
+        async move { cx.send_create_message_to_client(params).await }
+    }
+
+    /// Answer an elicitation request.
+    fn create_elicitation(
+        &self,
+        params: CreateElicitationRequestParam,
+        cx: McpContext<Link>,
+    ) -> impl Future<Output = Result<CreateElicitationResult, crate::Error>> + Send {
+        async move { cx.send_create_elicitation_to_client(params).await }
+    }
+}
+
+/// Erased version of [`McpClientRequestHandler`] that is dyn-compatible.
+pub(super) trait ErasedMcpClientRequestHandler<Link>: Send + Sync {
+    fn create_message(
+        &self,
+        params: CreateMessageRequestParam,
+        cx: McpContext<Link>,
+    ) -> BoxFuture<'_, Result<CreateMessageResult, crate::Error>>;
+
+    fn create_elicitation(
+        &self,
+        params: CreateElicitationRequestParam,
+        cx: McpContext<Link>,
+    ) -> BoxFuture<'_, Result<CreateElicitationResult, crate::Error>>;
+}
+
+impl<Link: JrLink, H: McpClientRequestHandler<Link>> ErasedMcpClientRequestHandler<Link> for H {
+    fn create_message(
+        &self,
+        params: CreateMessageRequestParam,
+        cx: McpContext<Link>,
+    ) -> BoxFuture<'_, Result<CreateMessageResult, crate::Error>> {
+        Box::pin(McpClientRequestHandler::create_message(self, params, cx))
+    }
+
+    fn create_elicitation(
+        &self,
+        params: CreateElicitationRequestParam,
+        cx: McpContext<Link>,
+    ) -> BoxFuture<'_, Result<CreateElicitationResult, crate::Error>> {
+        Box::pin(McpClientRequestHandler::create_elicitation(
+            self, params, cx,
+        ))
+    }
+}
+
+/// Answers elicitations by asking the user through ACP `session/request_permission`.
+///
+/// The elicitation message becomes the title of the permission prompt, with
+/// "Accept" and "Decline" options. Because a permission prompt cannot collect
+/// values, only elicitations without required fields are supported; others fail
+/// with an error. Sampling requests are forwarded to the MCP client.
+#[derive(Clone, Debug)]
+pub struct ElicitViaRequestPermission {
+    session_id: SessionId,
+}
+
+impl ElicitViaRequestPermission {
+    /// Ask for permission within the given ACP session.
+    pub fn new(session_id: impl Into<SessionId>) -> Self {
+        Self {
+            session_id: session_id.into(),
+        }
+    }
+}
+
+const ACCEPT_OPTION_ID: &str = "accept";
+const DECLINE_OPTION_ID: &str = "decline";
+
+impl<Link: JrLink> McpClientRequestHandler<Link> for ElicitViaRequestPermission
+where
+    Link: HasPeer<ClientPeer>,
+{
+    async fn create_elicitation(
+        &self,
+        params: CreateElicitationRequestParam,
+        cx: McpContext<Link>,
+    ) -> Result<CreateElicitationResult, crate::Error> {
+        if params
+            .requested_schema
+            .required
+            .as_ref()
+            .is_some_and(|required| !required.is_empty())
+        {
+            return Err(crate::Error::invalid_params().data(
+                "elicitations with required fields cannot be answered by `session/request_permission`",
+            ));
+        }
+
+        let response = cx
+            .connection_cx()
+            .send_request_to(
+                ClientPeer,
+                RequestPermissionRequest::new(
+                    self.session_id.clone(),
+                    ToolCallUpdate::new(
+                        format!("elicitation:{}", uuid::Uuid::new_v4()),
+                        ToolCallUpdateFields::new().title(params.message),
+                    ),
+                    vec![
+                        PermissionOption::new(
+                            ACCEPT_OPTION_ID,
+                            "Accept",
+                            PermissionOptionKind::AllowOnce,
+                        ),
+                        PermissionOption::new(
+                            DECLINE_OPTION_ID,
+                            "Decline",
+                            PermissionOptionKind::RejectOnce,
+                        ),
+                    ],
+                ),
+            )
+            .block_task()
+            .await?;
+
+        Ok(match response.outcome {
+            RequestPermissionOutcome::Selected(selected)
+                if &*selected.option_id.0 == ACCEPT_OPTION_ID =>
+            {
+                CreateElicitationResult {
+                    action: ElicitationAction::Accept,
+                    content: Some(serde_json::json!({})),
+                }
+            }
+            RequestPermissionOutcome::Selected(_) => CreateElicitationResult {
+                action: ElicitationAction::Decline,
+                content: None,
+            },
+            _ => CreateElicitationResult {
+                action: ElicitationAction::Cancel,
+                content: None,
+            },
+        })
+    }
+}
@@ -1,13 +1,42 @@
-use crate::{JrConnectionCx, JrLink};
+use std::sync::Arc;
+
+use rmcp::{
+    RoleServer,
+    model::{
+        CreateElicitationRequestParam, CreateElicitationResult, CreateMessageRequestParam,
+        CreateMessageResult,
+    },
+    service::Peer,
+};
+
+use crate::{
+    JrConnectionCx, JrLink, mcp_client::from_service_error,
+    mcp_server::client_requests::ErasedMcpClientRequestHandler,
+};
 
 /// Context about the ACP and MCP connection available to an MCP server.
 #[derive(Clone)]
 pub struct McpContext<Link> {
     pub(super) acp_url: String,
     pub(super) connection_cx: JrConnectionCx<Link>,
+
+    /// The MCP client that invoked the current tool, if any.
+    pub(super) client_peer: Option<Peer<RoleServer>>,
+
+    /// Handler for requests the server makes of its client, if one was installed.
+    pub(super) client_requests: Option<Arc<dyn ErasedMcpClientRequestHandler<Link>>>,
 }
 
 impl<Link: JrLink> McpContext<Link> {
+    pub(super) fn new(acp_url: String, connection_cx: JrConnectionCx<Link>) -> Self {
+        Self {
+            acp_url,
+            connection_cx,
+            client_peer: None,
+            client_requests: None,
+        }
+    }
+
     /// The `acp:UUID` that was given.
     pub fn acp_url(&self) -> String {
         self.acp_url.clone()
@@ -18,4 +47,82 @@ impl<Link: JrLink> McpContext<Link> {
     pub fn connection_cx(&self) -> JrConnectionCx<Link> {
         self.connection_cx.clone()
     }
+
+    /// Ask for a completion from a model (MCP "sampling").
+    ///
+    /// If the server has a [`McpClientRequestHandler`](`super::McpClientRequestHandler`),
+    /// it decides how to answer; otherwise the request is sent to the MCP client
+    /// (see [`send_create_message_to_client`](Self::send_create_message_to_client)).
+    pub async fn create_message(
+        &self,
+        params: CreateMessageRequestParam,
+    ) -> Result<CreateMessageResult, crate::Error> {
+        match &self.client_requests {
+            Some(handler) => handler.create_message(params, self.clone()).await,
+            None => self.send_create_message_to_client(params).await,
+        }
+    }
+
+    /// Ask the user for input (MCP "elicitation").
+    ///
+    /// If the server has a [`McpClientRequestHandler`](`super::McpClientRequestHandler`),
+    /// it decides how to answer; otherwise the request is sent to the MCP client
+    /// (see [`send_create_elicitation_to_client`](Self::send_create_elicitation_to_client)).
+    pub async fn create_elicitation(
+        &self,
+        params: CreateElicitationRequestParam,
+    ) -> Result<CreateElicitationResult, crate::Error> {
+        match &self.client_requests {
+            Some(handler) => handler.create_elicitation(params, self.clone()).await,
+            None => self.send_create_elicitation_to_client(params).await,
+        }
+    }
+
+    /// Send a sampling request directly to the MCP client (typically the agent).
+    ///
+    /// Fails if the client did not advertise the `sampling` capability, or if
+    /// this context does not belong to a tool call.
+    pub async fn send_create_message_to_client(
+        &self,
+        params: CreateMessageRequestParam,
+    ) -> Result<CreateMessageResult, crate::Error> {
+        let peer = self.client_peer()?;
+        if peer
+            .peer_info()
+            .is_none_or(|info| info.capabilities.sampling.is_none())
+        {
+            return Err(
+                crate::Error::method_not_found().data("MCP client does not support sampling")
+            );
+        }
+        peer.create_message(params)
+            .await
+            .map_err(from_service_error)
+    }
+
+    /// Send an elicitation request directly to the MCP client (typically the agent).
+    ///
+    /// Fails if the client did not advertise the `elicitation` capability, or if
+    /// this context does not belong to a tool call.
+    pub async fn send_create_elicitation_to_client(
+        &self,
+        params: CreateElicitationRequestParam,
+    ) -> Result<CreateElicitationResult, crate::Error> {
+        let peer = self.client_peer()?;
+        if !peer.supports_elicitation() {
+            return Err(
+                crate::Error::method_not_found().data("MCP client does not support elicitation")
+            );
+        }
+        peer.create_elicitation(params)
+            .await
+            .map_err(from_service_error)
+    }
+
+    fn client_peer(&self) -> Result<&Peer<RoleServer>, crate::Error> {
+        self.client_peer.as_ref().ok_or_else(|| {
+            crate::Error::invalid_request()
+                .data("requests to the MCP client can only be made while handling a tool call")
+        })
+    }
 }
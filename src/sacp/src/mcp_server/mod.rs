@@ -21,6 +21,37 @@
 //!     .await?;
 //! ```
 //!
+//! ## Rich Tool Results
+//!
+//! Tools whose output type is [`CallToolResult`] control the full result sent
+//! to the client, including images, multiple content blocks, and error results
+//! that the model can see:
+//!
+//! ```rust,ignore
+//! use sacp::mcp_server::{CallToolResult, Content};
+//!
+//! McpServer::builder("screenshots")
+//!     .tool_fn(
+//!         "screenshot",
+//!         "Capture the screen",
+//!         async |_input: ScreenshotInput, _cx| match capture() {
+//!             Ok(png) => Ok(CallToolResult::success(vec![Content::image(png, "image/png")])),
+//!             Err(e) => Ok(CallToolResult::error(vec![Content::text(e.to_string())])),
+//!         },
+//!         sacp::tool_fn!(),
+//!     )
+//!     .build();
+//! ```
+//!
+//! ## Sampling and Elicitation
+//!
+//! While handling a tool call, a tool can ask for a model completion with
+//! [`McpContext::create_message`] or ask the user a question with
+//! [`McpContext::create_elicitation`]. By default these are sent to the MCP
+//! client (the agent); install a [`McpClientRequestHandler`] with
+//! [`McpServerBuilder::client_request_handler`] to answer them differently,
+//! such as through ACP with [`ElicitViaRequestPermission`].
+//!
 //! ## Custom MCP Server Implementations
 //!
 //! You can implement [`McpServerConnect`](`crate::mcp_server::McpServerConnect`) to create custom MCP servers:
@@ -47,14 +78,25 @@
 
 mod active_session;
 mod builder;
+mod client_requests;
 mod connect;
 mod context;
 mod responder;
 mod server;
 mod tool;
+mod validate;
 
 pub use builder::{EnabledTools, McpServerBuilder};
+pub use client_requests::{ElicitViaRequestPermission, McpClientRequestHandler};
 pub use connect::McpServerConnect;
 pub use context::McpContext;
 pub use server::McpServer;
 pub use tool::McpTool;
+
+// Re-export the MCP types that tools use to describe themselves and their results,
+// and to make requests of their client.
+pub use rmcp::model::{
+    CallToolResult, Content, CreateElicitationRequestParam, CreateElicitationResult,
+    CreateMessageRequestParam, CreateMessageResult, ElicitationAction, ElicitationSchema, Icon,
+    Meta, Role, SamplingMessage, ToolAnnotations,
+};
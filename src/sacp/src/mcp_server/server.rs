@@ -210,10 +210,8 @@ where
                 crate::on_receive_message!(),
             )
             .with_spawned(async move |server_to_client_cx| {
-                let spawned_server: DynComponent<McpServerToClient> = connect.connect(McpContext {
-                    acp_url,
-                    connection_cx: server_to_client_cx.clone(),
-                });
+                let spawned_server: DynComponent<McpServerToClient> =
+                    connect.connect(McpContext::new(acp_url, server_to_client_cx.clone()));
 
                 McpClientToServer::builder()
                     .on_receive_message(
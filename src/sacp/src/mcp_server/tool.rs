@@ -5,7 +5,7 @@ use serde::{Serialize, de::DeserializeOwned};
 
 use crate::JrLink;
 
-use super::McpContext;
+use super::{Icon, McpContext, Meta, ToolAnnotations};
 
 /// Trait for defining MCP tools.
 ///
@@ -57,9 +57,22 @@ use super::McpContext;
 /// ```
 pub trait McpTool<Link: JrLink>: Send + Sync {
     /// The type of input the tool accepts.
+    ///
+    /// Arguments are checked against the JSON schema of this type before the tool
+    /// is called. Invalid arguments never reach the tool; instead, the model receives
+    /// a tool error describing which fields are wrong, so that it can retry.
     type Input: JsonSchema + DeserializeOwned + Send + 'static;
 
     /// The type of output the tool produces.
+    ///
+    /// Most tools return an ordinary serializable type, which is sent to the client
+    /// as structured content (for JSON objects) or as a single text block.
+    ///
+    /// To take full control of the result -- returning images, embedded resources,
+    /// multiple content blocks, or an error result that the model can see -- use
+    /// [`CallToolResult`](`super::CallToolResult`) as the output type. It is sent
+    /// to the client unchanged. Returning `Err` from [`call_tool`](Self::call_tool)
+    /// is reserved for protocol errors.
     type Output: JsonSchema + Serialize + Send + 'static;
 
     /// The name of the tool
@@ -73,6 +86,24 @@ pub trait McpTool<Link: JrLink>: Send + Sync {
         None
     }
 
+    /// Hints describing the tool's behavior (read-only, destructive, idempotent, open-world).
+    ///
+    /// Clients may use these to decide which calls need a permission prompt.
+    /// They are hints only and are not enforced.
+    fn annotations(&self) -> Option<ToolAnnotations> {
+        None
+    }
+
+    /// Icons that clients can display for the tool
+    fn icons(&self) -> Option<Vec<Icon>> {
+        None
+    }
+
+    /// Additional metadata sent as the tool's `_meta` field
+    fn meta(&self) -> Option<Meta> {
+        None
+    }
+
     /// Define the tool's behavior. You can implement this with an `async fn`.
     fn call_tool(
         &self,
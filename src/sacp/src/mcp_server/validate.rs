@@ -0,0 +1,457 @@
+//! Validation of tool arguments against the tool's input schema.
+//!
+//! Models frequently call tools with malformed arguments. Rather than failing
+//! with an opaque deserialization error, we check the arguments against the
+//! JSON schema we advertised for the tool and report each problem by field,
+//! so that the model can correct its call. Only the subset of JSON schema that
+//! `schemars` generates for tool inputs is understood; unknown keywords are ignored.
+
+use std::fmt;
+
+use rmcp::model::JsonObject;
+use serde_json::Value;
+
+/// A single problem found with the arguments to a tool.
+#[derive(Debug)]
+pub(super) struct InvalidArgument {
+    /// Path to the offending value, like `items[2].name`; empty for the arguments as a whole.
+    path: String,
+
+    /// What is wrong with the value.
+    problem: String,
+}
+
+impl fmt::Display for InvalidArgument {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.path.is_empty() {
+            write!(f, "arguments: {}", self.problem)
+        } else {
+            write!(f, "`{}`: {}", self.path, self.problem)
+        }
+    }
+}
+
+/// Check `value` against the JSON schema `schema`, returning every problem found.
+pub(super) fn validate_arguments(schema: &JsonObject, value: &Value) -> Vec<InvalidArgument> {
+    let mut validator = Validator {
+        root: schema,
+        errors: vec![],
+    };
+    validator.check_object_schema(schema, value, "");
+    validator.errors
+}
+
+struct Validator<'s> {
+    root: &'s JsonObject,
+    errors: Vec<InvalidArgument>,
+}
+
+impl<'s> Validator<'s> {
+    fn error(&mut self, path: &str, problem: impl ToString) {
+        self.errors.push(InvalidArgument {
+            path: path.to_string(),
+            problem: problem.to_string(),
+        });
+    }
+
+    fn check(&mut self, schema: &'s Value, value: &Value, path: &str) {
+        match schema {
+            Value::Bool(true) => {}
+            Value::Bool(false) => self.error(path, "no value is allowed here"),
+            Value::Object(schema) => self.check_object_schema(schema, value, path),
+            _ => {}
+        }
+    }
+
+    fn check_object_schema(&mut self, schema: &'s JsonObject, value: &Value, path: &str) {
+        if let Some(Value::String(reference)) = schema.get("$ref")
+            && let Some(target) = self.resolve(reference)
+        {
+            self.check(target, value, path);
+        }
+
+        if let Some(Value::Array(alternatives)) = schema.get("anyOf") {
+            self.check_alternatives(alternatives, value, path, false);
+        }
+
+        if let Some(Value::Array(alternatives)) = schema.get("oneOf") {
+            self.check_alternatives(alternatives, value, path, true);
+        }
+
+        if let Some(Value::Array(all)) = schema.get("allOf") {
+            for subschema in all {
+                self.check(subschema, value, path);
+            }
+        }
+
+        if let Some(Value::Array(allowed)) = schema.get("enum")
+            && !allowed.contains(value)
+        {
+            let allowed: Vec<String> = allowed.iter().map(|v| v.to_string()).collect();
+            self.error(
+                path,
+                format!("expected one of {}, got {value}", allowed.join(", ")),
+            );
+            return;
+        }
+
+        if let Some(expected) = schema.get("const")
+            && expected != value
+        {
+            self.error(path, format!("expected {expected}, got {value}"));
+            return;
+        }
+
+        if let Some(types) = schema.get("type") {
+            let types: Vec<&str> = match types {
+                Value::String(ty) => vec![ty.as_str()],
+                Value::Array(tys) => tys.iter().filter_map(Value::as_str).collect(),
+                _ => vec![],
+            };
+            if !types.is_empty() && !types.iter().any(|ty| has_type(value, ty)) {
+                self.error(
+                    path,
+                    format!("expected {}, got {}", types.join(" or "), kind(value)),
+                );
+                return;
+            }
+        }
+
+        match value {
+            Value::Object(object) => self.check_properties(schema, object, path),
+            Value::Array(items) => {
+                if let Some(item_schema) = schema.get("items") {
+                    for (index, item) in items.iter().enumerate() {
+                        self.check(item_schema, item, &format!("{path}[{index}]"));
+                    }
+                }
+            }
+            Value::Number(number) => {
+                if let Some(number) = number.as_f64() {
+                    if let Some(minimum) = schema.get("minimum").and_then(Value::as_f64)
+                        && number < minimum
+                    {
+                        self.error(path, format!("expected a value >= {minimum}, got {number}"));
+                    }
+                    if let Some(maximum) = schema.get("maximum").and_then(Value::as_f64)
+                        && number > maximum
+                    {
+                        self.error(path, format!("expected a value <= {maximum}, got {number}"));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn check_properties(
+        &mut self,
+        schema: &'s JsonObject,
+        object: &serde_json::Map<String, Value>,
+        path: &str,
+    ) {
+        if let Some(Value::Array(required)) = schema.get("required") {
+            for field in required.iter().filter_map(Value::as_str) {
+                if !object.contains_key(field) {
+                    self.error(&field_path(path, field), "missing required field");
+                }
+            }
+        }
+
+        let properties = schema.get("properties").and_then(Value::as_object);
+        for (field, field_value) in object {
+            match properties.and_then(|properties| properties.get(field)) {
+                Some(field_schema) => {
+                    self.check(field_schema, field_value, &field_path(path, field))
+                }
+                None => match schema.get("additionalProperties") {
+                    Some(Value::Bool(false)) => {
+                        self.error(&field_path(path, field), "unknown field")
+                    }
+                    Some(additional) => {
+                        self.check(additional, field_value, &field_path(path, field))
+                    }
+                    None => {}
+                },
+            }
+        }
+    }
+
+    /// Check a value that must match one of several schemas (`anyOf`/`oneOf`).
+    ///
+    /// If no alternative matches, report the problems with the closest one,
+    /// which is usually the most helpful (e.g., for `Option<T>`, the `T`).
+    /// With `exactly_one` (for `oneOf`), matching more than one is also a problem.
+    fn check_alternatives(
+        &mut self,
+        alternatives: &'s [Value],
+        value: &Value,
+        path: &str,
+        exactly_one: bool,
+    ) {
+        let mut matching = vec![];
+        let mut closest: Option<Vec<InvalidArgument>> = None;
+        for (index, alternative) in alternatives.iter().enumerate() {
+            let mut validator = Validator {
+                root: self.root,
+                errors: vec![],
+            };
+            validator.check(alternative, value, path);
+            if validator.errors.is_empty() {
+                if !exactly_one {
+                    return;
+                }
+                matching.push(index);
+            } else if closest
+                .as_ref()
+                .is_none_or(|closest| validator.errors.len() < closest.len())
+            {
+                closest = Some(validator.errors);
+            }
+        }
+        match matching.len() {
+            0 => self.errors.extend(closest.into_iter().flatten()),
+            1 => {}
+            _ => {
+                let matching: Vec<String> = matching.iter().map(|i| i.to_string()).collect();
+                self.error(
+                    path,
+                    format!(
+                        "expected exactly one alternative to match, but alternatives {} all match",
+                        matching.join(", ")
+                    ),
+                );
+            }
+        }
+    }
+
+    /// Resolve a local reference like `#/$defs/Name`.
+    fn resolve(&self, reference: &str) -> Option<&'s Value> {
+        let pointer = reference.strip_prefix('#')?;
+        let (first, rest) = pointer.strip_prefix('/')?.split_once('/')?;
+        self.root.get(first)?.pointer(&format!("/{rest}"))
+    }
+}
+
+fn field_path(path: &str, field: &str) -> String {
+    if path.is_empty() {
+        field.to_string()
+    } else {
+        format!("{path}.{field}")
+    }
+}
+
+fn has_type(value: &Value, ty: &str) -> bool {
+    match ty {
+        "null" => value.is_null(),
+        "boolean" => value.is_boolean(),
+        "string" => value.is_string(),
+        "array" => value.is_array(),
+        "object" => value.is_object(),
+        "number" => value.is_number(),
+        "integer" => {
+            value.is_i64() || value.is_u64() || value.as_f64().is_some_and(|n| n.fract() == 0.0)
+        }
+        _ => true,
+    }
+}
+
+fn kind(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(n) if n.is_i64() || n.is_u64() => "integer",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// Validate `value` against `schema`, returning the problems as strings.
+    fn errors(schema: Value, value: Value) -> Vec<String> {
+        let Value::Object(schema) = schema else {
+            panic!("schema must be an object");
+        };
+        validate_arguments(&schema, &value)
+            .iter()
+            .map(ToString::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn test_required_and_additional_properties() {
+        let schema = json!({
+            "type": "object",
+            "properties": { "name": { "type": "string" } },
+            "required": ["name"],
+            "additionalProperties": false,
+        });
+        assert_eq!(
+            errors(schema.clone(), json!({ "name": "x" })),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            errors(schema, json!({ "nmae": "x" })),
+            ["`name`: missing required field", "`nmae`: unknown field"]
+        );
+
+        let schema = json!({ "additionalProperties": { "type": "integer" } });
+        assert_eq!(
+            errors(schema, json!({ "a": 1, "b": "two" })),
+            ["`b`: expected integer, got string"]
+        );
+    }
+
+    #[test]
+    fn test_items() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "items": { "type": "array", "items": { "type": "integer" } },
+            },
+        });
+        assert_eq!(
+            errors(schema, json!({ "items": [1, "two", 3.5] })),
+            [
+                "`items[1]`: expected integer, got string",
+                "`items[2]`: expected integer, got number"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_minimum_and_maximum() {
+        let schema = json!({ "properties": { "n": { "minimum": 0, "maximum": 10 } } });
+        assert_eq!(
+            errors(schema.clone(), json!({ "n": 5 })),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            errors(schema.clone(), json!({ "n": -1 })),
+            ["`n`: expected a value >= 0, got -1"]
+        );
+        assert_eq!(
+            errors(schema, json!({ "n": 11 })),
+            ["`n`: expected a value <= 10, got 11"]
+        );
+    }
+
+    #[test]
+    fn test_enum_and_const() {
+        let schema = json!({
+            "properties": {
+                "color": { "enum": ["red", "green"] },
+                "kind": { "const": "circle" },
+            },
+        });
+        assert_eq!(
+            errors(schema.clone(), json!({ "color": "red", "kind": "circle" })),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            errors(schema, json!({ "color": "blue", "kind": "square" })),
+            [
+                r#"`color`: expected one of "red", "green", got "blue""#,
+                r#"`kind`: expected "circle", got "square""#
+            ]
+        );
+    }
+
+    #[test]
+    fn test_ref() {
+        let schema = json!({
+            "properties": { "point": { "$ref": "#/$defs/Point" } },
+            "$defs": {
+                "Point": {
+                    "type": "object",
+                    "properties": { "x": { "type": "number" } },
+                    "required": ["x"],
+                },
+            },
+        });
+        assert_eq!(
+            errors(schema.clone(), json!({ "point": { "x": 1 } })),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            errors(schema, json!({ "point": {} })),
+            ["`point.x`: missing required field"]
+        );
+    }
+
+    #[test]
+    fn test_all_of() {
+        let schema = json!({
+            "allOf": [
+                { "required": ["a"] },
+                { "required": ["b"] },
+            ],
+        });
+        assert_eq!(
+            errors(schema.clone(), json!({ "a": 1, "b": 2 })),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            errors(schema, json!({})),
+            ["`a`: missing required field", "`b`: missing required field"]
+        );
+    }
+
+    #[test]
+    fn test_any_of_reports_closest_alternative() {
+        // The shape `schemars` generates for `Option<Point>`.
+        let schema = json!({
+            "properties": {
+                "point": {
+                    "anyOf": [
+                        {
+                            "type": "object",
+                            "properties": { "x": { "type": "number" }, "y": { "type": "number" } },
+                        },
+                        { "type": "null" },
+                    ],
+                },
+            },
+        });
+        assert_eq!(
+            errors(schema.clone(), json!({ "point": null })),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            errors(schema.clone(), json!({ "point": { "x": 1 } })),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            errors(schema, json!({ "point": { "x": "one" } })),
+            ["`point.x`: expected number, got string"]
+        );
+    }
+
+    #[test]
+    fn test_any_of_accepts_several_matches() {
+        let schema = json!({ "anyOf": [{ "type": "number" }, { "type": "integer" }] });
+        assert_eq!(errors(schema, json!(1)), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_one_of_requires_exactly_one_match() {
+        let schema = json!({ "oneOf": [{ "type": "number" }, { "type": "integer" }] });
+        assert_eq!(errors(schema.clone(), json!(1.5)), Vec::<String>::new());
+        assert_eq!(
+            errors(schema.clone(), json!(1)),
+            [
+                "arguments: expected exactly one alternative to match, but alternatives 0, 1 all match"
+            ]
+        );
+        assert_eq!(
+            errors(schema, json!("one")),
+            ["arguments: expected number, got string"]
+        );
+    }
+}
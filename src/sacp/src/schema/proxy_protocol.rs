@@ -201,3 +201,142 @@ impl From<agent_client_protocol_schema::InitializeRequest> for InitializeProxyRe
         Self { initialize }
     }
 }
+
+// =============================================================================
+// Component logging protocol
+// =============================================================================
+
+/// JSON-RPC method name for component log notifications.
+pub const METHOD_LOG_NOTIFICATION: &str = "_proxy/log";
+
+/// A log record sent by a component to the conductor.
+///
+/// A conductor that is recording a trace writes these into it, interleaved with
+/// the messages the component sent and received; otherwise they are dropped.
+/// They are not forwarded to the client. See `sacp::log_forwarding` (behind
+/// the `log-forwarding` feature) for sending `tracing` events this way.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, crate::JrNotification)]
+#[notification(method = "_proxy/log", crate = crate)]
+pub struct LogNotification {
+    /// Severity of the record.
+    pub level: LogLevel,
+
+    /// Where the record came from, such as a Rust module path.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+
+    /// The log message.
+    pub message: String,
+
+    /// Structured fields attached to the record.
+    #[serde(default, skip_serializing_if = "serde_json::Map::is_empty")]
+    pub fields: serde_json::Map<String, serde_json::Value>,
+
+    /// Optional metadata
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub meta: Option<serde_json::Value>,
+}
+
+impl LogNotification {
+    /// Create a log record without a target or fields.
+    pub fn new(level: LogLevel, message: impl Into<String>) -> Self {
+        Self {
+            level,
+            target: None,
+            message: message.into(),
+            fields: Default::default(),
+            meta: None,
+        }
+    }
+
+    /// Convert a `tracing` event: its `message` field becomes the message and
+    /// its other fields the record's fields.
+    #[cfg(feature = "log-forwarding")]
+    pub fn from_event(event: &tracing::Event<'_>) -> Self {
+        let mut visitor = FieldVisitor::default();
+        event.record(&mut visitor);
+        let metadata = event.metadata();
+        Self {
+            level: (*metadata.level()).into(),
+            target: Some(metadata.target().to_string()),
+            message: visitor.message,
+            fields: visitor.fields,
+            meta: None,
+        }
+    }
+}
+
+/// Severity of a [`LogNotification`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    /// Very verbose diagnostics.
+    Trace,
+    /// Diagnostics.
+    Debug,
+    /// Normal operation.
+    Info,
+    /// Something unexpected that was recovered from.
+    Warn,
+    /// A failure.
+    Error,
+}
+
+impl From<tracing::Level> for LogLevel {
+    fn from(level: tracing::Level) -> Self {
+        match level {
+            tracing::Level::TRACE => LogLevel::Trace,
+            tracing::Level::DEBUG => LogLevel::Debug,
+            tracing::Level::INFO => LogLevel::Info,
+            tracing::Level::WARN => LogLevel::Warn,
+            tracing::Level::ERROR => LogLevel::Error,
+        }
+    }
+}
+
+/// Collects the fields of a `tracing` event.
+#[cfg(feature = "log-forwarding")]
+#[derive(Default)]
+struct FieldVisitor {
+    message: String,
+    fields: serde_json::Map<String, serde_json::Value>,
+}
+
+#[cfg(feature = "log-forwarding")]
+impl FieldVisitor {
+    fn record(&mut self, field: &tracing::field::Field, value: serde_json::Value) {
+        match (field.name(), value) {
+            ("message", serde_json::Value::String(message)) => self.message = message,
+            (name, value) => {
+                self.fields.insert(name.to_string(), value);
+            }
+        }
+    }
+}
+
+#[cfg(feature = "log-forwarding")]
+impl tracing::field::Visit for FieldVisitor {
+    fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+        self.record(field, format!("{value:?}").into());
+    }
+
+    fn record_str(&mut self, field: &tracing::field::Field, value: &str) {
+        self.record(field, value.into());
+    }
+
+    fn record_bool(&mut self, field: &tracing::field::Field, value: bool) {
+        self.record(field, value.into());
+    }
+
+    fn record_i64(&mut self, field: &tracing::field::Field, value: i64) {
+        self.record(field, value.into());
+    }
+
+    fn record_u64(&mut self, field: &tracing::field::Field, value: u64) {
+        self.record(field, value.into());
+    }
+
+    fn record_f64(&mut self, field: &tracing::field::Field, value: f64) {
+        self.record(field, value.into());
+    }
+}
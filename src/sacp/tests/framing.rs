@@ -0,0 +1,132 @@
+//! Integration tests for `Content-Length` framing and framing detection on byte streams.
+
+use sacp::link::{AgentToClient, ClientToAgent};
+use sacp::schema::{InitializeRequest, InitializeResponse, ProtocolVersion};
+use sacp::{ByteStreams, Framing};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader, DuplexStream};
+use tokio_util::compat::{TokioAsyncReadCompatExt, TokioAsyncWriteCompatExt};
+
+/// Serve an agent that answers `initialize` over `stream`, using `framing`.
+fn spawn_agent(stream: DuplexStream, framing: Framing) -> tokio::task::JoinHandle<()> {
+    let (read, write) = tokio::io::split(stream);
+    tokio::spawn(async move {
+        let _ = AgentToClient::builder()
+            .name("agent")
+            .on_receive_request(
+                async |initialize: InitializeRequest, request_cx, _cx| {
+                    request_cx.respond(InitializeResponse::new(initialize.protocol_version))
+                },
+                sacp::on_receive_request!(),
+            )
+            .serve(ByteStreams::new(write.compat_write(), read.compat()).with_framing(framing))
+            .await;
+    })
+}
+
+/// Initialize the agent at the other end of `stream`, using `framing`.
+async fn initialize(stream: DuplexStream, framing: Framing) -> Result<(), sacp::Error> {
+    let (read, write) = tokio::io::split(stream);
+    ClientToAgent::builder()
+        .name("client")
+        .run_until(
+            ByteStreams::new(write.compat_write(), read.compat()).with_framing(framing),
+            async |cx| {
+                let response = cx
+                    .send_request(InitializeRequest::new(ProtocolVersion::LATEST))
+                    .block_task()
+                    .await?;
+                assert_eq!(response.protocol_version, ProtocolVersion::LATEST);
+                Ok(())
+            },
+        )
+        .await
+}
+
+#[tokio::test]
+async fn test_content_length_round_trip() -> Result<(), sacp::Error> {
+    let (client, agent) = tokio::io::duplex(1024);
+    let agent = spawn_agent(agent, Framing::ContentLength);
+    initialize(client, Framing::ContentLength).await?;
+    agent.abort();
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_auto_detects_either_framing() -> Result<(), sacp::Error> {
+    for framing in [Framing::Newline, Framing::ContentLength] {
+        let (client, agent) = tokio::io::duplex(1024);
+        let agent = spawn_agent(agent, Framing::Auto);
+        initialize(client, framing).await?;
+        agent.abort();
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_content_length_wire_format() -> Result<(), sacp::Error> {
+    let (client, agent) = tokio::io::duplex(1024);
+    let agent = spawn_agent(agent, Framing::Auto);
+    let (read, mut write) = tokio::io::split(client);
+
+    // Headers are case-insensitive and may include others than Content-Length.
+    let request =
+        r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{"protocolVersion":1}}"#;
+    write
+        .write_all(
+            format!(
+                "content-length: {}\r\nContent-Type: application/vscode-jsonrpc; charset=utf-8\r\n\r\n{request}",
+                request.len()
+            )
+            .as_bytes(),
+        )
+        .await
+        .unwrap();
+
+    // The response is framed the same way.
+    let mut read = BufReader::new(read);
+    let mut header = String::new();
+    read.read_line(&mut header).await.unwrap();
+    let length: usize = header
+        .strip_prefix("Content-Length: ")
+        .and_then(|length| length.trim_end().parse().ok())
+        .unwrap_or_else(|| panic!("unexpected header {header:?}"));
+    let mut blank = String::new();
+    read.read_line(&mut blank).await.unwrap();
+    assert_eq!(blank, "\r\n");
+
+    let mut body = vec![0; length];
+    read.read_exact(&mut body).await.unwrap();
+    let response: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(response["id"], 1);
+    assert_eq!(response["result"]["protocolVersion"], 1);
+
+    agent.abort();
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_content_length_headers_are_limited() {
+    let (client, agent) = tokio::io::duplex(1024);
+    let (read, write) = tokio::io::split(agent);
+    let (_outgoing, incoming) =
+        Framing::ContentLength.frame_limited(write.compat_write(), read.compat(), Some(1024));
+
+    // A header line that never ends...
+    let (_, mut client_write) = tokio::io::split(client);
+    let writer = tokio::spawn(async move {
+        let junk = [b'x'; 1024];
+        let _ = client_write.write_all(b"X-Junk: ").await;
+        while client_write.write_all(&junk).await.is_ok() {}
+    });
+
+    // ...is cut off rather than buffered.
+    let mut incoming = std::pin::pin!(incoming);
+    let error = futures::StreamExt::next(&mut incoming)
+        .await
+        .expect("stream ended without an error")
+        .expect_err("headers should be rejected");
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    assert!(futures::StreamExt::next(&mut incoming).await.is_none());
+
+    writer.abort();
+}
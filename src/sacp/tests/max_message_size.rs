@@ -0,0 +1,151 @@
+//! Integration tests for maximum message sizes on transports and connections.
+
+use sacp::link::UntypedLink;
+use sacp::{ByteStreams, JrRequest, JrResponsePayload};
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, DuplexStream};
+use tokio_util::compat::{TokioAsyncReadCompatExt, TokioAsyncWriteCompatExt};
+
+#[derive(Debug, Clone, Serialize, Deserialize, JrRequest)]
+#[request(method = "_test/echo", response = EchoResponse)]
+struct EchoRequest {
+    text: String,
+    reply_size: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize, JrResponsePayload)]
+struct EchoResponse {
+    text: String,
+}
+
+/// The builder for an agent that echoes requests, padding replies to the requested size.
+fn echo_agent() -> sacp::JrConnectionBuilder<impl sacp::JrMessageHandler<Link = UntypedLink>> {
+    UntypedLink::builder().name("agent").on_receive_request(
+        async |request: EchoRequest, request_cx, _cx| {
+            let mut text = request.text;
+            text.extend(std::iter::repeat_n('x', request.reply_size));
+            request_cx.respond(EchoResponse { text })
+        },
+        sacp::on_receive_request!(),
+    )
+}
+
+fn byte_streams(
+    stream: DuplexStream,
+) -> ByteStreams<
+    tokio_util::compat::Compat<tokio::io::WriteHalf<DuplexStream>>,
+    tokio_util::compat::Compat<tokio::io::ReadHalf<DuplexStream>>,
+> {
+    let (read, write) = tokio::io::split(stream);
+    ByteStreams::new(write.compat_write(), read.compat())
+}
+
+fn echo(id: u64, text: &str) -> String {
+    format!(
+        r#"{{"jsonrpc":"2.0","id":{id},"method":"_test/echo","params":{{"text":"{text}","reply_size":0}}}}"#
+    )
+}
+
+#[tokio::test]
+async fn test_transport_rejects_oversized_messages() {
+    let (client, agent) = tokio::io::duplex(1024);
+    let agent = tokio::spawn(echo_agent().serve(byte_streams(agent).with_max_message_size(256)));
+    let (read, mut write) = tokio::io::split(client);
+    let mut read = BufReader::new(read).lines();
+
+    let big = "y".repeat(4096);
+    let notification =
+        format!(r#"{{"jsonrpc":"2.0","method":"_test/unknown","params":{{"text":"{big}"}}}}"#);
+    for line in [echo(1, &big), notification, echo(2, "small")] {
+        write.write_all(line.as_bytes()).await.unwrap();
+        write.write_all(b"\n").await.unwrap();
+    }
+
+    // The oversized request is answered with an error...
+    let response: serde_json::Value =
+        serde_json::from_str(&read.next_line().await.unwrap().unwrap()).unwrap();
+    assert_eq!(response["id"], 1);
+    assert_eq!(response["error"]["code"], -32600);
+    assert_eq!(response["error"]["data"]["max_message_size"], 256);
+
+    // ...the oversized notification is dropped, and the connection carries on.
+    let response: serde_json::Value =
+        serde_json::from_str(&read.next_line().await.unwrap().unwrap()).unwrap();
+    assert_eq!(response["id"], 2);
+    assert_eq!(response["result"]["text"], "small");
+
+    agent.abort();
+}
+
+#[tokio::test]
+async fn test_connection_rejects_oversized_request() -> Result<(), sacp::Error> {
+    let (client, agent) = tokio::io::duplex(1024);
+    let agent = tokio::spawn(
+        echo_agent()
+            .max_message_size(256)
+            .serve(byte_streams(agent)),
+    );
+
+    UntypedLink::builder()
+        .name("client")
+        .run_until(byte_streams(client), async |cx| {
+            let error = cx
+                .send_request(EchoRequest {
+                    text: "y".repeat(4096),
+                    reply_size: 0,
+                })
+                .block_task()
+                .await
+                .expect_err("oversized request should fail");
+            assert_eq!(error.code, sacp::Error::invalid_request().code);
+
+            let response = cx
+                .send_request(EchoRequest {
+                    text: "small".into(),
+                    reply_size: 0,
+                })
+                .block_task()
+                .await?;
+            assert_eq!(response.text, "small");
+            Ok(())
+        })
+        .await?;
+
+    agent.abort();
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_connection_rejects_oversized_response() -> Result<(), sacp::Error> {
+    let (client, agent) = tokio::io::duplex(1024);
+    let agent = tokio::spawn(echo_agent().serve(byte_streams(agent)));
+
+    UntypedLink::builder()
+        .name("client")
+        .max_message_size(256)
+        .run_until(byte_streams(client), async |cx| {
+            let error = cx
+                .send_request(EchoRequest {
+                    text: "big".into(),
+                    reply_size: 4096,
+                })
+                .block_task()
+                .await
+                .expect_err("oversized response should fail");
+            assert_eq!(error.code, sacp::Error::invalid_request().code);
+
+            let response = cx
+                .send_request(EchoRequest {
+                    text: "small".into(),
+                    reply_size: 0,
+                })
+                .block_task()
+                .await?;
+            assert_eq!(response.text, "small");
+            Ok(())
+        })
+        .await?;
+
+    agent.abort();
+    Ok(())
+}